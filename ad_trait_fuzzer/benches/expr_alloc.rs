@@ -0,0 +1,42 @@
+// benches/expr_alloc.rs
+
+// Cost of building and cloning the calculators for one deep expression, the allocation-heavy
+// part of every fuzz case. `cargo bench --bench expr_alloc`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use fuzz_core::ast_evaluator::unified::AdPyUnified;
+use fuzz_core::ast_expr::SimpleExpr;
+use fuzz_core::fuzz_harness::Calculator;
+
+/// A full binary tree of the given depth over `x_0` and `x_1`.
+fn tree(depth: usize, var: usize) -> SimpleExpr {
+    if depth == 0 {
+        return SimpleExpr::var(format!("x_{}", var));
+    }
+    let (l, r) = (tree(depth - 1, var), SimpleExpr::sin(tree(depth - 1, 1 - var)));
+    if depth.is_multiple_of(2) { SimpleExpr::mul(l, r) } else { SimpleExpr::add(l, r) }
+}
+
+fn bench_calculator(c: &mut Criterion) {
+    for depth in [8, 10] {
+        let expr = tree(depth, 0);
+        c.bench_function(&format!("AdPyUnified::new depth {}", depth), |b| {
+            b.iter(|| AdPyUnified::new(black_box(expr.clone()), 2, 1))
+        });
+        // The harness clones the calculator for each engine and each timed stage
+        let calc = AdPyUnified::new(expr, 2, 1);
+        c.bench_function(&format!("clone calculator x8 depth {}", depth), |b| {
+            b.iter(|| {
+                for _ in 0..8 {
+                    black_box(black_box(&calc).clone());
+                }
+            })
+        });
+        c.bench_function(&format!("eval f64 depth {}", depth), |b| {
+            b.iter(|| calc.eval_backend(black_box(&[0.5f64, 1.5][..])).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, bench_calculator);
+criterion_main!(benches);
@@ -4,7 +4,7 @@
 use fuzz_core::ast_expr::{SimpleExpr, Expr};
 use fuzz_core::ast_evaluator::unified::AdPyUnified;
 use fuzz_core::ast_evaluator::{SExprPrinter, InfixPrinter, SSAPrinter};
-use fuzz_core::fuzz_harness::run_custom_test;
+use fuzz_core::fuzz_harness::{run_custom_test, GroundTruthCalculator};
 use fuzz_core::gt_calculators::PyTorchGroundTruthCalculator;
 
 fn print_and_test(name: &str, expr: SimpleExpr, num_inputs: usize, inputs: &[f64]) {
@@ -14,7 +14,7 @@ fn print_and_test(name: &str, expr: SimpleExpr, num_inputs: usize, inputs: &[f64
     println!("SSA:\n{}", SSAPrinter::print(&expr));
     
     let evaluator = AdPyUnified::new(expr, num_inputs, 1);
-    let gt_calculators = [PyTorchGroundTruthCalculator];
+    let gt_calculators = [PyTorchGroundTruthCalculator::default().boxed()];
     
     println!("Testing with inputs {:?}:", inputs);
     let _ = run_custom_test(&inputs, evaluator, &gt_calculators);
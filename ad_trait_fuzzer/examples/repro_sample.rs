@@ -0,0 +1,43 @@
+// examples/repro_sample.rs
+// cargo +nightly run --example repro_sample
+//
+// f = (sin((x_0 * 2)) - trunc(x_1))
+// Rev vs Fwd failed
+
+use fuzz_core::ast_expr::{Expr, SimpleExpr, Type};
+use fuzz_core::ast_evaluator::unified::AdPyUnified;
+use fuzz_core::fuzz_harness::{run_ad_tests, GroundTruthCalculator, TestOutcome};
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+use fuzz_core::oracles::{FuzzingOracles, OracleSelection};
+
+fn main() {
+    let expr = Expr::Let(
+        (),
+        vec![
+            ("a".to_string(), SimpleExpr::mul(
+                SimpleExpr::var("x_0"),
+                SimpleExpr::num(2.0),
+            )),
+        ],
+        Box::new(SimpleExpr::sub(
+            SimpleExpr::sin(
+                SimpleExpr::var("a"),
+            ),
+            SimpleExpr::cast(
+                Type::Int,
+                SimpleExpr::var("x_1"),
+            ),
+        )),
+    );
+    let inputs = [0.5, f64::NEG_INFINITY];
+
+    let oracles = FuzzingOracles::new(OracleSelection::from_name("rev_fwd").unwrap()).specialize(&expr);
+    let evaluator = AdPyUnified::new(expr, inputs.len(), 1);
+    let result = run_ad_tests(&inputs, evaluator, &oracles, &[DefaultGroundTruth::default().boxed()])
+        .and_then(TestOutcome::into_result);
+    match &result {
+        Ok(()) => println!("Test PASSED"),
+        Err(e) => println!("Test FAILED: {}", e),
+    }
+    std::process::exit(if result.is_ok() { 0 } else { 1 });
+}
@@ -4,7 +4,7 @@
 use fuzz_core::ast_expr::SimpleExpr;
 use fuzz_core::ast_evaluator::EvalexprEvaluator;
 use fuzz_core::ast_evaluator::unified::AdPyUnified;
-use fuzz_core::fuzz_harness::run_custom_test;
+use fuzz_core::fuzz_harness::{run_custom_test, GroundTruthCalculator};
 use fuzz_core::gt_calculators::PyTorchGroundTruthCalculator;
 
 fn test_evalexpr_vs_ad_trait<const N: usize>(name: &str, expr: SimpleExpr, inputs: [f64; N]) {
@@ -36,7 +36,7 @@ fn test_evalexpr_vs_ad_trait<const N: usize>(name: &str, expr: SimpleExpr, input
     // Test with AD trait for comparison
     println!("\nUsing AD trait:");
     let evaluator = AdPyUnified::new(expr, N, 1);
-    let gt_calculators = [PyTorchGroundTruthCalculator];
+    let gt_calculators = [PyTorchGroundTruthCalculator::default().boxed()];
     let _ = run_custom_test(&inputs, evaluator, &gt_calculators);
 }
 
@@ -0,0 +1,288 @@
+// fuzz/ast_pipeline.rs
+
+// What the AST fuzz targets do with the outcome of their `Harness`: crash reports, JSON
+// reports, shrinking and the Continuous mode campaign. Independent of the fuzzing engine and
+// the referees: `fuzz_target_ast` (libFuzzer), `fuzz_target_ast_afl` and
+// `fuzz_target_ast_honggfuzz` run it with PyTorch as ground truth, `fuzz_target_ad_vs_evalexpr`
+// with evalexpr-jit.
+
+use std::cell::OnceCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use fuzz_core::fuzz_case::{CaseResult, IterationOutcome};
+use fuzz_core::harness::Harness;
+use fuzz_core::fuzz_harness::{set_eval_timeout, GroundTruthCalculator, HarnessMode, FuzzConfig}; 
+use fuzz_core::error::FuzzError;
+use fuzz_core::ast_evaluator::{printer, InfixPrinter};
+use fuzz_core::ast_expr::SimpleExpr;
+use fuzz_core::ast_shrinker::{shrink_failure, size};
+use fuzz_core::ast_localizer::localize_failure;
+use fuzz_core::input_shrinker::shrink_input_failure;
+use fuzz_core::symbolic_diff::symbolic_gradient;
+use fuzz_core::failure_report::FailureReport;
+use fuzz_core::campaign_report::CampaignReport;
+use fuzz_core::notifier::{notify, FailureNotice};
+use fuzz_core::tape_stats::{set_tape_budget, CountingAllocator};
+
+// Peak memory of the Reverse AD pass, see `tape_stats`
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+// Continuous mode: every test of the campaign, printed every STATS_INTERVAL failures and on exit.
+// Only the first failure per (canonical expression, oracle) bucket gets a crash report.
+static CAMPAIGN: Mutex<CampaignReport> = Mutex::new(CampaignReport::new());
+const STATS_INTERVAL: usize = 100;
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+// When the campaign was last saved to `config.checkpoint`
+static LAST_CHECKPOINT: Mutex<Option<Instant>> = Mutex::new(None);
+
+// The expression in each format of `config.printers`
+fn print_formats(expr: &SimpleExpr, num_vars: usize, config: &FuzzConfig) {
+    // Names were checked when the config was loaded
+    for p in config.printers.iter().filter_map(|name| printer::<()>(name)) {
+        eprintln!("\n{}:", p.title());
+        eprintln!("{}", p.print(expr, num_vars));
+    }
+}
+
+// Print utility function:
+fn print_vec(vec: &[f64])
+{
+    for (i, e) in vec.iter().enumerate()
+    {
+        println!("x_{}: {}", i, e);
+    }
+}
+
+// --- Configuration (fuzz_config.toml + environment, read once) ---
+
+static CONFIG: OnceLock<FuzzConfig> = OnceLock::new();
+
+thread_local! {
+    // Built on the first input of each thread, with the referees of that call
+    static HARNESS: OnceCell<Harness> = OnceCell::new();
+}
+
+/// Loads the config on the first input; `adjust` is where specialized targets pin their settings.
+fn get_fuzz_config(adjust: fn(&mut FuzzConfig)) -> &'static FuzzConfig {
+    CONFIG.get_or_init(|| {
+        let mut config = FuzzConfig::load().unwrap_or_else(|e| panic!("{}", e));
+        adjust(&mut config);
+        set_eval_timeout(config.eval_timeout);
+        set_tape_budget(config.tape_budget);
+        if let HarnessMode::Continuous = config.mode {
+            resume_campaign(&config);
+            install_campaign_hooks();
+        }
+        config
+    })
+}
+
+// --- Continuous mode: campaign report on exit ---
+
+// libFuzzer leaves with _Exit on SIGINT, which skips atexit handlers. Take SIGINT over and
+// only set a flag here (printing is not signal safe); the next input finishes the campaign.
+extern "C" fn on_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// Normal exit, e.g. after -runs or -max_total_time
+extern "C" fn on_exit() {
+    finish_campaign();
+}
+
+fn install_campaign_hooks() {
+    unsafe {
+        libc::atexit(on_exit);
+        libc::signal(libc::SIGINT, on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t);
+    }
+}
+
+// --- Continuous mode: checkpoints ---
+
+fn resume_campaign(config: &FuzzConfig) {
+    let Some(path) = &config.checkpoint else { return };
+    match CampaignReport::load_checkpoint(path) {
+        Ok(Some(campaign)) => {
+            eprintln!("Resuming campaign from {}: {} tests, {} known failure buckets",
+                path.display(), campaign.tests_run(), campaign.buckets.len());
+            *CAMPAIGN.lock().unwrap() = campaign;
+        }
+        Ok(None) => {}
+        // Starting over would report every known bucket again; better to stop and let the user decide
+        Err(err) => panic!("Could not resume campaign from {}: {}", path.display(), err),
+    }
+    *LAST_CHECKPOINT.lock().unwrap() = Some(Instant::now());
+}
+
+fn save_checkpoint(config: &FuzzConfig, campaign: &CampaignReport) {
+    if let Some(path) = &config.checkpoint {
+        if let Err(err) = campaign.save_checkpoint(path) {
+            eprintln!("Could not write campaign checkpoint to {}: {}", path.display(), err);
+        }
+    }
+}
+
+fn checkpoint_if_due(config: &FuzzConfig) {
+    let mut last = LAST_CHECKPOINT.lock().unwrap();
+    if config.checkpoint.is_none() || last.is_some_and(|t| t.elapsed() < config.checkpoint_interval) {
+        return;
+    }
+    *last = Some(Instant::now());
+    save_checkpoint(config, &CAMPAIGN.lock().unwrap());
+}
+
+fn finish_campaign() {
+    // A panic while holding the lock must not turn into a deadlock at exit
+    let campaign = match CAMPAIGN.try_lock() {
+        Ok(campaign) => campaign,
+        Err(_) => return,
+    };
+    eprintln!("\n{}", *campaign);
+    let Some(config) = CONFIG.get() else { return };
+    if let Some(dir) = &config.report_dir {
+        match campaign.write_to(dir) {
+            Ok(path) => eprintln!("Campaign report: {}", path.display()),
+            Err(err) => eprintln!("Could not write campaign report to {}: {}", dir.display(), err),
+        }
+    }
+    save_checkpoint(config, &campaign);
+}
+
+// --- Fuzz Target Implementation ---
+
+/// Runs one fuzzer input, with `gt_calculators` as the referees. Failures panic
+/// (PanicOnFirstError), which every engine records as a crash.
+#[allow(dead_code)] // Each target uses one of run_input / run_input_with
+pub fn run_input(data: &[u8], gt_calculators: &[Box<dyn GroundTruthCalculator>]) {
+    run_input_with(data, gt_calculators, |_| {});
+}
+
+/// `run_input` with `adjust` applied to the loaded config.
+pub fn run_input_with(
+    data: &[u8],
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+    adjust: fn(&mut FuzzConfig),
+) {
+    let config = get_fuzz_config(adjust);
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        finish_campaign();
+        // Not process::exit: libFuzzer's exit hook would save this input as a crash
+        unsafe { libc::_exit(130) };
+    }
+    if let HarnessMode::Continuous = config.mode {
+        checkpoint_if_due(config);
+    }
+    
+    let outcome = HARNESS.with(|harness| {
+        harness.get_or_init(|| {
+            Harness::builder()
+                .config(config.clone())
+                .ground_truths(gt_calculators.to_vec())
+                .build()
+                .unwrap_or_else(|e| panic!("{}", e))
+        }).run(data)
+    });
+    let (oracles, cases) = match outcome {
+        IterationOutcome::Ran { oracles, cases, .. } => (oracles, cases),
+        _ => return,
+    };
+    
+    for case in cases {
+        let (case, outcome) = match case {
+            CaseResult::Tested(case, outcome) => (case, outcome),
+            CaseResult::Error(case, e @ FuzzError::Timeout { .. }) => {
+                eprintln!("Timeout: {} for {} at {:?}", e, InfixPrinter::print(&case.expr, case.inputs.len()), case.inputs);
+                if let HarnessMode::Continuous = config.mode {
+                    CAMPAIGN.lock().unwrap().record_timeout();
+                }
+                continue;
+            }
+            CaseResult::Error(_, e) => {
+                println!("Skipping test case: {}", e);
+                continue;
+            }
+            CaseResult::NotGenerated(_) | CaseResult::Skipped(_) | CaseResult::Unsatisfiable(_) | CaseResult::OverBudget => continue,
+        };
+        if let HarnessMode::Continuous = config.mode {
+            let first_in_bucket = {
+                let mut campaign = CAMPAIGN.lock().unwrap();
+                let first_in_bucket = campaign.record(&case.expr, &outcome);
+                if outcome.first_failure().is_some() && campaign.stats.total() % STATS_INTERVAL == 0 {
+                    eprintln!("{}", *campaign);
+                }
+                first_in_bucket
+            };
+            if !first_in_bucket {
+                continue;
+            }
+            // For the bug surface; runs the oracles on every subtree, so not under the lock
+            if let Some(found) = localize_failure(&case.expr, &case.inputs, &case.oracles, gt_calculators) {
+                CAMPAIGN.lock().unwrap().record_localization(&found);
+            }
+        }
+        
+        if let Some(e) = outcome.first_failure() {
+            let expr = &case.expr;
+            let num_vars = case.inputs.len();
+            
+            eprintln!("\n=== CRASH DETECTED ===");
+            eprintln!("Expression that caused the crash:");
+            print_formats(expr, num_vars, config);
+            eprintln!("\nDebug format:");
+            eprintln!("{:#?}", expr);
+            eprintln!("\nInputs:");
+            print_vec(&case.inputs);
+            if let Ok(gradient) = symbolic_gradient(expr, num_vars) {
+                eprintln!("Symbolic gradient:");
+                for (i, d) in gradient.iter().enumerate() {
+                    eprintln!("  d/dx_{} = {}", i, InfixPrinter::print(d, num_vars));
+                }
+            }
+            eprintln!("Error ({}): {}", e.failure_class().map(|c| c.to_string()).unwrap_or_else(|| e.kind().to_string()), e);
+            
+            // Shrinking re-runs the oracles many times; only worth it when we stop here
+            let mut minimized = None;
+            if let HarnessMode::PanicOnFirstError = config.mode {
+                let shrunk = shrink_failure(expr, &case.inputs, &oracles, gt_calculators, e);
+                eprintln!("\nMinimized expression ({} -> {} nodes):", size(expr), size(&shrunk));
+                print_formats(&shrunk, num_vars, config);
+                eprintln!("\nSimplest inputs that still fail:");
+                print_vec(&shrink_input_failure(&shrunk, &case.inputs, &oracles, gt_calculators, e));
+                minimized = Some(shrunk);
+            }
+            
+            let mut report_path = None;
+            if let Some(dir) = &config.report_dir {
+                let report = FailureReport::new(expr, minimized.as_ref(), &outcome, &case.oracles);
+                match report.map(|r| r.write_to(dir)) {
+                    Some(Ok(path)) => {
+                        eprintln!("\nJSON report: {}", path.display());
+                        report_path = Some(path);
+                    }
+                    Some(Err(err)) => eprintln!("\nCould not write JSON report to {}: {}", dir.display(), err),
+                    None => {}
+                }
+            }
+            eprintln!("======================\n");
+            
+            if let HarnessMode::Continuous = config.mode {
+                let distinct_failures = CAMPAIGN.lock().unwrap().buckets.len();
+                // Not joined: libFuzzer runs for days, the notice has plenty of time to go out
+                notify(config, &FailureNotice {
+                    expr,
+                    inputs: &case.inputs,
+                    error: e,
+                    distinct_failures,
+                    report: report_path.as_deref(),
+                });
+                continue;
+            }
+            
+            // Panic so the fuzzing engine can capture it
+            panic!("Oracle check failed: {}", e);
+        }
+    }
+}
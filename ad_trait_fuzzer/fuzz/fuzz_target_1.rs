@@ -2,81 +2,64 @@
 
 #![no_main]
 use libfuzzer_sys::fuzz_target;
-use rand::thread_rng; 
-use std::env;
+use std::sync::OnceLock;
 
 // --- Imports from your library modules ---
 use fuzz_core::input_decoder::{FuzzInputDecoder, TwoInputDecoder}; 
-use fuzz_core::fuzz_harness::{run_ad_tests, HarnessMode, FuzzConfig}; 
-use fuzz_core::oracles::{FuzzingOracles}; 
-use fuzz_core::gt_calculators::PyTorchGroundTruthCalculator; 
+use fuzz_core::fuzz_harness::{run_ad_tests, GroundTruthCalculator, HarnessMode, FuzzConfig}; 
+use fuzz_core::gt_calculators::DefaultGroundTruth; 
 use fuzz_core::rpn_evaluator::RpnEvaluator; 
-use fuzz_core::test_generator; 
+use fuzz_core::test_generator::{self, RpnGenConfig}; 
 
-const NUM_GENERATED_TESTS: usize = 1; 
+// --- Configuration (fuzz_config.toml + environment, read once) ---
 
-// --- Configuration Reader (Reads Environment Variables) ---
-
-fn get_fuzz_config() -> FuzzConfig {
-    // 1. Harness Mode
-    let mode = match env::var("FUZZ_MODE") {
-        Ok(val) if val.eq_ignore_ascii_case("continuous") => HarnessMode::Continuous,
-        _ => HarnessMode::PanicOnFirstError,
-    };
-
-    // 2. Number of Tests
-    let num_generated_tests = match env::var("FUZZ_TESTS") {
-        Ok(val) => val.parse::<usize>().unwrap_or(NUM_GENERATED_TESTS),
-        _ => NUM_GENERATED_TESTS, 
-    };
-
-    // 3. Oracle Selection
-    let oracle_selection = env::var("FUZZ_ORACLE").unwrap_or_else(|_| "all".to_string());
-
-    FuzzConfig {
-        mode,
-        num_generated_tests,
-        oracle_selection,
-    }
+fn get_fuzz_config() -> &'static FuzzConfig {
+    static CONFIG: OnceLock<FuzzConfig> = OnceLock::new();
+    CONFIG.get_or_init(|| FuzzConfig::load().unwrap_or_else(|e| panic!("{}", e)))
 }
 
 // --- Fuzz Target Implementation ---
 
 fuzz_target!(|data: &[u8]| {
     
-    let config: FuzzConfig = get_fuzz_config();
+    let config = get_fuzz_config();
     
-    let inputs: Vec<f64> = match TwoInputDecoder::decode(data) {
-        Ok(inputs) => inputs,
+    let inputs: Vec<f64> = match TwoInputDecoder.decode_prefix(data) {
+        Ok((inputs, _)) => inputs,
         Err(_) => return,
     };
     
     // Input Sanitization
-    let x: f64 = inputs[0];
-    let y: f64 = inputs[1];
-    if !x.is_finite() || !y.is_finite() || x <= 0.0 || x.abs() > 1e10 || y.abs() > 100.0 {
+    if !config.inputs.accepts(&inputs) {
         return;
     }
     
     // --- Test Setup ---
-    let mut rng = thread_rng(); 
+    // Same operators as the AST targets, so the two pipelines run comparable campaigns
+    let gen_config = RpnGenConfig { seed: config.rpn_seed, ..RpnGenConfig::from(&config.ast) };
+    let mut rng = gen_config.rng(); 
     
     let mut test_definitions = Vec::new();
     for _ in 0..config.num_generated_tests {
-        let test_def = test_generator::generate_random_test(&mut rng);
+        let test_def = test_generator::generate_random_test(&mut rng, &gen_config);
         test_definitions.push(test_def);
     }
 
     // Pass the configuration to the oracle constructor
-    let oracles = FuzzingOracles::new(config.oracle_selection.clone());
+    let oracles = config.oracles().unwrap_or_else(|e| panic!("{}", e));
     
     let gt_calculators = [
-        PyTorchGroundTruthCalculator,
+        DefaultGroundTruth::default().boxed(),
     ];
     
     for test_def in test_definitions {
-        let evaluator = RpnEvaluator { definition: test_def };
+        let evaluator = RpnEvaluator::new(test_def, inputs.len());
         
-        run_ad_tests(inputs.clone(), evaluator, &oracles, &gt_calculators, config.mode); 
+        if let Err(e) = run_ad_tests(&inputs, evaluator, &oracles, &gt_calculators).and_then(|outcome| outcome.into_result()) {
+            if let HarnessMode::PanicOnFirstError = config.mode {
+                panic!("Oracle check failed: {}", e);
+            }
+            eprintln!("Oracle check failed: {}", e);
+        }
     }
 });
\ No newline at end of file
@@ -0,0 +1,23 @@
+// fuzz/fuzz_target_ab.rs
+
+// A/B mode: the version of ad_trait under test against the baseline of the `ab` feature
+// (`ad_trait_old`), e.g. a release candidate against the last release. The baseline's Reverse
+// and Forward AD are the only ground truths and the `ab` oracle is always on; otherwise the same
+// pipeline and config as fuzz_target_ast.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+mod ast_pipeline;
+
+use fuzz_core::ab_harness::BaselineGroundTruth;
+use fuzz_core::fuzz_harness::{FuzzConfig, GroundTruthCalculator};
+use fuzz_core::oracles::OracleSelection;
+
+fn ab_config(config: &mut FuzzConfig) {
+    config.oracle_selection |= OracleSelection::AB_VERSION;
+}
+
+fuzz_target!(|data: &[u8]| {
+    ast_pipeline::run_input_with(data, &[BaselineGroundTruth::reverse().boxed(), BaselineGroundTruth::forward().boxed()], ab_config);
+});
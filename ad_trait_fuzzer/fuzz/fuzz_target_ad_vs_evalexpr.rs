@@ -0,0 +1,17 @@
+// fuzz/fuzz_target_ad_vs_evalexpr.rs
+
+// Reverse/Forward AD checked against evalexpr-jit's symbolic derivatives, with no PyTorch call
+// in the loop. When fuzz_target_ast finds a mismatch, this tells whether ad_trait or the
+// PyTorch side is off. Same config, oracles and reports as fuzz_target_ast.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+mod ast_pipeline;
+
+use fuzz_core::fuzz_harness::GroundTruthCalculator;
+use fuzz_core::gt_calculators::EvalexprJitCalculator;
+
+fuzz_target!(|data: &[u8]| {
+    ast_pipeline::run_input(data, &[EvalexprJitCalculator.boxed()]);
+});
@@ -0,0 +1,16 @@
+// fuzz/fuzz_target_ast_afl.rs
+
+// AFL++ entry point for the AST pipeline:
+//   cargo afl build --no-default-features --features afl --bin fuzz_target_ast_afl
+//   cargo afl fuzz -i corpus/fuzz_target_ast -o afl_out target/debug/fuzz_target_ast_afl
+
+mod ast_pipeline;
+
+use fuzz_core::fuzz_harness::GroundTruthCalculator;
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+
+fn main() {
+    afl::fuzz!(|data: &[u8]| {
+        ast_pipeline::run_input(data, &[DefaultGroundTruth::default().boxed()]);
+    });
+}
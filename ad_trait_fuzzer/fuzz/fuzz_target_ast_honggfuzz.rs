@@ -0,0 +1,17 @@
+// fuzz/fuzz_target_ast_honggfuzz.rs
+
+// honggfuzz entry point for the AST pipeline:
+//   HFUZZ_BUILD_ARGS="--no-default-features --features honggfuzz" cargo hfuzz run fuzz_target_ast_honggfuzz
+
+mod ast_pipeline;
+
+use fuzz_core::fuzz_harness::GroundTruthCalculator;
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+
+fn main() {
+    loop {
+        honggfuzz::fuzz!(|data: &[u8]| {
+            ast_pipeline::run_input(data, &[DefaultGroundTruth::default().boxed()]);
+        });
+    }
+}
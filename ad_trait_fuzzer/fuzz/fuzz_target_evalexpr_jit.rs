@@ -3,63 +3,79 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
 use std::env;
-use std::error::Error;
+use std::sync::Once;
 
-use fuzz_core::input_decoder::{GeneralInputDecoder, FuzzInputDecoder};
+use fuzz_core::input_decoder::{FuzzInputSplitter, SanitizedInputDecoder};
 use fuzz_core::ast_evaluator::unified::EvalexprPyUnified;
 use fuzz_core::ast_generator::{generate_from_bytes, AstGenConfig};
-use fuzz_core::fuzz_harness::PyTorchComputable;
-use fuzz_core::oracles::{EvalexprVsPyTorchCheck, GroundTruth};
+use fuzz_core::error::FuzzError;
+use fuzz_core::expr_coverage;
+use fuzz_core::fuzz_harness::{catch_backend_panic, set_eval_timeout, with_timeout, FuzzConfig, PyTorchComputable};
+use fuzz_core::oracles::{EvalexprVsPyTorchCheck, GroundTruth, NanPolicy};
 use tch::{Tensor, Kind};
 
 const NUM_GENERATED_TESTS: usize = 1;
 
 fuzz_target!(|data: &[u8]| {
+    // Only the evaluation timeout comes from the campaign config here
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let config = FuzzConfig::load().unwrap_or_else(|e| panic!("{}", e));
+        set_eval_timeout(config.eval_timeout);
+    });
+
+    let nan_policy: NanPolicy = env::var("ORACLE_NAN_POLICY").ok().and_then(|s| s.parse().ok()).unwrap_or_default();
+
     let ast_config = {
         let max_depth = env::var("AST_MAX_DEPTH").ok().and_then(|s| s.parse().ok()).unwrap_or(4);
         let allow_division = env::var("AST_ALLOW_DIVISION").map(|s| s.eq_ignore_ascii_case("true")).unwrap_or(true);
         let allow_power = env::var("AST_ALLOW_POWER").map(|s| s.eq_ignore_ascii_case("true")).unwrap_or(true);
         let allow_log = env::var("AST_ALLOW_LOG").map(|s| s.eq_ignore_ascii_case("true")).unwrap_or(false);
+        let allow_cast = env::var("AST_ALLOW_CAST").map(|s| s.eq_ignore_ascii_case("true")).unwrap_or(false);
         let max_variables = env::var("AST_MAX_VARIABLES").ok().and_then(|s| s.parse().ok()).unwrap_or(2);
 
-        AstGenConfig { max_depth, max_variables, allow_division, allow_power, allow_log }
+        AstGenConfig { max_depth, max_variables, allow_division, allow_power, allow_log, allow_cast, ..AstGenConfig::default() }
     };
 
     let num_variables = ast_config.max_variables;
-    let input_decoder = GeneralInputDecoder { input_length: num_variables };
-    let min_data_size = num_variables * 8;
-    if data.len() < min_data_size {
-        return;
-    }
-    let inputs: Vec<f64> = match input_decoder.decode(&data[0..min_data_size]) {
-        Ok(inputs) => inputs,
+    // Finite and within 1e-6..1e6 in magnitude by construction, either sign
+    let input_decoder = SanitizedInputDecoder {
+        input_length: num_variables,
+        min_magnitude: 1e-6,
+        max_magnitude: 1e6,
+        max_magnitude_first: 1e6,
+        positive_first: false,
+        special_values: false,
+    };
+    let (inputs, ast_data) = match FuzzInputSplitter::default().decode(&input_decoder, data) {
+        Ok(decoded) => decoded,
         Err(_) => return,
     };
-    
-    // TODO: make all arbitrary inputs finite and reasonable
-    for &val in &inputs {
-        if !val.is_finite() || val.abs() > 1e10 {
-            return;
-        }
-    }
 
-    let ast_data = &data[min_data_size..];
     let mut evaluators = Vec::new();
     let mut used_vars_list = Vec::new();
     for i in 0..NUM_GENERATED_TESTS {
         let offset = i * 32;
         let test_data = if offset < ast_data.len() { &ast_data[offset..] } else { ast_data };
         if let Ok(generated_expr) = generate_from_bytes(test_data, ast_config.clone()) {
-            if let Ok(evaluator) = EvalexprPyUnified::new(generated_expr.expr, generated_expr.num_inputs) {
-                used_vars_list.push(generated_expr.num_inputs);
-                evaluators.push(evaluator);
+            expr_coverage::record(&generated_expr.expr);
+            let num_inputs = generated_expr.num_inputs;
+            let expr_string = format!("{:?}", generated_expr.expr);
+            // JIT compilation (including the symbolic derivatives) is the slow part
+            match with_timeout("evalexpr-jit compile", move || EvalexprPyUnified::new(generated_expr.expr, num_inputs)) {
+                Ok(Ok(evaluator)) => {
+                    used_vars_list.push(num_inputs);
+                    evaluators.push(evaluator);
+                }
+                Ok(Err(_)) => {}
+                Err(e @ FuzzError::Timeout { .. }) => eprintln!("Timeout: {}", e),
+                Err(e) => report_crash(&expr_string, &inputs[..num_inputs], &e),
             }
         }
     }
     if evaluators.is_empty() {
         return;
     }
-    let oracle = EvalexprVsPyTorchCheck::new();
     for (evaluator, num_inputs) in evaluators.iter().zip(used_vars_list.iter()) {
         if *num_inputs == 0 {
             continue;
@@ -69,7 +85,10 @@ fuzz_target!(|data: &[u8]| {
         for &val in test_inputs {
             tensors.push(Tensor::from(val).set_requires_grad(true).to_kind(Kind::Double));
         }
-        let outputs = evaluator.compute_pytorch(&tensors).unwrap_or_default();
+        let outputs = match catch_backend_panic("PyTorch", || evaluator.compute_pytorch(&tensors)) {
+            Ok(outputs) => outputs.unwrap_or_default(),
+            Err(e) => report_crash(&evaluator.expr_string(), test_inputs, &e),
+        };
         if outputs.is_empty() || outputs[0].numel() != 1 {
             continue;
         }
@@ -83,18 +102,36 @@ fuzz_target!(|data: &[u8]| {
             let grad = if grad_tensor.numel() > 0 { grad_tensor.double_value(&[]) } else { 0.0 };
             pytorch_jacobian.push(grad);
         }
-        let ground_truth = GroundTruth { name: "PyTorch", jacobian: pytorch_jacobian };
-        if let Err(e) = oracle.check_all(evaluator.evalexpr(), test_inputs, &[ground_truth]) {
-            eprintln!("\n=== CRASH DETECTED ===");
-            eprintln!("Expression that caused the mismatch:");
-            eprintln!("  {}", evaluator.expr_string());
-            eprintln!("\nInputs:");
-            for (i, &val) in test_inputs.iter().enumerate() {
-                eprintln!("  x_{}: {}", i, val);
+        let value = outputs[0].double_value(&[]);
+        let ground_truth = GroundTruth { name: "PyTorch", jacobian: pytorch_jacobian, value: Some(value), hessian: None };
+        let (jit, jit_inputs) = (evaluator.clone(), test_inputs.to_vec());
+        let checked = with_timeout("evalexpr-jit", move || {
+            EvalexprVsPyTorchCheck::new().nan_policy(nan_policy).check_all(jit.evalexpr(), &jit_inputs, &[ground_truth])
+        });
+        let result = match checked {
+            Ok(result) => result,
+            Err(e @ FuzzError::Timeout { .. }) => {
+                eprintln!("Timeout: {} for {}", e, evaluator.expr_string());
+                continue;
             }
-            eprintln!("\nError: {}", e);
-            eprintln!("======================\n");
-            panic!("Derivative mismatch: {}", e);
+            // A caught backend panic is reported like a mismatch
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            report_crash(&evaluator.expr_string(), test_inputs, &e);
         }
     }
 });
+
+fn report_crash(expr: &str, inputs: &[f64], e: &FuzzError) -> ! {
+    eprintln!("\n=== CRASH DETECTED ===");
+    eprintln!("Expression that caused the mismatch:");
+    eprintln!("  {}", expr);
+    eprintln!("\nInputs:");
+    for (i, &val) in inputs.iter().enumerate() {
+        eprintln!("  x_{}: {}", i, val);
+    }
+    eprintln!("\nError ({}): {}", e.kind(), e);
+    eprintln!("======================\n");
+    panic!("Derivative mismatch: {}", e);
+}
@@ -0,0 +1,29 @@
+// fuzz/fuzz_target_extreme.rs
+
+// Inputs from `ExtremeInputDecoder`: subnormals, values near f64::MAX and a few ulps either side
+// of the exp / x^2 / x^3 overflow thresholds, to probe gradient overflow and underflow handling.
+// Otherwise the same pipeline and config as fuzz_target_ast.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+mod ast_pipeline;
+
+use fuzz_core::fuzz_harness::{FuzzConfig, GroundTruthCalculator};
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+use fuzz_core::input_decoder::InputDecoding;
+
+fn extreme_config(config: &mut FuzzConfig) {
+    config.inputs.decoder = InputDecoding::Extreme;
+    config.inputs.max_abs_first = f64::MAX;
+    config.inputs.max_abs = f64::MAX;
+    config.inputs.allow_non_positive = true;
+    // Rounding near the thresholds is amplified; only relative error means anything at these
+    // magnitudes, and tiny gradients must still be compared. ORACLE_* variables still apply.
+    config.tolerances.rel_tol = Some(1e-6);
+    config.tolerances.abs_tol = Some(f64::MIN_POSITIVE);
+}
+
+fuzz_target!(|data: &[u8]| {
+    ast_pipeline::run_input_with(data, &[DefaultGroundTruth::default().boxed()], extreme_config);
+});
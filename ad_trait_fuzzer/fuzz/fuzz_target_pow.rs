@@ -0,0 +1,25 @@
+// fuzz/fuzz_target_pow.rs
+
+// Pow-only expressions (`Grammar::Pow`): negative bases, fractional exponents, 0^0, huge
+// exponents. powf's derivative rules cause most AD discrepancies, and the general grammar
+// rarely nests enough of them. Otherwise the same pipeline and config as fuzz_target_ast.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+mod ast_pipeline;
+
+use fuzz_core::ast_generator::Grammar;
+use fuzz_core::fuzz_harness::{FuzzConfig, GroundTruthCalculator};
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+
+fn pow_config(config: &mut FuzzConfig) {
+    config.ast.grammar = Grammar::Pow;
+    config.ast.allow_power = true;
+    // Negative bases are the point here
+    config.inputs.allow_non_positive = true;
+}
+
+fuzz_target!(|data: &[u8]| {
+    ast_pipeline::run_input_with(data, &[DefaultGroundTruth::default().boxed()], pow_config);
+});
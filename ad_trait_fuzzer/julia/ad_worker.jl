@@ -0,0 +1,52 @@
+# julia/ad_worker.jl
+#
+# Long-lived Julia worker used by `SubprocessJuliaCalculator`. The AD package is the first
+# argument: `julia ad_worker.jl forwarddiff` (the default) or `julia ad_worker.jl zygote`.
+# Reads one request per line on stdin and writes one response per line on stdout.
+#
+# Request:  <JuliaPrinter function, e.g. "x -> sin(x[1]) * x[2]">\t<x_0> <x_1> ...
+# Response: ok <value> <d/dx_0> <d/dx_1> ...   or   error <message>
+#
+# Plain text rather than JSON so the worker needs nothing but the AD package; numbers are
+# Julia reprs (NaN, Inf, -Inf), which Rust parses as well.
+
+const BACKEND = isempty(ARGS) ? "forwarddiff" : lowercase(ARGS[1])
+
+if BACKEND == "zygote"
+    using Zygote
+elseif BACKEND == "forwarddiff"
+    using ForwardDiff
+else
+    error("unknown AD backend '$(BACKEND)', expected forwarddiff or zygote")
+end
+
+function gradient_of(f, x)
+    if BACKEND == "zygote"
+        g = Zygote.gradient(f, x)[1]
+        # `nothing` when the result does not depend on x at all
+        return g === nothing ? zeros(length(x)) : [v === nothing ? 0.0 : Float64(v) for v in g]
+    else
+        return ForwardDiff.gradient(f, x)
+    end
+end
+
+function handle(line)
+    source, inputs = split(line, '\t')
+    f = eval(Meta.parse(source))
+    x = [parse(Float64, s) for s in split(inputs)]
+    # `f` was defined after this function was compiled
+    value = Base.invokelatest(f, x)
+    gradient = Base.invokelatest(gradient_of, f, x)
+    return join(["ok"; repr(Float64(value)); [repr(Float64(g)) for g in gradient]], ' ')
+end
+
+for line in eachline(stdin)
+    isempty(strip(line)) && continue
+    response = try
+        handle(line)
+    catch e
+        "error " * replace(sprint(showerror, e), '\n' => ' ')
+    end
+    println(response)
+    flush(stdout)
+end
@@ -0,0 +1,97 @@
+# python/pytorch_worker.py
+#
+# Long-lived PyTorch worker used by `SubprocessPyTorchCalculator`.
+# Reads one JSON request per line on stdin and writes one JSON response per line on stdout.
+#
+# Request:  {"expr": "<infix expression>", "inputs": ["1.5", "-2.0", ...], "hessian": false}
+# Response: {"value": "1.25", "gradients": ["0.5", "nan", ...]}  or  {"error": "<message>"}
+#           With "hessian": true the response also has "hessian": [["2.0", "0.0"], ...] (row i = d/dx_i grad)
+#
+# Floats travel as strings so NaN/Inf survive the trip (plain JSON has no encoding for them).
+# Non-finite constants in the expression are printed as `nan` and `inf` (`-inf` is a negation).
+
+import json
+import sys
+
+import torch
+
+NAMESPACE = {
+    "sin": torch.sin,
+    "cos": torch.cos,
+    "tan": torch.tan,
+    "exp": torch.exp,
+    "ln": torch.log,
+    "log": torch.log,
+    "sqrt": torch.sqrt,
+    "abs": torch.abs,
+    "trunc": torch.trunc,
+    "bool": lambda t: (t != 0).to(torch.float64),
+    "nan": torch.tensor(float("nan"), dtype=torch.float64),
+    "inf": torch.tensor(float("inf"), dtype=torch.float64),
+}
+
+
+def handle(request):
+    inputs = [
+        torch.tensor(float(v), dtype=torch.float64, requires_grad=True)
+        for v in request["inputs"]
+    ]
+    env = dict(NAMESPACE)
+    for i, t in enumerate(inputs):
+        env["x_{}".format(i)] = t
+
+    # The infix printer uses `^` for pow; Python spells it `**`.
+    source = request["expr"].replace("^", "**")
+    output = eval(source, {"__builtins__": {}}, env)
+
+    if not isinstance(output, torch.Tensor):
+        return {"value": repr(float(output)), "gradients": ["0.0"] * len(inputs)}
+
+    value = repr(output.item())
+    if not output.requires_grad:
+        response = {"value": value, "gradients": ["0.0"] * len(inputs)}
+        if request.get("hessian"):
+            response["hessian"] = [["0.0"] * len(inputs) for _ in inputs]
+        return response
+
+    if request.get("hessian"):
+        return with_hessian(output, inputs, value)
+
+    output.backward()
+    gradients = []
+    for t in inputs:
+        gradients.append(repr(t.grad.item()) if t.grad is not None else "0.0")
+    return {"value": value, "gradients": gradients}
+
+
+def with_hessian(output, inputs, value):
+    # Double backward: keep the graph of the first gradients and differentiate each of them again.
+    grads = torch.autograd.grad(output, inputs, create_graph=True, allow_unused=True)
+    hessian = []
+    for g in grads:
+        if g is None or not g.requires_grad:
+            hessian.append(["0.0"] * len(inputs))
+            continue
+        row = torch.autograd.grad(g, inputs, retain_graph=True, allow_unused=True)
+        hessian.append([repr(r.item()) if r is not None else "0.0" for r in row])
+    gradients = [repr(g.item()) if g is not None else "0.0" for g in grads]
+    return {"value": value, "gradients": gradients, "hessian": hessian}
+
+
+def main():
+    # One response per "\n"-terminated line, also where text mode would write "\r\n"
+    sys.stdout.reconfigure(newline="\n")
+    for line in sys.stdin:
+        line = line.strip()
+        if not line:
+            continue
+        try:
+            response = handle(json.loads(line))
+        except Exception as e:
+            response = {"error": "{}: {}".format(type(e).__name__, e)}
+        sys.stdout.write(json.dumps(response) + "\n")
+        sys.stdout.flush()
+
+
+if __name__ == "__main__":
+    main()
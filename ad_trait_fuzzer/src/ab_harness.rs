@@ -0,0 +1,144 @@
+// src/ab_harness.rs
+
+// A/B mode: the same function differentiated by a second ad_trait, the renamed dependency
+// `ad_trait_old` of the `ab` feature (point it at the last release, or at any checkout), so
+// release candidates can be fuzzed against it. The baseline's Reverse and Forward AD run as two
+// ground truths, `BaselineGroundTruth::reverse()` and `forward()`, and the `ab` oracle
+// (`oracles::AbVersionCheck`) compares each engine of the version under test to its own
+// counterpart. The baseline evaluates the expression parsed back from `infix_expr` over its AD
+// types, wrapped in `Baseline`: the blanket `MainBackend` impl only covers the current version's
+// `AD`.
+
+use ad_trait_old::differentiable_function::{DifferentiableFunctionTrait, ForwardAD, ReverseAD};
+use ad_trait_old::forward_ad::adfn::adfn;
+use ad_trait_old::function_engine::FunctionEngine;
+use ad_trait_old::reverse_ad::adr::adr;
+use ad_trait_old::AD;
+
+use std::sync::Arc;
+
+use crate::ast_evaluator::{evaluate, Env, MainBackend};
+use crate::ast_expr::{SimpleExpr, Type};
+use crate::ast_parser::parse_infix;
+use crate::error::FuzzError;
+use crate::fuzz_harness::{GroundTruthCalculator, PyTorchComputable};
+use crate::oracles::{ADType, GroundTruth, BASELINE_FORWARD, BASELINE_REVERSE};
+
+/// A value of the baseline ad_trait, evaluated like the current version's types are.
+#[derive(Clone, Debug)]
+pub struct Baseline<T>(pub T);
+
+impl<T: AD> MainBackend for Baseline<T> {
+    fn from_f64(val: f64) -> Self { Baseline(T::constant(val)) }
+    fn zero() -> Self { Baseline(T::zero()) }
+    fn one() -> Self { Baseline(T::one()) }
+
+    fn neg(self) -> Self { Baseline(-self.0) }
+    fn sin(self) -> Self { Baseline(self.0.sin()) }
+    fn cos(self) -> Self { Baseline(self.0.cos()) }
+    fn tan(self) -> Self { Baseline(self.0.tan()) }
+    fn exp(self) -> Self { Baseline(self.0.exp()) }
+    fn log(self) -> Self { Baseline(self.0.ln()) }
+    fn sqrt(self) -> Self { Baseline(self.0.sqrt()) }
+    fn abs(self) -> Self { Baseline(self.0.abs()) }
+
+    fn add(self, other: Self) -> Self { Baseline(self.0 + other.0) }
+    fn sub(self, other: Self) -> Self { Baseline(self.0 - other.0) }
+    fn mul(self, other: Self) -> Self { Baseline(self.0 * other.0) }
+    fn div(self, other: Self) -> Self { Baseline(self.0 / other.0) }
+    fn pow(self, other: Self) -> Self { Baseline(self.0.powf(other.0)) }
+
+    // Same lowering as the current version's backend
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => Baseline(self.0.trunc()),
+            Type::Bool => if self.0.to_constant() != 0.0 { Self::one() } else { Self::zero() },
+        }
+    }
+}
+
+/// `SimpleADFunction` for the baseline engines.
+#[derive(Clone)]
+struct BaselineFunction<T: AD> {
+    placeholder: T,
+    expr: Arc<SimpleExpr>,
+    num_inputs: usize,
+}
+
+impl<T: AD> DifferentiableFunctionTrait<T> for BaselineFunction<T> {
+    const NAME: &'static str = "BaselineFunc";
+    fn call(&self, inputs: &[T], _freeze: bool) -> Vec<T> {
+        let env: Env<Baseline<T>> = inputs.iter().enumerate().map(|(i, x)| (format!("x_{}", i), Baseline(*x))).collect();
+        match evaluate(&self.expr, &env) {
+            Ok(Baseline(result)) => vec![result],
+            Err(e) => panic!("Error during baseline AD evaluation: {}", e),
+        }
+    }
+
+    fn num_inputs(&self) -> usize { self.num_inputs }
+    fn num_outputs(&self) -> usize { 1 }
+}
+
+impl<T: AD> BaselineFunction<T> {
+    fn to_other_ad_type<T2: AD>(&self) -> BaselineFunction<T2> {
+        BaselineFunction { placeholder: self.placeholder.to_other_ad_type::<T2>(), expr: self.expr.clone(), num_inputs: self.num_inputs }
+    }
+}
+
+/// Ground Truth from one engine of the baseline ad_trait. Single-output functions with an
+/// infix expression only, like the other referees that rebuild the expression.
+#[derive(Clone)]
+pub struct BaselineGroundTruth {
+    pub ad_type: ADType,
+}
+
+impl BaselineGroundTruth {
+    pub fn reverse() -> Self {
+        BaselineGroundTruth { ad_type: ADType::Reverse }
+    }
+
+    pub fn forward() -> Self {
+        BaselineGroundTruth { ad_type: ADType::Forward }
+    }
+}
+
+impl GroundTruthCalculator for BaselineGroundTruth {
+    fn name(&self) -> &'static str {
+        match self.ad_type {
+            ADType::Reverse => BASELINE_REVERSE,
+            ADType::Forward => BASELINE_FORWARD,
+        }
+    }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("the baseline ad_trait only runs single-output functions".to_string()));
+        }
+        let infix = calc.infix_expr()
+            .ok_or_else(|| FuzzError::UnsupportedExpr("Calculator does not expose an infix expression".to_string()))?;
+        let (expr, _) = parse_infix(&infix)?;
+        // An expression that can't be evaluated is an error here, not a panic in the engine
+        let env: Env<f64> = inputs.iter().enumerate().map(|(i, x)| (format!("x_{}", i), *x)).collect();
+        evaluate(&expr, &env)?;
+
+        let func_standard = BaselineFunction { placeholder: 0.0, expr: Arc::new(expr), num_inputs: inputs.len() };
+        let (values, jacobian) = match self.ad_type {
+            ADType::Reverse => {
+                let func_derivative = func_standard.to_other_ad_type::<adr>();
+                FunctionEngine::new(func_standard, func_derivative, ReverseAD::new()).derivative(inputs)
+            }
+            ADType::Forward => {
+                let func_derivative = func_standard.to_other_ad_type::<adfn<1>>();
+                FunctionEngine::new(func_standard, func_derivative, ForwardAD::new()).derivative(inputs)
+            }
+        };
+        Ok(GroundTruth {
+            name: self.name(),
+            // One output: the Jacobian is a single row in either storage order
+            jacobian: jacobian.iter().copied().collect(),
+            value: values.first().copied(),
+            hessian: None,
+        })
+    }
+}
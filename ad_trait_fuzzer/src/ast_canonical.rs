@@ -0,0 +1,139 @@
+// src/ast_canonical.rs
+
+// One representative per family of expressions that compute bit-for-bit the same value and
+// gradient, so failure dedup (`failure_classifier::bucket_key`) groups `x_0 * (2 + 1)`,
+// `3 * x_0`, and `x_1 - x_0` with `-x_0 + x_1`. Every step is exact in IEEE arithmetic:
+//
+// - constants are folded (`partial_eval`, which also inlines `Let`);
+// - `a - b` becomes `a + -b`, and negations move out of products and quotients,
+//   `-a * b` becomes `-(a * b)` (rounding is symmetric);
+// - the identities of `RuleSet::simplify` are applied, plus `a + -0 = a`;
+// - negative constants factors leave products and quotients the same way, `x * -3` is `-(x * 3)`;
+// - the operands of `+` and `*` are sorted by their `expr_codec` encoding.
+//
+// Associativity is *not* used: `(a + b) + c` and `a + (b + c)` round differently.
+
+use std::sync::OnceLock;
+
+use crate::ast_evaluator::{partial_eval, Env};
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr};
+use crate::ast_rewrite::RuleSet;
+use crate::ast_visitor::{fold_children, ExprFolder};
+use crate::expr_codec::{encode, expr_hash};
+
+const CANONICAL_RULES: &str = "\
+sub_to_add: (- ?a ?b) => (+ ?a (neg ?b))
+add_neg_zero: (+ ?a -0) => ?a
+neg_mul_left: (* (neg ?a) ?b) => (neg (* ?a ?b))
+neg_mul_right: (* ?a (neg ?b)) => (neg (* ?a ?b))
+neg_div_left: (/ (neg ?a) ?b) => (neg (/ ?a ?b))
+neg_div_right: (/ ?a (neg ?b)) => (neg (/ ?a ?b))
+";
+
+/// Fold/rewrite rounds; each can expose constants or patterns for the next.
+const MAX_ROUNDS: usize = 4;
+
+fn rules() -> &'static RuleSet {
+    static RULES: OnceLock<RuleSet> = OnceLock::new();
+    RULES.get_or_init(|| RuleSet::simplify().extend(RuleSet::parse(CANONICAL_RULES).expect("built-in rules parse")))
+}
+
+/// The canonical form of `expr`, see the top of the file.
+pub fn canonicalize(expr: &SimpleExpr) -> SimpleExpr {
+    let fold = |e: &SimpleExpr| partial_eval(e, &Env::new());
+    // Nodes `evaluate` rejects (`If`, `Loop`, ...) are not folded or rewritten
+    let mut current = match fold(expr) {
+        Ok(folded) => folded,
+        Err(_) => return FinalForm.fold(expr.clone()),
+    };
+    for _ in 0..MAX_ROUNDS {
+        let rewritten = rules().rewrite(&current);
+        let next = fold(&rewritten).unwrap_or(rewritten);
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+    FinalForm.fold(current)
+}
+
+/// Structural hash of the canonical form: equal for expressions `canonicalize` identifies, and
+/// stable across runs and format versions, see `expr_hash`.
+pub fn canonical_hash(expr: &SimpleExpr) -> u64 {
+    expr_hash(&canonicalize(expr))
+}
+
+/// A constant with the sign bit set, which folding produced from a negation.
+fn negative_constant(expr: &SimpleExpr) -> Option<f64> {
+    match expr {
+        Expr::Number(_, n) if n.is_sign_negative() && !n.is_nan() => Some(-n),
+        _ => None,
+    }
+}
+
+/// Bottom-up: negative constant factors out of `*` and `/`, double negations removed, then the
+/// operands of `+` and `*` ordered.
+struct FinalForm;
+
+impl ExprFolder<()> for FinalForm {
+    fn fold(&mut self, expr: SimpleExpr) -> SimpleExpr {
+        match fold_children(self, expr) {
+            Expr::BinOp(t, op @ (Op2::Mul | Op2::Div), l, r) if negative_constant(&l).is_some() || negative_constant(&r).is_some() => {
+                // Two negative constants cancel
+                let flips = negative_constant(&l).is_some() != negative_constant(&r).is_some();
+                let positive = |e: Box<SimpleExpr>| Box::new(negative_constant(&e).map_or(*e, SimpleExpr::num));
+                let product = self.fold(Expr::BinOp(t, op, positive(l), positive(r)));
+                if flips { self.fold(SimpleExpr::neg(product)) } else { product }
+            }
+            Expr::UnOp(_, Op1::Neg, inner) => match *inner {
+                Expr::UnOp(_, Op1::Neg, e) => *e,
+                inner => SimpleExpr::neg(inner),
+            },
+            Expr::BinOp(t, op, l, r) if matches!(op, Op2::Add | Op2::Mul) && encode(&*l) > encode(&*r) => {
+                Expr::BinOp(t, op, r, l)
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::{evaluate, SExprPrinter};
+    use crate::ast_parser::parse_sexpr;
+
+    fn canonical(src: &str) -> String {
+        SExprPrinter::print(&canonicalize(&parse_sexpr(src).unwrap().0), 2)
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let same = [
+            ["(* x_0 (+ 2 1))", "(* 3 (* x_0 1))"],
+            ["(- x_1 x_0)", "(+ (neg x_0) x_1)"],
+            ["(* (neg x_0) (sin x_1))", "(neg (* (sin x_1) x_0))"],
+            ["(- x_0 0)", "(+ x_0 -0)"],
+            ["(/ x_0 (neg (- 4 1)))", "(neg (/ x_0 3))"],
+            ["(* (- 0 2) (neg x_0))", "(* x_0 2)"],
+        ];
+        for [a, b] in same {
+            assert_eq!(canonical(a), canonical(b), "{} vs {}", a, b);
+            assert_eq!(canonical_hash(&parse_sexpr(a).unwrap().0), canonical_hash(&parse_sexpr(b).unwrap().0));
+        }
+        assert_eq!(canonical("(- x_0 0)"), "x_0");
+        // Not associativity
+        assert_ne!(canonical("(+ (+ x_0 x_1) 1)"), canonical("(+ x_0 (+ x_1 1))"));
+        assert_ne!(canonical_hash(&parse_sexpr("(sin x_0)").unwrap().0), canonical_hash(&parse_sexpr("(cos x_0)").unwrap().0));
+
+        // Same values, bit for bit
+        for [a, b] in same {
+            for x in [[0.3, -2.0], [-0.0, 0.0], [f64::INFINITY, 1e-300]] {
+                let env: Env<f64> = x.iter().enumerate().map(|(i, v)| (format!("x_{}", i), *v)).collect();
+                let original: f64 = evaluate(&parse_sexpr(a).unwrap().0, &env).unwrap();
+                let canonical: f64 = evaluate(&canonicalize(&parse_sexpr(b).unwrap().0), &env).unwrap();
+                assert_eq!(original.to_bits(), canonical.to_bits(), "{} at {:?}", a, x);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// src/ast_dead_code.rs
+
+// Dead `Let` bindings: a binding whose variable the body never reads is still computed by
+// engines that walk the tree (the AD tape records it), but disappears from the infix string
+// evalexpr-jit compiles, so the engines would differentiate different programs. Removing them
+// first keeps every engine on the same computation.
+
+use crate::ast_expr::Expr;
+use crate::ast_visitor::{children, fold_children, ExprFolder};
+
+/// Whether `name` is read (or assigned by `Set`) somewhere in `expr` where it is not shadowed by
+/// an inner `Let`.
+fn uses<Tag>(expr: &Expr<Tag>, name: &str) -> bool {
+    match expr {
+        Expr::Id(_, id) => id == name,
+        Expr::Set(_, id, value) => id == name || uses(value, name),
+        Expr::Let(_, bindings, body) => {
+            // Values see the outer scope, the body sees the bindings
+            bindings.iter().any(|(_, value)| uses(value, name))
+                || (!bindings.iter().any(|(bound, _)| bound == name) && uses(body, name))
+        }
+        _ => children(expr).into_iter().any(|child| uses(child, name)),
+    }
+}
+
+/// Whether evaluating `expr` can do more than produce a value.
+fn has_effects<Tag>(expr: &Expr<Tag>) -> bool {
+    matches!(expr, Expr::Set(..) | Expr::Break(..)) || children(expr).into_iter().any(has_effects)
+}
+
+struct DeadBindings;
+
+impl<Tag> ExprFolder<Tag> for DeadBindings {
+    fn fold(&mut self, expr: Expr<Tag>) -> Expr<Tag> {
+        match fold_children(self, expr) {
+            Expr::Let(t, bindings, body) => {
+                let names: Vec<String> = bindings.iter().map(|(name, _)| name.clone()).collect();
+                // A later binding of the same name hides this one from the body
+                let live: Vec<(String, Expr<Tag>)> = bindings.into_iter().enumerate()
+                    .filter(|(i, (name, value))| {
+                        has_effects(value) || (!names[i + 1..].contains(name) && uses(&body, name))
+                    })
+                    .map(|(_, binding)| binding)
+                    .collect();
+                if live.is_empty() { *body } else { Expr::Let(t, live, body) }
+            }
+            other => other,
+        }
+    }
+}
+
+/// `expr` without the `Let` bindings nothing reads, see the top of the file. A `Let` left with no
+/// bindings becomes its body. Bindings with effects (`Set`, `Break`) are kept.
+pub fn eliminate_dead_bindings<Tag>(expr: Expr<Tag>) -> Expr<Tag> {
+    DeadBindings.fold(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::SExprPrinter;
+    use crate::ast_expr::SimpleExpr;
+
+    fn binding(name: &str, value: SimpleExpr) -> (String, SimpleExpr) {
+        (name.to_string(), value)
+    }
+
+    #[test]
+    fn test_eliminate_dead_bindings() {
+        let x = || SimpleExpr::var("x_0");
+        // `b` is dead, `a` is read
+        let expr = Expr::Let((), vec![binding("a", SimpleExpr::sin(x())), binding("b", SimpleExpr::exp(x()))],
+            Box::new(SimpleExpr::mul(SimpleExpr::var("a"), x())));
+        match eliminate_dead_bindings(expr) {
+            Expr::Let(_, bindings, _) => assert_eq!(bindings, vec![binding("a", SimpleExpr::sin(x()))]),
+            other => panic!("expected a let, got {:?}", other),
+        }
+
+        // Nothing read: just the body. The inner `a` shadows the outer one, which is dead too.
+        let inner = Expr::Let((), vec![binding("a", x())], Box::new(SimpleExpr::var("a")));
+        let expr = Expr::Let((), vec![binding("a", SimpleExpr::cos(x()))], Box::new(inner.clone()));
+        assert_eq!(eliminate_dead_bindings(expr), inner);
+        let unused = Expr::Let((), vec![binding("a", x())], Box::new(SimpleExpr::num(1.0)));
+        assert_eq!(SExprPrinter::print(&eliminate_dead_bindings(unused), 1), "1");
+
+        // Only the last of two bindings of one name is visible
+        let twice = Expr::Let((), vec![binding("a", x()), binding("a", SimpleExpr::sin(x()))], Box::new(SimpleExpr::var("a")));
+        match eliminate_dead_bindings(twice) {
+            Expr::Let(_, bindings, _) => assert_eq!(bindings, vec![binding("a", SimpleExpr::sin(x()))]),
+            other => panic!("expected a let, got {:?}", other),
+        }
+    }
+}
@@ -3,11 +3,12 @@
 // AST -> AD trait
 
 use ad_trait::AD;
-use crate::ast_expr::Expr;
+use crate::ast_expr::{Expr, Type};
+use crate::ast_lower::{lower, CoreExpr};
 use crate::fuzz_harness::Calculator;
-use super::{MainBackend, evaluate};
-use std::collections::HashMap;
-use std::fmt::format;
+use crate::error::FuzzError;
+use std::sync::{Arc, OnceLock};
+use super::{MainBackend, evaluate, Env};
 
 macro_rules! impl_forwarding_ops {
     () => {
@@ -39,28 +40,54 @@ impl<T: AD> MainBackend for T {
     fn one() -> Self { T::one() }
     
     impl_forwarding_ops!();
+
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => self.trunc(),
+            Type::Bool => if self.to_constant() != 0.0 { T::one() } else { T::zero() },
+        }
+    }
 }
 
 /// Evaluator that uses AD types
 #[derive(Clone)]
 pub struct AdEvaluator<Tag: Clone> {
-    pub expr: Expr<Tag>,
+    /// Shared with the other engines of a case, so cloning the evaluator never copies the tree.
+    pub expr: Arc<Expr<Tag>>,
     pub num_inputs: usize,
     pub num_outputs: usize,
+    /// `expr` lowered on the first evaluation and shared by the clones, so the AD passes only
+    /// run the core; `None` if it does not lower.
+    core: Arc<OnceLock<Option<CoreExpr>>>,
+}
+
+impl<Tag: Clone> AdEvaluator<Tag> {
+    pub fn new(expr: Arc<Expr<Tag>>, num_inputs: usize, num_outputs: usize) -> Self {
+        AdEvaluator { expr, num_inputs, num_outputs, core: Arc::new(OnceLock::new()) }
+    }
 }
 
 // specific eval for AD
 impl<Tag: Clone> Calculator for AdEvaluator<Tag> {
     fn eval_expr<T: AD>(&self, inputs: &[T]) -> T {
-        let mut env = HashMap::new();
+        match self.eval_backend(inputs) {
+            Ok(result) => result,
+            Err(e) => panic!("Error during AD evaluation: {}", e)
+        }
+    }
+    
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        let mut env = Env::new();
         for (i, e) in inputs.iter().enumerate()
         {
-            env.insert(format(format_args!("x_{}", i)), e.clone());
+            env.insert(format!("x_{}", i), e.clone());
         }
         
-        match evaluate(&self.expr, &env) {
-            Ok(result) => result,
-            Err(e) => panic!("Error during AD evaluation: {}", e)
+        match self.core.get_or_init(|| lower(self.expr.as_ref()).ok()) {
+            Some(core) => core.eval(&env),
+            // Lowered again for the error
+            None => evaluate(&self.expr, &env),
         }
     }
     
@@ -71,4 +98,9 @@ impl<Tag: Clone> Calculator for AdEvaluator<Tag> {
     fn num_outputs(&self) -> usize {
         self.num_outputs
     }
+
+    // Clones share the expression
+    fn same_function(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.expr, &other.expr) && self.num_inputs == other.num_inputs && self.num_outputs == other.num_outputs
+    }
 }
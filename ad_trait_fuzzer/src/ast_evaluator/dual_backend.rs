@@ -0,0 +1,116 @@
+// src/ast_evaluator/dual_backend.rs
+
+// AST -> in-crate dual numbers
+//
+// A deliberately small forward-mode implementation, independent from ad_trait, so it can be
+// read line by line when the big engines disagree. Each rule is the textbook derivative.
+
+use crate::ast_expr::Type;
+use super::MainBackend;
+
+/// `val + der * eps` with `eps^2 = 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub val: f64,
+    pub der: f64,
+}
+
+impl Dual {
+    pub fn new(val: f64, der: f64) -> Self {
+        Dual { val, der }
+    }
+
+    /// Independent variable: derivative seeded to 1.
+    pub fn variable(val: f64) -> Self {
+        Dual { val, der: 1.0 }
+    }
+
+    pub fn constant(val: f64) -> Self {
+        Dual { val, der: 0.0 }
+    }
+}
+
+impl MainBackend for Dual {
+    fn from_f64(val: f64) -> Self { Dual::constant(val) }
+    fn zero() -> Self { Dual::constant(0.0) }
+    fn one() -> Self { Dual::constant(1.0) }
+
+    fn neg(self) -> Self { Dual::new(-self.val, -self.der) }
+    fn sin(self) -> Self { Dual::new(self.val.sin(), self.val.cos() * self.der) }
+    fn cos(self) -> Self { Dual::new(self.val.cos(), -self.val.sin() * self.der) }
+    fn tan(self) -> Self {
+        let c = self.val.cos();
+        Dual::new(self.val.tan(), self.der / (c * c))
+    }
+    fn exp(self) -> Self {
+        let e = self.val.exp();
+        Dual::new(e, e * self.der)
+    }
+    fn log(self) -> Self { Dual::new(self.val.ln(), self.der / self.val) }
+    fn sqrt(self) -> Self {
+        let s = self.val.sqrt();
+        Dual::new(s, self.der / (2.0 * s))
+    }
+    // Subgradient 0 at the kink, same as torch.abs
+    fn abs(self) -> Self {
+        let sign = if self.val > 0.0 { 1.0 } else if self.val < 0.0 { -1.0 } else { 0.0 };
+        Dual::new(self.val.abs(), sign * self.der)
+    }
+
+    fn add(self, other: Self) -> Self { Dual::new(self.val + other.val, self.der + other.der) }
+    fn sub(self, other: Self) -> Self { Dual::new(self.val - other.val, self.der - other.der) }
+    fn mul(self, other: Self) -> Self {
+        Dual::new(self.val * other.val, self.der * other.val + self.val * other.der)
+    }
+    fn div(self, other: Self) -> Self {
+        let q = self.val / other.val;
+        Dual::new(q, (self.der - q * other.der) / other.val)
+    }
+
+    // d(a^b) = b * a^(b-1) * da + a^b * ln(a) * db
+    // A term is dropped when its seed is exactly zero, so a constant exponent on a negative
+    // base does not pick up ln(a) = NaN.
+    fn pow(self, other: Self) -> Self {
+        let val = self.val.powf(other.val);
+        let mut der = 0.0;
+        if self.der != 0.0 {
+            der += other.val * self.val.powf(other.val - 1.0) * self.der;
+        }
+        if other.der != 0.0 {
+            der += val * self.val.ln() * other.der;
+        }
+        Dual::new(val, der)
+    }
+
+    // Int and Bool are piecewise constant: zero derivative
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => Dual::constant(self.val.trunc()),
+            Type::Bool => Dual::constant(if self.val != 0.0 { 1.0 } else { 0.0 }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_product_and_quotient_rules() {
+        let x = Dual::variable(3.0);
+        let c = Dual::constant(2.0);
+
+        // d/dx (x * x) = 2x
+        assert_eq!(x.mul(x).der, 6.0);
+        // d/dx (2 / x) = -2 / x^2
+        assert!((c.div(x).der + 2.0 / 9.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_pow_constant_exponent_negative_base() {
+        // d/dx x^2 at x = -3 is -6, no NaN from ln(-3)
+        let x = Dual::variable(-3.0);
+        assert_eq!(x.pow(Dual::constant(2.0)).der, -6.0);
+    }
+}
@@ -2,23 +2,28 @@
 
 use super::print_backend::InfixPrinter;
 use crate::ast_expr::Expr;
-use crate::fuzz_harness::PyTorchComputable;
+use crate::ast_lower::lower_blocks;
 use evalexpr_jit::{Equation, backends::vector::Vector};
-use std::error::Error;
+use crate::error::FuzzError;
 use std::sync::Arc;
-use tch::Tensor;
+
+const BACKEND: &str = "evalexpr-jit";
+
+/// A compiled partial derivative, see `EvalexprEvaluator::derivative`.
+pub type DerivativeFn = Arc<dyn Fn(&[f64]) -> f64 + Send + Sync>;
 
 #[derive(Clone)]
 pub struct EvalexprEvaluator<Tag: Clone> {
-    pub expr: Expr<Tag>,
+    pub expr: Arc<Expr<Tag>>,
     pub num_inputs: usize,
     equation: Option<Equation>,
 }
 
 impl<Tag: Clone> EvalexprEvaluator<Tag> {
-    pub fn new(expr: Expr<Tag>, num_inputs: usize) -> Result<Self, Box<dyn Error>> {
-        let expr_str = InfixPrinter::print(&expr, num_inputs);
-        let equation = Equation::new(expr_str)?;
+    pub fn new(expr: impl Into<Arc<Expr<Tag>>>, num_inputs: usize) -> Result<Self, FuzzError> {
+        let expr = expr.into();
+        let expr_str = Self::print(&expr, num_inputs);
+        let equation = Equation::new(expr_str).map_err(|e| FuzzError::backend(BACKEND, e))?;
         
         Ok(EvalexprEvaluator {
             expr,
@@ -28,25 +33,31 @@ impl<Tag: Clone> EvalexprEvaluator<Tag> {
     }
     
     // fixed-size array issues are fixed
-    pub fn eval<V: Vector>(&self, inputs: &V) -> Result<f64, Box<dyn Error>> {
+    pub fn eval<V: Vector>(&self, inputs: &V) -> Result<f64, FuzzError> {
         match &self.equation {
-            Some(eq) => Ok(eq.eval(inputs)?),
-            None => Err("Equation not init".into()),
+            Some(eq) => eq.eval(inputs).map_err(|e| FuzzError::backend(BACKEND, e)),
+            None => Err(FuzzError::backend(BACKEND, "Equation not init")),
         }
     }
     
     /// Compute der with respect to var i
-    pub fn derivative(&self, var_index: usize) -> Result<Arc<dyn Fn(&[f64]) -> f64 + Send + Sync>, Box<dyn Error>> {
+    pub fn derivative(&self, var_index: usize) -> Result<DerivativeFn, FuzzError> {
         match &self.equation {
             Some(eq) => {
                 let var_name = format!("x_{}", var_index);
-                Ok(eq.derivative(&var_name)?.clone())
+                Ok(eq.derivative(&var_name).map_err(|e| FuzzError::backend(BACKEND, e))?.clone())
             },
-            None => Err("Equation not init".into()),
+            None => Err(FuzzError::backend(BACKEND, "Equation not init")),
         }
     }
     
     pub fn expr_string(&self) -> String {
-        InfixPrinter::print(&self.expr, self.num_inputs)
+        Self::print(&self.expr, self.num_inputs)
+    }
+
+    /// The string evalexpr-jit compiles: `Block`/`Set` lowered to `Let`s first, which the infix
+    /// string inlines.
+    fn print(expr: &Expr<Tag>, num_inputs: usize) -> String {
+        InfixPrinter::print(&lower_blocks(expr), num_inputs)
     }
 }
@@ -4,18 +4,26 @@
 // unified interface for evaluating AST expr
 
 use std::collections::HashMap;
-use crate::ast_expr::Expr;
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::ast_lower::lower;
+use crate::error::FuzzError;
 
 pub mod ad_backend;
+#[cfg(feature = "pytorch")]
 pub mod pytorch_backend;
 pub mod unified;
 pub mod print_backend;
 pub mod evalexpr_backend;
+pub mod partial_eval;
+pub mod dual_backend;
 
 pub use ad_backend::AdEvaluator;
+#[cfg(feature = "pytorch")]
 pub use pytorch_backend::PyTorchEvaluator;
-pub use print_backend::{SExprPrinter, SSAPrinter, InfixPrinter};
-pub use evalexpr_backend::{EvalexprEvaluator};
+pub use print_backend::{SExprPrinter, SSAPrinter, InfixPrinter, DotPrinter, PyTorchScriptPrinter, RustReproPrinter, CPrinter, JuliaPrinter, LatexPrinter, Printer, printer, PRINTER_NAMES};
+pub use evalexpr_backend::{DerivativeFn, EvalexprEvaluator};
+pub use partial_eval::{partial_eval, constant_value};
+pub use dual_backend::Dual;
 
 /// env for var bindings during eval
 pub type Env<T> = HashMap<String, T>;
@@ -40,71 +48,42 @@ pub trait MainBackend: Sized + Clone {
     fn div(self, other: Self) -> Self;
     
     fn pow(self, other: Self) -> Self;
+
+    /// Float is the identity, Int truncates toward zero, Bool maps non-zero to 1 and zero to 0.
+    fn cast(self, ty: &Type) -> Self;
+}
+
+/// `op val`, shared by `evaluate` and `RpnEvaluator`.
+pub fn apply_op1<T: MainBackend>(op: &Op1, val: T) -> T {
+    match op {
+        Op1::Neg => val.neg(),
+        Op1::Sin => val.sin(),
+        Op1::Cos => val.cos(),
+        Op1::Tan => val.tan(),
+        Op1::Exp => val.exp(),
+        Op1::Log => val.log(),
+        Op1::Sqrt => val.sqrt(),
+        Op1::Abs => val.abs(),
+    }
 }
 
-/// Generic eval for MainBackend
+/// `left op right`, shared by `evaluate` and `RpnEvaluator`.
+pub fn apply_op2<T: MainBackend>(op: &Op2, left: T, right: T) -> T {
+    match op {
+        Op2::Add => left.add(right),
+        Op2::Sub => left.sub(right),
+        Op2::Mul => left.mul(right),
+        Op2::Div => left.div(right),
+        Op2::Pow => left.pow(right),
+    }
+}
+
+/// Generic eval for MainBackend: `expr` is lowered to the core language first (see
+/// `ast_lower`, which also defines what `Let`, `Block`, `Set`, `If` and `Cast` mean), so every
+/// backend runs the same operations.
 pub fn evaluate<T: MainBackend, Tag>(
     expr: &Expr<Tag>,
     env: &Env<T>,
-) -> Result<T, String> {
-    use crate::ast_expr::{Op1, Op2};
-    match expr {
-        Expr::Number(_, val) => Ok(T::from_f64(*val)),
-        
-        Expr::Boolean(_, _) => Err("Bool not supported in numeric expressions (yet)".to_string()),
-        
-        Expr::Id(_, name) => {
-            env.get(name)
-                .cloned()
-                .ok_or_else(|| format!("Var '{}' not found", name))
-        }
-        
-        Expr::UnOp(_, op, sub_expr) => {
-            let val = evaluate(sub_expr, env)?;
-            Ok(match op {
-                Op1::Neg => val.neg(),
-                Op1::Sin => val.sin(),
-                Op1::Cos => val.cos(),
-                Op1::Tan => val.tan(),
-                Op1::Exp => val.exp(),
-                Op1::Log => val.log(),
-                Op1::Sqrt => val.sqrt(),
-                Op1::Abs => val.abs(),
-            })
-        }
-        
-        Expr::BinOp(_, op, left, right) => {
-            let left_val = evaluate(left, env)?;
-            let right_val = evaluate(right, env)?;
-            Ok(match op {
-                Op2::Add => left_val.add(right_val),
-                Op2::Sub => left_val.sub(right_val),
-                Op2::Mul => left_val.mul(right_val),
-                Op2::Div => left_val.div(right_val),
-                Op2::Pow => left_val.pow(right_val),
-            })
-        }
-        
-        Expr::Let(_, bindings, body) => {
-            let mut new_env = env.clone();
-            for (name, expr) in bindings {
-                let val = evaluate(expr, env)?;
-                new_env.insert(name.clone(), val);
-            }
-            evaluate(body, &new_env)
-        }
-        
-        Expr::Block(_, exprs) => {
-            if exprs.is_empty() {
-                return Ok(T::zero());
-            }
-            let mut result = T::zero();
-            for expr in exprs {
-                result = evaluate(expr, env)?;
-            }
-            Ok(result)
-        }
-        
-        _ => Err("Unsupported expression type".to_string()),
-    }
+) -> Result<T, FuzzError> {
+    lower(expr)?.eval(env)
 }
@@ -0,0 +1,130 @@
+// src/ast_evaluator/partial_eval.rs
+
+// Constant folding / partial evaluation via MainBackend
+
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::ast_visitor::for_each_node;
+use crate::error::FuzzError;
+use super::{MainBackend, evaluate, Env};
+
+/// Either a value known at fold time or the residual expression that computes it.
+#[derive(Clone, Debug)]
+pub enum Folded {
+    Const(f64),
+    Residual(Expr<()>),
+}
+
+impl Folded {
+    pub fn into_expr(self) -> Expr<()> {
+        match self {
+            Folded::Const(val) => Expr::Number((), val),
+            Folded::Residual(expr) => expr,
+        }
+    }
+
+    fn unary(self, op: Op1, f: fn(f64) -> f64) -> Self {
+        match self {
+            Folded::Const(val) => Folded::Const(f(val)),
+            Folded::Residual(expr) => Folded::Residual(Expr::UnOp((), op, Box::new(expr))),
+        }
+    }
+
+    fn binary(self, op: Op2, other: Self, f: fn(f64, f64) -> f64) -> Self {
+        match (self, other) {
+            (Folded::Const(a), Folded::Const(b)) => Folded::Const(f(a, b)),
+            (a, b) => Folded::Residual(Expr::BinOp((), op, Box::new(a.into_expr()), Box::new(b.into_expr()))),
+        }
+    }
+}
+
+// f64 semantics here must match the AD backend so the folded expression computes the same function.
+impl MainBackend for Folded {
+    fn from_f64(val: f64) -> Self { Folded::Const(val) }
+    fn zero() -> Self { Folded::Const(0.0) }
+    fn one() -> Self { Folded::Const(1.0) }
+
+    fn neg(self) -> Self { self.unary(Op1::Neg, |x| -x) }
+    fn sin(self) -> Self { self.unary(Op1::Sin, f64::sin) }
+    fn cos(self) -> Self { self.unary(Op1::Cos, f64::cos) }
+    fn tan(self) -> Self { self.unary(Op1::Tan, f64::tan) }
+    fn exp(self) -> Self { self.unary(Op1::Exp, f64::exp) }
+    fn log(self) -> Self { self.unary(Op1::Log, f64::ln) }
+    fn sqrt(self) -> Self { self.unary(Op1::Sqrt, f64::sqrt) }
+    fn abs(self) -> Self { self.unary(Op1::Abs, f64::abs) }
+
+    fn add(self, other: Self) -> Self { self.binary(Op2::Add, other, |a, b| a + b) }
+    fn sub(self, other: Self) -> Self { self.binary(Op2::Sub, other, |a, b| a - b) }
+    fn mul(self, other: Self) -> Self { self.binary(Op2::Mul, other, |a, b| a * b) }
+    fn div(self, other: Self) -> Self { self.binary(Op2::Div, other, |a, b| a / b) }
+    fn pow(self, other: Self) -> Self { self.binary(Op2::Pow, other, f64::powf) }
+
+    fn cast(self, ty: &Type) -> Self {
+        match self {
+            Folded::Const(val) => Folded::Const(match ty {
+                Type::Float => val,
+                Type::Int => val.trunc(),
+                Type::Bool => if val != 0.0 { 1.0 } else { 0.0 },
+            }),
+            Folded::Residual(expr) => Folded::Residual(Expr::Cast((), ty.clone(), Box::new(expr))),
+        }
+    }
+}
+
+/// Fold every subtree that only depends on constants and `known_vars`.
+///
+/// Variables not in `known_vars` stay symbolic. `Let` bindings are inlined, so the result is a
+/// different tape for the same function, which metamorphic oracles can compare against the original.
+pub fn partial_eval<Tag>(expr: &Expr<Tag>, known_vars: &Env<f64>) -> Result<Expr<()>, FuzzError> {
+    let mut names = Vec::new();
+    for_each_node(expr, |e| if let Expr::Id(_, name) = e { names.push(name.clone()) });
+
+    let mut env: Env<Folded> = Env::new();
+    for name in names {
+        let val = match known_vars.get(&name) {
+            Some(v) => Folded::Const(*v),
+            None => Folded::Residual(Expr::Id((), name.clone())),
+        };
+        env.insert(name, val);
+    }
+
+    Ok(evaluate(expr, &env)?.into_expr())
+}
+
+/// The value of `expr` if it does not depend on any input, e.g. to skip a PyTorch dispatch.
+pub fn constant_value<Tag>(expr: &Expr<Tag>) -> Option<f64> {
+    match partial_eval(expr, &Env::new()) {
+        Ok(Expr::Number(_, val)) => Some(val),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_fold_constant_subtree() {
+        // x_0 * (2 + 3) -> x_0 * 5
+        let expr = SimpleExpr::mul(
+            SimpleExpr::var("x_0"),
+            SimpleExpr::add(SimpleExpr::num(2.0), SimpleExpr::num(3.0)),
+        );
+        match partial_eval(&expr, &Env::new()).unwrap() {
+            Expr::BinOp(_, Op2::Mul, l, r) => {
+                assert!(matches!(*l, Expr::Id(_, ref name) if name == "x_0"));
+                assert!(matches!(*r, Expr::Number(_, v) if v == 5.0));
+            }
+            other => panic!("Unexpected fold result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_known_vars_fold_to_constant() {
+        let expr = SimpleExpr::sin(SimpleExpr::var("x_0"));
+        let mut known = Env::new();
+        known.insert("x_0".to_string(), 0.5);
+        assert_eq!(constant_value(&expr), None);
+        assert!(matches!(partial_eval(&expr, &known).unwrap(), Expr::Number(_, v) if v == 0.5f64.sin()));
+    }
+}
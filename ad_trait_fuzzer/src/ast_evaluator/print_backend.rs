@@ -2,9 +2,20 @@
 
 /// Pretty print AST using MainBackend
 
+use std::collections::HashMap;
+
 use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::expr_codec::StableHasher;
 use super::{MainBackend, evaluate, Env};
 
+fn type_name(ty: &Type) -> &'static str {
+    match ty {
+        Type::Float => "float",
+        Type::Int => "int",
+        Type::Bool => "bool",
+    }
+}
+
 #[derive(Clone)]
 pub struct SExprString(String);
 
@@ -29,6 +40,8 @@ impl MainBackend for SExprString {
     fn mul(self, other: Self) -> Self { SExprString(format!("(* {} {})", self.0, other.0)) }
     fn div(self, other: Self) -> Self { SExprString(format!("(/ {} {})", self.0, other.0)) }
     fn pow(self, other: Self) -> Self { SExprString(format!("(pow {} {})", self.0, other.0)) }
+
+    fn cast(self, ty: &Type) -> Self { SExprString(format!("(cast {} {})", type_name(ty), self.0)) }
 }
 
 #[derive(Clone)]
@@ -55,6 +68,15 @@ impl MainBackend for InfixString {
     fn mul(self, other: Self) -> Self { InfixString(format!("({} * {})", self.0, other.0)) }
     fn div(self, other: Self) -> Self { InfixString(format!("({} / {})", self.0, other.0)) }
     fn pow(self, other: Self) -> Self { InfixString(format!("({} ^ {})", self.0, other.0)) }
+
+    // evalexpr-jit has no trunc/bool, so those casts only parse on the PyTorch side.
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => InfixString(format!("trunc({})", self.0)),
+            Type::Bool => InfixString(format!("bool({})", self.0)),
+        }
+    }
 }
 
 /// Sexpr
@@ -99,59 +121,85 @@ impl InfixPrinter {
     }
 }
 
-/// SSA for LLVM looking stuff
+/// SSA for LLVM looking stuff, runnable as Python after `from math import *`: one assignment
+/// per operation to a fresh `tN`, then `return`. Every `Let` binding and `Set` gets a fresh
+/// `name__N` (so shadowing never reassigns a name), `Let` values see only the enclosing scope
+/// like in `evaluate`, and `If` computes both branches and joins them with a select,
+/// `t = a if c else b`. Nodes `evaluate` rejects (`Loop`, `Break`) become a `raise`.
 pub struct SSAPrinter;
 
+/// The statements emitted so far, and the number the next fresh name gets (shared by the `tN`
+/// temporaries and the `name__N` bindings). The source variables in scope are passed separately.
+struct SsaState {
+    counter: usize,
+    statements: Vec<String>,
+}
+
+impl SsaState {
+    fn fresh(&mut self, prefix: &str) -> String {
+        let name = format!("{}{}", prefix, self.counter);
+        self.counter += 1;
+        name
+    }
+
+    fn assign(&mut self, rhs: String) -> String {
+        let name = self.fresh("t");
+        self.statements.push(format!("{} = {}", name, rhs));
+        name
+    }
+}
+
 impl SSAPrinter {
     pub fn print<T>(expr: &Expr<T>) -> String {
-        let mut counter = 0;
-        let mut statements = Vec::new();
-        let result = Self::print_helper(expr, &mut counter, &mut statements);
-        
-        if statements.is_empty() {
+        let mut state = SsaState { counter: 0, statements: Vec::new() };
+        let mut scope = HashMap::new();
+        let result = Self::print_helper(expr, &mut scope, &mut state);
+
+        if state.statements.is_empty() {
             result
         } else {
-            statements.push(format!("return {}", result));
-            statements.join("\n")
+            state.statements.push(format!("return {}", result));
+            state.statements.join("\n")
         }
     }
 
-    fn print_helper<T>(expr: &Expr<T>, counter: &mut usize, statements: &mut Vec<String>) -> String {
+    /// Emits the statements computing `expr` and returns the name (or literal) holding it.
+    /// `scope` maps source variables to their current SSA names; `Set` updates it for the
+    /// expressions after it in the same block.
+    fn print_helper<T>(expr: &Expr<T>, scope: &mut HashMap<String, String>, state: &mut SsaState) -> String {
         match expr {
-            Expr::Number(_, n) => format!("{}", n),
-            Expr::Boolean(_, b) => format!("{}", b),
-            Expr::Id(_, name) => name.clone(),
+            Expr::Number(_, n) if n.is_nan() => "nan".to_string(),
+            // Debug keeps the `.0`, so Python computes in floats too
+            Expr::Number(_, n) => format!("{:?}", n),
+            Expr::Boolean(_, b) => if *b { "True" } else { "False" }.to_string(),
+            Expr::Id(_, name) => scope.get(name).cloned().unwrap_or_else(|| name.clone()),
             Expr::Let(_, bindings, body) => {
-                for (var, expr) in bindings {
-                    let val = Self::print_helper(expr, counter, statements);
-                    statements.push(format!("{} = {}", var, val));
+                // All values first, in the outer scope, then the new names
+                let values: Vec<String> = bindings.iter().map(|(_, value)| Self::print_helper(value, scope, state)).collect();
+                let mut inner = scope.clone();
+                for ((var, _), value) in bindings.iter().zip(values) {
+                    let name = state.fresh(&format!("{}__", var));
+                    state.statements.push(format!("{} = {}", name, value));
+                    inner.insert(var.clone(), name);
                 }
-                Self::print_helper(body, counter, statements)
+                Self::print_helper(body, &mut inner, state)
             }
             Expr::UnOp(_, op, expr) => {
-                let arg = Self::print_helper(expr, counter, statements);
-                let var_name = format!("t{}", counter);
-                *counter += 1;
-                
-                let stmt = match op {
-                    Op1::Neg => format!("{} = -{}", var_name, arg),
-                    Op1::Sin => format!("{} = sin({})", var_name, arg),
-                    Op1::Cos => format!("{} = cos({})", var_name, arg),
-                    Op1::Tan => format!("{} = tan({})", var_name, arg),
-                    Op1::Exp => format!("{} = exp({})", var_name, arg),
-                    Op1::Log => format!("{} = log({})", var_name, arg),
-                    Op1::Sqrt => format!("{} = sqrt({})", var_name, arg),
-                    Op1::Abs => format!("{} = abs({})", var_name, arg),
-                };
-                statements.push(stmt);
-                var_name
+                let arg = Self::print_helper(expr, scope, state);
+                state.assign(match op {
+                    Op1::Neg => format!("-{}", arg),
+                    Op1::Sin => format!("sin({})", arg),
+                    Op1::Cos => format!("cos({})", arg),
+                    Op1::Tan => format!("tan({})", arg),
+                    Op1::Exp => format!("exp({})", arg),
+                    Op1::Log => format!("log({})", arg),
+                    Op1::Sqrt => format!("sqrt({})", arg),
+                    Op1::Abs => format!("abs({})", arg),
+                })
             }
             Expr::BinOp(_, op, left, right) => {
-                let left_val = Self::print_helper(left, counter, statements);
-                let right_val = Self::print_helper(right, counter, statements);
-                let var_name = format!("t{}", counter);
-                *counter += 1;
-                
+                let left_val = Self::print_helper(left, scope, state);
+                let right_val = Self::print_helper(right, scope, state);
                 let op_str = match op {
                     Op2::Add => "+",
                     Op2::Sub => "-",
@@ -159,59 +207,814 @@ impl SSAPrinter {
                     Op2::Div => "/",
                     Op2::Pow => "**",
                 };
-                statements.push(format!("{} = {} {} {}", var_name, left_val, op_str, right_val));
-                var_name
+                state.assign(format!("{} {} {}", left_val, op_str, right_val))
             }
             Expr::If(_, cond, then_br, else_br) => {
-                let cond_val = Self::print_helper(cond, counter, statements);
-                let var_name = format!("t{}", counter);
-                *counter += 1;
-                
-                statements.push(format!("{} = if {} then", var_name, cond_val));
-                let then_val = Self::print_helper(then_br, counter, statements);
-                statements.push(format!("  {}", then_val));
-                statements.push("else".to_string());
-                let else_val = Self::print_helper(else_br, counter, statements);
-                statements.push(format!("  {}", else_val));
-                var_name
+                let cond_val = Self::print_helper(cond, scope, state);
+                // Branches can `Set` too; each starts from the scope before the If
+                let then_val = Self::print_helper(then_br, &mut scope.clone(), state);
+                let else_val = Self::print_helper(else_br, &mut scope.clone(), state);
+                state.assign(format!("{} if {} else {}", then_val, cond_val, else_val))
             }
-            Expr::Loop(_, body) => {
-                let var_name = format!("t{}", counter);
-                *counter += 1;
-                statements.push(format!("{} = loop", var_name));
-                let body_val = Self::print_helper(body, counter, statements);
-                statements.push(format!("  {}", body_val));
-                var_name
-            }
-            Expr::Break(_, val) => {
-                let val_str = Self::print_helper(val, counter, statements);
-                format!("break {}", val_str)
+            Expr::Loop(_, _) | Expr::Break(_, _) => {
+                let kind = if let Expr::Loop(..) = expr { "loop" } else { "break" };
+                state.statements.push(format!("raise NotImplementedError(\"{}\")", kind));
+                state.fresh("t")
             }
             Expr::Set(_, var, expr) => {
-                let val = Self::print_helper(expr, counter, statements);
-                statements.push(format!("{} = {}", var, val));
-                var.clone()
+                let val = Self::print_helper(expr, scope, state);
+                let name = state.fresh(&format!("{}__", var));
+                state.statements.push(format!("{} = {}", name, val));
+                scope.insert(var.clone(), name.clone());
+                name
             }
             Expr::Block(_, exprs) => {
-                let mut last = String::new();
+                // evaluate() gives 0 for an empty block
+                let mut last = "0".to_string();
                 for expr in exprs {
-                    last = Self::print_helper(expr, counter, statements);
+                    last = Self::print_helper(expr, scope, state);
                 }
                 last
             }
             // like C cast ig
             Expr::Cast(_, typ, expr) => {
-                let val = Self::print_helper(expr, counter, statements);
-                let var_name = format!("t{}", counter);
-                *counter += 1;
-                let type_str = match typ {
-                    Type::Float => "float",
-                    Type::Int => "int",
-                    Type::Bool => "bool",
+                let val = Self::print_helper(expr, scope, state);
+                match typ {
+                    Type::Float => val,
+                    Type::Int => state.assign(format!("float(trunc({}))", val)),
+                    Type::Bool => state.assign(format!("float({} != 0)", val)),
+                }
+            }
+        }
+    }
+}
+
+/// GraphViz DOT (`dot -Tsvg expr.dot > expr.svg`): one node per operation, edges from operands
+/// to the operation using them, drawn bottom-up like the tape runs. Each variable (input or
+/// `let`-bound) is one node however often it is used, so shared subterms show as fan-out.
+pub struct DotPrinter;
+
+struct DotGraph {
+    lines: Vec<String>,
+    vars: HashMap<String, String>,
+    next: usize,
+}
+
+impl DotGraph {
+    fn node(&mut self, label: &str, attrs: &str) -> String {
+        let id = format!("n{}", self.next);
+        self.next += 1;
+        self.lines.push(format!("  {} [label=\"{}\"{}];", id, label.replace('"', "\\\""), attrs));
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>) {
+        match label {
+            Some(label) => self.lines.push(format!("  {} -> {} [label=\"{}\"];", from, to, label)),
+            None => self.lines.push(format!("  {} -> {};", from, to)),
+        }
+    }
+
+    fn var(&mut self, name: &str) -> String {
+        if let Some(id) = self.vars.get(name) {
+            return id.clone();
+        }
+        let id = self.node(name, ", shape=box");
+        self.vars.insert(name.to_string(), id.clone());
+        id
+    }
+
+    /// Adds `expr`, returns the id of the node holding its value.
+    fn add<T>(&mut self, expr: &Expr<T>) -> String {
+        match expr {
+            Expr::Number(_, n) => self.node(&format!("{}", n), ", shape=plaintext"),
+            Expr::Boolean(_, b) => self.node(&format!("{}", b), ", shape=plaintext"),
+            Expr::Id(_, name) => self.var(name),
+            Expr::Let(_, bindings, body) => {
+                let mut shadowed = Vec::new();
+                for (name, value) in bindings {
+                    let value = self.add(value);
+                    let id = self.node(name, ", shape=box, style=dashed");
+                    self.edge(&value, &id, None);
+                    shadowed.push((name.clone(), self.vars.insert(name.clone(), id)));
+                }
+                let body = self.add(body);
+                for (name, old) in shadowed.into_iter().rev() {
+                    match old {
+                        Some(id) => self.vars.insert(name, id),
+                        None => self.vars.remove(&name),
+                    };
+                }
+                body
+            }
+            Expr::UnOp(_, op, arg) => {
+                let arg = self.add(arg);
+                let label = match op {
+                    Op1::Neg => "neg",
+                    Op1::Sin => "sin",
+                    Op1::Cos => "cos",
+                    Op1::Tan => "tan",
+                    Op1::Exp => "exp",
+                    Op1::Log => "log",
+                    Op1::Sqrt => "sqrt",
+                    Op1::Abs => "abs",
+                };
+                let id = self.node(label, "");
+                self.edge(&arg, &id, None);
+                id
+            }
+            Expr::BinOp(_, op, left, right) => {
+                let (left, right) = (self.add(left), self.add(right));
+                let label = match op {
+                    Op2::Add => "+",
+                    Op2::Sub => "-",
+                    Op2::Mul => "*",
+                    Op2::Div => "/",
+                    Op2::Pow => "^",
                 };
-                statements.push(format!("{} = ({}) {}", var_name, type_str, val));
-                var_name
+                let id = self.node(label, "");
+                self.edge(&left, &id, Some("0"));
+                self.edge(&right, &id, Some("1"));
+                id
+            }
+            Expr::If(_, cond, then_br, else_br) => {
+                let parts = [(self.add(cond), "cond"), (self.add(then_br), "then"), (self.add(else_br), "else")];
+                let id = self.node("if", ", shape=diamond");
+                for (part, label) in parts {
+                    self.edge(&part, &id, Some(label));
+                }
+                id
+            }
+            Expr::Loop(_, body) => {
+                let body = self.add(body);
+                let id = self.node("loop", ", shape=diamond");
+                self.edge(&body, &id, None);
+                id
+            }
+            Expr::Break(_, value) => {
+                let value = self.add(value);
+                let id = self.node("break", "");
+                self.edge(&value, &id, None);
+                id
+            }
+            Expr::Set(_, name, value) => {
+                let value = self.add(value);
+                let id = self.node(&format!("{} :=", name), ", shape=box, style=dashed");
+                self.edge(&value, &id, None);
+                self.vars.insert(name.clone(), id.clone());
+                id
+            }
+            Expr::Block(_, exprs) => {
+                let parts: Vec<String> = exprs.iter().map(|e| self.add(e)).collect();
+                let id = self.node("block", "");
+                for (i, part) in parts.iter().enumerate() {
+                    self.edge(part, &id, Some(&i.to_string()));
+                }
+                id
+            }
+            Expr::Cast(_, ty, arg) => {
+                let arg = self.add(arg);
+                let id = self.node(&format!("({})", type_name(ty)), "");
+                self.edge(&arg, &id, None);
+                id
+            }
+        }
+    }
+}
+
+impl DotPrinter {
+    pub fn print<T>(expr: &Expr<T>, num_inputs: usize) -> String {
+        let mut graph = DotGraph { lines: Vec::new(), vars: HashMap::new(), next: 0 };
+        // Inputs first, on one rank, even the ones the expression does not use
+        for i in 0..num_inputs {
+            graph.var(&format!("x_{}", i));
+        }
+        let inputs: Vec<String> = (0..num_inputs).map(|i| graph.vars[&format!("x_{}", i)].clone()).collect();
+        let root = graph.add(expr);
+        let mut out = String::from("digraph expr {\n  rankdir=BT;\n  node [fontname=\"monospace\"];\n");
+        for line in &graph.lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+        if !inputs.is_empty() {
+            out.push_str(&format!("  {{ rank=same; {}; }}\n", inputs.join("; ")));
+        }
+        out.push_str(&format!("  {} [peripheries=2];\n}}\n", root));
+        out
+    }
+}
+
+/// Python float literal; repr of a finite f64 round-trips exactly, NaN/Inf need `float(...)`.
+fn py_float(val: f64) -> String {
+    if val.is_nan() {
+        "float('nan')".to_string()
+    } else if val.is_infinite() {
+        format!("float('{}inf')", if val < 0.0 { "-" } else { "" })
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+#[derive(Clone)]
+pub struct TorchString(String);
+
+impl MainBackend for TorchString {
+    fn from_f64(val: f64) -> Self {
+        TorchString(format!("torch.tensor({}, dtype=torch.float64)", py_float(val)))
+    }
+    fn zero() -> Self { Self::from_f64(0.0) }
+    fn one() -> Self { Self::from_f64(1.0) }
+
+    fn neg(self) -> Self { TorchString(format!("torch.neg({})", self.0)) }
+    fn sin(self) -> Self { TorchString(format!("torch.sin({})", self.0)) }
+    fn cos(self) -> Self { TorchString(format!("torch.cos({})", self.0)) }
+    fn tan(self) -> Self { TorchString(format!("torch.tan({})", self.0)) }
+    fn exp(self) -> Self { TorchString(format!("torch.exp({})", self.0)) }
+    fn log(self) -> Self { TorchString(format!("torch.log({})", self.0)) }
+    fn sqrt(self) -> Self { TorchString(format!("torch.sqrt({})", self.0)) }
+    fn abs(self) -> Self { TorchString(format!("torch.abs({})", self.0)) }
+
+    fn add(self, other: Self) -> Self { TorchString(format!("torch.add({}, {})", self.0, other.0)) }
+    fn sub(self, other: Self) -> Self { TorchString(format!("torch.sub({}, {})", self.0, other.0)) }
+    fn mul(self, other: Self) -> Self { TorchString(format!("torch.mul({}, {})", self.0, other.0)) }
+    fn div(self, other: Self) -> Self { TorchString(format!("torch.div({}, {})", self.0, other.0)) }
+    fn pow(self, other: Self) -> Self { TorchString(format!("torch.pow({}, {})", self.0, other.0)) }
+
+    // Same lowering as `PyTorchTensor::cast`
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => TorchString(format!("torch.trunc({})", self.0)),
+            Type::Bool => TorchString(format!("({} != 0).to(torch.float64)", self.0)),
+        }
+    }
+}
+
+/// Self-contained PyTorch script (`python3 repro.py`) that builds the input tensors, evaluates the
+/// expression with the same torch calls as `PyTorchEvaluator`, runs `backward()` and prints the
+/// value and gradient next to the gradients the other engines reported. Needs nothing but torch,
+/// so a disagreement can be handed to PyTorch maintainers as is.
+pub struct PyTorchScriptPrinter;
+
+impl PyTorchScriptPrinter {
+    /// `expected` are (engine, gradient) pairs printed alongside PyTorch's, e.g. ad_trait's Rev.
+    pub fn print<Tag>(expr: &Expr<Tag>, inputs: &[f64], expected: &[(&str, &[f64])]) -> String {
+        let mut env = Env::new();
+        for i in 0..inputs.len() {
+            env.insert(format!("x_{}", i), TorchString(format!("x_{}", i)));
+        }
+        let body = match evaluate::<TorchString, Tag>(expr, &env) {
+            Ok(result) => result.0,
+            Err(e) => format!("None  # <error: {}>", e),
+        };
+        let names: Vec<String> = (0..inputs.len()).map(|i| format!("x_{}", i)).collect();
+
+        let mut out = String::new();
+        out.push_str("# Reproduction generated by ad_trait_fuzzer\n");
+        out.push_str(&format!("# f = {}\n", InfixPrinter::print(expr, inputs.len())));
+        out.push_str("import torch\n\n");
+        for (name, val) in names.iter().zip(inputs) {
+            out.push_str(&format!("{} = torch.tensor({}, dtype=torch.float64, requires_grad=True)\n", name, py_float(*val)));
+        }
+        out.push_str(&format!("inputs = [{}]\n\n", names.join(", ")));
+        out.push_str(&format!("out = {}\n", body));
+        out.push_str("print(\"value:\", out.item())\n");
+        out.push_str("# A constant result has no graph; every derivative is 0\n");
+        out.push_str("if out.requires_grad:\n    out.backward()\n");
+        out.push_str("gradient = [x.grad.item() if x.grad is not None else 0.0 for x in inputs]\n");
+        out.push_str("print(\"PyTorch gradient:\", gradient)\n");
+        if !expected.is_empty() {
+            out.push_str("\nexpected = {\n");
+            for (engine, gradient) in expected {
+                let values: Vec<String> = gradient.iter().map(|g| py_float(*g)).collect();
+                out.push_str(&format!("    {:?}: [{}],\n", engine, values.join(", ")));
             }
+            out.push_str("}\n");
+            out.push_str("for engine, other in expected.items():\n");
+            out.push_str("    print(\"{} gradient:\".format(engine), other)\n");
+            out.push_str("    print(\"  difference:\", [a - b for a, b in zip(gradient, other)])\n");
+        }
+        out
+    }
+}
+
+/// Rust f64 expression for `val`; Debug output of a finite f64 is a literal that round-trips.
+fn rust_float(val: f64) -> String {
+    if val.is_nan() {
+        "f64::NAN".to_string()
+    } else if val.is_infinite() {
+        if val < 0.0 { "f64::NEG_INFINITY" } else { "f64::INFINITY" }.to_string()
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+/// Complete `examples/repro_<hash>.rs` (see `file_stem`) that rebuilds the expression with the
+/// `SimpleExpr` builders and runs the failing oracle against the default ground truth, exiting
+/// with 1 while the failure reproduces. As a registry printer, just the builder expression.
+pub struct RustReproPrinter;
+
+/// Which of `Expr` / `Type` the generated source names, so it imports nothing unused.
+#[derive(Default)]
+struct RustImports {
+    expr: bool,
+    ty: bool,
+}
+
+impl RustReproPrinter {
+    /// `repro_<hash>`, the hash over the expression and the input bits.
+    pub fn file_stem<Tag>(expr: &Expr<Tag>, inputs: &[f64]) -> String {
+        use std::hash::Hasher;
+        let mut hasher = StableHasher::default();
+        hasher.write(SExprPrinter::print(expr, inputs.len()).as_bytes());
+        for x in inputs {
+            hasher.write_u64(x.to_bits());
+        }
+        format!("repro_{:016x}", hasher.finish())
+    }
+
+    /// `oracle` is the name of the oracle that failed (see `OracleSelection::from_name`), `None`
+    /// to run all of them. `note` (e.g. the error) is copied into the header comment, one `//`
+    /// line per line.
+    pub fn print<Tag>(expr: &Expr<Tag>, inputs: &[f64], oracle: Option<&str>, note: &str) -> String {
+        let stem = Self::file_stem(expr, inputs);
+        let mut imports = RustImports::default();
+        let builder = Self::builder(expr, 1, &mut imports);
+        let inputs: Vec<String> = inputs.iter().map(|x| rust_float(*x)).collect();
+
+        let mut ast_imports = vec!["SimpleExpr"];
+        if imports.expr {
+            ast_imports.insert(0, "Expr");
+        }
+        if imports.ty {
+            ast_imports.push("Type");
         }
+        let ast_imports = match ast_imports.as_slice() {
+            [single] => single.to_string(),
+            all => format!("{{{}}}", all.join(", ")),
+        };
+
+        let mut out = format!("// examples/{}.rs\n// cargo +nightly run --example {}\n//\n", stem, stem);
+        out.push_str(&format!("// f = {}\n", InfixPrinter::print(expr, inputs.len())));
+        for line in note.lines() {
+            out.push_str(&format!("// {}\n", line));
+        }
+        out.push('\n');
+        out.push_str(&format!("use fuzz_core::ast_expr::{};\n", ast_imports));
+        out.push_str("use fuzz_core::ast_evaluator::unified::AdPyUnified;\n");
+        out.push_str("use fuzz_core::fuzz_harness::{run_ad_tests, GroundTruthCalculator, TestOutcome};\n");
+        out.push_str("use fuzz_core::gt_calculators::DefaultGroundTruth;\n");
+        out.push_str("use fuzz_core::oracles::{FuzzingOracles, OracleSelection};\n\n");
+        out.push_str("fn main() {\n");
+        out.push_str(&format!("    let expr = {};\n", builder));
+        out.push_str(&format!("    let inputs = [{}];\n\n", inputs.join(", ")));
+        let selection = match oracle {
+            Some(name) => format!("OracleSelection::from_name({:?}).unwrap()", name),
+            None => "OracleSelection::ALL".to_string(),
+        };
+        out.push_str(&format!("    let oracles = FuzzingOracles::new({}).specialize(&expr);\n", selection));
+        out.push_str("    let evaluator = AdPyUnified::new(expr, inputs.len(), 1);\n");
+        out.push_str("    let result = run_ad_tests(&inputs, evaluator, &oracles, &[DefaultGroundTruth::default().boxed()])\n");
+        out.push_str("        .and_then(TestOutcome::into_result);\n");
+        out.push_str("    match &result {\n");
+        out.push_str("        Ok(()) => println!(\"Test PASSED\"),\n");
+        out.push_str("        Err(e) => println!(\"Test FAILED: {}\", e),\n");
+        out.push_str("    }\n");
+        out.push_str("    std::process::exit(if result.is_ok() { 0 } else { 1 });\n");
+        out.push_str("}\n");
+        out
+    }
+
+    /// Builder call for `expr`, its arguments one per line at `depth` levels of indentation.
+    fn builder<Tag>(expr: &Expr<Tag>, depth: usize, imports: &mut RustImports) -> String {
+        let boxed = |s: String| format!("Box::new({})", s);
+        match expr {
+            Expr::Number(_, n) => format!("SimpleExpr::num({})", rust_float(*n)),
+            Expr::Id(_, name) => format!("SimpleExpr::var({:?})", name),
+            Expr::Boolean(_, b) => {
+                imports.expr = true;
+                format!("Expr::Boolean((), {})", b)
+            }
+            Expr::UnOp(_, op, arg) => {
+                let name = match op {
+                    Op1::Neg => "neg",
+                    Op1::Sin => "sin",
+                    Op1::Cos => "cos",
+                    Op1::Tan => "tan",
+                    Op1::Exp => "exp",
+                    Op1::Log => "log",
+                    Op1::Sqrt => "sqrt",
+                    Op1::Abs => "abs",
+                };
+                rust_call(&format!("SimpleExpr::{}", name), vec![Self::builder(arg, depth + 1, imports)], depth)
+            }
+            Expr::BinOp(_, op, left, right) => {
+                let name = match op {
+                    Op2::Add => "add",
+                    Op2::Sub => "sub",
+                    Op2::Mul => "mul",
+                    Op2::Div => "div",
+                    Op2::Pow => "pow",
+                };
+                let args = vec![Self::builder(left, depth + 1, imports), Self::builder(right, depth + 1, imports)];
+                rust_call(&format!("SimpleExpr::{}", name), args, depth)
+            }
+            Expr::Cast(_, ty, arg) => {
+                imports.ty = true;
+                let ty = match ty {
+                    Type::Float => "Type::Float",
+                    Type::Int => "Type::Int",
+                    Type::Bool => "Type::Bool",
+                };
+                rust_call("SimpleExpr::cast", vec![ty.to_string(), Self::builder(arg, depth + 1, imports)], depth)
+            }
+            Expr::Let(_, bindings, body) => {
+                imports.expr = true;
+                let bindings: Vec<String> = bindings.iter()
+                    .map(|(name, value)| format!("({:?}.to_string(), {})", name, Self::builder(value, depth + 2, imports)))
+                    .collect();
+                let args = vec!["()".to_string(), rust_vec(bindings, depth + 1), boxed(Self::builder(body, depth + 1, imports))];
+                rust_call("Expr::Let", args, depth)
+            }
+            Expr::If(_, cond, then_br, else_br) => {
+                imports.expr = true;
+                let args = [cond, then_br, else_br].into_iter().map(|e| boxed(Self::builder(e, depth + 1, imports)));
+                rust_call("Expr::If", std::iter::once("()".to_string()).chain(args).collect(), depth)
+            }
+            Expr::Loop(_, body) => {
+                imports.expr = true;
+                rust_call("Expr::Loop", vec!["()".to_string(), boxed(Self::builder(body, depth + 1, imports))], depth)
+            }
+            Expr::Break(_, value) => {
+                imports.expr = true;
+                rust_call("Expr::Break", vec!["()".to_string(), boxed(Self::builder(value, depth + 1, imports))], depth)
+            }
+            Expr::Set(_, name, value) => {
+                imports.expr = true;
+                let args = vec!["()".to_string(), format!("{:?}.to_string()", name), boxed(Self::builder(value, depth + 1, imports))];
+                rust_call("Expr::Set", args, depth)
+            }
+            Expr::Block(_, exprs) => {
+                imports.expr = true;
+                let exprs: Vec<String> = exprs.iter().map(|e| Self::builder(e, depth + 2, imports)).collect();
+                rust_call("Expr::Block", vec!["()".to_string(), rust_vec(exprs, depth + 1)], depth)
+            }
+        }
+    }
+}
+
+/// `name(` then one argument per line at `depth + 1`, `)` at `depth`.
+fn rust_call(name: &str, args: Vec<String>, depth: usize) -> String {
+    format!("{}({})", name, rust_items(args, depth))
+}
+
+/// `vec![...]` laid out like `rust_call`.
+fn rust_vec(items: Vec<String>, depth: usize) -> String {
+    format!("vec![{}]", rust_items(items, depth))
+}
+
+fn rust_items(items: Vec<String>, depth: usize) -> String {
+    let indent = "    ".repeat(depth + 1);
+    let mut out = String::from("\n");
+    for item in items {
+        out.push_str(&format!("{}{},\n", indent, item));
+    }
+    out.push_str(&"    ".repeat(depth));
+    out
+}
+
+/// C double expression for `val`, with the `math.h` macros for NaN and infinities.
+fn c_float(val: f64) -> String {
+    if val.is_nan() {
+        "NAN".to_string()
+    } else if val.is_infinite() {
+        if val < 0.0 { "(-INFINITY)" } else { "INFINITY" }.to_string()
+    } else if val < 0.0 || (val == 0.0 && val.is_sign_negative()) {
+        format!("({:?})", val)
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+#[derive(Clone)]
+pub struct CString(String);
+
+impl MainBackend for CString {
+    fn from_f64(val: f64) -> Self { CString(c_float(val)) }
+    fn zero() -> Self { CString("0.0".to_string()) }
+    fn one() -> Self { CString("1.0".to_string()) }
+
+    fn neg(self) -> Self { CString(format!("(-{})", self.0)) }
+    fn sin(self) -> Self { CString(format!("sin({})", self.0)) }
+    fn cos(self) -> Self { CString(format!("cos({})", self.0)) }
+    fn tan(self) -> Self { CString(format!("tan({})", self.0)) }
+    fn exp(self) -> Self { CString(format!("exp({})", self.0)) }
+    fn log(self) -> Self { CString(format!("log({})", self.0)) }
+    fn sqrt(self) -> Self { CString(format!("sqrt({})", self.0)) }
+    fn abs(self) -> Self { CString(format!("fabs({})", self.0)) }
+
+    fn add(self, other: Self) -> Self { CString(format!("({} + {})", self.0, other.0)) }
+    fn sub(self, other: Self) -> Self { CString(format!("({} - {})", self.0, other.0)) }
+    fn mul(self, other: Self) -> Self { CString(format!("({} * {})", self.0, other.0)) }
+    fn div(self, other: Self) -> Self { CString(format!("({} / {})", self.0, other.0)) }
+    fn pow(self, other: Self) -> Self { CString(format!("pow({}, {})", self.0, other.0)) }
+
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => CString(format!("trunc({})", self.0)),
+            Type::Bool => CString(format!("({} != 0.0 ? 1.0 : 0.0)", self.0)),
+        }
+    }
+}
+
+/// C99 against `math.h`: `print` renders `static double f(const double *x)` with `x[i]` for
+/// `x_i`, `program` a complete file around it that prints the value and central differences
+/// (the `GradCheckOracle` step) at the given inputs, or at the ones passed on the command line.
+/// `cc -O0 repro.c -lm && ./a.out` checks the other engines against the system libm.
+pub struct CPrinter;
+
+impl CPrinter {
+    pub fn print<Tag>(expr: &Expr<Tag>, num_inputs: usize) -> String {
+        let mut env = Env::new();
+        for i in 0..num_inputs {
+            env.insert(format!("x_{}", i), CString(format!("x[{}]", i)));
+        }
+        let (body, note) = match evaluate::<CString, Tag>(expr, &env) {
+            Ok(result) => (result.0, String::new()),
+            Err(e) => ("NAN".to_string(), format!(" /* <error: {}> */", e.to_string().replace("*/", "* /"))),
+        };
+        format!("static double f(const double *x) {{\n    (void)x;\n    return {};{}\n}}\n", body, note)
+    }
+
+    pub fn program<Tag>(expr: &Expr<Tag>, inputs: &[f64]) -> String {
+        let n = inputs.len().max(1);
+        let mut values: Vec<String> = inputs.iter().map(|x| c_float(*x)).collect();
+        if values.is_empty() {
+            values.push("0.0".to_string());
+        }
+        let mut out = String::from("/* Generated by ad_trait_fuzzer */\n");
+        out.push_str(&format!("/* f = {} */\n", InfixPrinter::print(expr, inputs.len()).replace("*/", "* /")));
+        out.push_str("#include <math.h>\n#include <stdio.h>\n#include <stdlib.h>\n\n");
+        out.push_str(&Self::print(expr, inputs.len()));
+        out.push_str(&format!("\n#define N {}\n\n", n));
+        out.push_str("int main(int argc, char **argv) {\n");
+        out.push_str(&format!("    double x[N] = {{{}}};\n", values.join(", ")));
+        out.push_str("    for (int i = 1; i < argc && i <= N; i++) {\n");
+        out.push_str("        x[i - 1] = strtod(argv[i], NULL);\n");
+        out.push_str("    }\n");
+        out.push_str("    printf(\"value: %.17g\\n\", f(x));\n");
+        out.push_str("    for (int i = 0; i < N; i++) {\n");
+        out.push_str("        double xi = x[i];\n");
+        out.push_str("        double h = 6.0555e-6 * fmax(1.0, fabs(xi));\n");
+        out.push_str("        x[i] = xi + h;\n");
+        out.push_str("        double fp = f(x);\n");
+        out.push_str("        x[i] = xi - h;\n");
+        out.push_str("        double fm = f(x);\n");
+        out.push_str("        x[i] = xi;\n");
+        out.push_str("        printf(\"d/dx_%d: %.17g\\n\", i, (fp - fm) / (2.0 * h));\n");
+        out.push_str("    }\n");
+        out.push_str("    return 0;\n");
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Julia Float64 literal; `NaN` and `Inf` are spelled like Rust's Debug output but capitalized.
+fn julia_float(val: f64) -> String {
+    if val.is_nan() {
+        "NaN".to_string()
+    } else if val.is_infinite() {
+        if val < 0.0 { "(-Inf)" } else { "Inf" }.to_string()
+    } else if val < 0.0 || (val == 0.0 && val.is_sign_negative()) {
+        format!("({:?})", val)
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+#[derive(Clone)]
+pub struct JuliaString(String);
+
+impl MainBackend for JuliaString {
+    fn from_f64(val: f64) -> Self { JuliaString(julia_float(val)) }
+    fn zero() -> Self { JuliaString("0.0".to_string()) }
+    fn one() -> Self { JuliaString("1.0".to_string()) }
+
+    fn neg(self) -> Self { JuliaString(format!("(-{})", self.0)) }
+    fn sin(self) -> Self { JuliaString(format!("sin({})", self.0)) }
+    fn cos(self) -> Self { JuliaString(format!("cos({})", self.0)) }
+    fn tan(self) -> Self { JuliaString(format!("tan({})", self.0)) }
+    fn exp(self) -> Self { JuliaString(format!("exp({})", self.0)) }
+    fn log(self) -> Self { JuliaString(format!("log({})", self.0)) }
+    fn sqrt(self) -> Self { JuliaString(format!("sqrt({})", self.0)) }
+    fn abs(self) -> Self { JuliaString(format!("abs({})", self.0)) }
+
+    fn add(self, other: Self) -> Self { JuliaString(format!("({} + {})", self.0, other.0)) }
+    fn sub(self, other: Self) -> Self { JuliaString(format!("({} - {})", self.0, other.0)) }
+    fn mul(self, other: Self) -> Self { JuliaString(format!("({} * {})", self.0, other.0)) }
+    fn div(self, other: Self) -> Self { JuliaString(format!("({} / {})", self.0, other.0)) }
+    fn pow(self, other: Self) -> Self { JuliaString(format!("({} ^ {})", self.0, other.0)) }
+
+    // `one`/`zero` of the argument, so the result stays a ForwardDiff.Dual
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => JuliaString(format!("trunc({})", self.0)),
+            Type::Bool => JuliaString(format!("(v -> ifelse(v != 0, one(v), zero(v)))({})", self.0)),
+        }
+    }
+}
+
+/// Julia anonymous function `x -> ...` over the input vector, `x[i + 1]` for `x_i`, for
+/// `ForwardDiff.gradient` / `Zygote.gradient` (see `SubprocessJuliaCalculator`). Unlike the other
+/// engines, Julia's `log`, `sqrt` and `^` throw a `DomainError` outside their real domain.
+pub struct JuliaPrinter;
+
+impl JuliaPrinter {
+    pub fn print<Tag>(expr: &Expr<Tag>, num_inputs: usize) -> String {
+        let mut env = Env::new();
+        for i in 0..num_inputs {
+            env.insert(format!("x_{}", i), JuliaString(format!("x[{}]", i + 1)));
+        }
+        match evaluate::<JuliaString, Tag>(expr, &env) {
+            Ok(result) => format!("x -> {}", result.0),
+            Err(e) => format!("<error: {}>", e),
+        }
+    }
+}
+
+/// LaTeX number: `\infty`, `\mathrm{NaN}`, negatives parenthesized.
+fn latex_float(val: f64) -> String {
+    if val.is_nan() {
+        r"\mathrm{NaN}".to_string()
+    } else if val.is_infinite() {
+        if val < 0.0 { r"\left(-\infty\right)" } else { r"\infty" }.to_string()
+    } else if val < 0.0 || (val == 0.0 && val.is_sign_negative()) {
+        format!(r"\left({}\right)", val)
+    } else {
+        format!("{}", val)
+    }
+}
+
+#[derive(Clone)]
+pub struct LatexString(String);
+
+impl MainBackend for LatexString {
+    fn from_f64(val: f64) -> Self { LatexString(latex_float(val)) }
+    fn zero() -> Self { LatexString("0".to_string()) }
+    fn one() -> Self { LatexString("1".to_string()) }
+
+    fn neg(self) -> Self { LatexString(format!(r"-\left({}\right)", self.0)) }
+    fn sin(self) -> Self { LatexString(format!(r"\sin\left({}\right)", self.0)) }
+    fn cos(self) -> Self { LatexString(format!(r"\cos\left({}\right)", self.0)) }
+    fn tan(self) -> Self { LatexString(format!(r"\tan\left({}\right)", self.0)) }
+    fn exp(self) -> Self { LatexString(format!(r"\exp\left({}\right)", self.0)) }
+    fn log(self) -> Self { LatexString(format!(r"\ln\left({}\right)", self.0)) }
+    fn sqrt(self) -> Self { LatexString(format!(r"\sqrt{{{}}}", self.0)) }
+    fn abs(self) -> Self { LatexString(format!(r"\left|{}\right|", self.0)) }
+
+    fn add(self, other: Self) -> Self { LatexString(format!(r"\left({} + {}\right)", self.0, other.0)) }
+    fn sub(self, other: Self) -> Self { LatexString(format!(r"\left({} - {}\right)", self.0, other.0)) }
+    fn mul(self, other: Self) -> Self { LatexString(format!(r"\left({} \cdot {}\right)", self.0, other.0)) }
+    fn div(self, other: Self) -> Self { LatexString(format!(r"\frac{{{}}}{{{}}}", self.0, other.0)) }
+    fn pow(self, other: Self) -> Self { LatexString(format!("{{{}}}^{{{}}}", self.0, other.0)) }
+
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => LatexString(format!(r"\operatorname{{trunc}}\left({}\right)", self.0)),
+            Type::Bool => LatexString(format!(r"\mathbb{{1}}\left[{} \neq 0\right]", self.0)),
+        }
+    }
+}
+
+/// LaTeX math (no `$`), for pasting the expression into an issue or a paper. `x_i` is `x_{i}`.
+pub struct LatexPrinter;
+
+impl LatexPrinter {
+    pub fn print<Tag>(expr: &Expr<Tag>, num_inputs: usize) -> String {
+        let mut env = Env::new();
+        for i in 0..num_inputs {
+            env.insert(format!("x_{}", i), LatexString(format!("x_{{{}}}", i)));
+        }
+        match evaluate::<LatexString, Tag>(expr, &env) {
+            Ok(result) => result.0,
+            Err(e) => format!("<error: {}>", e),
+        }
+    }
+}
+
+/// An expression format that can be picked at runtime, e.g. by the `printers` setting of the
+/// fuzz config, which selects the formats of the crash report. See `printer`.
+pub trait Printer<Tag> {
+    /// Name in the registry and in the config, e.g. `"infix"`.
+    fn name(&self) -> &'static str;
+    /// Heading for the crash report.
+    fn title(&self) -> &'static str;
+    fn print(&self, expr: &Expr<Tag>, num_inputs: usize) -> String;
+}
+
+macro_rules! impl_printer {
+    ($printer:ident, $name:literal, $title:literal, |$expr:ident, $n:ident| $body:expr) => {
+        impl<Tag> Printer<Tag> for $printer {
+            fn name(&self) -> &'static str { $name }
+            fn title(&self) -> &'static str { $title }
+            fn print(&self, $expr: &Expr<Tag>, $n: usize) -> String { $body }
+        }
+    };
+}
+
+impl_printer!(InfixPrinter, "infix", "Infix notation", |expr, n| InfixPrinter::print(expr, n));
+impl_printer!(SExprPrinter, "sexpr", "S-expression format", |expr, n| SExprPrinter::print(expr, n));
+impl_printer!(SSAPrinter, "ssa", "SSA format", |expr, _n| SSAPrinter::print(expr));
+impl_printer!(LatexPrinter, "latex", "LaTeX", |expr, n| LatexPrinter::print(expr, n));
+impl_printer!(DotPrinter, "dot", "GraphViz DOT", |expr, n| DotPrinter::print(expr, n));
+impl_printer!(JuliaPrinter, "julia", "Julia function", |expr, n| JuliaPrinter::print(expr, n));
+impl_printer!(CPrinter, "c", "C function", |expr, n| CPrinter::print(expr, n));
+impl_printer!(RustReproPrinter, "rust", "Rust SimpleExpr builder", |expr, _n| RustReproPrinter::builder(expr, 0, &mut RustImports::default()));
+
+/// Every name `printer` knows, in the order `adfuzz` and the README list them.
+pub const PRINTER_NAMES: &[&str] = &["infix", "sexpr", "ssa", "latex", "dot", "julia", "c", "rust"];
+
+/// The registry: the printer called `name`, `None` for unknown names.
+pub fn printer<Tag: 'static>(name: &str) -> Option<&'static dyn Printer<Tag>> {
+    Some(match name {
+        "infix" => &InfixPrinter,
+        "sexpr" => &SExprPrinter,
+        "ssa" => &SSAPrinter,
+        "latex" => &LatexPrinter,
+        "dot" => &DotPrinter,
+        "julia" => &JuliaPrinter,
+        "c" => &CPrinter,
+        "rust" => &RustReproPrinter,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_ssa_let_shadowing_and_if() {
+        // let a = x_0 * 2 in (let a = a + 1, b = a in a / b)
+        let inner = Expr::Let(
+            (),
+            vec![
+                ("a".to_string(), SimpleExpr::add(SimpleExpr::var("a"), SimpleExpr::num(1.0))),
+                ("b".to_string(), SimpleExpr::var("a")),
+            ],
+            Box::new(SimpleExpr::div(SimpleExpr::var("a"), SimpleExpr::var("b"))),
+        );
+        let expr = Expr::Let((), vec![("a".to_string(), SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)))], Box::new(inner));
+        // b sees the outer a, as in evaluate()
+        assert_eq!(SSAPrinter::print(&expr), "\
+t0 = x_0 * 2.0
+a__1 = t0
+t2 = a__1 + 1.0
+a__3 = t2
+b__4 = a__1
+t5 = a__3 / b__4
+return t5");
+
+        let cond = Expr::If((), Box::new(Expr::Boolean((), true)), Box::new(SimpleExpr::sin(SimpleExpr::var("x_0"))), Box::new(SimpleExpr::num(f64::NAN)));
+        assert_eq!(SSAPrinter::print(&cond), "t0 = sin(x_0)\nt1 = t0 if True else nan\nreturn t1");
+
+        let block = Expr::Block((), vec![
+            Expr::Set((), "x_0".to_string(), Box::new(SimpleExpr::neg(SimpleExpr::var("x_0")))),
+            SimpleExpr::cast(Type::Int, SimpleExpr::var("x_0")),
+        ]);
+        assert_eq!(SSAPrinter::print(&block), "t0 = -x_0\nx_0__1 = t0\nt2 = float(trunc(x_0__1))\nreturn t2");
+        assert_eq!(SSAPrinter::print(&SimpleExpr::var("x_1")), "x_1");
+    }
+
+    #[test]
+    fn test_printer_registry() {
+        let expr = SimpleExpr::div(SimpleExpr::sqrt(SimpleExpr::var("x_0")), SimpleExpr::pow(SimpleExpr::var("x_1"), SimpleExpr::num(-2.0)));
+        assert_eq!(LatexPrinter::print(&expr, 2), r"\frac{\sqrt{x_{0}}}{{x_{1}}^{\left(-2\right)}}");
+
+        for name in PRINTER_NAMES {
+            let printer = printer::<()>(name).unwrap();
+            assert_eq!(printer.name(), *name);
+            assert!(!printer.print(&expr, 2).starts_with("<error"));
+        }
+        assert_eq!(printer::<()>("infix").unwrap().print(&expr, 2), InfixPrinter::print(&expr, 2));
+        assert!(printer::<()>("pdf").is_none());
+    }
+
+    #[test]
+    fn test_rust_repro_printer() {
+        let expr = Expr::Let(
+            (),
+            vec![("a".to_string(), SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)))],
+            Box::new(SimpleExpr::sub(SimpleExpr::sin(SimpleExpr::var("a")), SimpleExpr::cast(Type::Int, SimpleExpr::var("x_1")))),
+        );
+        let inputs = [0.5, f64::NEG_INFINITY];
+        let source = RustReproPrinter::print(&expr, &inputs, Some("rev_fwd"), "Rev vs Fwd failed");
+        // examples/repro_sample.rs is this output, so building the examples compiles it
+        let stem = RustReproPrinter::file_stem(&expr, &inputs);
+        assert_eq!(source.replace(&stem, "repro_sample"), include_str!("../../examples/repro_sample.rs"));
     }
 }
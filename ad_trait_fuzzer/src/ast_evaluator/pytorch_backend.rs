@@ -3,11 +3,12 @@
 // AST -> PyTorch
 
 use tch::Tensor;
-use crate::ast_expr::Expr;
+use crate::ast_expr::{Expr, Type};
 use crate::fuzz_harness::PyTorchComputable;
-use super::{MainBackend, evaluate};
+use super::{MainBackend, evaluate, InfixPrinter};
 use std::collections::HashMap;
-use std::error::Error;
+use crate::error::FuzzError;
+use std::sync::Arc;
 
 macro_rules! impl_unary_ops {
     ($wrapper:ty, .$field:tt) => {
@@ -59,20 +60,28 @@ impl MainBackend for PyTorchTensor {
     
     impl_unary_ops!(PyTorchTensor, .0);
     impl_binary_ops!(PyTorchTensor, .0);
+
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => PyTorchTensor(self.0.trunc()),
+            Type::Bool => PyTorchTensor(self.0.ne(0.0).to_kind(tch::Kind::Double)),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct PyTorchEvaluator<Tag: Clone> {
-    pub expr: Expr<Tag>,
+    pub expr: Arc<Expr<Tag>>,
     pub num_inputs: usize,
     pub num_outputs: usize,
 }
 
 // specific eval for PyTorch
 impl<Tag: Clone> PyTorchComputable for PyTorchEvaluator<Tag> {
-    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, Box<dyn Error>> {
+    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, FuzzError> {
         if inputs.len() < self.num_inputs {
-            return Err("Insufficient inputs".into());
+            return Err(FuzzError::InputMismatch { expected: self.num_inputs, got: inputs.len() });
         }
         
         let mut env = HashMap::new();
@@ -80,12 +89,14 @@ impl<Tag: Clone> PyTorchComputable for PyTorchEvaluator<Tag> {
             env.insert(format!("x_{}", i), PyTorchTensor(input.shallow_clone()));
         }
         
-        match evaluate(&self.expr, &env) {
-            Ok(PyTorchTensor(result)) => Ok(vec![result]),
-            Err(e) => Err(e.into()),
-        }
+        let PyTorchTensor(result) = evaluate(&self.expr, &env)?;
+        Ok(vec![result])
     }
     
     fn num_inputs(&self) -> usize { self.num_inputs }
     fn num_outputs(&self) -> usize { self.num_outputs }
+
+    fn infix_expr(&self) -> Option<String> {
+        Some(InfixPrinter::print(&self.expr, self.num_inputs))
+    }
 }
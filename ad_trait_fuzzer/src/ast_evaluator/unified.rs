@@ -1,40 +1,51 @@
 // src/ast_evaluator/unified.rs
 
 
-use crate::ast_expr::Expr;
+use std::sync::{Arc, OnceLock};
+
+use crate::ast_canonical::canonicalize;
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::ast_parser::parse_infix;
 use crate::fuzz_harness::{Calculator, PyTorchComputable};
-use super::{AdEvaluator, PyTorchEvaluator, EvalexprEvaluator, InfixPrinter};
+use crate::oracles::{replace_constant, swap_variables};
+use super::{AdEvaluator, Dual, EvalexprEvaluator, InfixPrinter, MainBackend};
+#[cfg(feature = "pytorch")]
+use super::PyTorchEvaluator;
 use ad_trait::AD;
+#[cfg(feature = "pytorch")]
 use tch::Tensor;
-use std::error::Error;
+use crate::error::FuzzError;
 
 
-/// Unified eval for both AD and PyTorch
+/// Unified eval for both AD and PyTorch. All engines share one copy of the expression: the
+/// harness clones the calculator for every stage and thread, which used to copy the whole tree
+/// each time.
 
 #[derive(Clone)]
 pub struct AdPyUnified<Tag: Clone> {
     ad_eval: AdEvaluator<Tag>,
+    #[cfg(feature = "pytorch")]
     pytorch_eval: PyTorchEvaluator<Tag>,
     num_inputs: usize,
-    expr: Expr<Tag>,
+    expr: Arc<Expr<Tag>>,
+    /// `PyTorchComputable::canonical_expr`, computed once and shared by the clones.
+    canonical: Arc<OnceLock<Option<Arc<SimpleExpr>>>>,
 }
 
 impl<Tag: Clone + std::fmt::Debug> AdPyUnified<Tag> {
-    pub fn new(expr: Expr<Tag>, num_inputs: usize, num_outputs: usize) -> Self {
-
+    pub fn new(expr: impl Into<Arc<Expr<Tag>>>, num_inputs: usize, num_outputs: usize) -> Self {
+        let expr = expr.into();
         AdPyUnified {
-            ad_eval: AdEvaluator {
-                expr: expr.clone(),
-                num_inputs,
-                num_outputs,
-            },
+            ad_eval: AdEvaluator::new(expr.clone(), num_inputs, num_outputs),
+            #[cfg(feature = "pytorch")]
             pytorch_eval: PyTorchEvaluator {
                 expr: expr.clone(),
                 num_inputs,
                 num_outputs,
             },
             num_inputs: num_inputs,
-            expr: expr.clone(),
+            expr,
+            canonical: Arc::new(OnceLock::new()),
         }
     }
     
@@ -52,6 +63,10 @@ impl<Tag: Clone> Calculator for AdPyUnified<Tag> {
         self.ad_eval.eval_expr(inputs)
     }
     
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        self.ad_eval.eval_backend(inputs)
+    }
+    
     fn num_inputs(&self) -> usize {
         self.num_inputs
     }
@@ -59,10 +74,39 @@ impl<Tag: Clone> Calculator for AdPyUnified<Tag> {
     fn num_outputs(&self) -> usize {
         self.ad_eval.num_outputs
     }
+
+    fn same_function(&self, other: &Self) -> bool {
+        self.ad_eval.same_function(&other.ad_eval)
+    }
+
+    fn relabeled(&self, pair: (usize, usize)) -> Option<Self> {
+        Some(self.with_expr(swap_variables((*self.expr).clone(), pair)))
+    }
+
+    fn with_constant(&self, index: usize, value: f64) -> Option<Self> {
+        Some(self.with_expr(replace_constant((*self.expr).clone(), index, |tag| Expr::Number(tag, value))))
+    }
+}
+
+impl<Tag: Clone> AdPyUnified<Tag> {
+    /// The same engines over `expr`.
+    fn with_expr(&self, expr: Expr<Tag>) -> Self {
+        let expr = Arc::new(expr);
+        let mut copy = self.clone();
+        copy.ad_eval = AdEvaluator::new(expr.clone(), self.ad_eval.num_inputs, self.ad_eval.num_outputs);
+        #[cfg(feature = "pytorch")]
+        {
+            copy.pytorch_eval.expr = expr.clone();
+        }
+        copy.expr = expr;
+        copy.canonical = Arc::new(OnceLock::new());
+        copy
+    }
 }
 
 impl<Tag: Clone> PyTorchComputable for AdPyUnified<Tag> {
-    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, Box<dyn Error>> {
+    #[cfg(feature = "pytorch")]
+    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, FuzzError> {
         self.pytorch_eval.compute_pytorch(inputs)
     }
     
@@ -73,6 +117,21 @@ impl<Tag: Clone> PyTorchComputable for AdPyUnified<Tag> {
     fn num_outputs(&self) -> usize {
         self.ad_eval.num_outputs
     }
+
+    fn infix_expr(&self) -> Option<String> {
+        Some(InfixPrinter::print(&self.expr, self.num_inputs))
+    }
+
+    fn canonical_expr(&self) -> Option<Arc<SimpleExpr>> {
+        self.canonical.get_or_init(|| {
+            let (expr, _) = parse_infix(&self.infix_expr()?).ok()?;
+            Some(Arc::new(canonicalize(&expr)))
+        }).clone()
+    }
+
+    fn eval_dual(&self, inputs: &[Dual]) -> Result<Dual, FuzzError> {
+        self.ad_eval.eval_backend(inputs)
+    }
 }
 
 
@@ -80,23 +139,24 @@ impl<Tag: Clone> PyTorchComputable for AdPyUnified<Tag> {
 #[derive(Clone)]
 pub struct EvalexprPyUnified<Tag: Clone> {
     evalexpr_eval: EvalexprEvaluator<Tag>,
-    pytorch_eval: super::PyTorchEvaluator<Tag>,
+    #[cfg(feature = "pytorch")]
+    pytorch_eval: PyTorchEvaluator<Tag>,
     num_inputs: usize,
-    expr: Expr<Tag>,
+    expr: Arc<Expr<Tag>>,
 }
 
 impl<Tag: Clone> EvalexprPyUnified<Tag> {
-    pub fn new(expr: Expr<Tag>, num_inputs: usize) -> Result<Self, Box<dyn Error>> {
+    pub fn new(expr: impl Into<Arc<Expr<Tag>>>, num_inputs: usize) -> Result<Self, FuzzError> {
+        let expr = expr.into();
         let evalexpr_eval = EvalexprEvaluator::new(expr.clone(), num_inputs)?;
-        let pytorch_eval = super::PyTorchEvaluator {
-            expr: expr.clone(),
-            num_inputs,
-            num_outputs: 1,
-        };
-        
         Ok(EvalexprPyUnified {
             evalexpr_eval,
-            pytorch_eval,
+            #[cfg(feature = "pytorch")]
+            pytorch_eval: PyTorchEvaluator {
+                expr: expr.clone(),
+                num_inputs,
+                num_outputs: 1,
+            },
             num_inputs,
             expr,
         })
@@ -116,7 +176,8 @@ impl<Tag: Clone> EvalexprPyUnified<Tag> {
 }
 
 impl<Tag: Clone> PyTorchComputable for EvalexprPyUnified<Tag> {
-    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, Box<dyn Error>> {
+    #[cfg(feature = "pytorch")]
+    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, FuzzError> {
         self.pytorch_eval.compute_pytorch(inputs)
     }
     
@@ -127,4 +188,8 @@ impl<Tag: Clone> PyTorchComputable for EvalexprPyUnified<Tag> {
     fn num_outputs(&self) -> usize {
         1
     }
+
+    fn infix_expr(&self) -> Option<String> {
+        Some(self.expr_string())
+    }
 }
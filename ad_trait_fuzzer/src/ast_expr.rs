@@ -1,8 +1,12 @@
 // src/ast_expr.rs
 
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::mem;
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum Op2 {
     Add,      // +
     Sub,      // -
@@ -11,7 +15,7 @@ pub enum Op2 {
     Pow,      // ^
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum Op1 {
     Neg,      // -x
     Sin,      // sin(x)
@@ -24,7 +28,7 @@ pub enum Op1 {
 }
 
 /// Type annots (for future use for if conditions, type checking, etc.)
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum Type {
     Float,
     Int,
@@ -33,7 +37,7 @@ pub enum Type {
 
 /// Main AST Expr type
 /// T is a tag/metadata type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Expr<T> {
     Number(T, f64),
     
@@ -86,6 +90,71 @@ impl<T> Expr<T> {
     }
 }
 
+/// Structural equality: tags are ignored and numbers compare by bits, so `NaN == NaN` and
+/// `0.0 != -0.0`, which makes it an equivalence for `Eq`/`Hash` (caches, dedup maps, corpora).
+/// Expressions that only compute the same function are not equal, see `ast_canonical` for that.
+impl<T> PartialEq for Expr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Number(_, a), Expr::Number(_, b)) => a.to_bits() == b.to_bits(),
+            (Expr::Boolean(_, a), Expr::Boolean(_, b)) => a == b,
+            (Expr::Id(_, a), Expr::Id(_, b)) => a == b,
+            (Expr::Let(_, a, body_a), Expr::Let(_, b, body_b)) => a == b && body_a == body_b,
+            (Expr::UnOp(_, op_a, a), Expr::UnOp(_, op_b, b)) => op_a == op_b && a == b,
+            (Expr::BinOp(_, op_a, la, ra), Expr::BinOp(_, op_b, lb, rb)) => op_a == op_b && la == lb && ra == rb,
+            (Expr::If(_, ca, ta, ea), Expr::If(_, cb, tb, eb)) => ca == cb && ta == tb && ea == eb,
+            (Expr::Loop(_, a), Expr::Loop(_, b)) => a == b,
+            (Expr::Break(_, a), Expr::Break(_, b)) => a == b,
+            (Expr::Set(_, name_a, a), Expr::Set(_, name_b, b)) => name_a == name_b && a == b,
+            (Expr::Block(_, a), Expr::Block(_, b)) => a == b,
+            (Expr::Cast(_, ty_a, a), Expr::Cast(_, ty_b, b)) => ty_a == ty_b && a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Eq for Expr<T> {}
+
+/// Consistent with `PartialEq`: tags skipped, numbers hashed by bits.
+impl<T> Hash for Expr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        match self {
+            Expr::Number(_, n) => n.to_bits().hash(state),
+            Expr::Boolean(_, b) => b.hash(state),
+            Expr::Id(_, name) => name.hash(state),
+            Expr::Let(_, bindings, body) => {
+                bindings.hash(state);
+                body.hash(state);
+            }
+            Expr::UnOp(_, op, e) => {
+                op.hash(state);
+                e.hash(state);
+            }
+            Expr::BinOp(_, op, l, r) => {
+                op.hash(state);
+                l.hash(state);
+                r.hash(state);
+            }
+            Expr::If(_, c, t, e) => {
+                c.hash(state);
+                t.hash(state);
+                e.hash(state);
+            }
+            Expr::Loop(_, e) | Expr::Break(_, e) => e.hash(state),
+            Expr::Set(_, name, e) => {
+                name.hash(state);
+                e.hash(state);
+            }
+            Expr::Block(_, exprs) => exprs.hash(state),
+            Expr::Cast(_, ty, e) => {
+                ty.hash(state);
+                e.hash(state);
+            }
+        }
+    }
+}
+
 /// Simple unit with no meta
 pub type SimpleExpr = Expr<()>;
 
@@ -127,6 +196,10 @@ impl SimpleExpr {
         Expr::UnOp((), Op1::Cos, Box::new(expr))
     }
     
+    pub fn tan(expr: SimpleExpr) -> Self {
+        Expr::UnOp((), Op1::Tan, Box::new(expr))
+    }
+    
     pub fn exp(expr: SimpleExpr) -> Self {
         Expr::UnOp((), Op1::Exp, Box::new(expr))
     }
@@ -146,7 +219,29 @@ impl SimpleExpr {
     pub fn abs(expr: SimpleExpr) -> Self {
         Expr::UnOp((), Op1::Abs, Box::new(expr))
     }
+
+    pub fn cast(ty: Type, expr: SimpleExpr) -> Self {
+        Expr::Cast((), ty, Box::new(expr))
+    }
 }
 
 /// Environment for variable bindings during evaluation
 pub type Env<T> = HashMap<String, T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_structural_eq() {
+        let tagged = |tag: usize, n: f64| Expr::BinOp(tag, Op2::Mul, Box::new(Expr::Id(tag, "x_0".to_string())), Box::new(Expr::Number(tag, n)));
+        assert_eq!(tagged(1, 2.0), tagged(2, 2.0));
+        assert_eq!(tagged(1, f64::NAN), tagged(2, f64::NAN));
+        assert_ne!(tagged(1, 0.0), tagged(1, -0.0));
+        assert_ne!(SimpleExpr::sin(SimpleExpr::var("x_0")), SimpleExpr::cos(SimpleExpr::var("x_0")));
+
+        let set: HashSet<Expr<usize>> = [tagged(1, 2.0), tagged(2, 2.0), tagged(3, f64::NAN), tagged(4, f64::NAN)].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
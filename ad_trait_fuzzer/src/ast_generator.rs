@@ -1,8 +1,12 @@
 // src/ast_generator.rs
 
-use crate::ast_expr::{Expr, Op1, Op2};
+use crate::ast_dead_code::eliminate_dead_bindings;
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::ast_typecheck::typecheck;
+use crate::expr_coverage::{op1_kind, op2_kind, pair_seen};
 use arbitrary::{Arbitrary, Unstructured, Error as ArbitraryError};
 use std::collections::HashSet;
+use std::str::FromStr;
 
 /// Config for AST
 #[derive(Debug, Clone)]
@@ -12,8 +16,41 @@ pub struct AstGenConfig {
     pub allow_division: bool,
     pub allow_power: bool,
     pub allow_log: bool,
+    pub allow_cast: bool,
+    pub grammar: Grammar,
+    /// Steer operator choices to (operator, operand) pairs the campaign has not generated yet,
+    /// see `expr_coverage::CoverageMatrix`. The same bytes then give different expressions as
+    /// the campaign goes on, so artifacts only replay exactly with it off.
+    pub coverage_bias: bool,
 }
 
+/// Which expressions `generate_from_bytes` produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grammar {
+    /// Every operator allowed by the config, see `generate_expr_arbitrary`.
+    General,
+    /// Nested `pow` with bases and exponents from `POW_BASES` / `POW_EXPONENTS`, see `generate_pow`.
+    Pow,
+}
+
+impl FromStr for Grammar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "general" => Ok(Grammar::General),
+            "pow" => Ok(Grammar::Pow),
+            _ => Err(format!("unknown grammar '{}' (expected \"general\" or \"pow\")", s)),
+        }
+    }
+}
+
+/// Constant bases for `Grammar::Pow`: negative, signed zero, one, subnormal-adjacent, huge.
+pub const POW_BASES: [f64; 12] = [0.0, -0.0, 1.0, -1.0, -2.0, 0.5, -0.5, 2.0, 1e-300, f64::MIN_POSITIVE, 1e300, -1e300];
+
+/// Constant exponents for `Grammar::Pow`: zero (for 0^0), fractional, negative, huge.
+pub const POW_EXPONENTS: [f64; 15] = [0.0, -0.0, 1.0, -1.0, 0.5, -0.5, 1.0 / 3.0, 1.5, 2.0, 3.0, -2.0, 1e3, 1e10, -1e10, 1e308];
+
 #[derive(Debug, Clone)]
 pub struct GeneratedExpr {
     pub expr: Expr<()>,
@@ -29,6 +66,9 @@ impl Default for AstGenConfig {
             allow_division: true,
             allow_power: true,
             allow_log: false,
+            allow_cast: false,
+            grammar: Grammar::General,
+            coverage_bias: false,
         }
     }
 }
@@ -72,11 +112,13 @@ pub fn generate_expr_arbitrary(
         return generate_terminal(u, config, used_vars, var_stack);
     }
 
-    // Choose between terminal, unary, or binary
-    match u.int_in_range(0..=2)? {
+    // Choose between terminal, unary, binary, or cast
+    let max_choice = if config.allow_cast { 3 } else { 2 };
+    match u.int_in_range(0..=max_choice)? {
         0 => generate_terminal(u, config, used_vars, var_stack),
         1 => generate_unary(u, config, depth, used_vars, var_stack),
-        _ => generate_binary(u, config, depth, used_vars, var_stack),
+        2 => generate_binary(u, config, depth, used_vars, var_stack),
+        _ => generate_cast(u, config, depth, used_vars, var_stack),
     }
 }
 
@@ -87,39 +129,7 @@ fn generate_terminal(
     var_stack: &mut Vec<usize>,
 ) -> Result<Expr<()>, ArbitraryError> {
     if u.ratio(2, 5)? {
-        // Gen a var
-        if u.is_empty()
-        {       
-            return Err(ArbitraryError::NotEnoughData);
-        }
-        
-        let num_used = used_vars.len();
-        let num_available = config.max_variables - var_stack.len();
-        
-        let var_idx = if num_used == 0 {
-            // No vars, so add x_0
-            var_stack.push(0);
-            used_vars.insert(0);
-            0
-        } else if num_available == 0 {
-            // Must reuse existing var
-            let existing: Vec<_> = used_vars.iter().copied().collect();
-            existing[u.int_in_range(0..=existing.len() - 1)?]
-        } else {
-            // Probability of reusing a var vs creating a new one
-            if u.ratio(num_used as u32, (num_used + num_available) as u32)? {
-                let existing: Vec<_> = used_vars.iter().copied().collect();
-                existing[u.int_in_range(0..=existing.len() - 1)?]
-            } else {
-                let new_idx = var_stack.len();
-                var_stack.push(new_idx);
-                used_vars.insert(new_idx);
-                new_idx
-            }
-        };
-        
-        let name = format!("x_{}", var_idx);
-        Ok(Expr::Id((), name))
+        generate_variable(u, config, used_vars, var_stack)
     } else {
         // Gen a number
         let val = match u.int_in_range(0..=4)? {
@@ -133,6 +143,46 @@ fn generate_terminal(
     }
 }
 
+fn generate_variable(
+    u: &mut Unstructured,
+    config: &AstGenConfig,
+    used_vars: &mut HashSet<usize>,
+    var_stack: &mut Vec<usize>,
+) -> Result<Expr<()>, ArbitraryError> {
+    if u.is_empty()
+    {       
+        return Err(ArbitraryError::NotEnoughData);
+    }
+    
+    let num_used = used_vars.len();
+    let num_available = config.max_variables - var_stack.len();
+    
+    let var_idx = if num_used == 0 {
+        // No vars, so add x_0
+        var_stack.push(0);
+        used_vars.insert(0);
+        0
+    } else if num_available == 0 {
+        // Must reuse existing var
+        let existing: Vec<_> = used_vars.iter().copied().collect();
+        existing[u.int_in_range(0..=existing.len() - 1)?]
+    } else {
+        // Probability of reusing a var vs creating a new one
+        if u.ratio(num_used as u32, (num_used + num_available) as u32)? {
+            let existing: Vec<_> = used_vars.iter().copied().collect();
+            existing[u.int_in_range(0..=existing.len() - 1)?]
+        } else {
+            let new_idx = var_stack.len();
+            var_stack.push(new_idx);
+            used_vars.insert(new_idx);
+            new_idx
+        }
+    };
+    
+    let name = format!("x_{}", var_idx);
+    Ok(Expr::Id((), name))
+}
+
 fn generate_unary(
     u: &mut Unstructured,
     config: &AstGenConfig,
@@ -159,6 +209,16 @@ fn generate_unary(
         _ => Op1::Abs,
     };
     
+    let op = if config.coverage_bias {
+        let mut allowed = vec![Op1::Neg, Op1::Sin, Op1::Cos, Op1::Tan, Op1::Exp, Op1::Sqrt, Op1::Abs];
+        if config.allow_log {
+            allowed.push(Op1::Log);
+        }
+        prefer_uncovered(u, op, &allowed, op1_kind, &[&sub_expr])?
+    } else {
+        op
+    };
+    
     Ok(Expr::UnOp((), op, Box::new(sub_expr)))
 }
 
@@ -190,16 +250,103 @@ fn generate_binary(
         4 if config.allow_power => Op2::Pow,
         _ => Op2::Add, // Default fallback
     };
+    let op = if config.coverage_bias {
+        let allowed: Vec<Op2> = [(Op2::Add, true), (Op2::Sub, true), (Op2::Mul, true), (Op2::Div, config.allow_division), (Op2::Pow, config.allow_power)]
+            .into_iter().filter(|(_, on)| *on).map(|(op, _)| op).collect();
+        prefer_uncovered(u, op, &allowed, op2_kind, &[&left, &right])?
+    } else {
+        op
+    };
     
     Ok(Expr::BinOp((), op, Box::new(left), Box::new(right)))
 }
 
-/// Generate from fuzzer bytes using arbitrary
+/// Half of the time, swaps `op` for an allowed operator that has not been generated over one of
+/// `operands` yet, if there is one.
+fn prefer_uncovered<T: Clone>(
+    u: &mut Unstructured,
+    op: T,
+    allowed: &[T],
+    kind: fn(&T) -> usize,
+    operands: &[&Expr<()>],
+) -> Result<T, ArbitraryError> {
+    if !u.ratio(1, 2)? {
+        return Ok(op);
+    }
+    let uncovered: Vec<&T> = allowed.iter().filter(|op| operands.iter().any(|e| !pair_seen(kind(op), e))).collect();
+    if uncovered.is_empty() {
+        return Ok(op);
+    }
+    Ok((*u.choose(&uncovered)?).clone())
+}
+
+fn generate_cast(
+    u: &mut Unstructured,
+    config: &AstGenConfig,
+    depth: usize,
+    used_vars: &mut HashSet<usize>,
+    var_stack: &mut Vec<usize>,
+) -> Result<Expr<()>, ArbitraryError> {
+    let sub_expr = generate_expr_arbitrary(u, config, depth + 1, used_vars, var_stack)?;
+    
+    let ty = match u.int_in_range(0..=2)? {
+        0 => Type::Float,
+        1 => Type::Int,
+        _ => Type::Bool,
+    };
+    
+    let cast = Expr::Cast((), ty.clone(), Box::new(sub_expr));
+    // Booleans are not numbers: back to 0/1 before any arithmetic sees them
+    Ok(if ty == Type::Bool { Expr::Cast((), Type::Float, Box::new(cast)) } else { cast })
+}
+
+/// `Grammar::Pow`: a `pow` whose base and exponent are each a variable, a (negated) variable,
+/// a pool constant, another `pow` or, rarely, a general subexpression.
+pub fn generate_pow(
+    u: &mut Unstructured,
+    config: &AstGenConfig,
+    depth: usize,
+    used_vars: &mut HashSet<usize>,
+    var_stack: &mut Vec<usize>,
+) -> Result<Expr<()>, ArbitraryError> {
+    let base = generate_pow_operand(u, config, depth + 1, &POW_BASES, used_vars, var_stack)?;
+    let exponent = generate_pow_operand(u, config, depth + 1, &POW_EXPONENTS, used_vars, var_stack)?;
+    Ok(Expr::BinOp((), Op2::Pow, Box::new(base), Box::new(exponent)))
+}
+
+fn generate_pow_operand(
+    u: &mut Unstructured,
+    config: &AstGenConfig,
+    depth: usize,
+    pool: &[f64],
+    used_vars: &mut HashSet<usize>,
+    var_stack: &mut Vec<usize>,
+) -> Result<Expr<()>, ArbitraryError> {
+    let max_choice = if depth >= config.max_depth { 2 } else { 4 };
+    match u.int_in_range(0..=max_choice)? {
+        0 => generate_variable(u, config, used_vars, var_stack),
+        1 => Ok(Expr::Number((), *u.choose(pool)?)),
+        2 => Ok(Expr::UnOp((), Op1::Neg, Box::new(generate_variable(u, config, used_vars, var_stack)?))),
+        3 => generate_pow(u, config, depth, used_vars, var_stack),
+        _ => generate_expr_arbitrary(u, config, depth, used_vars, var_stack),
+    }
+}
+
+/// Generate from fuzzer bytes using arbitrary. Candidates `typecheck` rejects, or that are not
+/// numbers, are discarded with `IncorrectFormat`; dead `Let` bindings are removed, see
+/// `ast_dead_code`.
 pub fn generate_from_bytes(data: &[u8], config: AstGenConfig) -> Result<GeneratedExpr, ArbitraryError> {
     let mut u = Unstructured::new(data);
     let mut used_vars = HashSet::new();
     let mut var_stack = Vec::new();
-    let expr = generate_expr_arbitrary(&mut u, &config, 0, &mut used_vars, &mut var_stack)?;
+    let expr = match config.grammar {
+        Grammar::General => generate_expr_arbitrary(&mut u, &config, 0, &mut used_vars, &mut var_stack)?,
+        Grammar::Pow => generate_pow(&mut u, &config, 0, &mut used_vars, &mut var_stack)?,
+    };
+    if !matches!(typecheck(&expr), Ok(Type::Float | Type::Int)) {
+        return Err(ArbitraryError::IncorrectFormat);
+    }
+    let expr = eliminate_dead_bindings(expr);
     
     let num_inputs = used_vars.len();
     
@@ -231,6 +378,75 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_generate_with_cast() {
+        let config = AstGenConfig {
+            allow_cast: true,
+            ..Default::default()
+        };
+        
+        let casts = |expr: &Expr<()>| {
+            let mut count = 0;
+            crate::ast_visitor::for_each_node(expr, |node| count += matches!(node, Expr::Cast(..)) as usize);
+            count
+        };
+        let (mut with_cast, mut without_cast) = (0, 0);
+        for i in 0..50u64 {
+            let data: Vec<u8> = (0..256u64).map(|j| ((i * 256 + j).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8).collect();
+            if let Ok(gen) = generate_from_bytes(&data, config.clone()) {
+                assert!(typecheck(&gen.expr).is_ok(), "{:?}", gen.expr);
+                with_cast += casts(&gen.expr);
+            }
+            if let Ok(gen) = generate_from_bytes(&data, AstGenConfig::default()) {
+                without_cast += casts(&gen.expr);
+            }
+        }
+        assert!(with_cast > 0);
+        assert_eq!(without_cast, 0);
+    }
+    
+    #[test]
+    fn test_generate_pow() {
+        let config = AstGenConfig { grammar: Grammar::Pow, ..Default::default() };
+        for i in 0..20 {
+            let data = format!("pow data {} with some more bytes", i).into_bytes();
+            if let Ok(gen) = generate_from_bytes(&data, config.clone()) {
+                assert!(matches!(gen.expr, Expr::BinOp(_, Op2::Pow, _, _)), "{:?}", gen.expr);
+            }
+        }
+        assert_eq!("POW".parse(), Ok(Grammar::Pow));
+        assert!("rpn".parse::<Grammar>().is_err());
+    }
+    
+    #[test]
+    fn test_coverage_bias() {
+        let config = AstGenConfig { coverage_bias: true, allow_division: false, ..Default::default() };
+        let unbiased = AstGenConfig { coverage_bias: false, ..config.clone() };
+        // Tan is never an unbiased choice, so only the bias towards unseen pairs generates it
+        let has_tan = |expr: &Expr<()>| {
+            let mut found = false;
+            crate::ast_visitor::for_each_node(expr, |node| found |= matches!(node, Expr::UnOp(_, Op1::Tan, _)));
+            found
+        };
+        let (mut biased_tan, mut unbiased_tan) = (0, 0);
+        for i in 0..100u64 {
+            let data: Vec<u8> = (0..256u64).map(|j| ((i * 256 + j).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 56) as u8).collect();
+            if let Ok(gen) = generate_from_bytes(&data, config.clone()) {
+                // Only operators the config allows, whatever the matrix says
+                crate::ast_visitor::for_each_node(&gen.expr, |node| {
+                    assert!(!matches!(node, Expr::UnOp(_, Op1::Log, _) | Expr::BinOp(_, Op2::Div, _, _)), "{:?}", gen.expr);
+                });
+                biased_tan += has_tan(&gen.expr) as usize;
+                crate::expr_coverage::record(&gen.expr);
+            }
+            if let Ok(gen) = generate_from_bytes(&data, unbiased.clone()) {
+                unbiased_tan += has_tan(&gen.expr) as usize;
+            }
+        }
+        assert_eq!(unbiased_tan, 0);
+        assert!(biased_tan > 0);
+    }
+    
     #[test]
     fn test_generate_multiple() {
         let config = AstGenConfig::default();
@@ -0,0 +1,125 @@
+// src/ast_localizer.rs
+
+// Fault localization for failing expressions. Every subtree is run on its own as a function of
+// the same inputs; the disagreement "first appears" at a failing subtree whose children all
+// pass, since everything below it is computed correctly by every engine. Of those, the smallest
+// is reported. Subtrees that occur several times are run once (`Expr` equality ignores tags), and
+// subtrees that cannot run on their own (variables bound by an enclosing `Let`) count as passing.
+
+use std::collections::HashMap;
+
+use crate::ast_evaluator::constant_value;
+use crate::ast_evaluator::unified::AdPyUnified;
+use crate::ast_expr::SimpleExpr;
+use crate::ast_shrinker::size;
+use crate::ast_visitor::children;
+use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator};
+use crate::oracles::FuzzingOracles;
+
+#[derive(Debug, Clone)]
+pub struct Localization {
+    /// The smallest failing subtree whose children all pass.
+    pub subtree: SimpleExpr,
+    /// Child indices (see `ast_visitor::children`) from the root down to `subtree`.
+    pub path: Vec<usize>,
+    /// Distinct subtrees that were run, and how many of them failed.
+    pub tested: usize,
+    pub failed: usize,
+}
+
+struct Localizer<F> {
+    fails: F,
+    verdicts: HashMap<SimpleExpr, bool>,
+    /// (size, path, subtree) of the best origin so far
+    best: Option<(usize, Vec<usize>, SimpleExpr)>,
+}
+
+impl<F: FnMut(&SimpleExpr) -> bool> Localizer<F> {
+    fn fails(&mut self, expr: &SimpleExpr) -> bool {
+        if let Some(&verdict) = self.verdicts.get(expr) {
+            return verdict;
+        }
+        let verdict = (self.fails)(expr);
+        self.verdicts.insert(expr.clone(), verdict);
+        verdict
+    }
+
+    /// Runs `expr` and everything below it; returns whether `expr` fails.
+    fn visit(&mut self, expr: &SimpleExpr, path: &mut Vec<usize>) -> bool {
+        let mut any_child_fails = false;
+        for (i, child) in children(expr).into_iter().enumerate() {
+            path.push(i);
+            any_child_fails |= self.visit(child, path);
+            path.pop();
+        }
+        let fails = self.fails(expr);
+        if fails && !any_child_fails {
+            let size = size(expr);
+            if self.best.as_ref().is_none_or(|(best, ..)| size < *best) {
+                self.best = Some((size, path.clone(), expr.clone()));
+            }
+        }
+        fails
+    }
+}
+
+/// Where in `expr` the failure `fails` detects first appears, see the top of the file. `None` if
+/// `expr` itself does not fail.
+pub fn localize(expr: &SimpleExpr, fails: impl FnMut(&SimpleExpr) -> bool) -> Option<Localization> {
+    let mut localizer = Localizer { fails, verdicts: HashMap::new(), best: None };
+    if !localizer.visit(expr, &mut Vec::new()) {
+        return None;
+    }
+    let (_, path, subtree) = localizer.best.expect("a failing expression has a failing origin");
+    let failed = localizer.verdicts.values().filter(|&&fails| fails).count();
+    Some(Localization { subtree, path, tested: localizer.verdicts.len(), failed })
+}
+
+/// `localize` with the oracles as the test: a subtree fails if any oracle fails on it at
+/// `inputs`. Constant subtrees are skipped like the fuzz target does.
+pub fn localize_failure(
+    expr: &SimpleExpr,
+    inputs: &[f64],
+    oracles: &FuzzingOracles,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+) -> Option<Localization> {
+    localize(expr, |subtree| {
+        if constant_value(subtree).is_some() {
+            return false;
+        }
+        let calc = AdPyUnified::new(subtree.clone(), inputs.len(), 1);
+        let oracles = oracles.specialize(subtree);
+        matches!(run_ad_tests(inputs, calc, &oracles, gt_calculators), Ok(outcome) if !outcome.passed())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::{Expr, Op1};
+
+    fn has_sqrt(expr: &SimpleExpr) -> bool {
+        matches!(expr, Expr::UnOp(_, Op1::Sqrt, _)) || children(expr).into_iter().any(has_sqrt)
+    }
+
+    #[test]
+    fn test_localize() {
+        let x = || SimpleExpr::var("x_0");
+        // Anything containing a sqrt "fails": the sqrt node itself is the origin
+        let expr = SimpleExpr::mul(SimpleExpr::exp(SimpleExpr::sqrt(SimpleExpr::sin(x()))), SimpleExpr::cos(x()));
+        let found = localize(&expr, has_sqrt).unwrap();
+        assert_eq!(found.subtree, SimpleExpr::sqrt(SimpleExpr::sin(x())));
+        assert_eq!(found.path, vec![0, 0]);
+        assert_eq!(found.failed, 3);
+
+        // The smaller of two origins; the repeated `sin(x_0)` is only run once
+        let expr = SimpleExpr::add(SimpleExpr::sqrt(SimpleExpr::mul(x(), SimpleExpr::sin(x()))), SimpleExpr::sqrt(SimpleExpr::sin(x())));
+        let mut runs = 0;
+        let found = localize(&expr, |e| { runs += 1; has_sqrt(e) }).unwrap();
+        assert_eq!(found.path, vec![1]);
+        assert_eq!(runs, found.tested);
+        assert_eq!(found.tested, 6);
+
+        assert!(localize(&expr, |_| false).is_none());
+    }
+}
@@ -0,0 +1,339 @@
+// src/ast_lower.rs
+
+// Lowering of the rich AST to what the engines run.
+//
+// `lower` turns an expression into a `CoreExpr`: a straight-line program of constants, inputs,
+// `Op1`/`Op2` operations and the two numeric casts, each node reading only nodes before it.
+// `Let`, `Block`, `Set`, `If` and `Cast` to `Float` leave no node of their own. `evaluate` runs
+// every `MainBackend` (AD, PyTorch, `Dual`, the string printers, `partial_eval`) on the core, so
+// each of them means the same for all backends, and a node kind the lowering does not know is
+// rejected by all of them alike; `AdEvaluator` lowers once per expression and keeps the core
+// for its AD passes. Printers that show the structure (`SSAPrinter`, `DotPrinter`,
+// `RustReproPrinter`) keep walking the original tree.
+//
+// `lower_blocks` is the same desugaring kept as an `Expr`: `Block` and `Set` as nested `Let`s,
+// for consumers of the tree that only understand pure expressions. Each `Set` (and each `Let`
+// binding) becomes a binding of a fresh `name__N`, and later reads of the variable are renamed to
+// it, like the names `SSAPrinter` assigns; the statements of a `Block` other than the last are
+// bound to a fresh `block__N` so their computation stays in the tree. `If` branches, `Loop` and
+// `Break` get their own bindings, so nothing they assign is hoisted out of them.
+//
+// The semantics, shared by both: evaluation is sequential, left to right, and `Set` rebinds a
+// variable (evaluating to the new value) for everything after it, whether the next `Block`
+// statement or the right operand of the same `BinOp`. A `Let` body keeps its bindings and the
+// `Set`s inside it to itself; binding values see the outer scope and keep their `Set`s to
+// themselves too. A `Block` is the value of its last expression, 0 if it is empty. An `If` needs
+// a condition that does not depend on the inputs and only lowers the branch it takes; `Loop` and
+// `Break` are not supported.
+
+use std::collections::HashMap;
+use std::mem;
+
+use crate::ast_evaluator::{apply_op1, apply_op2, Env, MainBackend};
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::error::FuzzError;
+
+/// One operation of a `CoreExpr`. Operands are indices of earlier nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoreNode {
+    Const(f64),
+    /// A free variable, looked up in the environment.
+    Input(String),
+    Unary(Op1, usize),
+    Binary(Op2, usize, usize),
+    /// `Cast` to `Int`: truncation toward zero.
+    Trunc(usize),
+    /// `Cast` to `Bool`: 1 if non-zero, else 0.
+    Truthy(usize),
+}
+
+/// An expression in the core language, see the top of the file. A `Let`-bound value used twice
+/// is one node read twice, like on the tape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoreExpr {
+    pub nodes: Vec<CoreNode>,
+    /// The node holding the value of the expression.
+    pub root: usize,
+}
+
+impl CoreExpr {
+    /// Runs every node in order, so values nothing reads are still computed, like `Let` bindings
+    /// on the tape.
+    pub fn eval<T: MainBackend>(&self, env: &Env<T>) -> Result<T, FuzzError> {
+        let mut values: Vec<T> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match node {
+                CoreNode::Const(val) => T::from_f64(*val),
+                CoreNode::Input(name) => env.get(name).cloned().ok_or_else(|| FuzzError::UnboundVariable(name.clone()))?,
+                CoreNode::Unary(op, a) => apply_op1(op, values[*a].clone()),
+                CoreNode::Binary(op, a, b) => apply_op2(op, values[*a].clone(), values[*b].clone()),
+                CoreNode::Trunc(a) => values[*a].clone().cast(&Type::Int),
+                CoreNode::Truthy(a) => values[*a].clone().cast(&Type::Bool),
+            };
+            values.push(value);
+        }
+        Ok(values.swap_remove(self.root))
+    }
+
+    /// The value of node `id` if it does not depend on any input.
+    fn constant(&self, id: usize) -> Option<f64> {
+        Some(match &self.nodes[id] {
+            CoreNode::Const(val) => *val,
+            CoreNode::Input(_) => return None,
+            CoreNode::Unary(op, a) => apply_op1(op, self.constant(*a)?),
+            CoreNode::Binary(op, a, b) => apply_op2(op, self.constant(*a)?, self.constant(*b)?),
+            CoreNode::Trunc(a) => self.constant(*a)?.cast(&Type::Int),
+            CoreNode::Truthy(a) => self.constant(*a)?.cast(&Type::Bool),
+        })
+    }
+}
+
+struct CoreLowering {
+    core: CoreExpr,
+    /// The `Input` node of each free variable read so far.
+    inputs: HashMap<String, usize>,
+}
+
+impl CoreLowering {
+    fn push(&mut self, node: CoreNode) -> usize {
+        self.core.nodes.push(node);
+        self.core.nodes.len() - 1
+    }
+
+    fn var(&mut self, name: &str, scope: &HashMap<String, usize>) -> usize {
+        if let Some(&id) = scope.get(name).or_else(|| self.inputs.get(name)) {
+            return id;
+        }
+        let id = self.push(CoreNode::Input(name.to_string()));
+        self.inputs.insert(name.to_string(), id);
+        id
+    }
+
+    /// `scope` maps variables to the nodes holding their current value; `Set` updates it for
+    /// what is lowered after it.
+    fn lower<Tag>(&mut self, expr: &Expr<Tag>, scope: &mut HashMap<String, usize>) -> Result<usize, FuzzError> {
+        Ok(match expr {
+            Expr::Number(_, val) => self.push(CoreNode::Const(*val)),
+            Expr::Boolean(..) => return Err(FuzzError::UnsupportedExpr("Bool not supported in numeric expressions (yet)".to_string())),
+            Expr::Id(_, name) => self.var(name, scope),
+            Expr::Let(_, bindings, body) => {
+                let mut inner = scope.clone();
+                for (name, value) in bindings {
+                    // A `Set` in a binding value stays in it
+                    let id = self.lower(value, &mut scope.clone())?;
+                    inner.insert(name.clone(), id);
+                }
+                self.lower(body, &mut inner)?
+            }
+            Expr::UnOp(_, op, e) => {
+                let a = self.lower(e, scope)?;
+                self.push(CoreNode::Unary(op.clone(), a))
+            }
+            Expr::BinOp(_, op, l, r) => {
+                let a = self.lower(l, scope)?;
+                let b = self.lower(r, scope)?;
+                self.push(CoreNode::Binary(op.clone(), a, b))
+            }
+            Expr::Cast(_, ty, e) => {
+                let a = self.lower(e, scope)?;
+                match ty {
+                    Type::Float => a,
+                    Type::Int => self.push(CoreNode::Trunc(a)),
+                    Type::Bool => self.push(CoreNode::Truthy(a)),
+                }
+            }
+            Expr::Set(_, name, value) => {
+                // Only bound variables can be set: an unbound one fails like a read of it
+                self.var(name, scope);
+                let id = self.lower(value, scope)?;
+                scope.insert(name.clone(), id);
+                id
+            }
+            Expr::Block(_, exprs) => {
+                let mut last = None;
+                for e in exprs {
+                    last = Some(self.lower(e, scope)?);
+                }
+                match last {
+                    Some(id) => id,
+                    None => self.push(CoreNode::Const(0.0)),
+                }
+            }
+            Expr::If(_, cond, then, els) => {
+                let taken = match cond.as_ref() {
+                    Expr::Boolean(_, b) => *b,
+                    cond => {
+                        let id = self.lower(cond, scope)?;
+                        let value = self.core.constant(id)
+                            .ok_or_else(|| FuzzError::UnsupportedExpr("If condition depends on the inputs".to_string()))?;
+                        value != 0.0
+                    }
+                };
+                self.lower(if taken { then } else { els }, &mut scope.clone())?
+            }
+            Expr::Loop(..) | Expr::Break(..) => return Err(FuzzError::UnsupportedExpr("Unsupported expression type".to_string())),
+        })
+    }
+}
+
+/// `expr` in the core language, see the top of the file.
+pub fn lower<Tag>(expr: &Expr<Tag>) -> Result<CoreExpr, FuzzError> {
+    let mut lowering = CoreLowering { core: CoreExpr { nodes: Vec::new(), root: 0 }, inputs: HashMap::new() };
+    lowering.core.root = lowering.lower(expr, &mut HashMap::new())?;
+    Ok(lowering.core)
+}
+
+struct Lowering<Tag> {
+    counter: usize,
+    /// Bindings emitted so far, outermost first.
+    bindings: Vec<(Tag, String, Expr<Tag>)>,
+}
+
+impl<Tag: Clone> Lowering<Tag> {
+    fn bind(&mut self, tag: &Tag, var: &str, value: Expr<Tag>) -> String {
+        let name = format!("{}__{}", var, self.counter);
+        self.counter += 1;
+        self.bindings.push((tag.clone(), name.clone(), value));
+        name
+    }
+
+    /// `expr` lowered with its bindings wrapped around it instead of emitted.
+    fn scoped(&mut self, expr: &Expr<Tag>, scope: &HashMap<String, String>) -> Expr<Tag> {
+        let outer = mem::take(&mut self.bindings);
+        let body = self.lower(expr, &mut scope.clone());
+        let inner = mem::replace(&mut self.bindings, outer);
+        wrap(inner, body)
+    }
+
+    /// `scope` maps source variables to the names bound for them; `Set` updates it for what is
+    /// lowered after it.
+    fn lower(&mut self, expr: &Expr<Tag>, scope: &mut HashMap<String, String>) -> Expr<Tag> {
+        let boxed = |e: Expr<Tag>| Box::new(e);
+        match expr {
+            Expr::Number(..) | Expr::Boolean(..) => expr.clone(),
+            Expr::Id(t, name) => Expr::Id(t.clone(), scope.get(name).cloned().unwrap_or_else(|| name.clone())),
+            Expr::Let(t, bindings, body) => {
+                // All values first, each in a copy of the outer scope, then the new names
+                let values: Vec<Expr<Tag>> = bindings.iter().map(|(_, value)| self.lower(value, &mut scope.clone())).collect();
+                let mut inner = scope.clone();
+                for ((var, _), value) in bindings.iter().zip(values) {
+                    let name = self.bind(t, var, value);
+                    inner.insert(var.clone(), name);
+                }
+                self.lower(body, &mut inner)
+            }
+            Expr::UnOp(t, op, e) => Expr::UnOp(t.clone(), op.clone(), boxed(self.lower(e, scope))),
+            Expr::BinOp(t, op, l, r) => {
+                let l = self.lower(l, scope);
+                Expr::BinOp(t.clone(), op.clone(), boxed(l), boxed(self.lower(r, scope)))
+            }
+            Expr::Cast(t, ty, e) => Expr::Cast(t.clone(), ty.clone(), boxed(self.lower(e, scope))),
+            Expr::Set(t, var, value) => {
+                let value = self.lower(value, scope);
+                let name = self.bind(t, var, value);
+                scope.insert(var.clone(), name.clone());
+                Expr::Id(t.clone(), name)
+            }
+            Expr::Block(t, exprs) => {
+                let Some((last, statements)) = exprs.split_last() else {
+                    return Expr::Number(t.clone(), 0.0);
+                };
+                for statement in statements {
+                    // A `Set` is bound already, a read of a bound variable or a number computes
+                    // nothing; a free variable is kept, so an unbound one still fails like in
+                    // `evaluate`
+                    match self.lower(statement, scope) {
+                        Expr::Id(_, name) if scope.values().any(|bound| *bound == name) => {}
+                        Expr::Number(..) => {}
+                        value => { self.bind(t, "block", value); }
+                    }
+                }
+                self.lower(last, scope)
+            }
+            Expr::If(t, cond, then, els) => {
+                let cond = self.lower(cond, scope);
+                Expr::If(t.clone(), boxed(cond), boxed(self.scoped(then, scope)), boxed(self.scoped(els, scope)))
+            }
+            Expr::Loop(t, body) => Expr::Loop(t.clone(), boxed(self.scoped(body, scope))),
+            Expr::Break(t, value) => Expr::Break(t.clone(), boxed(self.scoped(value, scope))),
+        }
+    }
+}
+
+/// `body` inside one single-binding `Let` per binding, the first outermost.
+fn wrap<Tag>(bindings: Vec<(Tag, String, Expr<Tag>)>, body: Expr<Tag>) -> Expr<Tag> {
+    bindings.into_iter().rev().fold(body, |body, (t, name, value)| Expr::Let(t, vec![(name, value)], Box::new(body)))
+}
+
+/// `expr` without `Block` or `Set`, see `lower_blocks` at the top of the file. Expressions without them come back
+/// with every `Let` binding renamed and split into one `Let` each.
+pub fn lower_blocks<Tag: Clone>(expr: &Expr<Tag>) -> Expr<Tag> {
+    let mut lowering = Lowering { counter: 0, bindings: Vec::new() };
+    lowering.scoped(expr, &HashMap::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::{evaluate, InfixPrinter};
+    use crate::ast_expr::SimpleExpr;
+    use crate::ast_parser::parse_infix;
+    use crate::ast_visitor::for_each_node;
+
+    #[test]
+    fn test_lower() {
+        let x = SimpleExpr::var("x_0");
+        // let a = sin(x_0) in (float) a * a: one sin node, read twice, and no node for the cast
+        let shared = Expr::Let((), vec![("a".to_string(), SimpleExpr::sin(x.clone()))],
+            Box::new(SimpleExpr::cast(Type::Float, SimpleExpr::mul(SimpleExpr::var("a"), SimpleExpr::var("a")))));
+        let core = lower(&shared).unwrap();
+        assert_eq!(core.nodes, vec![
+            CoreNode::Input("x_0".to_string()),
+            CoreNode::Unary(Op1::Sin, 0),
+            CoreNode::Binary(Op2::Mul, 1, 1),
+        ]);
+        let env: Env<f64> = [("x_0".to_string(), 0.5)].into_iter().collect();
+        assert_eq!(core.eval(&env).unwrap(), 0.5f64.sin() * 0.5f64.sin());
+
+        // A constant condition picks its branch; the other one is never lowered
+        let branch = |cond| Expr::If((), Box::new(cond), Box::new(x.clone()), Box::new(Expr::Loop((), Box::new(x.clone()))));
+        assert_eq!(evaluate(&branch(SimpleExpr::cast(Type::Bool, SimpleExpr::num(2.0))), &env).unwrap(), 0.5);
+        assert!(lower(&branch(SimpleExpr::cast(Type::Bool, x.clone()))).is_err());
+        assert!(lower(&Expr::Loop((), Box::new(x))).is_err());
+    }
+
+    #[test]
+    fn test_lower_blocks() {
+        let (x, y, a) = (SimpleExpr::var("x_0"), SimpleExpr::var("x_1"), SimpleExpr::var("a"));
+        let set = |name: &str, value| Expr::Set((), name.to_string(), Box::new(value));
+        // let a = x_0 in { a = a * x_1; x_0 = sin(a); { let b = 1 in x_1 = b }; a + x_0 * x_1 }
+        let expr = Expr::Let((), vec![("a".to_string(), x.clone())], Box::new(Expr::Block((), vec![
+            set("a", SimpleExpr::mul(a.clone(), y.clone())),
+            set("x_0", SimpleExpr::sin(a.clone())),
+            Expr::Let((), vec![("b".to_string(), SimpleExpr::num(1.0))], Box::new(set("x_1", SimpleExpr::var("b")))),
+            SimpleExpr::add(a, SimpleExpr::mul(x, y)),
+        ])));
+        let env: Env<f64> = [("x_0".to_string(), 0.5), ("x_1".to_string(), 3.0)].into_iter().collect();
+        // The Set of x_1 stays inside its Let
+        let expected = 1.5 + 1.5f64.sin() * 3.0;
+        assert_eq!(evaluate(&expr, &env).unwrap(), expected);
+
+        let lowered = lower_blocks(&expr);
+        for_each_node(&lowered, |e| assert!(!matches!(e, Expr::Set(..) | Expr::Block(..)), "{:?}", e));
+        assert_eq!(evaluate(&lowered, &env).unwrap(), expected);
+        // What the evalexpr path compiles
+        let (infix, _) = parse_infix(&InfixPrinter::print(&lowered, 2)).unwrap();
+        assert_eq!(evaluate(&infix, &env).unwrap(), expected);
+
+        // let a = (x_0 = 2) in a * x_0: the Set stays in the binding value
+        let leak = Expr::Let((), vec![("a".to_string(), set("x_0", SimpleExpr::num(2.0)))],
+            Box::new(SimpleExpr::mul(SimpleExpr::var("a"), SimpleExpr::var("x_0"))));
+        assert_eq!(evaluate(&leak, &env).unwrap(), 1.0);
+        assert_eq!(evaluate(&lower_blocks(&leak), &env).unwrap(), 1.0);
+
+        // { y; x_0 } with y unbound fails on both paths
+        let unbound = Expr::Block((), vec![SimpleExpr::var("y"), SimpleExpr::var("x_0")]);
+        assert!(matches!(evaluate(&unbound, &env), Err(FuzzError::UnboundVariable(_))));
+        assert!(matches!(evaluate(&lower_blocks(&unbound), &env), Err(FuzzError::UnboundVariable(_))));
+    }
+}
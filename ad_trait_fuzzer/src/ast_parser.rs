@@ -0,0 +1,413 @@
+// src/ast_parser.rs
+
+// Parser for infix expressions, the syntax `InfixPrinter` produces:
+//
+//     sin(x_0) * (x_1 ^ 2) - ln(abs(x_0)) / -(3.5)
+//
+// Operators: + - * / ^ (right associative, binds tighter than unary minus) and unary -.
+// Functions: sin cos tan exp ln (or log) sqrt abs, plus the casts trunc, bool and float.
+// Variables are x_0, x_1, ...; a minus sign right before a number literal is part of it.
+//
+// `parse_sexpr` reads the `SExprPrinter` syntax back.
+
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr, Type};
+use crate::error::FuzzError;
+
+/// Parses `src` into an expression over `x_0 .. x_{n-1}`. Returns the expression and `n`
+/// (one past the highest variable index used).
+pub fn parse_infix(src: &str) -> Result<(SimpleExpr, usize), FuzzError> {
+    let mut parser = Parser { src, pos: 0, num_inputs: 0 };
+    let expr = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos < src.len() {
+        return Err(parser.error("unexpected input"));
+    }
+    Ok((expr, parser.num_inputs))
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    num_inputs: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, msg: &str) -> FuzzError {
+        FuzzError::UnsupportedExpr(format!("{} at column {} of '{}'", msg, self.pos + 1, self.src))
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_ws(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.src.len() - trimmed.len();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), FuzzError> {
+        if self.eat(c) { Ok(()) } else { Err(self.error(&format!("expected '{}'", c))) }
+    }
+
+    fn binop(op: Op2, left: SimpleExpr, right: SimpleExpr) -> SimpleExpr {
+        Expr::BinOp((), op, Box::new(left), Box::new(right))
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<SimpleExpr, FuzzError> {
+        let mut left = self.term()?;
+        loop {
+            let op = if self.eat('+') { Op2::Add } else if self.eat('-') { Op2::Sub } else { return Ok(left) };
+            left = Self::binop(op, left, self.term()?);
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<SimpleExpr, FuzzError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat('*') { Op2::Mul } else if self.eat('/') { Op2::Div } else { return Ok(left) };
+            left = Self::binop(op, left, self.unary()?);
+        }
+    }
+
+    // unary := '-' unary | power
+    fn unary(&mut self) -> Result<SimpleExpr, FuzzError> {
+        if self.eat('-') {
+            if self.rest().starts_with(|c: char| c.is_ascii_digit() || c == '.') || self.rest().starts_with("inf") {
+                self.pos -= 1;
+                return self.power();
+            }
+            return Ok(SimpleExpr::neg(self.unary()?));
+        }
+        self.power()
+    }
+
+    // power := atom ('^' unary)?
+    fn power(&mut self) -> Result<SimpleExpr, FuzzError> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(SimpleExpr::pow(base, self.unary()?));
+        }
+        Ok(base)
+    }
+
+    // atom := number | variable | function '(' expr ')' | '(' expr ')'
+    fn atom(&mut self) -> Result<SimpleExpr, FuzzError> {
+        self.skip_ws();
+        if self.eat('(') {
+            let inner = self.expr()?;
+            self.expect(')')?;
+            return Ok(inner);
+        }
+
+        let start = self.pos;
+        let rest = self.rest();
+        if rest.starts_with(|c: char| c.is_ascii_digit() || c == '.' || c == '-') {
+            return self.number(start);
+        }
+
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error("expected a number, variable or function"));
+        }
+        let name = &rest[..len];
+        self.pos += len;
+
+        match name {
+            "inf" => return Ok(SimpleExpr::num(f64::INFINITY)),
+            "NaN" => return Ok(SimpleExpr::num(f64::NAN)),
+            _ => {}
+        }
+        if let Some(index) = name.strip_prefix("x_").and_then(|i| i.parse::<usize>().ok()) {
+            self.num_inputs = self.num_inputs.max(index + 1);
+            return Ok(SimpleExpr::var(name));
+        }
+
+        let apply: fn(SimpleExpr) -> SimpleExpr = match name {
+            "sin" => SimpleExpr::sin,
+            "cos" => SimpleExpr::cos,
+            "tan" => |e| Expr::UnOp((), Op1::Tan, Box::new(e)),
+            "exp" => SimpleExpr::exp,
+            "ln" | "log" => SimpleExpr::log,
+            "sqrt" => SimpleExpr::sqrt,
+            "abs" => SimpleExpr::abs,
+            "trunc" => |e| SimpleExpr::cast(Type::Int, e),
+            "bool" => |e| SimpleExpr::cast(Type::Bool, e),
+            "float" => |e| SimpleExpr::cast(Type::Float, e),
+            _ => {
+                self.pos = start;
+                return Err(self.error(&format!("unknown name '{}' (variables are x_0, x_1, ...)", name)));
+            }
+        };
+        self.expect('(')?;
+        let arg = self.expr()?;
+        self.expect(')')?;
+        Ok(apply(arg))
+    }
+
+    fn number(&mut self, start: usize) -> Result<SimpleExpr, FuzzError> {
+        let bytes = self.src.as_bytes();
+        let mut end = start;
+        if bytes.get(end) == Some(&b'-') {
+            end += 1;
+        }
+        if self.src[end..].starts_with("inf") {
+            self.pos = end + 3;
+            return Ok(SimpleExpr::num(f64::NEG_INFINITY));
+        }
+        while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b'.') {
+            end += 1;
+        }
+        // Exponent, only if digits follow (so `2e` is not swallowed)
+        if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+            let mut exp_end = end + 1;
+            if exp_end < bytes.len() && (bytes[exp_end] == b'+' || bytes[exp_end] == b'-') {
+                exp_end += 1;
+            }
+            if exp_end < bytes.len() && bytes[exp_end].is_ascii_digit() {
+                while exp_end < bytes.len() && bytes[exp_end].is_ascii_digit() {
+                    exp_end += 1;
+                }
+                end = exp_end;
+            }
+        }
+        let value = self.src[start..end].parse::<f64>()
+            .map_err(|_| self.error("invalid number"))?;
+        self.pos = end;
+        Ok(SimpleExpr::num(value))
+    }
+}
+
+/// Parses the S-expression syntax `SExprPrinter` produces, e.g. `(* (sin x_0) (pow x_1 2))`, so
+/// saved crash reports and hand-written cases load back. Unary forms: `neg sin cos tan exp log
+/// sqrt abs` and `(cast float|int|bool e)`; binary forms: `+ - * / pow`. Numbers are anything
+/// `f64` parses (`NaN`, `inf`, `-inf`, `1e-3`). Returns the expression and the number of inputs,
+/// like `parse_infix`; printing the result gives back the printed source.
+pub fn parse_sexpr(src: &str) -> Result<(SimpleExpr, usize), FuzzError> {
+    let mut parser = SExprParser { src, pos: 0, num_inputs: 0, patterns: false };
+    let expr = parser.expr()?;
+    if parser.token().is_some() {
+        return Err(parser.error("unexpected input after the expression"));
+    }
+    Ok((expr, parser.num_inputs))
+}
+
+/// `parse_sexpr` that also accepts pattern variables, `?name`, as `Expr::Id("?name")`: the
+/// syntax of `ast_rewrite::Rewrite` rules.
+pub fn parse_pattern(src: &str) -> Result<SimpleExpr, FuzzError> {
+    let mut parser = SExprParser { src, pos: 0, num_inputs: 0, patterns: true };
+    let expr = parser.expr()?;
+    if parser.token().is_some() {
+        return Err(parser.error("unexpected input after the pattern"));
+    }
+    Ok(expr)
+}
+
+struct SExprParser<'a> {
+    src: &'a str,
+    pos: usize,
+    num_inputs: usize,
+    /// Accept `?name` atoms, see `parse_pattern`.
+    patterns: bool,
+}
+
+impl<'a> SExprParser<'a> {
+    fn error(&self, msg: &str) -> FuzzError {
+        FuzzError::UnsupportedExpr(format!("{} at column {} of '{}'", msg, self.pos + 1, self.src))
+    }
+
+    /// The next token without consuming it: `(`, `)` or an atom.
+    fn token(&mut self) -> Option<&'a str> {
+        let rest = &self.src[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        if trimmed.starts_with(['(', ')']) {
+            return Some(&trimmed[..1]);
+        }
+        let len = trimmed.find(|c: char| c.is_whitespace() || c == '(' || c == ')').unwrap_or(trimmed.len());
+        if len == 0 { None } else { Some(&trimmed[..len]) }
+    }
+
+    fn next_token(&mut self) -> Result<&'a str, FuzzError> {
+        let token = self.token().ok_or_else(|| self.error("unexpected end of input"))?;
+        self.pos += token.len();
+        Ok(token)
+    }
+
+    fn expect_close(&mut self) -> Result<(), FuzzError> {
+        match self.token() {
+            Some(")") => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error("expected ')'")),
+        }
+    }
+
+    // expr := atom | '(' head expr+ ')'
+    fn expr(&mut self) -> Result<SimpleExpr, FuzzError> {
+        let start = self.pos;
+        match self.next_token()? {
+            "(" => {}
+            ")" => {
+                self.pos = start;
+                return Err(self.error("unexpected ')'"));
+            }
+            atom => return self.atom(atom, start),
+        }
+
+        let head_start = self.pos;
+        let head = self.next_token()?;
+        let unary: Option<fn(SimpleExpr) -> SimpleExpr> = match head {
+            "neg" => Some(SimpleExpr::neg),
+            "sin" => Some(SimpleExpr::sin),
+            "cos" => Some(SimpleExpr::cos),
+            "tan" => Some(SimpleExpr::tan),
+            "exp" => Some(SimpleExpr::exp),
+            "log" => Some(SimpleExpr::log),
+            "sqrt" => Some(SimpleExpr::sqrt),
+            "abs" => Some(SimpleExpr::abs),
+            _ => None,
+        };
+        let expr = if let Some(apply) = unary {
+            apply(self.expr()?)
+        } else if head == "cast" {
+            let ty_start = self.pos;
+            let ty = match self.next_token()? {
+                "float" => Type::Float,
+                "int" => Type::Int,
+                "bool" => Type::Bool,
+                _ => {
+                    self.pos = ty_start;
+                    return Err(self.error("expected a type (float, int or bool)"));
+                }
+            };
+            SimpleExpr::cast(ty, self.expr()?)
+        } else {
+            let op = match head {
+                "+" => Op2::Add,
+                "-" => Op2::Sub,
+                "*" => Op2::Mul,
+                "/" => Op2::Div,
+                "pow" => Op2::Pow,
+                _ => {
+                    self.pos = head_start;
+                    return Err(self.error(&format!("unknown operator '{}'", head)));
+                }
+            };
+            let left = self.expr()?;
+            let right = self.expr()?;
+            Expr::BinOp((), op, Box::new(left), Box::new(right))
+        };
+        self.expect_close()?;
+        Ok(expr)
+    }
+
+    fn atom(&mut self, atom: &str, start: usize) -> Result<SimpleExpr, FuzzError> {
+        if let Some(index) = atom.strip_prefix("x_").and_then(|i| i.parse::<usize>().ok()) {
+            self.num_inputs = self.num_inputs.max(index + 1);
+            return Ok(SimpleExpr::var(atom));
+        }
+        if let Some(name) = atom.strip_prefix('?').filter(|_| self.patterns) {
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Ok(SimpleExpr::var(atom));
+            }
+        }
+        match atom.parse::<f64>() {
+            Ok(value) => Ok(SimpleExpr::num(value)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.error(&format!("expected a number or variable, got '{}'", atom)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::{evaluate, Env, InfixPrinter, SExprPrinter};
+    use crate::ast_generator::{generate_from_bytes, AstGenConfig, Grammar};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn eval(expr: &SimpleExpr, x: &[f64]) -> f64 {
+        let env: Env<f64> = x.iter().enumerate().map(|(i, v)| (format!("x_{}", i), *v)).collect();
+        evaluate(expr, &env).unwrap()
+    }
+
+    #[test]
+    fn test_parse_infix() {
+        let (expr, n) = parse_infix("sin(x_0) * x_2 ^ 2 - -3 / -(x_1) + ln(abs(x_0))").unwrap();
+        assert_eq!(n, 3);
+        let x = [0.5, 2.0, 1.5];
+        let expected = 0.5f64.sin() * 1.5f64.powf(2.0) - (-3.0 / -2.0) + 0.5f64.ln();
+        assert!((eval(&expr, &x) - expected).abs() < 1e-12);
+
+        // -x^2 is -(x^2), and ^ is right associative
+        let (expr, _) = parse_infix("-x_0 ^ 2 ^ 3").unwrap();
+        assert_eq!(eval(&expr, &[2.0]), -256.0);
+
+        // Round trip through the printer
+        let printed = InfixPrinter::print(&expr, 1);
+        let (reparsed, _) = parse_infix(&printed).unwrap();
+        assert_eq!(InfixPrinter::print(&reparsed, 1), printed);
+
+        assert!(parse_infix("sin(x_0").is_err());
+        assert!(parse_infix("foo(x_0)").is_err());
+        assert!(parse_infix("x_0 +").is_err());
+    }
+
+    #[test]
+    fn test_parse_sexpr() {
+        let (expr, n) = parse_sexpr("(+ (* (sin x_0) (pow x_2 2)) (cast int (neg -3.5)))").unwrap();
+        assert_eq!(n, 3);
+        let x = [0.5, 2.0, 1.5];
+        let expected = 0.5f64.sin() * 1.5f64.powf(2.0) + 3.0;
+        assert!((eval(&expr, &x) - expected).abs() < 1e-12);
+
+        let (expr, _) = parse_sexpr("(/ NaN -inf)").unwrap();
+        assert_eq!(SExprPrinter::print(&expr, 0), "(/ NaN -inf)");
+
+        assert!(parse_sexpr("(sin x_0").is_err());
+        assert!(parse_sexpr("(sin x_0 x_1)").is_err());
+        assert!(parse_sexpr("(foo x_0)").is_err());
+        assert!(parse_sexpr("(cast long x_0)").is_err());
+        assert!(parse_sexpr("x_0 x_1").is_err());
+        assert!(parse_sexpr("y").is_err());
+    }
+
+    #[test]
+    fn test_sexpr_round_trip() {
+        // print -> parse -> print is stable for everything the generator produces
+        let mut rng = StdRng::seed_from_u64(291);
+        let configs = [
+            AstGenConfig { max_depth: 6, max_variables: 3, allow_log: true, allow_cast: true, ..Default::default() },
+            AstGenConfig { grammar: Grammar::Pow, ..Default::default() },
+        ];
+        let mut checked = 0;
+        for _ in 0..500 {
+            let data: Vec<u8> = (0..rng.gen_range(8..256)).map(|_| rng.gen()).collect();
+            for config in &configs {
+                let Ok(generated) = generate_from_bytes(&data, config.clone()) else { continue };
+                let printed = SExprPrinter::print(&generated.expr, config.max_variables);
+                let (parsed, n) = parse_sexpr(&printed).unwrap_or_else(|e| panic!("{}: {}", printed, e));
+                assert!(n <= config.max_variables);
+                assert_eq!(SExprPrinter::print(&parsed, config.max_variables), printed);
+                checked += 1;
+            }
+        }
+        assert!(checked > 500);
+    }
+}
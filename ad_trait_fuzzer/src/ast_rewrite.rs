@@ -0,0 +1,273 @@
+// src/ast_rewrite.rs
+
+// Pattern-matching rewrites in the S-expression syntax, with `?name` for "any subexpression":
+//
+//     Rewrite::new("(* ?a 1)", "?a")
+//
+// A variable used twice on the left only matches equal subexpressions, `(- ?a ?a)`. Rules only
+// see the pure operators (numbers, variables, unary/binary operators and casts), which is what
+// the S-expression syntax can write.
+//
+// Rule files hold one rule per line, `lhs => rhs`, optionally named `name: lhs => rhs`, with `#`
+// comments:
+//
+//     # x * 1 = x
+//     mul_one: (* ?a 1) => ?a
+//
+// `RuleSet::simplify` is the built-in set. Its rules keep the value the same everywhere (NaN, ±Inf
+// and the sign of zero included) and the gradient wherever the matched subexpressions are finite,
+// so it is safe for `ast_canonical`. `(* ?a 0) => 0`, `(+ ?a 0) => ?a` (turns
+// -0 into +0) or `(pow ?a 1) => ?a` (NaN gradient for negative bases) are not.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::ast_parser::parse_pattern;
+use crate::ast_visitor::{fold_children, for_each_node, ExprFolder};
+use crate::error::FuzzError;
+
+/// Passes `RuleSet::rewrite` makes at most, in case the rules cycle.
+const MAX_PASSES: usize = 100;
+
+const SIMPLIFY_RULES: &str = "\
+mul_one: (* ?a 1) => ?a
+one_mul: (* 1 ?a) => ?a
+div_one: (/ ?a 1) => ?a
+sub_zero: (- ?a 0) => ?a
+neg_neg: (neg (neg ?a)) => ?a
+abs_abs: (abs (abs ?a)) => (abs ?a)
+abs_neg: (abs (neg ?a)) => (abs ?a)
+cast_float: (cast float ?a) => ?a
+";
+
+fn is_pattern_var(name: &str) -> bool {
+    name.starts_with('?')
+}
+
+/// `lhs => rhs`, a rewrite of any subexpression matching `lhs`.
+#[derive(Debug, Clone)]
+pub struct Rewrite {
+    pub name: String,
+    lhs: SimpleExpr,
+    rhs: SimpleExpr,
+}
+
+impl Rewrite {
+    /// Parses both sides. Every pattern variable of `rhs` must occur in `lhs`, and `lhs` must not
+    /// be a bare variable (it would match everything).
+    pub fn new(lhs: &str, rhs: &str) -> Result<Self, FuzzError> {
+        let name = format!("{} => {}", lhs, rhs);
+        let (lhs, rhs) = (parse_pattern(lhs)?, parse_pattern(rhs)?);
+        if matches!(&lhs, Expr::Id(_, v) if is_pattern_var(v)) {
+            return Err(FuzzError::UnsupportedExpr(format!("rule '{}' matches every expression", name)));
+        }
+        let mut bound = Vec::new();
+        collect_vars(&lhs, &mut bound);
+        let mut used = Vec::new();
+        collect_vars(&rhs, &mut used);
+        if let Some(unbound) = used.iter().find(|v| !bound.contains(v)) {
+            return Err(FuzzError::UnsupportedExpr(format!("rule '{}' uses {} without matching it", name, unbound)));
+        }
+        Ok(Rewrite { name, lhs, rhs })
+    }
+
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The rewrite of `expr` itself (not its subexpressions), if `lhs` matches it.
+    pub fn apply(&self, expr: &SimpleExpr) -> Option<SimpleExpr> {
+        let mut bindings = HashMap::new();
+        if !matches(&self.lhs, expr, &mut bindings) {
+            return None;
+        }
+        Some(Instantiate(&bindings).fold(self.rhs.clone()))
+    }
+}
+
+fn collect_vars(pattern: &SimpleExpr, out: &mut Vec<String>) {
+    for_each_node(pattern, |e| match e {
+        Expr::Id(_, v) if is_pattern_var(v) && !out.contains(v) => out.push(v.clone()),
+        _ => {}
+    });
+}
+
+fn matches(pattern: &SimpleExpr, expr: &SimpleExpr, bindings: &mut HashMap<String, SimpleExpr>) -> bool {
+    match (pattern, expr) {
+        (Expr::Id(_, v), _) if is_pattern_var(v) => match bindings.get(v) {
+            // Structural equality, NaN constants included
+            Some(bound) => bound == expr,
+            None => {
+                bindings.insert(v.clone(), expr.clone());
+                true
+            }
+        },
+        (Expr::Number(_, a), Expr::Number(_, b)) => a.to_bits() == b.to_bits(),
+        (Expr::Id(_, a), Expr::Id(_, b)) => a == b,
+        (Expr::UnOp(_, op, a), Expr::UnOp(_, op2, b)) => op == op2 && matches(a, b, bindings),
+        (Expr::BinOp(_, op, l, r), Expr::BinOp(_, op2, l2, r2)) => {
+            op == op2 && matches(l, l2, bindings) && matches(r, r2, bindings)
+        }
+        (Expr::Cast(_, ty, a), Expr::Cast(_, ty2, b)) => ty == ty2 && matches(a, b, bindings),
+        _ => false,
+    }
+}
+
+/// Replaces the pattern variables of a right-hand side with what they matched.
+struct Instantiate<'a>(&'a HashMap<String, SimpleExpr>);
+
+impl ExprFolder<()> for Instantiate<'_> {
+    fn fold(&mut self, expr: SimpleExpr) -> SimpleExpr {
+        match expr {
+            Expr::Id(_, v) if is_pattern_var(&v) => self.0[&v].clone(),
+            other => fold_children(self, other),
+        }
+    }
+}
+
+/// Rules applied together, first match wins at each node.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub rules: Vec<Rewrite>,
+}
+
+impl RuleSet {
+    /// Identities that preserve value and gradient, see the top of the file.
+    pub fn simplify() -> Self {
+        Self::parse(SIMPLIFY_RULES).expect("built-in rules parse")
+    }
+
+    /// Rules in the file format described at the top of the file.
+    pub fn parse(text: &str) -> Result<Self, FuzzError> {
+        let mut rules = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, rule) = match line.split_once(':') {
+                Some((name, rule)) => (Some(name.trim()), rule),
+                None => (None, line),
+            };
+            let (lhs, rhs) = rule.split_once("=>")
+                .ok_or_else(|| FuzzError::InvalidConfig(format!("line {}: expected 'lhs => rhs'", i + 1)))?;
+            let rewrite = Rewrite::new(lhs.trim(), rhs.trim())
+                .map_err(|e| FuzzError::InvalidConfig(format!("line {}: {}", i + 1, e)))?;
+            rules.push(match name {
+                Some(name) => rewrite.named(name),
+                None => rewrite,
+            });
+        }
+        Ok(RuleSet { rules })
+    }
+
+    /// Rules from a file, see `parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FuzzError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| FuzzError::InvalidConfig(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text).map_err(|e| FuzzError::InvalidConfig(format!("{}: {}", path.display(), e)))
+    }
+
+    /// These rules followed by `other`'s.
+    pub fn extend(mut self, other: RuleSet) -> Self {
+        self.rules.extend(other.rules);
+        self
+    }
+
+    /// `expr` rewritten bottom-up until no rule matches anywhere (or `MAX_PASSES` passes, if the
+    /// rules cycle).
+    pub fn rewrite(&self, expr: &SimpleExpr) -> SimpleExpr {
+        let mut current = expr.clone();
+        for _ in 0..MAX_PASSES {
+            let mut pass = Pass { rules: self, changed: false };
+            current = pass.fold(current);
+            if !pass.changed {
+                break;
+            }
+        }
+        current
+    }
+
+    /// Every expression one rule application away from `expr`, at any node: with rules that
+    /// preserve the function (e.g. `simplify`, or its rules reversed), equivalent expressions for
+    /// metamorphic testing.
+    pub fn rewrites(&self, expr: &SimpleExpr) -> Vec<SimpleExpr> {
+        let mut out: Vec<SimpleExpr> = self.rules.iter().filter_map(|rule| rule.apply(expr)).collect();
+        match expr {
+            Expr::UnOp(_, op, e) => {
+                out.extend(self.rewrites(e).into_iter().map(|new| Expr::UnOp((), op.clone(), Box::new(new))));
+            }
+            Expr::Cast(_, ty, e) => {
+                out.extend(self.rewrites(e).into_iter().map(|new| Expr::Cast((), ty.clone(), Box::new(new))));
+            }
+            Expr::BinOp(_, op, l, r) => {
+                out.extend(self.rewrites(l).into_iter().map(|new| Expr::BinOp((), op.clone(), Box::new(new), r.clone())));
+                out.extend(self.rewrites(r).into_iter().map(|new| Expr::BinOp((), op.clone(), l.clone(), Box::new(new))));
+            }
+            _ => {}
+        }
+        out
+    }
+}
+
+/// One bottom-up pass of `RuleSet::rewrite`.
+struct Pass<'a> {
+    rules: &'a RuleSet,
+    changed: bool,
+}
+
+impl ExprFolder<()> for Pass<'_> {
+    fn fold(&mut self, expr: SimpleExpr) -> SimpleExpr {
+        let expr = fold_children(self, expr);
+        match self.rules.rules.iter().find_map(|rule| rule.apply(&expr)) {
+            Some(rewritten) => {
+                self.changed = true;
+                rewritten
+            }
+            None => expr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::SExprPrinter;
+    use crate::ast_parser::parse_sexpr;
+
+    fn rewrite(rules: &RuleSet, src: &str) -> String {
+        SExprPrinter::print(&rules.rewrite(&parse_sexpr(src).unwrap().0), 2)
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let rule = Rewrite::new("(* ?a 1)", "?a").unwrap();
+        let (expr, _) = parse_sexpr("(* (sin x_0) 1)").unwrap();
+        assert_eq!(SExprPrinter::print(&rule.apply(&expr).unwrap(), 1), "(sin x_0)");
+        assert!(rule.apply(&parse_sexpr("(* x_0 2)").unwrap().0).is_none());
+
+        let simplify = RuleSet::simplify();
+        assert_eq!(rewrite(&simplify, "(+ (neg (neg (* x_0 1))) (- (/ x_1 1) 0))"), "(+ x_0 x_1)");
+        assert_eq!(rewrite(&simplify, "(abs (neg (abs x_1)))"), "(abs x_1)");
+
+        // Repeated variables only match equal subexpressions
+        let user = RuleSet::parse("# x - x\nsub_self: (- ?a ?a) => 0\n").unwrap();
+        assert_eq!(user.rules[0].name, "sub_self");
+        assert_eq!(rewrite(&user, "(- (sin x_0) (sin x_0))"), "0");
+        assert_eq!(rewrite(&user, "(- (sin x_0) (sin x_1))"), "(- (sin x_0) (sin x_1))");
+
+        assert!(Rewrite::new("(* ?a 1)", "?b").is_err());
+        assert!(Rewrite::new("?a", "(* ?a 1)").is_err());
+        assert!(RuleSet::parse("(* ?a 1) -> ?a").is_err());
+
+        // One step anywhere, here the reverse of `mul_one`
+        let (expr, _) = parse_sexpr("(+ x_0 (sin x_1))").unwrap();
+        let grow = RuleSet::parse("(sin ?a) => (* (sin ?a) 1)").unwrap();
+        let variants: Vec<String> = grow.rewrites(&expr).iter().map(|e| SExprPrinter::print(e, 2)).collect();
+        assert_eq!(variants, ["(+ x_0 (* (sin x_1) 1))"]);
+    }
+}
@@ -0,0 +1,179 @@
+// src/ast_shrinker.rs
+
+// Greedy test case reduction for failing expressions. Each pass tries to replace one node with
+// something smaller (one of its children, or a simpler constant) and keeps the first candidate
+// that still fails, until no single replacement does.
+
+use crate::ast_evaluator::constant_value;
+use crate::ast_evaluator::unified::AdPyUnified;
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::error::FuzzError;
+use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator, TestOutcome};
+use crate::oracles::FuzzingOracles;
+
+/// Upper bound on `still_fails` calls; each one is a full oracle run in the fuzz target.
+pub const MAX_SHRINK_ATTEMPTS: usize = 500;
+
+/// Number of nodes in `expr`.
+pub fn size<Tag>(expr: &Expr<Tag>) -> usize {
+    1 + children(expr).iter().map(|child| size(child)).sum::<usize>()
+}
+
+/// Shrinks `expr` while `still_fails` holds, trying at most `MAX_SHRINK_ATTEMPTS` candidates.
+/// Returns `expr` itself if nothing smaller fails.
+pub fn shrink(expr: &SimpleExpr, mut still_fails: impl FnMut(&SimpleExpr) -> bool) -> SimpleExpr {
+    let mut current = expr.clone();
+    let mut attempts = 0;
+    'pass: loop {
+        for index in 0..size(&current) {
+            for candidate in candidates(node_at(&current, index)) {
+                if attempts == MAX_SHRINK_ATTEMPTS {
+                    return current;
+                }
+                attempts += 1;
+                let shrunk = replace_at(&current, index, candidate);
+                if still_fails(&shrunk) {
+                    current = shrunk;
+                    continue 'pass;
+                }
+            }
+        }
+        return current;
+    }
+}
+
+/// Shrinks an expression that failed the oracles with `error`, keeping only candidates that fail
+/// the same way (same oracle and failure class) on the same inputs.
+pub fn shrink_failure(
+    expr: &SimpleExpr,
+    inputs: &[f64],
+    oracles: &FuzzingOracles,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+    error: &FuzzError,
+) -> SimpleExpr {
+    shrink(expr, |candidate| {
+        // The fuzz target skips constant expressions, so they cannot reproduce anything
+        if constant_value(candidate).is_some() {
+            return false;
+        }
+        let calc = AdPyUnified::new(candidate.clone(), inputs.len(), 1);
+        let oracles = oracles.specialize(candidate);
+        match run_ad_tests(inputs, calc, &oracles, gt_calculators).and_then(TestOutcome::into_result) {
+            Err(e) => same_failure(&e, error),
+            Ok(()) => false,
+        }
+    })
+}
+
+pub(crate) fn same_failure(a: &FuzzError, b: &FuzzError) -> bool {
+    match (a, b) {
+        (
+            FuzzError::OracleMismatch { oracle: oracle_a, class: class_a, .. },
+            FuzzError::OracleMismatch { oracle: oracle_b, class: class_b, .. },
+        ) => oracle_a == oracle_b && class_a == class_b,
+        _ => a.kind() == b.kind(),
+    }
+}
+
+fn children<Tag>(expr: &Expr<Tag>) -> Vec<&Expr<Tag>> {
+    match expr {
+        Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => vec![e],
+        Expr::BinOp(_, _, l, r) => vec![l, r],
+        _ => Vec::new(),
+    }
+}
+
+/// Replacements for one node, smallest first. Numbers only move towards 0, then 1, then
+/// integers, so shrinking always terminates.
+fn candidates(node: &SimpleExpr) -> Vec<SimpleExpr> {
+    match node {
+        Expr::Number(_, v) => {
+            let mut out = Vec::new();
+            if *v != 0.0 {
+                out.push(SimpleExpr::num(0.0));
+            }
+            if *v != 0.0 && *v != 1.0 {
+                out.push(SimpleExpr::num(1.0));
+            }
+            if v.is_finite() && v.fract() != 0.0 {
+                out.push(SimpleExpr::num(v.trunc()));
+            }
+            out
+        }
+        Expr::Id(..) => Vec::new(),
+        _ => {
+            let mut out: Vec<SimpleExpr> = children(node).into_iter().cloned().collect();
+            out.push(SimpleExpr::num(1.0));
+            out
+        }
+    }
+}
+
+/// The `index`-th node of `expr` in pre-order.
+fn node_at(expr: &SimpleExpr, index: usize) -> &SimpleExpr {
+    if index == 0 {
+        return expr;
+    }
+    let mut offset = 1;
+    for child in children(expr) {
+        let n = size(child);
+        if index < offset + n {
+            return node_at(child, index - offset);
+        }
+        offset += n;
+    }
+    unreachable!("node index {} out of range", index)
+}
+
+/// `expr` with its `index`-th node (pre-order) replaced by `new`.
+fn replace_at(expr: &SimpleExpr, index: usize, new: SimpleExpr) -> SimpleExpr {
+    if index == 0 {
+        return new;
+    }
+    let mut offset = 1;
+    let mut replace_child = |child: &SimpleExpr| {
+        let n = size(child);
+        let replaced = if index >= offset && index < offset + n {
+            replace_at(child, index - offset, new.clone())
+        } else {
+            child.clone()
+        };
+        offset += n;
+        Box::new(replaced)
+    };
+    match expr {
+        Expr::UnOp(_, op, e) => Expr::UnOp((), op.clone(), replace_child(e)),
+        Expr::Cast(_, ty, e) => Expr::Cast((), ty.clone(), replace_child(e)),
+        Expr::BinOp(_, op, l, r) => {
+            let l = replace_child(l);
+            let r = replace_child(r);
+            Expr::BinOp((), op.clone(), l, r)
+        }
+        _ => unreachable!("leaf nodes have no children"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::InfixPrinter;
+
+    fn contains_log(expr: &SimpleExpr) -> bool {
+        matches!(expr, Expr::UnOp(_, crate::ast_expr::Op1::Log, _)) || children(expr).into_iter().any(contains_log)
+    }
+
+    #[test]
+    fn test_shrink_keeps_failing_node() {
+        // "Fails" whenever a log is left
+        let expr = SimpleExpr::mul(
+            SimpleExpr::sin(SimpleExpr::add(SimpleExpr::var("x_0"), SimpleExpr::num(2.5))),
+            SimpleExpr::log(SimpleExpr::exp(SimpleExpr::var("x_1"))),
+        );
+        let shrunk = shrink(&expr, contains_log);
+        assert_eq!(InfixPrinter::print(&shrunk, 2), "ln(x_1)");
+
+        // Nothing smaller fails: unchanged
+        let shrunk = shrink(&expr, |_| false);
+        assert_eq!(InfixPrinter::print(&shrunk, 2), InfixPrinter::print(&expr, 2));
+    }
+}
@@ -0,0 +1,210 @@
+// src/ast_typecheck.rs
+
+// Static types over `Type`, so ill-typed expressions are rejected before any engine sees them:
+//
+// - arithmetic (`UnOp`, `BinOp`) takes `Float` or `Int`, never `Bool`; `Int` stays `Int` under
+//   `+ - *`, `neg` and `abs`, everything else is `Float`;
+// - `If` conditions are `Bool` and both branches have the same type (`Int` and `Float` meet at
+//   `Float`);
+// - `Cast` takes any well-typed operand and has the target type;
+// - `Let` bindings are typed in the outer scope (like `evaluate`), `Set` keeps a variable's type,
+//   `Break` only occurs inside a `Loop`, and a `Loop` has the type of its `Break`s.
+//
+// Free variables are the inputs, all `Float`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::error::FuzzError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError {
+    /// An operand of `context` (e.g. `sin`, `+`) is not a number.
+    NotNumeric { context: String, found: Type },
+    /// An `If` condition is not a `Bool`.
+    NotBool { found: Type },
+    /// Two expressions that must have the same type (`If` branches, the `Break`s of one `Loop`,
+    /// a variable and what `Set` assigns to it) do not.
+    Mismatch { context: String, left: Type, right: Type },
+    /// `Set` of a variable no `Let` binds.
+    UnboundSet(String),
+    /// `Break` outside any `Loop`.
+    BreakOutsideLoop,
+    /// A `Loop` without a `Break`, which never produces a value.
+    LoopWithoutBreak,
+    EmptyBlock,
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeError::NotNumeric { context, found } => write!(f, "operand of {} is {:?}, not a number", context, found),
+            TypeError::NotBool { found } => write!(f, "if condition is {:?}, not Bool", found),
+            TypeError::Mismatch { context, left, right } => write!(f, "{}: {:?} vs {:?}", context, left, right),
+            TypeError::UnboundSet(name) => write!(f, "set of unbound variable '{}'", name),
+            TypeError::BreakOutsideLoop => write!(f, "break outside a loop"),
+            TypeError::LoopWithoutBreak => write!(f, "loop without a break"),
+            TypeError::EmptyBlock => write!(f, "empty block"),
+        }
+    }
+}
+
+impl Error for TypeError {}
+
+impl From<TypeError> for FuzzError {
+    fn from(e: TypeError) -> Self {
+        FuzzError::UnsupportedExpr(format!("ill-typed: {}", e))
+    }
+}
+
+fn is_numeric(ty: &Type) -> bool {
+    matches!(ty, Type::Float | Type::Int)
+}
+
+/// The common type of two expressions that must agree, `Int` widening to `Float`.
+fn meet(context: &str, left: Type, right: Type) -> Result<Type, TypeError> {
+    match (left, right) {
+        (l, r) if l == r => Ok(l),
+        (l, r) if is_numeric(&l) && is_numeric(&r) => Ok(Type::Float),
+        (left, right) => Err(TypeError::Mismatch { context: context.to_string(), left, right }),
+    }
+}
+
+struct Checker {
+    scopes: Vec<HashMap<String, Type>>,
+    /// Types of the `Break`s seen in each enclosing `Loop`, innermost last.
+    loops: Vec<Option<Type>>,
+}
+
+impl Checker {
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    fn numeric<Tag>(&mut self, context: String, expr: &Expr<Tag>) -> Result<Type, TypeError> {
+        let ty = self.check(expr)?;
+        if is_numeric(&ty) { Ok(ty) } else { Err(TypeError::NotNumeric { context, found: ty }) }
+    }
+
+    fn check<Tag>(&mut self, expr: &Expr<Tag>) -> Result<Type, TypeError> {
+        match expr {
+            Expr::Number(..) => Ok(Type::Float),
+            Expr::Boolean(..) => Ok(Type::Bool),
+            Expr::Id(_, name) => Ok(self.lookup(name).cloned().unwrap_or(Type::Float)),
+            Expr::Let(_, bindings, body) => {
+                let mut scope = HashMap::new();
+                for (name, value) in bindings {
+                    scope.insert(name.clone(), self.check(value)?);
+                }
+                self.scopes.push(scope);
+                let ty = self.check(body);
+                self.scopes.pop();
+                ty
+            }
+            Expr::UnOp(_, op, e) => {
+                let ty = self.numeric(format!("{:?}", op).to_lowercase(), e)?;
+                Ok(if ty == Type::Int && matches!(op, Op1::Neg | Op1::Abs) { Type::Int } else { Type::Float })
+            }
+            Expr::BinOp(_, op, l, r) => {
+                let context = format!("{:?}", op).to_lowercase();
+                let left = self.numeric(context.clone(), l)?;
+                let right = self.numeric(context, r)?;
+                let closed = matches!(op, Op2::Add | Op2::Sub | Op2::Mul);
+                Ok(if closed && left == Type::Int && right == Type::Int { Type::Int } else { Type::Float })
+            }
+            Expr::If(_, cond, then, els) => {
+                match self.check(cond)? {
+                    Type::Bool => {}
+                    found => return Err(TypeError::NotBool { found }),
+                }
+                let then = self.check(then)?;
+                let els = self.check(els)?;
+                meet("if branches", then, els)
+            }
+            Expr::Loop(_, body) => {
+                self.loops.push(None);
+                let body = self.check(body);
+                let breaks = self.loops.pop().flatten();
+                body?;
+                breaks.ok_or(TypeError::LoopWithoutBreak)
+            }
+            Expr::Break(_, e) => {
+                let ty = self.check(e)?;
+                let slot = self.loops.last_mut().ok_or(TypeError::BreakOutsideLoop)?;
+                let ty = match slot.take() {
+                    Some(previous) => meet("loop breaks", previous, ty)?,
+                    None => ty,
+                };
+                *slot = Some(ty.clone());
+                Ok(ty)
+            }
+            Expr::Set(_, name, e) => {
+                let ty = self.check(e)?;
+                let declared = self.lookup(name).cloned().ok_or_else(|| TypeError::UnboundSet(name.clone()))?;
+                if declared != ty {
+                    return Err(TypeError::Mismatch { context: format!("set {}", name), left: declared, right: ty });
+                }
+                Ok(ty)
+            }
+            Expr::Block(_, exprs) => {
+                let mut last = Err(TypeError::EmptyBlock);
+                for e in exprs {
+                    last = Ok(self.check(e)?);
+                }
+                last
+            }
+            Expr::Cast(_, ty, e) => {
+                self.check(e)?;
+                Ok(ty.clone())
+            }
+        }
+    }
+}
+
+/// The type of `expr`, see the top of the file, or the first rule it breaks.
+pub fn typecheck<Tag>(expr: &Expr<Tag>) -> Result<Type, TypeError> {
+    Checker { scopes: Vec::new(), loops: Vec::new() }.check(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_typecheck() {
+        let x = || SimpleExpr::var("x_0");
+        let boolean = |b| Box::new(Expr::Boolean((), b));
+        let int = |e| Expr::Cast((), Type::Int, Box::new(e));
+
+        assert_eq!(typecheck(&SimpleExpr::sin(x())), Ok(Type::Float));
+        assert_eq!(typecheck(&SimpleExpr::add(int(x()), int(x()))), Ok(Type::Int));
+        assert_eq!(typecheck(&SimpleExpr::div(int(x()), int(x()))), Ok(Type::Float));
+        assert_eq!(typecheck(&Expr::Cast((), Type::Bool, Box::new(x()))), Ok(Type::Bool));
+
+        // Booleans are not numbers, conditions are booleans
+        let bool_arith = SimpleExpr::add(x(), Expr::Cast((), Type::Bool, Box::new(x())));
+        assert!(matches!(typecheck(&bool_arith), Err(TypeError::NotNumeric { found: Type::Bool, .. })));
+        let numeric_cond = Expr::If((), Box::new(x()), Box::new(x()), Box::new(x()));
+        assert_eq!(typecheck(&numeric_cond), Err(TypeError::NotBool { found: Type::Float }));
+        let branches = Expr::If((), boolean(true), Box::new(int(x())), Box::new(x()));
+        assert_eq!(typecheck(&branches), Ok(Type::Float));
+        let mixed = Expr::If((), boolean(true), Box::new(x()), boolean(false));
+        assert!(matches!(typecheck(&mixed), Err(TypeError::Mismatch { .. })));
+
+        // Scopes and loops
+        let flag = Expr::Let((), vec![("b".to_string(), Expr::Boolean((), true))], Box::new(Expr::If(
+            (), Box::new(SimpleExpr::var("b")), Box::new(x()), Box::new(SimpleExpr::num(1.0)),
+        )));
+        assert_eq!(typecheck(&flag), Ok(Type::Float));
+        let set = Expr::Let((), vec![("b".to_string(), Expr::Boolean((), true))], Box::new(Expr::Set((), "b".to_string(), Box::new(x()))));
+        assert!(matches!(typecheck(&set), Err(TypeError::Mismatch { .. })));
+        let looped = Expr::Loop((), Box::new(Expr::Break((), Box::new(int(x())))));
+        assert_eq!(typecheck(&looped), Ok(Type::Int));
+        assert_eq!(typecheck(&Expr::Break((), Box::new(x()))), Err(TypeError::BreakOutsideLoop));
+        assert_eq!(typecheck(&Expr::Loop((), Box::new(x()))), Err(TypeError::LoopWithoutBreak));
+        assert_eq!(typecheck(&Expr::Block((), Vec::<SimpleExpr>::new())), Err(TypeError::EmptyBlock));
+    }
+}
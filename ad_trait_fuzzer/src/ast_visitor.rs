@@ -0,0 +1,127 @@
+// src/ast_visitor.rs
+
+// The traversal of `Expr`, written once. A pass overrides `visit` / `fold` for the nodes it cares
+// about and hands everything else to `walk` / `fold_children`, so only this file has to learn
+// about a new kind of node.
+
+use crate::ast_expr::Expr;
+
+/// Read-only pass over an expression, e.g. collecting variables or operators.
+pub trait ExprVisitor<Tag> {
+    /// Called on every node, parent before children. The default just recurses; overrides call
+    /// `walk(self, expr)` to keep going below the node.
+    fn visit(&mut self, expr: &Expr<Tag>) {
+        walk(self, expr)
+    }
+}
+
+/// Rewriting pass, e.g. simplification or substitution. Takes the expression by value, so
+/// untouched subtrees are moved, not cloned.
+pub trait ExprFolder<Tag> {
+    /// Returns the replacement for `expr`. The default rewrites the children and keeps the node;
+    /// overrides call `fold_children(self, expr)` first to work bottom-up.
+    fn fold(&mut self, expr: Expr<Tag>) -> Expr<Tag> {
+        fold_children(self, expr)
+    }
+}
+
+/// Direct subexpressions of `expr` in evaluation order: `Let` values before the body, `If`
+/// condition before the branches.
+pub fn children<Tag>(expr: &Expr<Tag>) -> Vec<&Expr<Tag>> {
+    match expr {
+        Expr::Number(..) | Expr::Boolean(..) | Expr::Id(..) => Vec::new(),
+        Expr::Let(_, bindings, body) => {
+            bindings.iter().map(|(_, e)| e).chain(std::iter::once(body.as_ref())).collect()
+        }
+        Expr::UnOp(_, _, e) | Expr::Loop(_, e) | Expr::Break(_, e) | Expr::Set(_, _, e) | Expr::Cast(_, _, e) => vec![e],
+        Expr::BinOp(_, _, l, r) => vec![l, r],
+        Expr::If(_, c, t, e) => vec![c, t, e],
+        Expr::Block(_, exprs) => exprs.iter().collect(),
+    }
+}
+
+/// Visits each child of `expr`.
+pub fn walk<Tag, V: ExprVisitor<Tag> + ?Sized>(visitor: &mut V, expr: &Expr<Tag>) {
+    for child in children(expr) {
+        visitor.visit(child);
+    }
+}
+
+/// `expr` with each child replaced by its fold; tags, names and operators are kept.
+pub fn fold_children<Tag, F: ExprFolder<Tag> + ?Sized>(folder: &mut F, expr: Expr<Tag>) -> Expr<Tag> {
+    let mut fold = |e: Box<Expr<Tag>>| Box::new(folder.fold(*e));
+    match expr {
+        Expr::Number(..) | Expr::Boolean(..) | Expr::Id(..) => expr,
+        Expr::Let(t, bindings, body) => {
+            let bindings = bindings.into_iter().map(|(name, e)| (name, *fold(Box::new(e)))).collect();
+            Expr::Let(t, bindings, fold(body))
+        }
+        Expr::UnOp(t, op, e) => Expr::UnOp(t, op, fold(e)),
+        Expr::BinOp(t, op, l, r) => {
+            let l = fold(l);
+            Expr::BinOp(t, op, l, fold(r))
+        }
+        Expr::If(t, c, then, els) => {
+            let c = fold(c);
+            let then = fold(then);
+            Expr::If(t, c, then, fold(els))
+        }
+        Expr::Loop(t, e) => Expr::Loop(t, fold(e)),
+        Expr::Break(t, e) => Expr::Break(t, fold(e)),
+        Expr::Set(t, name, e) => Expr::Set(t, name, fold(e)),
+        Expr::Block(t, exprs) => Expr::Block(t, exprs.into_iter().map(|e| *fold(Box::new(e))).collect()),
+        Expr::Cast(t, ty, e) => Expr::Cast(t, ty, fold(e)),
+    }
+}
+
+/// Calls `f` on every node of `expr`, parent first: the visitor for passes that need no state
+/// besides what `f` captures.
+pub fn for_each_node<Tag>(expr: &Expr<Tag>, f: impl FnMut(&Expr<Tag>)) {
+    struct Each<F>(F);
+    impl<Tag, F: FnMut(&Expr<Tag>)> ExprVisitor<Tag> for Each<F> {
+        fn visit(&mut self, expr: &Expr<Tag>) {
+            (self.0)(expr);
+            walk(self, expr);
+        }
+    }
+    Each(f).visit(expr);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::InfixPrinter;
+    use crate::ast_expr::SimpleExpr;
+
+    /// Replaces every `x_0` with `x_1`.
+    struct Rename;
+
+    impl ExprFolder<()> for Rename {
+        fn fold(&mut self, expr: SimpleExpr) -> SimpleExpr {
+            match expr {
+                Expr::Id(_, name) if name == "x_0" => SimpleExpr::var("x_1"),
+                other => fold_children(self, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_visit_and_fold() {
+        let expr = Expr::Let(
+            (),
+            vec![("a".to_string(), SimpleExpr::sin(SimpleExpr::var("x_0")))],
+            Box::new(SimpleExpr::mul(SimpleExpr::var("a"), SimpleExpr::var("x_0"))),
+        );
+        let mut ids = Vec::new();
+        for_each_node(&expr, |e| if let Expr::Id(_, name) = e { ids.push(name.clone()) });
+        assert_eq!(ids, ["x_0", "a", "x_0"]);
+
+        let renamed = Rename.fold(expr);
+        let mut ids = Vec::new();
+        for_each_node(&renamed, |e| if let Expr::Id(_, name) = e { ids.push(name.clone()) });
+        assert_eq!(ids, ["x_1", "a", "x_1"]);
+
+        let sum = SimpleExpr::add(SimpleExpr::var("x_0"), SimpleExpr::num(2.0));
+        assert_eq!(InfixPrinter::print(&Rename.fold(sum), 2), "(x_1 + 2)");
+    }
+}
@@ -0,0 +1,665 @@
+// src/bin/adfuzz.rs
+// cargo +nightly run --bin adfuzz -- check "sin(x_0) * x_1" 0.5 2.0
+
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::{value_parser, Arg, ArgMatches};
+
+use fuzz_core::ast_evaluator::{constant_value, EvalexprEvaluator, InfixPrinter, RustReproPrinter, SExprPrinter, SSAPrinter};
+use fuzz_core::ast_evaluator::unified::AdPyUnified;
+use fuzz_core::ast_expr::SimpleExpr;
+use fuzz_core::ast_localizer::localize_failure;
+use fuzz_core::ast_parser::{parse_infix, parse_sexpr};
+use fuzz_core::ast_rewrite::RuleSet;
+use fuzz_core::ast_shrinker::{shrink_failure, size};
+use fuzz_core::error::FuzzError;
+use fuzz_core::expr_codec::{append_to_corpus, read_corpus, CorpusEntry};
+use fuzz_core::fuzz_case::{run_fuzz_iteration, CaseResult, IterationOutcome};
+use fuzz_core::fuzz_config::FuzzConfig;
+use fuzz_core::fuzz_harness::{catch_backend_panic, compute_engine_results, run_ad_tests, GroundTruthCalculator, TestOutcome};
+use fuzz_core::input_shrinker::shrink_input_failure;
+#[cfg(feature = "pytorch")]
+use fuzz_core::gt_calculators::PyTorchGroundTruthCalculator;
+use fuzz_core::gt_calculators::{backends, DefaultGroundTruth, DualNumberCalculator, SymbolicGroundTruthCalculator};
+use fuzz_core::oracles::{FuzzingOracles, OracleSelection, Outcome};
+use fuzz_core::smt_export::SmtQuery;
+use fuzz_core::tape_stats::{set_tape_budget, CountingAllocator};
+use fuzz_core::test_definition::{load_suite, run_suite};
+
+// Peak memory of the Reverse AD pass, see `tape_stats`
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+/// Corpus `repro` appends to, in its `--out` directory.
+const REGRESSION_CORPUS: &str = "regressions.adxc";
+
+/// After the generated help.
+const NOTES: &str = "\
+<expr> uses the infix syntax of the crash reports, e.g. \"sin(x_0) * (x_1 ^ 2)\", or their
+S-expression syntax, e.g. \"(* (sin x_0) (pow x_1 2))\".
+`replay` and `repro` decode the artifact with the fuzzer's config (FUZZ_CONFIG or fuzz_config.toml, then
+env overrides), so run it with the same settings as the campaign that found the crash.";
+
+enum Command {
+    Eval,
+    Diff,
+    Check,
+    Localize,
+    Replay,
+    Repro,
+    Smt,
+    Regress,
+    Suite,
+    Simplify,
+    ListOracles,
+    ListBackends,
+}
+
+struct Args {
+    command: Command,
+    expr: String,
+    inputs: Vec<f64>,
+    oracles: OracleSelection,
+    out: String,
+    epsilon: f64,
+    radius: f64,
+    rules: Option<String>,
+}
+
+fn cli() -> clap::Command {
+    let expr = || Arg::new("expr").required(true).help("Expression, infix or S-expression");
+    let path = |id: &'static str, help: &'static str| Arg::new(id).required(true).help(help);
+    // `allow_negative_numbers`: -1.5 is an input, not an option
+    let inputs = || {
+        Arg::new("inputs")
+            .value_name("x_i")
+            .num_args(1..)
+            .required(true)
+            .allow_negative_numbers(true)
+            .value_parser(value_parser!(f64))
+            .help("x_0 [x_1 ...]")
+    };
+    let oracle = || {
+        Arg::new("oracle")
+            .long("oracle")
+            .value_name("names")
+            .default_value("all")
+            .value_parser(|s: &str| s.parse::<OracleSelection>())
+            .help("Oracles to run, FUZZ_ORACLE syntax")
+    };
+    let number = |id: &'static str, default: &'static str, help: &'static str| {
+        Arg::new(id).long(id).value_name("x").default_value(default).allow_negative_numbers(true).value_parser(value_parser!(f64)).help(help)
+    };
+
+    clap::Command::new("adfuzz")
+        .about("Evaluates, checks and reproduces the AD fuzzer's expressions")
+        .after_help(NOTES)
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(clap::Command::new("eval").about("Value and gradient from every engine").arg(expr()).arg(inputs()))
+        .subcommand(clap::Command::new("diff").about("Each engine's gradient minus Reverse AD's").arg(expr()).arg(inputs()))
+        .subcommand(
+            clap::Command::new("check")
+                .about("Run the oracles; exits with 1 if one fails")
+                .arg(expr())
+                .arg(inputs())
+                .arg(oracle()),
+        )
+        .subcommand(
+            clap::Command::new("localize")
+                .about(
+                    "Run the oracles on every subtree and show the engines on the smallest failing one whose \
+                     children all pass, where the failure first appears; exits with 1 if nothing fails",
+                )
+                .arg(expr())
+                .arg(inputs())
+                .arg(oracle()),
+        )
+        .subcommand(
+            clap::Command::new("smt")
+                .about(
+                    "Print an SMT-LIB2 query (for dReal or Z3) for a point of the box x_i +- --radius * max(1, |x_i|) \
+                     where Reverse AD's gradient is more than --epsilon off the symbolic one; run dReal with \
+                     --precision below --epsilon",
+                )
+                .arg(expr())
+                .arg(inputs())
+                .arg(number("epsilon", "1e-9", "Gradient tolerance"))
+                .arg(number("radius", "1e-12", "Relative half-width of the input box")),
+        )
+        .subcommand(
+            clap::Command::new("replay")
+                .about("Re-run a fuzz_target_ast crash artifact stage by stage; exits with 1 if an oracle fails")
+                .arg(path("artifact", "Crash artifact")),
+        )
+        .subcommand(
+            clap::Command::new("repro")
+                .about(
+                    "Shrink the first failing expression of a crash artifact, simplify its inputs, write it as a \
+                     standalone example, <dir>/repro_<hash>.rs, and append it to the regression corpus \
+                     <dir>/regressions.adxc; exits with 1 if nothing fails",
+                )
+                .arg(path("artifact", "Crash artifact"))
+                .arg(Arg::new("out").long("out").value_name("dir").default_value("examples").help("Directory to write to")),
+        )
+        .subcommand(
+            clap::Command::new("regress")
+                .about("Run the oracles on every expression of a regression corpus; exits with 1 if one fails")
+                .arg(path("corpus", "Regression corpus (.adxc)"))
+                .arg(oracle()),
+        )
+        .subcommand(
+            clap::Command::new("suite")
+                .about(
+                    "Run a suite of RPN test definitions (see `test_definition`) at their inputs; exits with 1 if \
+                     a test fails, or passes while marked `expect_failure`",
+                )
+                .arg(path("suite", "Suite (.yaml or .json)"))
+                .arg(oracle()),
+        )
+        .subcommand(
+            clap::Command::new("simplify")
+                .about("Rewrite the expression with the built-in simplification rules (and --rules) until none applies, see `ast_rewrite`")
+                .arg(expr())
+                .arg(
+                    Arg::new("rules")
+                        .long("rules")
+                        .value_name("file")
+                        .help("Rules applied after the built-in ones, one `lhs => rhs` per line, e.g. `(- ?a ?a) => 0`"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("list-oracles")
+                .about("Every oracle with its default tolerances, whether `all` includes it, and what it needs to check anything"),
+        )
+        .subcommand(clap::Command::new("list-backends").about("Every engine and ground truth, whether this build has it, and what it needs"))
+}
+
+fn parse_args(matches: &ArgMatches) -> Args {
+    let (name, sub) = matches.subcommand().expect("a subcommand is required");
+    let command = match name {
+        "eval" => Command::Eval,
+        "diff" => Command::Diff,
+        "check" => Command::Check,
+        "localize" => Command::Localize,
+        "replay" => Command::Replay,
+        "repro" => Command::Repro,
+        "smt" => Command::Smt,
+        "regress" => Command::Regress,
+        "suite" => Command::Suite,
+        "simplify" => Command::Simplify,
+        "list-oracles" => Command::ListOracles,
+        _ => Command::ListBackends,
+    };
+    // `try_get_*`: each subcommand only defines some of these
+    let string = |id: &str| sub.try_get_one::<String>(id).ok().flatten().cloned();
+    let number = |id: &str, default: f64| sub.try_get_one::<f64>(id).ok().flatten().copied().unwrap_or(default);
+    Args {
+        command,
+        expr: ["expr", "artifact", "corpus", "suite"].into_iter().find_map(string).unwrap_or_default(),
+        inputs: sub.try_get_many::<f64>("inputs").ok().flatten().map(|v| v.copied().collect()).unwrap_or_default(),
+        oracles: sub.try_get_one::<OracleSelection>("oracle").ok().flatten().copied().unwrap_or(OracleSelection::ALL),
+        out: string("out").unwrap_or_default(),
+        epsilon: number("epsilon", 1e-9),
+        radius: number("radius", 1e-12),
+        rules: string("rules"),
+    }
+}
+
+/// PyTorch only with the `pytorch` feature; one case at a time, so it reports Hessians too.
+fn referees() -> Vec<Box<dyn GroundTruthCalculator>> {
+    vec![
+        #[cfg(feature = "pytorch")]
+        PyTorchGroundTruthCalculator::default().with_hessians().boxed(),
+        DualNumberCalculator.boxed(),
+        SymbolicGroundTruthCalculator.boxed(),
+    ]
+}
+
+/// Each engine's name and its value and gradient, in order.
+type EngineRows = Vec<(String, Result<(f64, Vec<f64>), FuzzError>)>;
+
+/// (engine, value, gradient) from Rev, Fwd, every referee and evalexpr-jit.
+fn engine_rows(expr: &SimpleExpr, calc: &AdPyUnified<()>, inputs: &[f64]) -> EngineRows {
+    let mut rows = match catch_backend_panic("AD (Rev/Fwd)", || compute_engine_results(calc, inputs)) {
+        Ok(engine) => vec![
+            ("Rev".to_string(), Ok((engine.reverse_primal[0], engine.reverse.clone()))),
+            ("Fwd".to_string(), Ok((engine.forward_primal[0], engine.forward))),
+        ],
+        Err(e) => vec![("Rev/Fwd".to_string(), Err(e))],
+    };
+    for referee in referees() {
+        let row = catch_backend_panic(referee.name(), || referee.calculate(calc, inputs))
+            .and_then(|r| r)
+            .map(|gt| (gt.value.unwrap_or(f64::NAN), gt.jacobian));
+        rows.push((referee.name().to_string(), row));
+    }
+    let evalexpr = catch_backend_panic("evalexpr-jit", || EvalexprEvaluator::new(expr.clone(), inputs.len()).and_then(|eval| {
+        let value = eval.eval(&inputs.to_vec())?;
+        let gradient = (0..inputs.len())
+            .map(|i| eval.derivative(i).map(|d| d(inputs)))
+            .collect::<Result<Vec<f64>, FuzzError>>()?;
+        Ok((value, gradient))
+    }));
+    rows.push(("evalexpr-jit".to_string(), evalexpr.and_then(|r| r)));
+    rows
+}
+
+fn print_header(first: &str, n: usize) {
+    let mut header = format!("{:<22}{:>20}", "engine", first);
+    for i in 0..n {
+        header.push_str(&format!("{:>20}", format!("d/dx_{}", i)));
+    }
+    println!("{}", header);
+}
+
+fn print_row(name: &str, first: f64, values: &[f64]) {
+    let mut line = format!("{:<22}{:>20.12e}", name, first);
+    for v in values {
+        line.push_str(&format!("{:>20.12e}", v));
+    }
+    println!("{}", line);
+}
+
+fn eval(expr: &SimpleExpr, calc: &AdPyUnified<()>, inputs: &[f64]) {
+    print_header("value", inputs.len());
+    for (name, row) in engine_rows(expr, calc, inputs) {
+        match row {
+            Ok((value, gradient)) => print_row(&name, value, &gradient),
+            Err(e) => println!("{:<22}error: {}", name, e),
+        }
+    }
+}
+
+fn diff(expr: &SimpleExpr, calc: &AdPyUnified<()>, inputs: &[f64]) {
+    let rows = engine_rows(expr, calc, inputs);
+    let (reference_value, reference) = match &rows[0].1 {
+        Ok(row) => row.clone(),
+        Err(e) => {
+            println!("nothing to diff against: {}", e);
+            return;
+        }
+    };
+    print_header("value - Rev", inputs.len());
+    for (name, row) in rows.iter().skip(1) {
+        match row {
+            Ok((value, gradient)) => {
+                let deltas: Vec<f64> = gradient.iter().zip(&reference).map(|(g, r)| g - r).collect();
+                print_row(name, value - reference_value, &deltas);
+            }
+            Err(e) => println!("{:<22}error: {}", name, e),
+        }
+    }
+}
+
+fn check(calc: AdPyUnified<()>, inputs: &[f64], selection: OracleSelection) -> Result<bool, FuzzError> {
+    let oracles = FuzzingOracles::builder()
+        .selection(selection)
+        .with_env_overrides()?
+        .build()
+        .specialize(calc.get_expr());
+    let outcome = run_ad_tests(inputs, calc, &oracles, &referees())?;
+
+    print_verdicts(&outcome);
+    Ok(outcome.passed())
+}
+
+/// Where the failure of `expr` at `inputs` first appears, see `ast_localizer`, with every
+/// engine's results on that subtree. Returns whether anything failed.
+fn localize(expr: &SimpleExpr, inputs: &[f64], selection: OracleSelection) -> Result<bool, FuzzError> {
+    let oracles = FuzzingOracles::builder()
+        .selection(selection)
+        .with_env_overrides()?
+        .build();
+    let Some(found) = localize_failure(expr, inputs, &oracles, &referees()) else {
+        println!("no oracle fails on this expression");
+        return Ok(false);
+    };
+    println!("{} of {} distinct subtrees fail; the failure first appears at child path {:?} ({} nodes):",
+        found.failed, found.tested, found.path, size(&found.subtree));
+    println!("  {}
+", InfixPrinter::print(&found.subtree, inputs.len()));
+    eval(&found.subtree, &AdPyUnified::new(found.subtree.clone(), inputs.len(), 1), inputs);
+    Ok(true)
+}
+
+/// SMT-LIB query checking Reverse AD's gradient at `inputs`, see `SmtQuery`.
+fn smt(expr: &SimpleExpr, calc: &AdPyUnified<()>, inputs: &[f64], epsilon: f64, radius: f64) -> Result<String, FuzzError> {
+    let engine = catch_backend_panic("AD (Rev/Fwd)", || compute_engine_results(calc, inputs))?;
+    SmtQuery { expr, inputs, gradient: &engine.reverse, epsilon, radius }.to_smtlib()
+}
+
+fn print_verdicts(outcome: &TestOutcome) {
+    for (name, e) in &outcome.failed_ground_truths {
+        println!("{} unavailable: {}", name, e);
+    }
+    println!("{:<14}{:<8}{:>12}", "oracle", "verdict", "time");
+    for verdict in &outcome.verdicts {
+        let label = match verdict.outcome() {
+            Outcome::Pass => "pass",
+            Outcome::Warn if verdict.borderline => "border",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "FAIL",
+        };
+        println!("{:<14}{:<8}{:>12?}", verdict.oracle, label, verdict.elapsed);
+    }
+    let engine = &outcome.engine_results.timing;
+    println!("Rev: {:?}, Fwd: {:?}; Reverse AD tape: {}", engine.reverse, engine.forward, outcome.tape);
+    for e in outcome.failures().chain(outcome.warnings()) {
+        println!("\n{}", e);
+    }
+}
+
+/// Same stages as `fuzz_target_ast`: decode inputs, generate each expression, run the oracles
+/// against the default ground truth. Returns whether every expression passed.
+fn replay(path: &str) -> Result<bool, FuzzError> {
+    let data = fs::read(path).map_err(|e| FuzzError::InvalidConfig(format!("cannot read '{}': {}", path, e)))?;
+    let config = FuzzConfig::load()?;
+    // A tape over the budget fails the case in the fuzzer too
+    set_tape_budget(config.tape_budget);
+    println!("artifact: {} ({} bytes)", path, data.len());
+    println!("config: mode {:?}, {} tests per input, max_variables {}, max_depth {}\n",
+        config.mode, config.num_generated_tests, config.ast.max_variables, config.ast.max_depth);
+
+    let (inputs, cases) = match run_fuzz_iteration(&data, &config)? {
+        IterationOutcome::TooShort => {
+            println!("too short to decode {} inputs ({:?} layout); the fuzzer ignores this input",
+                config.ast.max_variables, config.inputs.layout);
+            return Ok(true);
+        }
+        IterationOutcome::InputsRejected(inputs) => {
+            print_inputs(&inputs);
+            println!("rejected by the [inputs] domain; the fuzzer ignores this input");
+            return Ok(true);
+        }
+        IterationOutcome::Ran { inputs, cases, .. } => (inputs, cases),
+    };
+    print_inputs(&inputs);
+
+    let mut passed = true;
+    let mut expression = 0;
+    for case in cases {
+        match &case {
+            CaseResult::Tested(case, _) | CaseResult::Error(case, _) if case.point > 0 => {
+                println!("\n== expression {}, sweep point {} ==", expression - 1, case.point);
+            }
+            _ => {
+                println!("\n== expression {} ==", expression);
+                expression += 1;
+            }
+        }
+        let (case, outcome) = match case {
+            CaseResult::NotGenerated(e) => {
+                println!("generation failed ({}); skipped", e);
+                continue;
+            }
+            CaseResult::Skipped(generated) => {
+                print_expr(&generated.expr, generated.num_inputs);
+                match constant_value(&generated.expr) {
+                    Some(value) => println!("constant ({}); skipped", value),
+                    None => println!("no variables; skipped"),
+                }
+                continue;
+            }
+            CaseResult::Error(case, e) => {
+                print_expr(&case.expr, case.inputs.len());
+                println!("skipped by the harness: {}", e);
+                continue;
+            }
+            CaseResult::Unsatisfiable(generated) => {
+                print_expr(&generated.expr, generated.num_inputs);
+                println!("no inputs found where it is defined (synthesize); skipped");
+                continue;
+            }
+            CaseResult::OverBudget => {
+                println!("iteration budget (max_iteration_millis) used up; skipped");
+                continue;
+            }
+            CaseResult::Tested(case, outcome) => (case, outcome),
+        };
+        print_expr(&case.expr, case.inputs.len());
+        println!();
+        eval(&case.expr, &AdPyUnified::new(case.expr.clone(), case.inputs.len(), 1), &case.inputs);
+        println!();
+        print_verdicts(&outcome);
+        passed &= outcome.passed();
+    }
+    Ok(passed)
+}
+
+/// Writes the first failing expression of the artifact, shrunk, as `<out>/repro_<hash>.rs`.
+/// Returns whether anything failed.
+fn repro(path: &str, out: &str) -> Result<bool, FuzzError> {
+    let data = fs::read(path).map_err(|e| FuzzError::InvalidConfig(format!("cannot read '{}': {}", path, e)))?;
+    let config = FuzzConfig::load()?;
+    set_tape_budget(config.tape_budget);
+    let (oracles, cases) = match run_fuzz_iteration(&data, &config)? {
+        IterationOutcome::Ran { oracles, cases, .. } => (oracles, cases),
+        _ => {
+            println!("the fuzzer ignores this input (see `adfuzz replay {}`)", path);
+            return Ok(false);
+        }
+    };
+    let failing = cases.into_iter().find_map(|case| match case {
+        CaseResult::Tested(case, outcome) if !outcome.passed() => Some((case, outcome)),
+        _ => None,
+    });
+    let Some((case, outcome)) = failing else {
+        println!("no expression of this input fails (see `adfuzz replay {}`)", path);
+        return Ok(false);
+    };
+    let verdict = outcome.verdicts.iter().find(|v| v.is_failure()).expect("the outcome did not pass");
+    let error = verdict.result.as_ref().unwrap_err();
+    // "shape" and "backend" are no oracles: the example runs all of them
+    let oracle = OracleSelection::from_name(&verdict.oracle).map(|_| verdict.oracle.as_str());
+    let shrunk = shrink_failure(&case.expr, &case.inputs, &oracles, &[DefaultGroundTruth::default().boxed()], error);
+    println!("{} ({} nodes)", InfixPrinter::print(&case.expr, case.inputs.len()), size(&case.expr));
+    println!("minimized to {} ({} nodes)", InfixPrinter::print(&shrunk, case.inputs.len()), size(&shrunk));
+    let inputs = shrink_input_failure(&shrunk, &case.inputs, &oracles, &[DefaultGroundTruth::default().boxed()], error);
+    println!("at {:?}, simplified from {:?}", inputs, case.inputs);
+
+    let stem = RustReproPrinter::file_stem(&shrunk, &inputs);
+    let note = format!("Found by `adfuzz repro {}`:\n{}", path, error);
+    let file = Path::new(out).join(format!("{}.rs", stem));
+    fs::create_dir_all(out)
+        .and_then(|_| fs::write(&file, RustReproPrinter::print(&shrunk, &inputs, oracle, &note)))
+        .map_err(|e| FuzzError::InvalidConfig(format!("cannot write '{}': {}", file.display(), e)))?;
+    println!("\nwrote {}\nrun it with: cargo +nightly run --example {}", file.display(), stem);
+
+    let corpus = Path::new(out).join(REGRESSION_CORPUS);
+    let entry = CorpusEntry { expr: shrunk, inputs };
+    let existing = fs::read(&corpus).ok().and_then(|data| read_corpus(&data).ok()).unwrap_or_default();
+    if existing.contains(&entry) {
+        println!("already in {}", corpus.display());
+        return Ok(true);
+    }
+    append_to_corpus(&corpus, &entry)
+        .map_err(|e| FuzzError::InvalidConfig(format!("cannot write '{}': {}", corpus.display(), e)))?;
+    println!("added to {} (check it with: adfuzz regress {})", corpus.display(), corpus.display());
+    Ok(true)
+}
+
+/// `check` on every entry of the corpus at `path`. Returns whether all of them passed.
+fn regress(path: &str, selection: OracleSelection) -> Result<bool, FuzzError> {
+    let data = fs::read(path).map_err(|e| FuzzError::InvalidConfig(format!("cannot read '{}': {}", path, e)))?;
+    let entries = read_corpus(&data)?;
+    let mut failed = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        println!("== {} / {}: {} at {:?} ==", i + 1, entries.len(), InfixPrinter::print(&entry.expr, entry.inputs.len()), entry.inputs);
+        let calc = AdPyUnified::new(entry.expr.clone(), entry.inputs.len(), 1);
+        match check(calc, &entry.inputs, selection) {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(e) => {
+                println!("error: {}", e);
+                failed += 1;
+            }
+        }
+        println!();
+    }
+    println!("{} of {} regression cases failed", failed, entries.len());
+    Ok(failed == 0)
+}
+
+/// `check` on every point of every test definition in the suite at `path`. Returns whether all
+/// of them passed, or failed where `expect_failure` says they should.
+fn suite(path: &str, selection: OracleSelection) -> Result<bool, FuzzError> {
+    let suite = load_suite(path)?;
+    let oracles = FuzzingOracles::builder()
+        .selection(selection)
+        .with_env_overrides()?
+        .build();
+    let results = run_suite(&suite, &oracles, &referees());
+    let mut unexpected = 0;
+    for result in &results {
+        println!("== {} at {:?} ==", result.name, result.inputs);
+        match &result.outcome {
+            Ok(outcome) => print_verdicts(outcome),
+            Err(e) => println!("error: {}", e),
+        }
+        if !result.as_expected() {
+            unexpected += 1;
+            println!("UNEXPECTED: {}", if result.expect_failure { "passed, but is marked expect_failure" } else { "failed" });
+        }
+        println!();
+    }
+    println!("{} of {} suite cases did not go as expected", unexpected, results.len());
+    Ok(unexpected == 0)
+}
+
+/// The built-in oracles with the builder's defaults, see `FuzzingOraclesBuilder::catalog`.
+fn list_oracles() {
+    println!("{:<13}{:<9}{:<22}{:<12}requires", "oracle", "in all", "tolerance (abs, rel)", "comparison");
+    for info in FuzzingOracles::builder().catalog() {
+        let tolerances = info.tolerances.map_or("-".to_string(), |(abs, rel)| format!("{:e}, {:e}", abs, rel));
+        let comparison = info.comparison.map_or("-".to_string(), |comparison| format!("{:?}", comparison));
+        println!("{:<13}{:<9}{:<22}{:<12}{}", info.name, if info.in_all { "yes" } else { "opt-in" },
+            tolerances, comparison, info.requires.unwrap_or("-"));
+    }
+}
+
+/// Every engine of `gt_calculators::backends`; `*` marks the default ground truth.
+fn list_backends() {
+    let default = DefaultGroundTruth::default().name();
+    println!("{:<24}{:<15}{:<9}{:<11}requires", "backend", "role", "hessian", "built in");
+    for info in backends() {
+        let name = if info.name == default { format!("{} *", info.name) } else { info.name.to_string() };
+        println!("{:<24}{:<15}{:<9}{:<11}{}", name, if info.ground_truth { "ground truth" } else { "under test" },
+            if info.hessian { "yes" } else { "no" }, if info.available { "yes" } else { "no" }, info.requires.unwrap_or("-"));
+    }
+    println!("\n* default ground truth of the fuzz targets");
+}
+
+fn print_inputs(inputs: &[f64]) {
+    println!("== decoded inputs ==");
+    for (i, x) in inputs.iter().enumerate() {
+        println!("x_{} = {:e}  (bits {:#018x})", i, x, x.to_bits());
+    }
+}
+
+fn print_expr(expr: &SimpleExpr, num_inputs: usize) {
+    println!("Infix:  {}", InfixPrinter::print(expr, num_inputs));
+    println!("S-expr: {}", SExprPrinter::print(expr, num_inputs));
+    println!("SSA:\n{}", SSAPrinter::print(expr));
+    println!("Debug:\n{:#?}", expr);
+}
+
+fn main() -> ExitCode {
+    let args = parse_args(&cli().get_matches());
+
+    if let Command::ListOracles | Command::ListBackends = args.command {
+        match args.command {
+            Command::ListOracles => list_oracles(),
+            _ => list_backends(),
+        }
+        return ExitCode::SUCCESS;
+    }
+    if let Command::Replay | Command::Repro | Command::Regress | Command::Suite = args.command {
+        let result = match args.command {
+            Command::Repro => repro(&args.expr, &args.out),
+            Command::Regress => regress(&args.expr, args.oracles),
+            Command::Suite => suite(&args.expr, args.oracles),
+            _ => replay(&args.expr),
+        };
+        return match result {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+
+    // Only a lone number or variable parses both ways, and means the same in both
+    let (expr, num_vars) = match parse_sexpr(&args.expr).or_else(|_| parse_infix(&args.expr)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    if let Command::Simplify = args.command {
+        let rules = match &args.rules {
+            Some(path) => RuleSet::load(path).map(|user| RuleSet::simplify().extend(user)),
+            None => Ok(RuleSet::simplify()),
+        };
+        return match rules {
+            Ok(rules) => {
+                let simplified = rules.rewrite(&expr);
+                println!("{}", InfixPrinter::print(&simplified, num_vars));
+                println!("{}", SExprPrinter::print(&simplified, num_vars));
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+    if args.inputs.len() < num_vars.max(1) {
+        eprintln!("error: '{}' needs {} inputs, got {}", args.expr, num_vars.max(1), args.inputs.len());
+        return ExitCode::from(2);
+    }
+
+    let inputs = &args.inputs;
+    let calc = AdPyUnified::new(expr.clone(), inputs.len(), 1);
+    if let Command::Smt = args.command {
+        return match smt(&expr, &calc, inputs, args.epsilon, args.radius) {
+            Ok(query) => {
+                print!("{}", query);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+    println!("f = {}   at {:?}\n", InfixPrinter::print(&expr, inputs.len()), inputs);
+    match args.command {
+        Command::Eval => eval(&expr, &calc, inputs),
+        Command::Diff => diff(&expr, &calc, inputs),
+        Command::Localize => match localize(&expr, inputs, args.oracles) {
+            Ok(true) => {}
+            Ok(false) => return ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(2);
+            }
+        },
+        Command::Replay | Command::Repro | Command::Regress | Command::Suite | Command::Smt | Command::Simplify
+            | Command::ListOracles | Command::ListBackends => unreachable!("handled above"),
+        Command::Check => match check(calc, inputs, args.oracles) {
+            Ok(true) => {}
+            Ok(false) => return ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(2);
+            }
+        },
+    }
+    ExitCode::SUCCESS
+}
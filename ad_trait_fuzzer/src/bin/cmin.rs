@@ -0,0 +1,130 @@
+// src/bin/cmin.rs
+// cargo run --release --bin cmin -- fuzz/corpus/fuzz_target_ast corpus_min
+
+// Shrinks a fuzz_target_ast corpus to a few inputs with the same expression features, see
+// `corpus_min`. Inputs are decoded with the fuzzer's config, like `adfuzz replay`, so run it with
+// the settings of the campaign that grew the corpus.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use fuzz_core::corpus_min::{input_features, minimize, CorpusInput};
+use fuzz_core::error::FuzzError;
+use fuzz_core::fuzz_config::FuzzConfig;
+
+const USAGE: &str = "\
+Usage: cmin <corpus dir> <output dir> [--dry-run]
+
+Decodes every input of the corpus to the expressions the fuzzer generates from it (with the
+fuzzer's config: FUZZ_CONFIG or fuzz_config.toml, then env overrides) and copies a small subset
+covering every operator bigram, operator depth bucket, size and variable count seen to the
+output directory. Inputs the fuzzer ignores are dropped.
+
+Options:
+  --dry-run    Only print what would be kept
+  -h, --help   Show this message";
+
+struct Args {
+    corpus: PathBuf,
+    out: PathBuf,
+    dry_run: bool,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut positional = Vec::new();
+    let mut dry_run = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            s if s.starts_with("--") => return Err(format!("unknown option '{}'", s)),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    let mut positional = positional.into_iter();
+    let corpus = positional.next().ok_or("missing corpus directory")?;
+    let out = positional.next().ok_or("missing output directory")?;
+    if let Some(extra) = positional.next() {
+        return Err(format!("unexpected argument '{}'", extra.display()));
+    }
+    Ok(Args { corpus, out, dry_run })
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> FuzzError {
+    FuzzError::InvalidConfig(format!("cannot access '{}': {}", path.display(), e))
+}
+
+/// The files of `dir`, by name, so runs keep the same inputs on ties.
+fn corpus_files(dir: &Path) -> Result<Vec<PathBuf>, FuzzError> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn cmin(args: &Args) -> Result<(), FuzzError> {
+    let config = FuzzConfig::load()?;
+    if args.out.exists() && fs::canonicalize(&args.out).ok() == fs::canonicalize(&args.corpus).ok() {
+        return Err(FuzzError::InvalidConfig("the output directory must differ from the corpus".to_string()));
+    }
+    let files = corpus_files(&args.corpus)?;
+    // One input in memory at a time: corpora of long campaigns run into gigabytes
+    let mut inputs = Vec::with_capacity(files.len());
+    for path in &files {
+        let data = fs::read(path).map_err(|e| io_error(path, e))?;
+        inputs.push(CorpusInput { len: data.len(), features: input_features(&data, &config) });
+    }
+    let kept = minimize(&inputs);
+
+    let total_bytes: usize = inputs.iter().map(|input| input.len).sum();
+    let kept_bytes: usize = kept.iter().map(|&i| inputs[i].len).sum();
+    let mut features: Vec<usize> = inputs.iter().flat_map(|input| input.features.iter().copied()).collect();
+    features.sort_unstable();
+    features.dedup();
+    let ignored = inputs.iter().filter(|input| input.features.is_empty()).count();
+    println!("{} inputs ({} bytes), {} ignored by the fuzzer, {} distinct expression features",
+        inputs.len(), total_bytes, ignored, features.len());
+    println!("kept {} inputs ({} bytes)", kept.len(), kept_bytes);
+    if args.dry_run {
+        for &i in &kept {
+            println!("  {}", files[i].display());
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(&args.out).map_err(|e| io_error(&args.out, e))?;
+    for &i in &kept {
+        let target = args.out.join(files[i].file_name().expect("read_dir entries have a name"));
+        fs::copy(&files[i], &target).map_err(|e| io_error(&target, e))?;
+    }
+    println!("written to {}", args.out.display());
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match cmin(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// src/bin/soak.rs
+// cargo run --release --bin soak -- --iterations 5000000 --seed 42
+
+// Sustained randomized testing without libFuzzer or nightly: random bytes from a seeded RNG go
+// through the same `run_fuzz_iteration` as the fuzz targets. Failures are bucketed like
+// Continuous mode; the first of each bucket is printed and, with FUZZ_REPORT_DIR, saved as a
+// JSON report plus the raw input, which `adfuzz replay` accepts. With a checkpoint configured
+// the campaign is saved with every progress line and resumed by the next run.
+// With --calibrate it also records how far apart the engines and ground truths are and writes
+// the tolerances that cover it as an oracles.toml, see `calibration`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use fuzz_core::ast_evaluator::InfixPrinter;
+use fuzz_core::ast_localizer::localize_failure;
+use fuzz_core::calibration::Calibration;
+use fuzz_core::campaign_report::CampaignReport;
+use fuzz_core::error::FuzzError;
+use fuzz_core::failure_report::FailureReport;
+use fuzz_core::fuzz_case::{CaseResult, FuzzCase};
+use fuzz_core::fuzz_config::FuzzConfig;
+use fuzz_core::fuzz_harness::{set_eval_timeout, GroundTruthCalculator, TestOutcome};
+use fuzz_core::gt_calculators::DefaultGroundTruth;
+use fuzz_core::notifier::{notify, FailureNotice};
+use fuzz_core::tape_stats::{set_tape_budget, CountingAllocator};
+use fuzz_core::{run_fuzz_iteration, IterationOutcome};
+
+// Peak memory of the Reverse AD pass, see `tape_stats`
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+const USAGE: &str = "\
+Usage: soak [options]
+
+Runs random inputs through the full oracle pipeline (decode, generate, AD engines, ground truth,
+oracles) with the fuzzer's config (FUZZ_CONFIG or fuzz_config.toml, then env overrides).
+Failures are deduplicated like FUZZ_MODE=continuous, across runs when FUZZ_CHECKPOINT is set.
+Exits with 1 if anything failed, except with --calibrate, where mismatches are the data.
+
+Options:
+  --iterations <n>   Inputs to run (default: 1000000)
+  --seed <n>         Seed of the first input; input i uses seed + i (default: time based)
+  --bytes <n>        Bytes per input (default: 128)
+  --progress <secs>  Seconds between throughput lines (default: 10)
+  --calibrate <file> Write the rev_fwd/rev_gt/fwd_gt tolerances (per operator class) that pass
+                     99.9% of the differences seen, times 2, as an oracles.toml for ORACLE_CONFIG
+  -h, --help         Show this message
+
+A failure at input i is reproduced alone with `--seed <seed + i> --iterations 1`.";
+
+struct Args {
+    iterations: u64,
+    seed: u64,
+    bytes: usize,
+    progress: Duration,
+    calibrate: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        iterations: 1_000_000,
+        seed: time_seed(),
+        bytes: 128,
+        progress: Duration::from_secs(10),
+        calibrate: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> Result<u64, String> {
+            let s = args.next().ok_or(format!("{} needs a value", name))?;
+            s.parse().map_err(|_| format!("{} '{}' is not a number", name, s))
+        };
+        match arg.as_str() {
+            "--iterations" => parsed.iterations = value("--iterations")?,
+            "--seed" => parsed.seed = value("--seed")?,
+            "--bytes" => parsed.bytes = value("--bytes")? as usize,
+            "--progress" => parsed.progress = Duration::from_secs(value("--progress")?),
+            "--calibrate" => parsed.calibrate = Some(args.next().ok_or("--calibrate needs a value")?.into()),
+            other => return Err(format!("unknown argument '{}'", other)),
+        }
+    }
+    Ok(parsed)
+}
+
+fn time_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+/// Prints the first failure of a bucket, saves it to `report_dir` if set and sends the
+/// configured notifications, returning the thread sending them.
+fn report_failure(
+    config: &FuzzConfig,
+    campaign: &CampaignReport,
+    seed: u64,
+    data: &[u8],
+    case: &FuzzCase,
+    outcome: &TestOutcome,
+) -> Option<JoinHandle<()>> {
+    let error = outcome.first_failure()?;
+    let class = error.failure_class().map(|c| c.to_string()).unwrap_or_else(|| error.kind().to_string());
+    println!("\n=== NEW FAILURE (seed {}) ===", seed);
+    println!("{}", InfixPrinter::print(&case.expr, case.inputs.len()));
+    println!("inputs: {:?}", case.inputs);
+    println!("{}: {}", class, error);
+
+    let mut report_path = None;
+    if let Some(dir) = &config.report_dir {
+        report_path = save_failure(dir, seed, data, case, outcome);
+    }
+    notify(config, &FailureNotice {
+        expr: &case.expr,
+        inputs: &case.inputs,
+        error,
+        distinct_failures: campaign.buckets.len(),
+        report: report_path.as_deref(),
+    })
+}
+
+/// Saves the input and the JSON report to `dir`, returns the report's path.
+fn save_failure(dir: &Path, seed: u64, data: &[u8], case: &FuzzCase, outcome: &TestOutcome) -> Option<PathBuf> {
+    let artifact = Path::new(dir).join(format!("soak-{}.bin", seed));
+    let saved = fs::create_dir_all(dir).and_then(|_| fs::write(&artifact, data));
+    match saved {
+        Ok(()) => println!("input: {} (adfuzz replay)", artifact.display()),
+        Err(e) => println!("could not save input to {}: {}", artifact.display(), e),
+    }
+    let report = FailureReport::new(&case.expr, None, outcome, &case.oracles)?;
+    match report.write_to(dir) {
+        Ok(path) => {
+            println!("JSON report: {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            println!("could not write JSON report to {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+fn save_checkpoint(config: &FuzzConfig, campaign: &CampaignReport) {
+    if let Some(path) = &config.checkpoint {
+        if let Err(e) = campaign.save_checkpoint(path) {
+            println!("could not write checkpoint to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn soak(args: &Args) -> Result<CampaignReport, FuzzError> {
+    let config = FuzzConfig::load()?;
+    set_eval_timeout(config.eval_timeout);
+    set_tape_budget(config.tape_budget);
+    println!("soak: {} inputs of {} bytes from seed {}", args.iterations, args.bytes, args.seed);
+
+    let mut campaign = match &config.checkpoint {
+        Some(path) => CampaignReport::load_checkpoint(path)
+            .map_err(|e| FuzzError::InvalidConfig(format!("cannot resume from {}: {}", path.display(), e)))?
+            .unwrap_or_default(),
+        None => CampaignReport::new(),
+    };
+    if campaign.tests_run() > 0 {
+        println!("resuming: {} tests, {} known failure buckets", campaign.tests_run(), campaign.buckets.len());
+    }
+    let mut calibration = args.calibrate.as_ref().map(|_| Calibration::new());
+    let mut data = vec![0u8; args.bytes];
+    let start = Instant::now();
+    let mut last_progress = start;
+    let mut notifications = Vec::new();
+    for i in 0..args.iterations {
+        let seed = args.seed.wrapping_add(i);
+        StdRng::seed_from_u64(seed).fill_bytes(&mut data);
+
+        let cases = match run_fuzz_iteration(&data, &config)? {
+            IterationOutcome::Ran { cases, .. } => cases,
+            IterationOutcome::TooShort | IterationOutcome::InputsRejected(_) => Vec::new(),
+        };
+        for case in cases {
+            match case {
+                CaseResult::Tested(case, outcome) => {
+                    if let Some(calibration) = &mut calibration {
+                        calibration.record(&case.expr, &outcome);
+                    }
+                    if campaign.record(&case.expr, &outcome) {
+                        if let Some(found) = localize_failure(&case.expr, &case.inputs, &case.oracles, &[DefaultGroundTruth::default().boxed()]) {
+                            campaign.record_localization(&found);
+                        }
+                        notifications.extend(report_failure(&config, &campaign, seed, &data, &case, &outcome));
+                    }
+                }
+                CaseResult::Error(_, FuzzError::Timeout { .. }) => campaign.record_timeout(),
+                CaseResult::Error(..) | CaseResult::NotGenerated(_) | CaseResult::Skipped(_) | CaseResult::Unsatisfiable(_) | CaseResult::OverBudget => {}
+            }
+        }
+
+        if last_progress.elapsed() >= args.progress {
+            last_progress = Instant::now();
+            let secs = start.elapsed().as_secs_f64();
+            println!(
+                "[{:.0}s] {} inputs ({:.0}/s), {} tests ({:.0}/s), {} failed, {} distinct",
+                secs, i + 1, (i + 1) as f64 / secs, campaign.tests_run(), campaign.tests_run() as f64 / secs,
+                campaign.failing_tests(), campaign.buckets.len(),
+            );
+            save_checkpoint(&config, &campaign);
+        }
+    }
+    save_checkpoint(&config, &campaign);
+    if let (Some(path), Some(calibration)) = (&args.calibrate, &calibration) {
+        fs::write(path, calibration.suggest(&config.oracles()?))
+            .map_err(|e| FuzzError::InvalidConfig(format!("cannot write {}: {}", path.display(), e)))?;
+        println!("suggested tolerances from {} tests: {}", calibration.tests(), path.display());
+    }
+    for notification in notifications {
+        let _ = notification.join();
+    }
+    Ok(campaign)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let args = match parse_args(args.into_iter()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let start = Instant::now();
+    let campaign = match soak(&args) {
+        Ok(campaign) => campaign,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let secs = start.elapsed().as_secs_f64();
+    println!("\n{}", campaign);
+    println!("{} inputs in {:.1}s ({:.0} inputs/s, {:.0} tests/s)",
+        args.iterations, secs, args.iterations as f64 / secs, campaign.tests_run() as f64 / secs);
+    if campaign.failing_tests() > 0 && args.calibrate.is_none() { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
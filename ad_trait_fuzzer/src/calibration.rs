@@ -0,0 +1,228 @@
+// src/calibration.rs
+
+// Tolerance calibration: instead of hand-picking `abs_tol`/`rel_tol`, a campaign (`soak
+// --calibrate`) records how far apart Reverse AD, Forward AD and the ground truths actually are,
+// per oracle and per operator class, and suggests an `oracles.toml` that covers what it saw.
+// Differences are split like the hybrid check `max(abs_tol, |reference| * rel_tol)`: relative to
+// the reference where `|reference| >= 1`, absolute below. The suggested tolerance is a high
+// quantile of them times a margin, rounded up to one significant digit, and never tighter than
+// the current one: the point is to stop systematic false positives (exp/pow chains drift by far
+// more than 1e-9), not to tighten what already passes. A derivative bug that fires on a large
+// share of the tests is calibrated away like noise, so run it on a build that is trusted.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::ast_expr::SimpleExpr;
+use crate::fuzz_harness::TestOutcome;
+use crate::oracles::{FuzzingOracles, OpClass, OracleSelection};
+
+/// Differences of one oracle's comparison, see the top of the file.
+#[derive(Debug, Clone, Default)]
+struct Samples {
+    relative: Vec<f64>,
+    absolute: Vec<f64>,
+}
+
+impl Samples {
+    /// Non-finite pairs are left out: no tolerance covers them.
+    fn add(&mut self, value: f64, reference: f64) {
+        if !value.is_finite() || !reference.is_finite() {
+            return;
+        }
+        let diff = (value - reference).abs();
+        if reference.abs() >= 1.0 {
+            self.relative.push(diff / reference.abs());
+        } else {
+            self.absolute.push(diff);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.relative.len() + self.absolute.len()
+    }
+}
+
+/// `q`-quantile of `values`, `None` if there are none.
+fn quantile(values: &[f64], q: f64) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = ((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len().max(1));
+    sorted.get(rank - 1).copied()
+}
+
+/// `x` rounded up to one significant digit, as TOML: 3.2e-9 -> "4e-9".
+fn round_up(x: f64) -> (f64, String) {
+    if x <= 0.0 {
+        return (0.0, "0.0".to_string());
+    }
+    let mut exp = x.log10().floor() as i32;
+    // So that 2e-6, off in the last bit after the division, stays 2e-6
+    let mut digit = (x / 10f64.powi(exp) - 1e-9).ceil().max(1.0) as u64;
+    if digit == 10 {
+        digit = 1;
+        exp += 1;
+    }
+    let text = format!("{}e{}", digit, exp);
+    (text.parse().unwrap(), text)
+}
+
+/// Differences seen over a campaign, see the top of the file. Feed it every tested case with
+/// `record`, then write `suggest` to a file and pass it as `ORACLE_CONFIG`.
+#[derive(Debug, Clone)]
+pub struct Calibration {
+    /// Share of the differences the suggested tolerances cover.
+    pub quantile: f64,
+    /// Factor the quantile is multiplied by.
+    pub margin: f64,
+    tests: usize,
+    /// Per oracle, for expressions in no operator class (`None`) and per class: `[rev_gt.pow]`
+    /// can only loosen `[rev_gt]`, so the base tolerances are calibrated without those. A class
+    /// only gets expressions in no other class, so `exp(x) * x^2` drifting does not loosen
+    /// `[rev_gt.log]` for what `pow` did; expressions in several classes are left out.
+    samples: BTreeMap<(&'static str, Option<&'static str>), Samples>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration { quantile: 0.999, margin: 2.0, tests: 0, samples: BTreeMap::new() }
+    }
+}
+
+impl Calibration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the differences `rev_fwd`, `rev_gt` and `fwd_gt` compare, with every ground
+    /// truth for the last two.
+    pub fn record(&mut self, expr: &SimpleExpr, outcome: &TestOutcome) {
+        self.tests += 1;
+        let classes = OpClass::of_expr(expr);
+        let class = match classes.as_slice() {
+            [] => None,
+            [class] => Some(class.name()),
+            _ => return,
+        };
+        let engine = &outcome.engine_results;
+        self.add("rev_fwd", class, &engine.reverse, &engine.forward);
+        for gt in &outcome.ground_truths {
+            self.add("rev_gt", class, &engine.reverse, &gt.jacobian);
+            self.add("fwd_gt", class, &engine.forward, &gt.jacobian);
+        }
+    }
+
+    fn add(&mut self, oracle: &'static str, class: Option<&'static str>, values: &[f64], reference: &[f64]) {
+        if values.len() != reference.len() {
+            return;
+        }
+        let samples = self.samples.entry((oracle, class)).or_default();
+        for (v, r) in values.iter().zip(reference) {
+            samples.add(*v, *r);
+        }
+    }
+
+    pub fn tests(&self) -> usize {
+        self.tests
+    }
+
+    /// `(abs_tol, rel_tol)` covering `samples`, at least `floor`, and the TOML for each.
+    fn tolerances(&self, samples: &Samples, floor: (f64, f64)) -> [(f64, Option<String>); 2] {
+        let suggest = |values: &[f64], floor: f64| match quantile(values, self.quantile) {
+            Some(q) if q * self.margin > floor => {
+                let (value, text) = round_up(q * self.margin);
+                (value, Some(text))
+            }
+            _ => (floor, None),
+        };
+        [suggest(&samples.absolute, floor.0), suggest(&samples.relative, floor.1)]
+    }
+
+    /// An `oracles.toml` with the tolerances each oracle needs to pass `quantile` of the
+    /// differences recorded: a table per oracle, and one per operator class that needs looser
+    /// ones than that. Settings that would not loosen `oracles`' tolerances are left out.
+    pub fn suggest(&self, oracles: &FuzzingOracles) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Suggested by tolerance calibration over {} tests: the {}-quantile of the observed", self.tests, self.quantile);
+        let _ = writeln!(out, "# differences times {}, where looser than the current tolerances. Use with ORACLE_CONFIG.", self.margin);
+        let defaults = FuzzingOracles::new(OracleSelection::ALL);
+        for oracle in ["rev_fwd", "rev_gt", "fwd_gt"] {
+            let current = oracles.tolerances(oracle).or_else(|| defaults.tolerances(oracle)).unwrap_or_default();
+            let (mut abs, mut rel) = (current.abs_tol.unwrap_or(0.0), current.rel_tol.unwrap_or(0.0));
+            if let Some(base) = self.samples.get(&(oracle, None)) {
+                let [(a, abs_text), (r, rel_text)] = self.tolerances(base, (abs, rel));
+                (abs, rel) = (a, r);
+                if abs_text.is_some() || rel_text.is_some() {
+                    let _ = writeln!(out, "\n# {} differences", base.len());
+                    let _ = writeln!(out, "[{}]", oracle);
+                    write_tolerances(&mut out, abs_text, rel_text);
+                }
+            }
+
+            for ((_, class), samples) in self.samples.range((oracle, Some(""))..).take_while(|((o, _), _)| *o == oracle) {
+                let [(_, abs_text), (_, rel_text)] = self.tolerances(samples, (abs, rel));
+                if abs_text.is_some() || rel_text.is_some() {
+                    let _ = writeln!(out, "\n# {} differences", samples.len());
+                    let _ = writeln!(out, "[{}.{}]", oracle, class.unwrap());
+                    write_tolerances(&mut out, abs_text, rel_text);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn write_tolerances(out: &mut String, abs: Option<String>, rel: Option<String>) {
+    for (key, value) in [("abs_tol", abs), ("rel_tol", rel)] {
+        if let Some(value) = value {
+            let _ = writeln!(out, "{} = {}", key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_parser::parse_infix;
+    use crate::fuzz_harness::run_ad_tests;
+    use crate::oracles::OracleConfig;
+
+    #[test]
+    fn test_suggest() {
+        assert_eq!(round_up(3.2e-9).1, "4e-9");
+        assert_eq!(round_up(2e-6).1, "2e-6");
+        assert_eq!(round_up(9.5e-3).1, "1e-2");
+
+        // Each test case of an expression, with Reverse AD off Forward AD by `drift(i)` relative
+        let mut calibration = Calibration::new();
+        let oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        let mut record = |src: &str, drift: &dyn Fn(f64) -> f64| {
+            let expr = parse_infix(src).unwrap().0;
+            let mut outcome = run_ad_tests(&[1.5], AdPyUnified::new(expr.clone(), 1, 1), &oracles, &[]).unwrap();
+            let forward = outcome.engine_results.forward[0];
+            for i in 0..1000 {
+                outcome.engine_results.reverse[0] = forward * (1.0 + drift(i as f64));
+                calibration.record(&expr, &outcome);
+            }
+        };
+        record("x_0 * x_0", &|i| 1e-13 * i);
+        record("100 * sin(x_0)", &|i| 1e-13 * i);
+        // pow chains drift by up to 1e-6
+        record("x_0 ^ 3", &|i| 1e-9 * i);
+        // In two classes: counted in neither
+        record("sin(x_0) * x_0 ^ 3", &|i| 1e-4 * i);
+        assert_eq!(calibration.tests(), 4000);
+
+        let text = calibration.suggest(&oracles);
+        // Only the pow class needs more than the default 1e-9, and no table is left empty
+        assert!(text.contains("[rev_fwd.pow]\nrel_tol = 2e-6"));
+        assert!(!text.contains("[rev_fwd]") && !text.contains("[rev_fwd.trig]") && !text.contains("[rev_gt]"));
+
+        let mut suggested = FuzzingOracles::new(OracleSelection::REV_FWD);
+        OracleConfig::parse(&text).unwrap().apply(&mut suggested);
+        let pow = suggested.specialize(&SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(3.0)));
+        assert_eq!(pow.tolerances("rev_fwd").unwrap().rel_tol, Some(2e-6));
+        assert_eq!(suggested.tolerances("rev_fwd").unwrap().rel_tol, Some(1e-9));
+    }
+}
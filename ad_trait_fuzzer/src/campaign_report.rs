@@ -0,0 +1,399 @@
+// src/campaign_report.rs
+
+// Continuous mode bookkeeping: every failure of a campaign tallied by oracle, operator and
+// failure class, so an overnight run can be reviewed from one summary instead of the log.
+// Per operator it also keeps how many tests used it and how many distinct failures the fault
+// localizer traced back to it: the "bug surface", the derivative rules of ad_trait that
+// deserve a closer look.
+// Warnings (`Outcome::Warn`) are tallied by oracle too, the borderline ones separately: how
+// often each oracle comes within `borderline_factor` of its tolerances is the data to tune them on.
+// The whole state, bucket keys included, can be checkpointed and loaded back so an interrupted
+// campaign resumes without reporting its known buckets again.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::ast_localizer::Localization;
+use crate::expr_coverage::coverage_matrix;
+use crate::failure_classifier::{FailureBuckets, FailureClass, FailureStats};
+use crate::fuzz_harness::TestOutcome;
+use crate::oracles::Outcome;
+use crate::gt_calculators::ground_truth_cache_stats;
+
+/// File name `CampaignReport::write_to` uses inside the report directory.
+pub const CAMPAIGN_REPORT_FILE: &str = "campaign.json";
+
+/// Version of the `save_checkpoint` format; other versions are refused by `load_checkpoint`.
+const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CampaignReport {
+    started: Option<SystemTime>,
+    tests_run: usize,
+    failing_tests: usize,
+    timeouts: usize,
+    pub stats: FailureStats,
+    pub buckets: FailureBuckets,
+    per_oracle: BTreeMap<String, usize>,
+    per_operator: BTreeMap<String, usize>,
+    /// Tests that used each operator, failing or not.
+    #[serde(default)]
+    tests_per_operator: BTreeMap<String, usize>,
+    /// Failure buckets whose origin is rooted at each operator, see `record_localization`.
+    #[serde(default)]
+    implicated: BTreeMap<String, usize>,
+    /// Tests with at least one warning.
+    #[serde(default)]
+    warning_tests: usize,
+    #[serde(default)]
+    warnings_per_oracle: BTreeMap<String, usize>,
+    /// The warnings that are borderline mismatches, see `OracleVerdict::borderline`.
+    #[serde(default)]
+    borderline_per_oracle: BTreeMap<String, usize>,
+}
+
+/// One row of `CampaignReport::bug_surface`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperatorStats {
+    pub operator: String,
+    /// Tests that used the operator.
+    pub tests: usize,
+    /// Of those, tests that failed.
+    pub failing: usize,
+    /// Distinct failures localized to the operator.
+    pub implicated: usize,
+}
+
+impl OperatorStats {
+    pub fn failure_rate(&self) -> f64 {
+        self.failing as f64 / self.tests.max(1) as f64
+    }
+}
+
+impl CampaignReport {
+    pub const fn new() -> Self {
+        CampaignReport {
+            started: None,
+            tests_run: 0,
+            failing_tests: 0,
+            timeouts: 0,
+            stats: FailureStats::new(),
+            buckets: FailureBuckets::new(),
+            per_oracle: BTreeMap::new(),
+            per_operator: BTreeMap::new(),
+            tests_per_operator: BTreeMap::new(),
+            implicated: BTreeMap::new(),
+            warning_tests: 0,
+            warnings_per_oracle: BTreeMap::new(),
+            borderline_per_oracle: BTreeMap::new(),
+        }
+    }
+
+    /// Counts one test case. Every failing or warning oracle counts towards its oracle, every
+    /// operator of `expr` counts once per failing test, and the first failure counts towards its
+    /// class and bucket. Returns whether the test failed and is the first in its bucket.
+    pub fn record(&mut self, expr: &SimpleExpr, outcome: &TestOutcome) -> bool {
+        self.started.get_or_insert_with(SystemTime::now);
+        self.tests_run += 1;
+        let mut operators = Vec::new();
+        collect_operators(expr, &mut operators);
+        for op in &operators {
+            *self.tests_per_operator.entry(op.clone()).or_insert(0) += 1;
+        }
+        let mut warned = false;
+        for verdict in outcome.verdicts.iter().filter(|v| v.outcome() == Outcome::Warn) {
+            warned = true;
+            *self.warnings_per_oracle.entry(verdict.oracle.clone()).or_insert(0) += 1;
+            if verdict.borderline {
+                *self.borderline_per_oracle.entry(verdict.oracle.clone()).or_insert(0) += 1;
+            }
+        }
+        self.warning_tests += usize::from(warned);
+        let first = match outcome.first_failure() {
+            Some(e) => e,
+            None => return false,
+        };
+        self.failing_tests += 1;
+
+        for verdict in outcome.verdicts.iter().filter(|v| v.is_failure()) {
+            *self.per_oracle.entry(verdict.oracle.clone()).or_insert(0) += 1;
+        }
+        for op in operators {
+            *self.per_operator.entry(op).or_insert(0) += 1;
+        }
+        self.stats.record(first);
+        self.buckets.record(expr, first)
+    }
+
+    /// Counts a failure towards the operator at the root of its origin, see
+    /// `ast_localizer::localize_failure`. Meant for the first failure of each bucket, like
+    /// shrinking: localizing runs the oracles on every subtree.
+    pub fn record_localization(&mut self, found: &Localization) {
+        if let Some(op) = operator_name(&found.subtree) {
+            *self.implicated.entry(op).or_insert(0) += 1;
+        }
+    }
+
+    /// Every operator the campaign has used, most implicated first, then by failure rate.
+    pub fn bug_surface(&self) -> Vec<OperatorStats> {
+        let count = |counts: &BTreeMap<String, usize>, op: &str| counts.get(op).copied().unwrap_or(0);
+        let mut rows: Vec<OperatorStats> = self.tests_per_operator.iter()
+            .map(|(op, &tests)| OperatorStats {
+                operator: op.clone(),
+                tests,
+                failing: count(&self.per_operator, op),
+                implicated: count(&self.implicated, op),
+            })
+            .collect();
+        rows.sort_by(|a, b| b.implicated.cmp(&a.implicated).then(b.failure_rate().total_cmp(&a.failure_rate())));
+        rows
+    }
+
+    /// Counts a test case abandoned by the evaluation timeout.
+    pub fn record_timeout(&mut self) {
+        self.started.get_or_insert_with(SystemTime::now);
+        self.tests_run += 1;
+        self.timeouts += 1;
+    }
+
+    pub fn tests_run(&self) -> usize {
+        self.tests_run
+    }
+
+    pub fn failing_tests(&self) -> usize {
+        self.failing_tests
+    }
+
+    pub fn warning_tests(&self) -> usize {
+        self.warning_tests
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.and_then(|s| s.elapsed().ok()).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let millis = |t: SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let mut per_class: BTreeMap<String, usize> = FailureClass::ALL
+            .iter()
+            .map(|c| (c.to_string(), self.stats.count(*c)))
+            .collect();
+        per_class.insert("Other".to_string(), self.stats.other());
+        let cache = ground_truth_cache_stats();
+        json!({
+            "started_ms": self.started.map(millis),
+            "finished_ms": millis(SystemTime::now()),
+            "tests_run": self.tests_run,
+            "failing_tests": self.failing_tests,
+            "timeouts": self.timeouts,
+            "distinct_failures": self.buckets.len(),
+            "per_oracle": self.per_oracle,
+            "warning_tests": self.warning_tests,
+            "warnings_per_oracle": self.warnings_per_oracle,
+            "borderline_per_oracle": self.borderline_per_oracle,
+            "per_operator": self.per_operator,
+            "bug_surface": self.bug_surface().iter().map(|row| json!({
+                "operator": row.operator,
+                "tests": row.tests,
+                "failing": row.failing,
+                "implicated": row.implicated,
+                "failure_rate": row.failure_rate(),
+            })).collect::<Vec<_>>(),
+            "per_class": per_class,
+            "gt_cache": { "hits": cache.hits, "misses": cache.misses },
+            "coverage": coverage_matrix().to_json(),
+        })
+    }
+
+    /// Writes `to_json()` to `CAMPAIGN_REPORT_FILE` in `dir`, replacing an earlier one.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let path = dir.join(CAMPAIGN_REPORT_FILE);
+        let text = serde_json::to_string_pretty(&self.to_json()).map_err(io::Error::other)?;
+        fs::write(&path, text)?;
+        Ok(path)
+    }
+
+    /// Saves the full state to `path`: counters, per-class stats and every bucket with its
+    /// count. Written to a temporary file first, so an interrupted save keeps the previous one.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let checkpoint = json!({ "version": CHECKPOINT_VERSION, "campaign": self });
+        let text = serde_json::to_string(&checkpoint).map_err(io::Error::other)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Campaign saved by `save_checkpoint`, or `None` if `path` does not exist. Bucket keys are
+    /// `bucket_key` hashes, the same in every build.
+    pub fn load_checkpoint(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut checkpoint: Value = serde_json::from_str(&text).map_err(io::Error::other)?;
+        let version = checkpoint["version"].as_u64();
+        if version != Some(CHECKPOINT_VERSION as u64) {
+            return Err(io::Error::other(format!("unsupported checkpoint version {:?}", version)));
+        }
+        serde_json::from_value(checkpoint["campaign"].take()).map(Some).map_err(io::Error::other)
+    }
+}
+
+/// `Sin`, `Pow`, `Cast`, ... for an operator node, `None` for anything else.
+fn operator_name(expr: &SimpleExpr) -> Option<String> {
+    match expr {
+        Expr::UnOp(_, op, _) => Some(format!("{:?}", op)),
+        Expr::BinOp(_, op, _, _) => Some(format!("{:?}", op)),
+        Expr::Cast(..) => Some("Cast".to_string()),
+        _ => None,
+    }
+}
+
+/// Names of the operators in `expr`, each once.
+fn collect_operators(expr: &SimpleExpr, ops: &mut Vec<String>) {
+    if let Some(name) = operator_name(expr) {
+        if !ops.contains(&name) {
+            ops.push(name);
+        }
+    }
+    match expr {
+        Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => collect_operators(e, ops),
+        Expr::BinOp(_, _, l, r) => {
+            collect_operators(l, ops);
+            collect_operators(r, ops);
+        }
+        _ => {}
+    }
+}
+
+fn write_counts(f: &mut fmt::Formatter<'_>, title: &str, counts: &BTreeMap<String, usize>) -> fmt::Result {
+    write!(f, "\n{}:", title)?;
+    let mut counts: Vec<(&String, &usize)> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1));
+    for (name, count) in counts {
+        write!(f, "\n  {:<24} {}", name, count)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for CampaignReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "=== CAMPAIGN REPORT ===")?;
+        writeln!(f, "{} tests in {:.0?}, {} failed, {} with warnings, {} timed out",
+            self.tests_run, self.elapsed(), self.failing_tests, self.warning_tests, self.timeouts)?;
+        let cache = ground_truth_cache_stats();
+        if cache.lookups() > 0 {
+            writeln!(f, "Ground truth cache: {}", cache)?;
+        }
+        writeln!(f, "Coverage: {}", coverage_matrix())?;
+        write!(f, "{}", self.stats)?;
+        write_counts(f, "Failures by oracle", &self.per_oracle)?;
+        write_counts(f, "Warnings by oracle", &self.warnings_per_oracle)?;
+        write_counts(f, "Failing tests by operator", &self.per_operator)?;
+        write!(f, "\nBug surface (tests, failing, distinct failures localized to it):")?;
+        for row in self.bug_surface() {
+            write!(f, "\n  {:<10} {:>10} {:>8} ({:.1}%) {:>6}", row.operator, row.tests, row.failing, 100.0 * row.failure_rate(), row.implicated)?;
+        }
+        write!(f, "\n{}", self.buckets)?;
+        write!(f, "\n=======================")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_expr::Op2;
+    use crate::ast_localizer::localize;
+    use crate::ast_visitor::for_each_node;
+    use crate::error::FuzzError;
+    use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator};
+    use crate::gt_calculators::DualNumberCalculator;
+    use crate::oracles::{FuzzingOracles, Oracle, OracleContext, OracleSelection};
+
+    /// Fails whenever the first input is negative.
+    #[derive(Clone)]
+    struct NegativeInput;
+
+    impl Oracle for NegativeInput {
+        fn name(&self) -> &str { "negative_input" }
+        fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+            if ctx.engine.inputs[0] >= 0.0 {
+                return Ok(());
+            }
+            Err(FuzzError::OracleMismatch {
+                oracle: "Negative".to_string(),
+                inputs: ctx.engine.inputs.clone(),
+                output: 0,
+                index: 0,
+                class: FailureClass::SignFlip,
+                details: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_campaign_report() {
+        let mut oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        oracles.register(NegativeInput);
+        let expr = SimpleExpr::sin(SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)));
+        let mut report = CampaignReport::new();
+        let mut record = |x: f64| {
+            let calc = AdPyUnified::new(expr.clone(), 1, 1);
+            let outcome = run_ad_tests(&[x], calc, &oracles, &[DualNumberCalculator.boxed()]).unwrap();
+            report.record(&expr, &outcome)
+        };
+        assert!(!record(0.5));
+        assert!(record(-0.5));
+        assert!(!record(-1.5));
+
+        assert_eq!((report.tests_run(), report.failing_tests()), (3, 2));
+        let json = report.to_json();
+        assert_eq!(json["per_oracle"]["negative_input"], 2);
+        assert_eq!(json["per_operator"]["Sin"], 2);
+        assert_eq!(json["per_operator"]["Pow"], 2);
+        assert_eq!(json["per_class"]["SignFlip"], 2);
+        assert_eq!(json["distinct_failures"], 1);
+
+        // Every test counts towards the surface; the failure is traced to the pow
+        let has_pow = |e: &SimpleExpr| {
+            let mut found = false;
+            for_each_node(e, |node| found |= matches!(node, Expr::BinOp(_, Op2::Pow, ..)));
+            found
+        };
+        let found = localize(&expr, has_pow).unwrap();
+        report.record_localization(&found);
+        let surface = report.bug_surface();
+        assert_eq!(surface[0], OperatorStats { operator: "Pow".to_string(), tests: 3, failing: 2, implicated: 1 });
+        assert_eq!(surface[1].operator, "Sin");
+        assert_eq!(report.to_json()["bug_surface"][0]["implicated"], 1);
+        assert!(report.to_string().contains("3 tests"));
+
+        let path = std::env::temp_dir().join(format!("campaign-checkpoint-{}.json", std::process::id()));
+        report.save_checkpoint(&path).unwrap();
+        let mut resumed = CampaignReport::load_checkpoint(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed.to_json()["per_class"], json["per_class"]);
+        assert_eq!(resumed.buckets.len(), 1);
+        // The bucket survived: the same failure is not new after resuming
+        let outcome = run_ad_tests(&[-2.5], AdPyUnified::new(expr.clone(), 1, 1), &oracles, &[DualNumberCalculator.boxed()]).unwrap();
+        assert!(!resumed.record(&expr, &outcome));
+        assert_eq!(resumed.failing_tests(), 3);
+        assert!(CampaignReport::load_checkpoint(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,97 @@
+// src/corpus_min.rs
+
+// Corpus minimization on expression features, for the `cmin` binary. libFuzzer's `-merge=1`
+// keeps the inputs that add code coverage, which saturates early (see `expr_coverage`), so a
+// long campaign's corpus grows without covering much more. Here each input is decoded to the
+// expressions the fuzzer generates from it and described by their `expr_coverage::features`
+// (operator bigrams, operator depth buckets, size and variable count); a greedy set cover keeps
+// few inputs that together still have every feature the whole corpus has.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::expr_coverage;
+use crate::fuzz_case::{decode_inputs, generate_test};
+use crate::fuzz_config::FuzzConfig;
+
+/// One corpus input: its size in bytes and the distinct features of its expressions.
+#[derive(Debug, Clone, Default)]
+pub struct CorpusInput {
+    pub len: usize,
+    pub features: Vec<usize>,
+}
+
+/// Distinct features of every expression `run_fuzz_iteration` generates from `data`, sorted.
+/// Empty for an input the fuzzer ignores (too short, or inputs outside `config.inputs`).
+pub fn input_features(data: &[u8], config: &FuzzConfig) -> Vec<usize> {
+    let Some((mut inputs, ast_data)) = decode_inputs(data, config) else {
+        return Vec::new();
+    };
+    if config.inputs.perturbation_step.is_some() {
+        inputs.truncate(inputs.len() / 2);
+    }
+    if !config.inputs.synthesize && !config.inputs.accepts(&inputs) {
+        return Vec::new();
+    }
+    let mut features: Vec<usize> = (0..config.num_generated_tests)
+        .filter_map(|i| generate_test(ast_data, i, &config.ast).ok())
+        .flat_map(|generated| expr_coverage::features(&generated.expr))
+        .collect();
+    features.sort_unstable();
+    features.dedup();
+    features
+}
+
+/// Indices of a subset of `inputs` with every feature any of them has, in ascending order.
+/// Greedy: the input adding the most new features goes first, the smaller one on a tie. Inputs
+/// without features are never kept.
+pub fn minimize(inputs: &[CorpusInput]) -> Vec<usize> {
+    let mut covered: HashSet<usize> = HashSet::new();
+    // Gains only shrink as features get covered, so a stale gain is an upper bound: an input is
+    // taken once its recomputed gain still beats every other bound (lazy greedy)
+    let mut queue: BinaryHeap<(usize, Reverse<usize>, Reverse<usize>)> = inputs.iter().enumerate()
+        .filter(|(_, input)| !input.features.is_empty())
+        .map(|(i, input)| (input.features.len(), Reverse(input.len), Reverse(i)))
+        .collect();
+    let mut kept = Vec::new();
+    while let Some((_, len, Reverse(i))) = queue.pop() {
+        let gain = inputs[i].features.iter().filter(|f| !covered.contains(f)).count();
+        if gain == 0 {
+            continue;
+        }
+        if queue.peek().is_some_and(|&(bound, ..)| bound > gain) {
+            queue.push((gain, len, Reverse(i)));
+            continue;
+        }
+        covered.extend(&inputs[i].features);
+        kept.push(i);
+    }
+    kept.sort_unstable();
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_decoder::{InputDecoding, InputLayout};
+
+    #[test]
+    fn test_minimize() {
+        let input = |len, features: &[usize]| CorpusInput { len, features: features.to_vec() };
+        // The superset wins; of two equal ones the smaller; an input without features never
+        let inputs = [input(10, &[1, 2]), input(10, &[2, 3]), input(50, &[1, 2, 3]), input(1, &[]), input(40, &[4]), input(30, &[4])];
+        assert_eq!(minimize(&inputs), vec![2, 5]);
+        assert!(minimize(&[]).is_empty());
+
+        // Real inputs: features of their expressions, none for one the fuzzer ignores
+        let mut config = FuzzConfig { num_generated_tests: 4, ..FuzzConfig::default() };
+        config.inputs.decoder = InputDecoding::General;
+        config.inputs.layout = InputLayout::Prefix;
+        assert!(input_features(&[0u8; 3], &config).is_empty());
+        let mut data = vec![0x3fu8; 16];
+        data.extend((0..200u8).map(|b| b.wrapping_mul(37)));
+        let features = input_features(&data, &config);
+        assert!(!features.is_empty() && features.iter().all(|&f| f < expr_coverage::NUM_FEATURES));
+        assert!(features.windows(2).all(|w| w[0] < w[1]));
+    }
+}
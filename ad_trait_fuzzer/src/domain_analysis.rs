@@ -0,0 +1,430 @@
+// src/domain_analysis.rs
+
+// Input ranges an expression never leaves its operators' domains in: log arguments positive,
+// sqrt arguments and bases of fractional powers non-negative, divisors and bases of negative
+// integer powers nonzero. Interval arithmetic over the whole tree, so `log(x_0 - 1)` and
+// `1 / (x_0 + x_1)` constrain their variables too, unlike `domain_requirements`, which only sees
+// `log(x_0)`.
+//
+// Each requirement is first pushed down to the variables (`narrow`: `log(x_0 + 2)` needs
+// `x_0 + 2 > 0`, so `x_0 > -2`), which keeps every good point but not only good points. The
+// requirements are then checked forward over the resulting box and, while one does not hold
+// everywhere, the widest variable it uses is halved (keeping the more promising half), until the
+// box is safe. Endpoints are not rounded outward, so a range can be off by an ulp at a boundary;
+// the input synthesizer evaluates its candidates anyway.
+
+use std::collections::HashMap;
+
+use crate::ast_evaluator::{evaluate, Env, MainBackend};
+use crate::ast_expr::{Expr, Op1, Op2, Type};
+use crate::ast_visitor::{children, for_each_node};
+
+/// Contraction passes over all requirements before and after each split.
+const CONTRACTION_ROUNDS: usize = 8;
+
+/// Splits before giving up on a requirement.
+const MAX_SPLITS: usize = 64;
+
+/// Relative distance outside a safe range of `out_of_domain_points`.
+const PROBE_OFFSET: f64 = 1e-6;
+
+/// Closed interval `[lo, hi]`, possibly unbounded. Empty when `lo > hi`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl Interval {
+    pub const ENTIRE: Interval = Interval { lo: f64::NEG_INFINITY, hi: f64::INFINITY };
+    pub const EMPTY: Interval = Interval { lo: f64::INFINITY, hi: f64::NEG_INFINITY };
+
+    pub fn new(lo: f64, hi: f64) -> Self {
+        Interval { lo, hi }
+    }
+
+    pub fn point(x: f64) -> Self {
+        Interval { lo: x, hi: x }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        // NaN endpoints count as empty too
+        self.lo > self.hi || self.lo.is_nan() || self.hi.is_nan()
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn intersect(self, other: Interval) -> Interval {
+        Interval::new(self.lo.max(other.lo), self.hi.min(other.hi))
+    }
+
+    /// The point of the interval closest to `x`; `x` itself if the interval is empty.
+    pub fn clamp(&self, x: f64) -> f64 {
+        if self.is_empty() { x } else { x.max(self.lo).min(self.hi) }
+    }
+
+    fn is_point(&self) -> bool {
+        self.lo == self.hi
+    }
+
+    /// Each finite end moved inward by a few ulps, past rounding errors of `narrow`.
+    fn nudge(self) -> Interval {
+        let step = |end: f64| (end.abs() * 4.0 * f64::EPSILON).max(f64::MIN_POSITIVE);
+        let lo = if self.lo.is_finite() { self.lo + step(self.lo) } else { self.lo };
+        let hi = if self.hi.is_finite() { self.hi - step(self.hi) } else { self.hi };
+        Interval::new(lo, hi)
+    }
+
+    /// Unbounded ends, then the width: which variable to split first.
+    fn size(&self) -> (usize, f64) {
+        let unbounded = [self.lo, self.hi].iter().filter(|end| end.is_infinite()).count();
+        (unbounded, if unbounded > 0 { 0.0 } else { self.hi - self.lo })
+    }
+
+    /// Halves at the midpoint; unbounded sides are split at 0, or at twice the finite bound.
+    fn split(self) -> (Interval, Interval) {
+        let mid = match (self.lo.is_finite(), self.hi.is_finite()) {
+            (true, true) => self.lo / 2.0 + self.hi / 2.0,
+            (true, false) if self.lo >= 0.0 => self.lo * 2.0 + 1.0,
+            (false, true) if self.hi <= 0.0 => self.hi * 2.0 - 1.0,
+            _ => 0.0,
+        };
+        (Interval::new(self.lo, mid), Interval::new(mid, self.hi))
+    }
+
+    fn unary(self, f: impl FnOnce(Interval) -> Interval) -> Interval {
+        if self.is_empty() { Interval::EMPTY } else { f(self) }
+    }
+
+    fn binary(self, other: Interval, f: impl FnOnce(Interval, Interval) -> Interval) -> Interval {
+        if self.is_empty() || other.is_empty() { Interval::EMPTY } else { f(self, other) }
+    }
+
+    /// Smallest interval holding `f` of every endpoint pair; `0 * ∞` counts as 0.
+    fn corners(self, other: Interval, f: fn(f64, f64) -> f64) -> Interval {
+        let values = [f(self.lo, other.lo), f(self.lo, other.hi), f(self.hi, other.lo), f(self.hi, other.hi)];
+        let values = values.map(|v| if v.is_nan() { 0.0 } else { v });
+        Interval::new(values.iter().copied().fold(f64::INFINITY, f64::min), values.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+    }
+
+    /// Constant intervals evaluate exactly, the rest to `otherwise`.
+    fn periodic(self, f: fn(f64) -> f64, otherwise: Interval) -> Interval {
+        self.unary(|x| if x.is_point() { Interval::point(f(x.lo)) } else { otherwise })
+    }
+}
+
+// Ranges over the part of each operator's domain the argument overlaps, so `log([-1, 4])` is
+// `[-∞, ln 4]`; whether the argument stays in the domain is checked separately.
+impl MainBackend for Interval {
+    fn from_f64(val: f64) -> Self { Interval::point(val) }
+    fn zero() -> Self { Interval::point(0.0) }
+    fn one() -> Self { Interval::point(1.0) }
+
+    fn neg(self) -> Self { self.unary(|x| Interval::new(-x.hi, -x.lo)) }
+    fn sin(self) -> Self { self.periodic(f64::sin, Interval::new(-1.0, 1.0)) }
+    fn cos(self) -> Self { self.periodic(f64::cos, Interval::new(-1.0, 1.0)) }
+    fn tan(self) -> Self { self.periodic(f64::tan, Interval::ENTIRE) }
+    fn exp(self) -> Self { self.unary(|x| Interval::new(x.lo.exp(), x.hi.exp())) }
+    fn log(self) -> Self {
+        self.unary(|x| Interval::new(x.lo.max(0.0).ln(), if x.hi < 0.0 { f64::NEG_INFINITY } else { x.hi.ln() }))
+    }
+    fn sqrt(self) -> Self {
+        self.unary(|x| if x.hi < 0.0 { Interval::EMPTY } else { Interval::new(x.lo.max(0.0).sqrt(), x.hi.sqrt()) })
+    }
+    fn abs(self) -> Self {
+        self.unary(|x| match (x.lo >= 0.0, x.hi <= 0.0) {
+            (true, _) => x,
+            (_, true) => x.neg(),
+            _ => Interval::new(0.0, x.hi.max(-x.lo)),
+        })
+    }
+
+    fn add(self, other: Self) -> Self { self.binary(other, |a, b| Interval::new(a.lo + b.lo, a.hi + b.hi)) }
+    fn sub(self, other: Self) -> Self { self.binary(other, |a, b| Interval::new(a.lo - b.hi, a.hi - b.lo)) }
+    fn mul(self, other: Self) -> Self { self.binary(other, |a, b| a.corners(b, |x, y| x * y)) }
+    fn div(self, other: Self) -> Self {
+        self.binary(other, |a, b| {
+            if b.contains(0.0) { Interval::ENTIRE } else { a.corners(Interval::new(1.0 / b.hi, 1.0 / b.lo), |x, y| x * y) }
+        })
+    }
+    fn pow(self, other: Self) -> Self {
+        self.binary(other, |base, exponent| {
+            if base.is_point() && exponent.is_point() {
+                return Interval::point(base.lo.powf(exponent.lo));
+            }
+            if base.lo > 0.0 {
+                return exponent.mul(base.log()).exp();
+            }
+            // Integer powers are monotone on each side of 0
+            let n = exponent.lo;
+            if !exponent.is_point() || n.fract() != 0.0 {
+                return Interval::ENTIRE;
+            }
+            let ends = base.corners(exponent, f64::powf);
+            match (base.contains(0.0), n > 0.0, n % 2.0 == 0.0) {
+                (false, _, _) | (true, true, false) => ends,
+                (true, true, true) => Interval::new(0.0, ends.hi),
+                (true, false, _) => Interval::ENTIRE,
+            }
+        })
+    }
+
+    fn cast(self, ty: &Type) -> Self {
+        self.unary(|x| match ty {
+            Type::Float => x,
+            Type::Int => Interval::new(x.lo.trunc(), x.hi.trunc()),
+            Type::Bool if !x.contains(0.0) => Interval::point(1.0),
+            Type::Bool if x.is_point() => Interval::point(0.0),
+            Type::Bool => Interval::new(0.0, 1.0),
+        })
+    }
+}
+
+/// What an operator needs of one of its arguments.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Requirement {
+    Positive,
+    NonNegative,
+    Nonzero,
+}
+
+impl Requirement {
+    /// Holds at every point of `range`; vacuously for an empty one.
+    fn holds(&self, range: Interval) -> bool {
+        range.is_empty() || match self {
+            Requirement::Positive => range.lo > 0.0,
+            Requirement::NonNegative => range.lo >= 0.0,
+            Requirement::Nonzero => !range.contains(0.0),
+        }
+    }
+
+    /// Where an argument now ranging over `range` has to be. A divisor keeps the wider side of 0.
+    fn target(&self, range: Interval) -> Interval {
+        let positive = Interval::new(f64::MIN_POSITIVE, f64::INFINITY);
+        match self {
+            Requirement::Positive => positive,
+            Requirement::NonNegative => Interval::new(0.0, f64::INFINITY),
+            Requirement::Nonzero if !range.contains(0.0) => Interval::ENTIRE,
+            Requirement::Nonzero if range.hi >= -range.lo => positive,
+            Requirement::Nonzero => Interval::new(f64::NEG_INFINITY, -f64::MIN_POSITIVE),
+        }
+    }
+
+    /// How close `range` is to meeting the requirement, higher is closer.
+    fn score(&self, range: Interval) -> f64 {
+        match self {
+            _ if self.holds(range) => f64::INFINITY,
+            Requirement::Positive | Requirement::NonNegative => range.lo,
+            Requirement::Nonzero => range.lo.max(-range.hi),
+        }
+    }
+}
+
+/// Range of `expr` over `env`; unbounded for nodes `evaluate` rejects.
+fn range<Tag>(expr: &Expr<Tag>, env: &Env<Interval>) -> Interval {
+    evaluate(expr, env).unwrap_or(Interval::ENTIRE)
+}
+
+/// Shrinks the variables of `expr` in `env` so that `expr` can only land in `target`, as far as
+/// each operator can be inverted over intervals.
+fn narrow<Tag>(expr: &Expr<Tag>, target: Interval, env: &mut Env<Interval>) {
+    let target = target.intersect(range(expr, env));
+    match expr {
+        Expr::Id(_, name) => {
+            if let Some(r) = env.get_mut(name) {
+                *r = r.intersect(target);
+            }
+        }
+        Expr::UnOp(_, op, e) => {
+            let arg = match op {
+                Op1::Neg => target.neg(),
+                Op1::Exp => target.log(),
+                Op1::Log => target.exp(),
+                Op1::Sqrt if target.hi < 0.0 => Interval::EMPTY,
+                Op1::Sqrt => Interval::new(target.lo.max(0.0).powi(2), target.hi.powi(2)),
+                Op1::Abs => Interval::new(-target.hi, target.hi),
+                Op1::Sin | Op1::Cos | Op1::Tan => return,
+            };
+            narrow(e, arg, env);
+        }
+        Expr::BinOp(_, op, l, r) => {
+            let (left, right) = (range(l, env), range(r, env));
+            let (l_target, r_target) = match op {
+                Op2::Add => (target.sub(right), target.sub(left)),
+                Op2::Sub => (target.add(right), left.sub(target)),
+                Op2::Mul => (target.div(right), target.div(left)),
+                Op2::Div => (target.mul(right), left.div(target)),
+                Op2::Pow => return,
+            };
+            narrow(l, l_target, env);
+            narrow(r, r_target, env);
+        }
+        Expr::Cast(_, Type::Float, e) => narrow(e, target, env),
+        _ => {}
+    }
+}
+
+/// The domain requirements in `expr`: each argument with what its operator needs of it.
+fn requirements<Tag>(expr: &Expr<Tag>) -> Vec<(&Expr<Tag>, Requirement)> {
+    let mut out = Vec::new();
+    match expr {
+        Expr::UnOp(_, Op1::Log, arg) => out.push((&**arg, Requirement::Positive)),
+        Expr::UnOp(_, Op1::Sqrt, arg) => out.push((&**arg, Requirement::NonNegative)),
+        Expr::BinOp(_, Op2::Div, _, divisor) => out.push((&**divisor, Requirement::Nonzero)),
+        Expr::BinOp(_, Op2::Pow, base, exponent) => match exponent.as_ref() {
+            Expr::Number(_, n) if n.fract() == 0.0 && *n >= 0.0 => {}
+            Expr::Number(_, n) if n.fract() == 0.0 => out.push((&**base, Requirement::Nonzero)),
+            _ => out.push((&**base, Requirement::NonNegative)),
+        },
+        _ => {}
+    }
+    for child in children(expr) {
+        out.extend(requirements(child));
+    }
+    out
+}
+
+/// The free variables of `expr`: every `Id` not bound by a `Let`.
+fn input_names<Tag>(expr: &Expr<Tag>) -> Vec<String> {
+    let (mut ids, mut bound) = (Vec::new(), Vec::new());
+    for_each_node(expr, |e| match e {
+        Expr::Id(_, name) if !ids.contains(name) => ids.push(name.clone()),
+        Expr::Let(_, bindings, _) => bound.extend(bindings.iter().map(|(name, _)| name.clone())),
+        _ => {}
+    });
+    ids.retain(|name| !bound.contains(name));
+    ids
+}
+
+fn contract<Tag>(requirements: &[(&Expr<Tag>, Requirement)], env: &mut Env<Interval>) {
+    for _ in 0..CONTRACTION_ROUNDS {
+        let before = env.clone();
+        for (arg, requirement) in requirements {
+            let target = requirement.target(range(arg, env));
+            narrow(*arg, target, env);
+        }
+        if *env == before {
+            break;
+        }
+    }
+}
+
+/// Safe range of each free variable of `expr`, see the top of the file: within them, no `log`,
+/// `sqrt`, division or power is evaluated outside its domain. Empty for the variables of a
+/// requirement no such range was found for (`log(x_0 - x_0)`). Requirements on `Let`-bound
+/// names or inside control flow are not analyzed.
+pub fn analyze_domains<Tag>(expr: &Expr<Tag>) -> HashMap<String, Interval> {
+    analyze_domains_within(expr, &HashMap::new())
+}
+
+/// `analyze_domains` with the variables that have an entry in `bounds` starting from it
+/// instead of the whole real line.
+pub fn analyze_domains_within<Tag>(expr: &Expr<Tag>, bounds: &HashMap<String, Interval>) -> HashMap<String, Interval> {
+    let mut env: Env<Interval> = input_names(expr).into_iter()
+        .map(|name| {
+            let start = bounds.get(&name).copied().unwrap_or(Interval::ENTIRE);
+            (name, start)
+        })
+        .collect();
+    let requirements: Vec<_> = requirements(expr).into_iter().filter(|(arg, _)| evaluate(*arg, &env).is_ok()).collect();
+
+    contract(&requirements, &mut env);
+    for _ in 0..MAX_SPLITS {
+        let failing = requirements.iter().find(|(arg, requirement)| !requirement.holds(range(arg, &env)));
+        let Some((arg, requirement)) = failing else { break };
+        let widest = input_names(*arg).into_iter()
+            .filter(|name| env.get(name).is_some_and(|r| !r.is_point()))
+            .max_by(|a, b| {
+                let (a, b) = (env[a].size(), env[b].size());
+                a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+            });
+        let Some(name) = widest else { break };
+
+        let score = |candidate: Interval| {
+            let mut env = env.clone();
+            env.insert(name.clone(), candidate);
+            requirement.score(range(*arg, &env))
+        };
+        // A boundary off by rounding only needs a nudge, anything else a split
+        let nudged = env[&name].nudge();
+        let (low, high) = env[&name].split();
+        let next = if score(nudged) == f64::INFINITY {
+            nudged
+        } else if score(high) >= score(low) {
+            high
+        } else {
+            low
+        };
+        env.insert(name, next);
+        contract(&requirements, &mut env);
+    }
+
+    for (arg, requirement) in &requirements {
+        if !requirement.holds(range(arg, &env)) {
+            for name in input_names(*arg) {
+                env.insert(name, Interval::EMPTY);
+            }
+        }
+    }
+    env
+}
+
+/// Points just outside the safe ranges of `expr`, for the `domain` oracle: `inputs` with one
+/// variable `x_i` moved past one finite end of its range from `analyze_domains`.
+pub fn out_of_domain_points<Tag>(expr: &Expr<Tag>, inputs: &[f64]) -> Vec<Vec<f64>> {
+    let safe = analyze_domains(expr);
+    let mut points = Vec::new();
+    for i in 0..inputs.len() {
+        let Some(range) = safe.get(&format!("x_{}", i)).filter(|r| !r.is_empty()) else { continue };
+        for (end, direction) in [(range.lo, -1.0), (range.hi, 1.0)] {
+            if end.is_finite() {
+                let mut point = inputs.to_vec();
+                point[i] = end + direction * end.abs().max(1.0) * PROBE_OFFSET;
+                points.push(point);
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_parser::parse_sexpr;
+
+    fn domains(src: &str) -> HashMap<String, Interval> {
+        analyze_domains(&parse_sexpr(src).unwrap().0)
+    }
+
+    #[test]
+    fn test_analyze_domains() {
+        // Pushed through the arithmetic to the variable
+        let d = domains("(log (- x_0 1))");
+        assert!(d["x_0"].lo >= 1.0 && d["x_0"].lo < 1.0 + 1e-9 && d["x_0"].hi == f64::INFINITY);
+        let d = domains("(sqrt (neg (* 2 x_0)))");
+        assert_eq!(d["x_0"], Interval::new(f64::NEG_INFINITY, 0.0));
+        // Unconstrained variables keep the whole line
+        assert_eq!(domains("(+ (sin x_0) (sqrt x_1))")["x_0"], Interval::ENTIRE);
+
+        // Several variables: split until every requirement holds
+        let expr = parse_sexpr("(+ (/ 1 (+ x_0 x_1)) (log x_1))").unwrap().0;
+        let d = analyze_domains(&expr);
+        assert!(d["x_1"].lo > 0.0);
+        let sum = d["x_0"].add(d["x_1"]);
+        assert!(!sum.contains(0.0) && !sum.is_empty());
+
+        // Within bounds, and never satisfiable
+        let bounds = HashMap::from([("x_0".to_string(), Interval::new(-5.0, 5.0))]);
+        let d = analyze_domains_within(&parse_sexpr("(log x_0)").unwrap().0, &bounds);
+        assert_eq!(d["x_0"], Interval::new(f64::MIN_POSITIVE, 5.0));
+        assert!(domains("(log (- x_0 x_0))")["x_0"].is_empty());
+        assert_eq!(domains("(sqrt (neg (abs (- x_0 3))))")["x_0"], Interval::point(3.0));
+
+        // Probes just past the boundary are out of the domain
+        let points = out_of_domain_points(&parse_sexpr("(log (- x_0 1))").unwrap().0, &[4.0]);
+        assert_eq!(points.len(), 1);
+        assert!(points[0][0] < 1.0 && points[0][0] > 0.99);
+    }
+}
@@ -0,0 +1,139 @@
+// src/error.rs
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use crate::failure_classifier::FailureClass;
+
+/// Error type shared by the evaluators, ground truth calculators, oracles and harness.
+///
+/// Callers can branch on the variant (e.g. skip `UnsupportedExpr`, report `OracleMismatch`),
+/// and `kind()` gives a short stable label for crash reports.
+#[derive(Debug)]
+pub enum FuzzError {
+    /// An `Expr::Id` referenced a variable that is not in the environment.
+    UnboundVariable(String),
+
+    /// The expression contains a node the backend cannot evaluate.
+    UnsupportedExpr(String),
+
+    /// An operation was evaluated outside of its mathematical domain (e.g. log of a negative).
+    DomainError(String),
+
+    /// An oracle that compares against a ground truth was run without one.
+    MissingGroundTruth { oracle: String },
+
+    /// The number of inputs does not match what the calculator expects.
+    InputMismatch { expected: usize, got: usize },
+
+    /// An engine or external library failed (PyTorch, evalexpr-jit, subprocess worker, ...).
+    BackendFailure {
+        backend: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+
+    /// An engine panicked (e.g. `AdEvaluator` on an unbound variable) and the panic was caught,
+    /// see `fuzz_harness::catch_backend_panic`.
+    BackendPanic { backend: String, message: String },
+
+    /// A configuration file could not be read or contains unknown keys/values.
+    InvalidConfig(String),
+
+    /// An evaluation stage (AD, a ground truth, evalexpr-jit) ran longer than the configured
+    /// limit and was abandoned.
+    Timeout { stage: String, limit: Duration },
+
+    /// The Reverse AD pass of a test case used more tape nodes or memory (`measure`) than the
+    /// configured `tape_stats::TapeBudget` allows.
+    TapeBudgetExceeded { measure: &'static str, used: usize, limit: usize },
+
+    /// An oracle found two engines disagreeing on the derivative of output `output`
+    /// with respect to input `index`.
+    OracleMismatch {
+        oracle: String,
+        inputs: Vec<f64>,
+        output: usize,
+        index: usize,
+        class: FailureClass,
+        details: String,
+    },
+
+    /// An oracle found one engine far slower than the other on the same expression, see
+    /// `oracles::TimingCheck`.
+    PerformanceAnomaly {
+        oracle: String,
+        inputs: Vec<f64>,
+        details: String,
+    },
+}
+
+impl FuzzError {
+    pub fn backend(backend: &'static str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        FuzzError::BackendFailure { backend, source: source.into() }
+    }
+
+    /// How the values disagreed, for oracle mismatches.
+    pub fn failure_class(&self) -> Option<FailureClass> {
+        match self {
+            FuzzError::OracleMismatch { class, .. } => Some(*class),
+            _ => None,
+        }
+    }
+
+    /// Short label used to classify failures.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FuzzError::UnboundVariable(_) => "UnboundVariable",
+            FuzzError::UnsupportedExpr(_) => "UnsupportedExpr",
+            FuzzError::DomainError(_) => "DomainError",
+            FuzzError::MissingGroundTruth { .. } => "MissingGroundTruth",
+            FuzzError::InputMismatch { .. } => "InputMismatch",
+            FuzzError::BackendFailure { .. } => "BackendFailure",
+            FuzzError::BackendPanic { .. } => "BackendPanic",
+            FuzzError::InvalidConfig(_) => "InvalidConfig",
+            FuzzError::Timeout { .. } => "Timeout",
+            FuzzError::TapeBudgetExceeded { .. } => "TapeBudgetExceeded",
+            FuzzError::OracleMismatch { .. } => "OracleMismatch",
+            FuzzError::PerformanceAnomaly { .. } => "PerformanceAnomaly",
+        }
+    }
+}
+
+impl fmt::Display for FuzzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuzzError::UnboundVariable(name) => write!(f, "Var '{}' not found", name),
+            FuzzError::UnsupportedExpr(msg) => write!(f, "Unsupported expression: {}", msg),
+            FuzzError::DomainError(msg) => write!(f, "Domain error: {}", msg),
+            FuzzError::MissingGroundTruth { oracle } => write!(f, "{} needs a ground truth, none was given", oracle),
+            FuzzError::InputMismatch { expected, got } => {
+                write!(f, "Input length mismatch: expected {}, got {}", expected, got)
+            }
+            FuzzError::BackendFailure { backend, source } => write!(f, "{} failed: {}", backend, source),
+            FuzzError::BackendPanic { backend, message } => write!(f, "{} panicked: {}", backend, message),
+            FuzzError::InvalidConfig(msg) => write!(f, "Invalid config: {}", msg),
+            FuzzError::Timeout { stage, limit } => write!(f, "{} did not finish within {:?}", stage, limit),
+            FuzzError::TapeBudgetExceeded { measure, used, limit } => {
+                write!(f, "Reverse AD tape used {} {} (budget: {})", used, measure, limit)
+            }
+            FuzzError::OracleMismatch { oracle, inputs, output, index, class, details } => write!(
+                f,
+                "Oracle check failed for inputs {:?} ({}, df_{}/dx_{}, {}):\n{}",
+                inputs, oracle, output, index, class, details
+            ),
+            FuzzError::PerformanceAnomaly { oracle, inputs, details } => {
+                write!(f, "Performance anomaly for inputs {:?} ({}):\n{}", inputs, oracle, details)
+            }
+        }
+    }
+}
+
+impl Error for FuzzError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FuzzError::BackendFailure { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,463 @@
+// src/expr_codec.rs
+
+// Compact binary encoding of `Expr`, for storing expressions themselves rather than the fuzzer
+// bytes that generate them (those decode differently whenever the generator or the config
+// changes). Prefix order, one code byte per node:
+//
+//     Number     0x01 f64 (8 bytes LE)
+//     Boolean    0x02 0|1
+//     Id         0x03 name
+//     Let        0x04 count (name expr)* body
+//     UnOp       0x05 op expr
+//     BinOp      0x06 op lhs rhs
+//     If         0x07 cond then else
+//     Loop       0x08 body
+//     Break      0x09 value
+//     Set        0x0a name expr
+//     Block      0x0b count expr*
+//     Cast       0x0c type expr
+//
+// Counts and lengths are LEB128 varints, names are length-prefixed UTF-8. `Op1`, `Op2` and
+// `Type` are one byte each, see `op1_code` and friends. Codes are only ever appended: a grammar
+// extension bumps `FORMAT_VERSION` and adds codes, so everything written by an older version
+// still decodes, and a newer encoding fails with a message naming its version.
+//
+// Regression corpus files (`.adxc`) are the magic `ADXC` followed by one record per test case:
+// varint length, then the version the record was written with, the inputs (varint count, f64s)
+// and the expression. Records are appended, so a corpus can mix versions.
+
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr, Type};
+use crate::error::FuzzError;
+
+/// Version written by `encode`; `decode` reads it and every older one.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// First bytes of a regression corpus file.
+pub const CORPUS_MAGIC: &[u8; 4] = b"ADXC";
+
+/// 64-bit FNV-1a, integers as little-endian `u64`s: the same hashes on every platform and Rust
+/// toolchain, unlike `DefaultHasher`, for what is saved to disk or derives seeds from fuzz inputs.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+fn op1_code(op: &Op1) -> u8 {
+    match op {
+        Op1::Neg => 0,
+        Op1::Sin => 1,
+        Op1::Cos => 2,
+        Op1::Tan => 3,
+        Op1::Exp => 4,
+        Op1::Log => 5,
+        Op1::Sqrt => 6,
+        Op1::Abs => 7,
+    }
+}
+
+fn op2_code(op: &Op2) -> u8 {
+    match op {
+        Op2::Add => 0,
+        Op2::Sub => 1,
+        Op2::Mul => 2,
+        Op2::Div => 3,
+        Op2::Pow => 4,
+    }
+}
+
+fn type_code(ty: &Type) -> u8 {
+    match ty {
+        Type::Float => 0,
+        Type::Int => 1,
+        Type::Bool => 2,
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    write_varint(out, name.len() as u64);
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn write_expr<Tag>(out: &mut Vec<u8>, expr: &Expr<Tag>) {
+    match expr {
+        Expr::Number(_, n) => {
+            out.push(0x01);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Expr::Boolean(_, b) => out.extend_from_slice(&[0x02, *b as u8]),
+        Expr::Id(_, name) => {
+            out.push(0x03);
+            write_name(out, name);
+        }
+        Expr::Let(_, bindings, body) => {
+            out.push(0x04);
+            write_varint(out, bindings.len() as u64);
+            for (name, value) in bindings {
+                write_name(out, name);
+                write_expr(out, value);
+            }
+            write_expr(out, body);
+        }
+        Expr::UnOp(_, op, arg) => {
+            out.extend_from_slice(&[0x05, op1_code(op)]);
+            write_expr(out, arg);
+        }
+        Expr::BinOp(_, op, lhs, rhs) => {
+            out.extend_from_slice(&[0x06, op2_code(op)]);
+            write_expr(out, lhs);
+            write_expr(out, rhs);
+        }
+        Expr::If(_, cond, then, els) => {
+            out.push(0x07);
+            write_expr(out, cond);
+            write_expr(out, then);
+            write_expr(out, els);
+        }
+        Expr::Loop(_, body) => {
+            out.push(0x08);
+            write_expr(out, body);
+        }
+        Expr::Break(_, value) => {
+            out.push(0x09);
+            write_expr(out, value);
+        }
+        Expr::Set(_, name, value) => {
+            out.push(0x0a);
+            write_name(out, name);
+            write_expr(out, value);
+        }
+        Expr::Block(_, exprs) => {
+            out.push(0x0b);
+            write_varint(out, exprs.len() as u64);
+            for e in exprs {
+                write_expr(out, e);
+            }
+        }
+        Expr::Cast(_, ty, arg) => {
+            out.extend_from_slice(&[0x0c, type_code(ty)]);
+            write_expr(out, arg);
+        }
+    }
+}
+
+/// `expr` in the current format, version byte first. Tags are dropped.
+pub fn encode<Tag>(expr: &Expr<Tag>) -> Vec<u8> {
+    let mut out = vec![FORMAT_VERSION];
+    write_expr(&mut out, expr);
+    out
+}
+
+/// `StableHasher` of the encoding without the version byte, so it stays the same for an
+/// expression across format versions, platforms and Rust toolchains.
+pub fn expr_hash<Tag>(expr: &Expr<Tag>) -> u64 {
+    let mut bytes = Vec::new();
+    write_expr(&mut bytes, expr);
+    let mut hasher = StableHasher::default();
+    hasher.write(&bytes);
+    hasher.finish()
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    version: u8,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, msg: &str) -> FuzzError {
+        FuzzError::UnsupportedExpr(format!("{} at byte {} of an encoded expression", msg, self.pos))
+    }
+
+    fn byte(&mut self) -> Result<u8, FuzzError> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], FuzzError> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.error("unexpected end"));
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    fn varint(&mut self) -> Result<u64, FuzzError> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            n |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.error("varint too long"))
+    }
+
+    /// A count or length, which can't exceed the bytes left (every element takes one).
+    fn len(&mut self) -> Result<usize, FuzzError> {
+        let n = self.varint()?;
+        if n > (self.bytes.len() - self.pos) as u64 {
+            return Err(self.error("length past the end"));
+        }
+        Ok(n as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, FuzzError> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().expect("took 8 bytes")))
+    }
+
+    fn name(&mut self) -> Result<String, FuzzError> {
+        let len = self.len()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| self.error("name is not UTF-8"))
+    }
+
+    fn op1(&mut self) -> Result<Op1, FuzzError> {
+        Ok(match self.byte()? {
+            0 => Op1::Neg,
+            1 => Op1::Sin,
+            2 => Op1::Cos,
+            3 => Op1::Tan,
+            4 => Op1::Exp,
+            5 => Op1::Log,
+            6 => Op1::Sqrt,
+            7 => Op1::Abs,
+            code => return Err(self.unknown("unary operator", code)),
+        })
+    }
+
+    fn op2(&mut self) -> Result<Op2, FuzzError> {
+        Ok(match self.byte()? {
+            0 => Op2::Add,
+            1 => Op2::Sub,
+            2 => Op2::Mul,
+            3 => Op2::Div,
+            4 => Op2::Pow,
+            code => return Err(self.unknown("binary operator", code)),
+        })
+    }
+
+    fn ty(&mut self) -> Result<Type, FuzzError> {
+        Ok(match self.byte()? {
+            0 => Type::Float,
+            1 => Type::Int,
+            2 => Type::Bool,
+            code => return Err(self.unknown("type", code)),
+        })
+    }
+
+    fn unknown(&self, what: &str, code: u8) -> FuzzError {
+        self.error(&format!("unknown {} code {:#04x} in format version {}", what, code, self.version))
+    }
+
+    fn boxed(&mut self) -> Result<Box<SimpleExpr>, FuzzError> {
+        self.expr().map(Box::new)
+    }
+
+    fn expr(&mut self) -> Result<SimpleExpr, FuzzError> {
+        Ok(match self.byte()? {
+            0x01 => Expr::Number((), self.f64()?),
+            0x02 => match self.byte()? {
+                0 => Expr::Boolean((), false),
+                1 => Expr::Boolean((), true),
+                _ => return Err(self.error("boolean is not 0 or 1")),
+            },
+            0x03 => Expr::Id((), self.name()?),
+            0x04 => {
+                let count = self.len()?;
+                let bindings = (0..count)
+                    .map(|_| Ok((self.name()?, self.expr()?)))
+                    .collect::<Result<Vec<_>, FuzzError>>()?;
+                Expr::Let((), bindings, self.boxed()?)
+            }
+            0x05 => Expr::UnOp((), self.op1()?, self.boxed()?),
+            0x06 => Expr::BinOp((), self.op2()?, self.boxed()?, self.boxed()?),
+            0x07 => Expr::If((), self.boxed()?, self.boxed()?, self.boxed()?),
+            0x08 => Expr::Loop((), self.boxed()?),
+            0x09 => Expr::Break((), self.boxed()?),
+            0x0a => Expr::Set((), self.name()?, self.boxed()?),
+            0x0b => {
+                let count = self.len()?;
+                Expr::Block((), (0..count).map(|_| self.expr()).collect::<Result<_, _>>()?)
+            }
+            0x0c => Expr::Cast((), self.ty()?, self.boxed()?),
+            code => return Err(self.unknown("node", code)),
+        })
+    }
+
+    /// Version byte, rejecting ones newer than this build.
+    fn version(&mut self) -> Result<(), FuzzError> {
+        self.version = self.byte()?;
+        if self.version == 0 || self.version > FORMAT_VERSION {
+            return Err(self.error(&format!("format version {} (this build reads 1 to {})", self.version, FORMAT_VERSION)));
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), FuzzError> {
+        if self.pos != self.bytes.len() {
+            return Err(self.error("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+/// Inverse of `encode`, for any format version up to `FORMAT_VERSION`.
+pub fn decode(bytes: &[u8]) -> Result<SimpleExpr, FuzzError> {
+    let mut reader = Reader { bytes, pos: 0, version: 0 };
+    reader.version()?;
+    let expr = reader.expr()?;
+    reader.finish()?;
+    Ok(expr)
+}
+
+/// One regression test case: an expression and the inputs it is checked at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusEntry {
+    pub expr: SimpleExpr,
+    pub inputs: Vec<f64>,
+}
+
+impl CorpusEntry {
+    fn record(&self) -> Vec<u8> {
+        let mut payload = vec![FORMAT_VERSION];
+        write_varint(&mut payload, self.inputs.len() as u64);
+        for x in &self.inputs {
+            payload.extend_from_slice(&x.to_le_bytes());
+        }
+        write_expr(&mut payload, &self.expr);
+
+        let mut record = Vec::with_capacity(payload.len() + 2);
+        write_varint(&mut record, payload.len() as u64);
+        record.extend_from_slice(&payload);
+        record
+    }
+}
+
+/// A whole corpus file holding `entries`.
+pub fn write_corpus(entries: &[CorpusEntry]) -> Vec<u8> {
+    let mut out = CORPUS_MAGIC.to_vec();
+    for entry in entries {
+        out.extend_from_slice(&entry.record());
+    }
+    out
+}
+
+/// Every entry of a corpus file, failing on the first malformed record.
+pub fn read_corpus(bytes: &[u8]) -> Result<Vec<CorpusEntry>, FuzzError> {
+    let body = bytes.strip_prefix(CORPUS_MAGIC.as_slice())
+        .ok_or_else(|| FuzzError::UnsupportedExpr("not an expression corpus (no ADXC header)".to_string()))?;
+    let mut outer = Reader { bytes: body, pos: 0, version: 0 };
+    let mut entries = Vec::new();
+    while outer.pos < body.len() {
+        let len = outer.len()?;
+        let mut reader = Reader { bytes: outer.take(len)?, pos: 0, version: 0 };
+        reader.version()?;
+        let count = reader.len()?;
+        let inputs = (0..count).map(|_| reader.f64()).collect::<Result<Vec<f64>, FuzzError>>()?;
+        let expr = reader.expr()?;
+        reader.finish()?;
+        entries.push(CorpusEntry { expr, inputs });
+    }
+    Ok(entries)
+}
+
+/// Appends `entry` to the corpus at `path`, creating the file if needed.
+pub fn append_to_corpus(path: impl AsRef<Path>, entry: &CorpusEntry) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        file.write_all(CORPUS_MAGIC)?;
+    }
+    file.write_all(&entry.record())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_parser::parse_sexpr;
+
+    #[test]
+    fn test_expr_codec_round_trip() {
+        let (expr, _) = parse_sexpr("(pow (sin (+ x_0 -0.5)) (cast int (log x_1)))").unwrap();
+        let shadowed = Expr::Let((), vec![("a".to_string(), expr.clone())], Box::new(Expr::Block((), vec![
+            Expr::Set((), "a".to_string(), Box::new(SimpleExpr::num(f64::NAN))),
+            Expr::If((), Box::new(Expr::Boolean((), true)), Box::new(SimpleExpr::var("a")), Box::new(SimpleExpr::num(-0.0))),
+        ])));
+        for e in [&expr, &shadowed] {
+            let bytes = encode(e);
+            assert_eq!(bytes[0], FORMAT_VERSION);
+            assert_eq!(&decode(&bytes).unwrap(), e);
+        }
+        assert_eq!(encode(&SimpleExpr::var("x_0")), [1, 0x03, 3, b'x', b'_', b'0']);
+        assert_eq!(expr_hash(&expr), expr_hash(&decode(&encode(&expr)).unwrap()));
+        assert_ne!(expr_hash(&expr), expr_hash(&shadowed));
+        // FNV-1a of the bytes after the version, pinned: saved bucket keys depend on it
+        assert_eq!(expr_hash(&SimpleExpr::var("x_0")), 0xe14f_0cf9_eaa1_039e);
+
+        let bytes = encode(&expr);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode(&[FORMAT_VERSION + 1, 0x03, 0]).is_err());
+        assert!(decode(&[1, 0x05, 99, 0x03, 0]).is_err());
+        assert!(decode(&[1, 0x0b, 0xff, 0xff, 0xff, 0xff, 0x0f]).is_err());
+
+        let entries = vec![
+            CorpusEntry { expr: expr.clone(), inputs: vec![2.0, 1e-300] },
+            CorpusEntry { expr: SimpleExpr::num(1.0), inputs: vec![] },
+        ];
+        let corpus = write_corpus(&entries);
+        let read = read_corpus(&corpus).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].inputs, [2.0, 1e-300]);
+        assert_eq!(read[0], entries[0]);
+        assert!(read_corpus(&corpus[4..]).is_err());
+        assert!(read_corpus(&corpus[..corpus.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,349 @@
+// src/expr_coverage.rs
+
+// Synthetic coverage for libFuzzer. Branch coverage of the evaluators saturates after a few
+// hundred inputs, so every generated expression also bumps counters keyed on its shape
+// (operator bigrams, operator depth, size, variables used). libFuzzer picks up the counters
+// from the `__libfuzzer_extra_counters` section (Linux only) and keeps inputs that reach new
+// expression shapes in the corpus. Without libFuzzer the counters are just never read.
+//
+// The same walk also fills a `CoverageMatrix` for the campaign report: how often each (parent
+// operator, child) pair was generated, and each operator was applied to an operand in each
+// `InputRegime`. `AstGenConfig::coverage_bias` reads it back to steer generation to the cells
+// that are still empty.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::ast_evaluator::{evaluate, Env};
+use crate::ast_expr::{Expr, Op1, Op2};
+
+const NUM_KINDS: usize = 17;
+const DEPTH_BUCKETS: usize = 7;
+const SIZE_BUCKETS: usize = 8;
+const VARIABLE_BUCKETS: usize = 8;
+
+const BIGRAM_BASE: usize = 0;
+const DEPTH_BASE: usize = BIGRAM_BASE + NUM_KINDS * NUM_KINDS * 2;
+const SIZE_BASE: usize = DEPTH_BASE + NUM_KINDS * DEPTH_BUCKETS;
+const VARIABLE_BASE: usize = SIZE_BASE + SIZE_BUCKETS;
+
+/// Number of distinct features, and of counters.
+pub const NUM_FEATURES: usize = VARIABLE_BASE + VARIABLE_BUCKETS;
+
+#[used]
+#[cfg_attr(target_os = "linux", link_section = "__libfuzzer_extra_counters")]
+static COUNTERS: [AtomicU8; NUM_FEATURES] = [const { AtomicU8::new(0) }; NUM_FEATURES];
+
+/// Bumps the counter of every feature of `expr`, and its operator pairs in the matrix.
+/// libFuzzer clears the counters before each input.
+pub fn record<Tag>(expr: &Expr<Tag>) {
+    let features = features(expr);
+    let mut matrix = MATRIX.lock().unwrap_or_else(|e| e.into_inner());
+    for &feature in &features {
+        // Saturating: libFuzzer buckets hit counts, a wrap to 0 would hide the feature
+        let _ = COUNTERS[feature].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| c.checked_add(1));
+        if feature < DEPTH_BASE {
+            let pair = (feature - BIGRAM_BASE) / 2;
+            matrix.pairs[pair / NUM_KINDS][pair % NUM_KINDS] += 1;
+        }
+    }
+}
+
+/// Counts the regime of every operand of every operator of `expr` at `inputs` (`x_i` is
+/// `inputs[i]`). Operands that can't be evaluated on their own, e.g. under a `Let`, are skipped.
+pub fn record_inputs<Tag>(expr: &Expr<Tag>, inputs: &[f64]) {
+    let env: Env<f64> = inputs.iter().enumerate().map(|(i, x)| (format!("x_{}", i), *x)).collect();
+    let mut matrix = MATRIX.lock().unwrap_or_else(|e| e.into_inner());
+    record_regimes(expr, &env, &mut matrix);
+}
+
+fn record_regimes<Tag>(expr: &Expr<Tag>, env: &Env<f64>, matrix: &mut CoverageMatrix) {
+    let operands: Vec<&Expr<Tag>> = match expr {
+        Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => vec![e],
+        Expr::BinOp(_, _, l, r) => vec![l, r],
+        _ => return,
+    };
+    for operand in operands {
+        if let Ok(val) = evaluate(operand, env) {
+            matrix.regimes[kind(expr)][InputRegime::of(val) as usize] += 1;
+        }
+        record_regimes(operand, env, matrix);
+    }
+}
+
+/// Feature indices of `expr` (below `NUM_FEATURES`), once per occurrence:
+/// - (parent operator, child operator, child position) for every edge
+/// - (operator, depth bucket) for every node
+/// - size bucket (log2 of the node count) and number of distinct variables
+pub fn features<Tag>(expr: &Expr<Tag>) -> Vec<usize> {
+    let mut out = Vec::new();
+    let mut variables = Vec::new();
+    let size = walk(expr, 0, &mut out, &mut variables);
+    let size_bucket = (usize::BITS - size.leading_zeros()) as usize - 1;
+    out.push(SIZE_BASE + size_bucket.min(SIZE_BUCKETS - 1));
+    out.push(VARIABLE_BASE + variables.len().min(VARIABLE_BUCKETS - 1));
+    out
+}
+
+/// Records the node and edge features below `expr`, returns its node count.
+fn walk<'a, Tag>(expr: &'a Expr<Tag>, depth: usize, out: &mut Vec<usize>, variables: &mut Vec<&'a str>) -> usize {
+    let kind = kind(expr);
+    out.push(DEPTH_BASE + kind * DEPTH_BUCKETS + depth_bucket(depth));
+    if let Expr::Id(_, name) = expr {
+        if !variables.contains(&name.as_str()) {
+            variables.push(name);
+        }
+    }
+    let children: Vec<&Expr<Tag>> = match expr {
+        Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => vec![e],
+        Expr::BinOp(_, _, l, r) => vec![l, r],
+        _ => Vec::new(),
+    };
+    let mut size = 1;
+    for (position, child) in children.into_iter().enumerate() {
+        out.push(BIGRAM_BASE + (kind * NUM_KINDS + self::kind(child)) * 2 + position);
+        size += walk(child, depth + 1, out, variables);
+    }
+    size
+}
+
+/// 0, 1, 2, 3, 4-5, 6-7, 8+
+fn depth_bucket(depth: usize) -> usize {
+    match depth {
+        0..=3 => depth,
+        4..=5 => 4,
+        6..=7 => 5,
+        _ => 6,
+    }
+}
+
+fn kind<Tag>(expr: &Expr<Tag>) -> usize {
+    match expr {
+        Expr::Number(..) => 0,
+        Expr::Id(..) => 1,
+        Expr::Cast(..) => 2,
+        Expr::UnOp(_, op, _) => op1_kind(op),
+        Expr::BinOp(_, op, _, _) => op2_kind(op),
+        _ => 16,
+    }
+}
+
+/// Row of `op` in `CoverageMatrix`, and its index in `KIND_NAMES`.
+pub fn op1_kind(op: &Op1) -> usize {
+    3 + match op {
+        Op1::Neg => 0,
+        Op1::Sin => 1,
+        Op1::Cos => 2,
+        Op1::Tan => 3,
+        Op1::Exp => 4,
+        Op1::Log => 5,
+        Op1::Sqrt => 6,
+        Op1::Abs => 7,
+    }
+}
+
+pub fn op2_kind(op: &Op2) -> usize {
+    11 + match op {
+        Op2::Add => 0,
+        Op2::Sub => 1,
+        Op2::Mul => 2,
+        Op2::Div => 3,
+        Op2::Pow => 4,
+    }
+}
+
+// --- PAIR AND REGIME MATRIX ---
+
+/// Node kinds, rows and columns of `CoverageMatrix`. Operators are `Cast` to `Pow`; `Other`
+/// is every other node (`Let`, `If`, ...).
+pub const KIND_NAMES: [&str; NUM_KINDS] = [
+    "Number", "Id", "Cast", "Neg", "Sin", "Cos", "Tan", "Exp", "Log", "Sqrt", "Abs",
+    "Add", "Sub", "Mul", "Div", "Pow", "Other",
+];
+
+const OPERATORS: std::ops::Range<usize> = 2..16;
+const NUM_REGIMES: usize = 8;
+
+/// Where an operand value lies, as far as the operators' derivatives care: sign, magnitude,
+/// and the f64 edge cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputRegime {
+    Zero,
+    Subnormal,
+    NegativeSmall,
+    PositiveSmall,
+    NegativeModerate,
+    PositiveModerate,
+    /// |x| >= 1e8, either sign.
+    Large,
+    NonFinite,
+}
+
+impl InputRegime {
+    pub const ALL: [InputRegime; NUM_REGIMES] = [
+        InputRegime::Zero,
+        InputRegime::Subnormal,
+        InputRegime::NegativeSmall,
+        InputRegime::PositiveSmall,
+        InputRegime::NegativeModerate,
+        InputRegime::PositiveModerate,
+        InputRegime::Large,
+        InputRegime::NonFinite,
+    ];
+
+    /// Small is below 1 in magnitude, moderate below 1e8.
+    pub fn of(x: f64) -> Self {
+        match x.abs() {
+            _ if !x.is_finite() => InputRegime::NonFinite,
+            0.0 => InputRegime::Zero,
+            a if a < f64::MIN_POSITIVE => InputRegime::Subnormal,
+            a if a < 1.0 => if x < 0.0 { InputRegime::NegativeSmall } else { InputRegime::PositiveSmall },
+            a if a < 1e8 => if x < 0.0 { InputRegime::NegativeModerate } else { InputRegime::PositiveModerate },
+            _ => InputRegime::Large,
+        }
+    }
+}
+
+impl fmt::Display for InputRegime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// What the campaign has generated so far, see the top of the file. Rows are indexed like
+/// `KIND_NAMES` (see `op1_kind`/`op2_kind`); only operator rows are ever filled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageMatrix {
+    /// `pairs[parent][child]`, both operand positions together.
+    pairs: [[u64; NUM_KINDS]; NUM_KINDS],
+    /// `regimes[operator][regime as usize]`, one count per operand.
+    regimes: [[u64; NUM_REGIMES]; NUM_KINDS],
+}
+
+static MATRIX: Mutex<CoverageMatrix> = Mutex::new(CoverageMatrix::new());
+
+/// Snapshot of the matrix `record` and `record_inputs` have filled in this process.
+pub fn coverage_matrix() -> CoverageMatrix {
+    MATRIX.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Whether an operator of kind `parent` has been generated over `child` yet.
+pub fn pair_seen<Tag>(parent: usize, child: &Expr<Tag>) -> bool {
+    MATRIX.lock().unwrap_or_else(|e| e.into_inner()).pairs[parent][kind(child)] > 0
+}
+
+impl CoverageMatrix {
+    pub const fn new() -> Self {
+        CoverageMatrix { pairs: [[0; NUM_KINDS]; NUM_KINDS], regimes: [[0; NUM_REGIMES]; NUM_KINDS] }
+    }
+
+    pub fn pair(&self, parent: usize, child: usize) -> u64 {
+        self.pairs[parent][child]
+    }
+
+    pub fn regime(&self, operator: usize, regime: InputRegime) -> u64 {
+        self.regimes[operator][regime as usize]
+    }
+
+    /// Covered and possible (operator, child) cells; `Other` children don't count.
+    pub fn pair_coverage(&self) -> (usize, usize) {
+        let covered = OPERATORS.map(|op| self.pairs[op][..NUM_KINDS - 1].iter().filter(|&&n| n > 0).count()).sum();
+        (covered, OPERATORS.len() * (NUM_KINDS - 1))
+    }
+
+    /// Covered and possible (operator, regime) cells.
+    pub fn regime_coverage(&self) -> (usize, usize) {
+        let covered = OPERATORS.map(|op| self.regimes[op].iter().filter(|&&n| n > 0).count()).sum();
+        (covered, OPERATORS.len() * NUM_REGIMES)
+    }
+
+    /// Both matrices with their row and column names, operator rows only.
+    pub fn to_json(&self) -> Value {
+        let rows = |row: &dyn Fn(usize) -> Value| -> Value {
+            Value::Object(OPERATORS.map(|op| (KIND_NAMES[op].to_string(), row(op))).collect())
+        };
+        let pairs = rows(&|op| json!(self.pairs[op]));
+        let regimes = rows(&|op| json!(self.regimes[op]));
+        let (pairs_covered, pairs_total) = self.pair_coverage();
+        let (regimes_covered, regimes_total) = self.regime_coverage();
+        json!({
+            "children": KIND_NAMES,
+            "pairs": pairs,
+            "pairs_covered": pairs_covered,
+            "pairs_total": pairs_total,
+            "regimes": InputRegime::ALL.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            "operator_regimes": regimes,
+            "regimes_covered": regimes_covered,
+            "regimes_total": regimes_total,
+        })
+    }
+}
+
+impl Default for CoverageMatrix {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for CoverageMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (pairs, pairs_total) = self.pair_coverage();
+        let (regimes, regimes_total) = self.regime_coverage();
+        write!(f, "{}/{} operator pairs, {}/{} operator regimes", pairs, pairs_total, regimes, regimes_total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_features_follow_shape() {
+        let x = || SimpleExpr::var("x_0");
+        let sin_sum = SimpleExpr::sin(SimpleExpr::add(x(), SimpleExpr::num(1.0)));
+        let cos_sum = SimpleExpr::cos(SimpleExpr::add(x(), SimpleExpr::num(1.0)));
+        let sin_sum_2 = SimpleExpr::sin(SimpleExpr::add(x(), SimpleExpr::num(2.0)));
+
+        // Constants don't matter, operators do
+        assert_eq!(features(&sin_sum), features(&sin_sum_2));
+        assert_ne!(features(&sin_sum), features(&cos_sum));
+        // 4 nodes, 3 edges, size and variables
+        assert_eq!(features(&sin_sum).len(), 9);
+
+        let mut deep = x();
+        for _ in 0..20 {
+            deep = SimpleExpr::pow(SimpleExpr::exp(deep), SimpleExpr::var("x_9"));
+        }
+        assert!(features(&deep).iter().all(|&f| f < NUM_FEATURES));
+        record(&deep);
+    }
+    #[test]
+    fn test_coverage_matrix() {
+        // Other tests record too: only ever more
+        let x = || SimpleExpr::var("x_0");
+        let expr = SimpleExpr::log(SimpleExpr::mul(x(), SimpleExpr::tan(x())));
+        let before = coverage_matrix();
+        record(&expr);
+        record_inputs(&expr, &[-0.5]);
+        let after = coverage_matrix();
+        let (log, mul, tan, id) = (op1_kind(&Op1::Log), op2_kind(&Op2::Mul), op1_kind(&Op1::Tan), 1);
+        assert_eq!(after.pair(log, mul), before.pair(log, mul) + 1);
+        assert_eq!(after.pair(mul, id), before.pair(mul, id) + 1);
+        assert_eq!(after.pair(mul, tan), before.pair(mul, tan) + 1);
+        assert!(pair_seen(log, &SimpleExpr::mul(x(), x())));
+        // log sees -0.5 * tan(-0.5) = 0.27..., mul sees -0.5 twice over
+        assert_eq!(after.regime(log, InputRegime::PositiveSmall), before.regime(log, InputRegime::PositiveSmall) + 1);
+        assert_eq!(after.regime(mul, InputRegime::NegativeSmall), before.regime(mul, InputRegime::NegativeSmall) + 2);
+        assert!(after.pair_coverage().0 >= 3 && after.regime_coverage().1 == 14 * 8);
+        assert_eq!(after.to_json()["pairs"]["Log"][mul], after.pair(log, mul));
+
+        assert_eq!(InputRegime::of(-1e300), InputRegime::Large);
+        assert_eq!(InputRegime::of(5e-324), InputRegime::Subnormal);
+        assert_eq!(InputRegime::of(-0.0), InputRegime::Zero);
+        assert_eq!(InputRegime::of(f64::NAN), InputRegime::NonFinite);
+    }
+}
@@ -0,0 +1,247 @@
+// src/failure_classifier.rs
+
+// Tags oracle failures by *how* two values disagree, so large campaigns can be triaged by class
+// instead of by reading tolerance-failure strings.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::Hasher;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast_canonical::{canonical_hash, canonicalize};
+use crate::ast_evaluator::InfixPrinter;
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::error::FuzzError;
+use crate::expr_codec::StableHasher;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailureClass {
+    /// Same magnitude range, opposite signs.
+    SignFlip,
+    /// Same sign, values differ by more than the tolerance.
+    MagnitudeDrift,
+    /// One side is NaN, the other is not.
+    NaNMismatch,
+    /// One side is ±Inf (and not NaN), the other finite.
+    InfMismatch,
+    /// +Inf against -Inf.
+    InfSignFlip,
+    /// Exactly one side is zero.
+    ZeroVsNonzero,
+    /// The engines return a different number of partials/outputs.
+    ShapeMismatch,
+}
+
+impl FailureClass {
+    pub const ALL: [FailureClass; 7] = [
+        FailureClass::SignFlip,
+        FailureClass::MagnitudeDrift,
+        FailureClass::NaNMismatch,
+        FailureClass::InfMismatch,
+        FailureClass::InfSignFlip,
+        FailureClass::ZeroVsNonzero,
+        FailureClass::ShapeMismatch,
+    ];
+
+    /// Classifies a disagreement between `value` and the `reference` it was checked against.
+    pub fn of(value: f64, reference: f64) -> FailureClass {
+        if value.is_nan() || reference.is_nan() {
+            FailureClass::NaNMismatch
+        } else if value.is_infinite() && reference.is_infinite() && value != reference {
+            FailureClass::InfSignFlip
+        } else if value.is_infinite() || reference.is_infinite() {
+            FailureClass::InfMismatch
+        } else if (value == 0.0) != (reference == 0.0) {
+            FailureClass::ZeroVsNonzero
+        } else if value.signum() != reference.signum() {
+            FailureClass::SignFlip
+        } else {
+            FailureClass::MagnitudeDrift
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|c| *c == self).unwrap()
+    }
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Failure counts per class, for Continuous mode summaries.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FailureStats {
+    counts: [usize; 7],
+    /// Failures that were not oracle mismatches (backend errors, unsupported expressions, ...).
+    other: usize,
+}
+
+impl FailureStats {
+    pub const fn new() -> Self {
+        FailureStats { counts: [0; 7], other: 0 }
+    }
+
+    pub fn record(&mut self, error: &FuzzError) {
+        match error.failure_class() {
+            Some(class) => self.counts[class.index()] += 1,
+            None => self.other += 1,
+        }
+    }
+
+    pub fn count(&self, class: FailureClass) -> usize {
+        self.counts[class.index()]
+    }
+
+    /// Failures that were not oracle mismatches.
+    pub fn other(&self) -> usize {
+        self.other
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum::<usize>() + self.other
+    }
+}
+
+impl fmt::Display for FailureStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Failures by class ({} total):", self.total())?;
+        for class in FailureClass::ALL {
+            writeln!(f, "  {:<15} {}", class.to_string(), self.count(class))?;
+        }
+        write!(f, "  {:<15} {}", "Other", self.other)
+    }
+}
+
+/// Which oracle reported `error`: the oracle label for mismatches, the error kind otherwise.
+pub(crate) fn oracle_kind(error: &FuzzError) -> &str {
+    match error {
+        FuzzError::OracleMismatch { oracle, .. } => oracle,
+        _ => error.kind(),
+    }
+}
+
+/// Dedup key for a failure: the canonical expression (see `ast_canonical`) plus the oracle that
+/// caught it. The same in every build, see `StableHasher`.
+pub fn bucket_key(expr: &SimpleExpr, error: &FuzzError) -> u64 {
+    let mut hasher = StableHasher::default();
+    hasher.write_u64(canonical_hash(expr));
+    hasher.write(oracle_kind(error).as_bytes());
+    hasher.finish()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bucket {
+    /// Infix form of the canonical expression of the first failure.
+    example: String,
+    oracle: String,
+    count: usize,
+}
+
+/// Failures grouped by `bucket_key`, for Continuous mode: only the first failure of each bucket
+/// is worth a full report.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FailureBuckets {
+    buckets: BTreeMap<u64, Bucket>,
+}
+
+impl FailureBuckets {
+    pub const fn new() -> Self {
+        FailureBuckets { buckets: BTreeMap::new() }
+    }
+
+    /// Counts the failure. Returns whether it is the first one in its bucket.
+    pub fn record(&mut self, expr: &SimpleExpr, error: &FuzzError) -> bool {
+        let key = bucket_key(expr, error);
+        match self.buckets.get_mut(&key) {
+            Some(bucket) => {
+                bucket.count += 1;
+                false
+            }
+            None => {
+                let canonical = canonicalize(expr);
+                let num_inputs = expr_num_inputs(&canonical);
+                self.buckets.insert(key, Bucket {
+                    example: InfixPrinter::print(&canonical, num_inputs),
+                    oracle: oracle_kind(error).to_string(),
+                    count: 1,
+                });
+                true
+            }
+        }
+    }
+
+    /// Number of distinct buckets.
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+/// One past the highest `x_i` in `expr`, for the printers.
+fn expr_num_inputs(expr: &SimpleExpr) -> usize {
+    match expr {
+        Expr::Id(_, name) => name.strip_prefix("x_").and_then(|i| i.parse::<usize>().ok()).map_or(0, |i| i + 1),
+        Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => expr_num_inputs(e),
+        Expr::BinOp(_, _, l, r) => expr_num_inputs(l).max(expr_num_inputs(r)),
+        _ => 0,
+    }
+}
+
+impl fmt::Display for FailureBuckets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: usize = self.buckets.values().map(|b| b.count).sum();
+        write!(f, "Distinct failures: {} buckets, {} failures", self.len(), total)?;
+        let mut buckets: Vec<&Bucket> = self.buckets.values().collect();
+        buckets.sort_by_key(|b| Reverse(b.count));
+        for bucket in buckets {
+            write!(f, "\n  {:>6}  {:<24} {}", bucket.count, bucket.oracle, bucket.example)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(FailureClass::of(2.0, -2.0), FailureClass::SignFlip);
+        assert_eq!(FailureClass::of(2.0, 2.5), FailureClass::MagnitudeDrift);
+        assert_eq!(FailureClass::of(f64::NAN, 0.0), FailureClass::NaNMismatch);
+        assert_eq!(FailureClass::of(f64::INFINITY, 1.0), FailureClass::InfMismatch);
+        assert_eq!(FailureClass::of(f64::NEG_INFINITY, f64::INFINITY), FailureClass::InfSignFlip);
+        assert_eq!(FailureClass::of(0.0, 1e-3), FailureClass::ZeroVsNonzero);
+    }
+
+    #[test]
+    fn test_failure_buckets() {
+        let mismatch = |oracle: &str| FuzzError::OracleMismatch {
+            oracle: oracle.to_string(),
+            inputs: vec![1.0],
+            output: 0,
+            index: 0,
+            class: FailureClass::MagnitudeDrift,
+            details: String::new(),
+        };
+        let x = || SimpleExpr::var("x_0");
+        let a = SimpleExpr::mul(x(), SimpleExpr::add(SimpleExpr::num(2.0), SimpleExpr::num(1.0)));
+        let b = SimpleExpr::mul(SimpleExpr::num(3.0), x());
+
+        let mut buckets = FailureBuckets::new();
+        assert!(buckets.record(&a, &mismatch("Rev vs Fwd")));
+        assert!(!buckets.record(&b, &mismatch("Rev vs Fwd")));
+        assert!(buckets.record(&b, &mismatch("Rev vs PyTorch")));
+        assert!(buckets.record(&SimpleExpr::sin(x()), &mismatch("Rev vs Fwd")));
+        assert_eq!(buckets.len(), 3);
+        assert!(buckets.to_string().contains("     2  Rev vs Fwd"));
+    }
+}
@@ -0,0 +1,237 @@
+// src/failure_report.rs
+
+// Machine-readable companion to the crash report the fuzz targets print: one JSON document per
+// oracle failure. Non-finite numbers (NaN, ±inf) come out as `null`, since JSON has no
+// representation for them; the infix string keeps them as `NaN` / `inf`. A GraphViz rendering of
+// the expression is written next to each report, see `DotPrinter`, and standalone PyTorch and C
+// programs reproducing it, see `PyTorchScriptPrinter` and `CPrinter`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::ast_evaluator::{CPrinter, DotPrinter, InfixPrinter, PyTorchScriptPrinter, SExprPrinter};
+use crate::ast_expr::SimpleExpr;
+use crate::fuzz_harness::TestOutcome;
+use crate::oracles::FuzzingOracles;
+use crate::symbolic_diff::symbolic_gradient;
+use crate::tape_stats::TapeStats;
+
+#[derive(Debug, Serialize)]
+pub struct ReportExpr {
+    pub infix: String,
+    pub sexpr: String,
+    pub ast: SimpleExpr,
+}
+
+impl ReportExpr {
+    pub fn new(expr: &SimpleExpr, num_inputs: usize) -> Self {
+        ReportExpr {
+            infix: InfixPrinter::print(expr, num_inputs),
+            sexpr: SExprPrinter::print(expr, num_inputs),
+            ast: expr.clone(),
+        }
+    }
+}
+
+/// Value and row-major Jacobian from one engine or ground truth.
+#[derive(Debug, Serialize)]
+pub struct EngineReport {
+    pub engine: String,
+    pub value: Option<f64>,
+    pub jacobian: Vec<f64>,
+}
+
+/// The failing oracle and the settings it ran with.
+#[derive(Debug, Serialize)]
+pub struct OracleReport {
+    pub name: String,
+    pub kind: &'static str,
+    pub class: Option<String>,
+    pub message: String,
+    pub abs_tol: Option<f64>,
+    pub rel_tol: Option<f64>,
+    pub ulps: Option<u64>,
+}
+
+/// Stage timings of the failing test case, in microseconds.
+#[derive(Debug, Serialize)]
+pub struct TimingReport {
+    pub ad_us: u128,
+    /// The `derivative` call of each AD engine, part of `ad_us`.
+    pub reverse_us: u128,
+    pub forward_us: u128,
+    pub ground_truths_us: u128,
+    pub oracles_us: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailureReport {
+    /// Milliseconds since the Unix epoch when the report was created.
+    pub timestamp_ms: u128,
+    pub expr: ReportExpr,
+    pub minimized: Option<ReportExpr>,
+    pub inputs: Vec<f64>,
+    pub engines: Vec<EngineReport>,
+    /// Ground truth calculators that returned an error instead of a result.
+    pub unavailable: Vec<(String, String)>,
+    /// Closed-form `d/dx_i` of `expr` in infix, see `symbolic_diff`; empty if it has none.
+    pub symbolic_gradient: Vec<String>,
+    pub oracle: OracleReport,
+    pub timing: TimingReport,
+    /// Size of the Reverse AD tape, see `tape_stats`.
+    pub tape: TapeStats,
+}
+
+impl FailureReport {
+    /// Report for the first failing oracle of `outcome`, `None` if it passed.
+    pub fn new(
+        expr: &SimpleExpr,
+        minimized: Option<&SimpleExpr>,
+        outcome: &TestOutcome,
+        oracles: &FuzzingOracles,
+    ) -> Option<Self> {
+        let verdict = outcome.verdicts.iter().find(|v| v.is_failure())?;
+        let error = verdict.result.as_ref().err()?;
+        let tolerances = oracles.tolerances(&verdict.oracle).unwrap_or_default();
+
+        let engine = &outcome.engine_results;
+        let num_inputs = engine.inputs.len();
+        let mut engines = vec![
+            EngineReport { engine: "Rev".to_string(), value: engine.reverse_primal.first().copied(), jacobian: engine.reverse.clone() },
+            EngineReport { engine: "Fwd".to_string(), value: engine.forward_primal.first().copied(), jacobian: engine.forward.clone() },
+        ];
+        engines.extend(outcome.ground_truths.iter().map(|gt| EngineReport {
+            engine: gt.name.to_string(),
+            value: gt.value,
+            jacobian: gt.jacobian.clone(),
+        }));
+
+        Some(FailureReport {
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0),
+            expr: ReportExpr::new(expr, num_inputs),
+            minimized: minimized.map(|m| ReportExpr::new(m, num_inputs)),
+            inputs: engine.inputs.clone(),
+            engines,
+            unavailable: outcome.failed_ground_truths.iter().map(|(name, e)| (name.to_string(), e.to_string())).collect(),
+            symbolic_gradient: symbolic_gradient(expr, num_inputs)
+                .map(|gradient| gradient.iter().map(|d| InfixPrinter::print(d, num_inputs)).collect())
+                .unwrap_or_default(),
+            oracle: OracleReport {
+                name: verdict.oracle.clone(),
+                kind: error.kind(),
+                class: error.failure_class().map(|c| c.to_string()),
+                message: error.to_string(),
+                abs_tol: tolerances.abs_tol,
+                rel_tol: tolerances.rel_tol,
+                ulps: tolerances.ulps,
+            },
+            timing: TimingReport {
+                ad_us: outcome.timing.ad.as_micros(),
+                reverse_us: engine.timing.reverse.as_micros(),
+                forward_us: engine.timing.forward.as_micros(),
+                ground_truths_us: outcome.timing.ground_truths.as_micros(),
+                oracles_us: outcome.timing.oracles.as_micros(),
+            },
+            tape: outcome.tape,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("failure reports always serialize")
+    }
+
+    /// Writes the report to a new `failure-<timestamp>-<pid>-<n>.json` in `dir`, creating `dir`
+    /// if needed, and the expression as GraphViz to the same name with `.dot` (the minimized one,
+    /// if any, to `.minimized.dot`), and a PyTorch script and a C program with finite differences
+    /// reproducing it at the failing inputs to `.py` and `.c`. Returns the JSON file's path.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("failure-{}-{}-{}.json", self.timestamp_ms, process::id(), n));
+        fs::write(&path, self.to_json())?;
+        let num_inputs = self.inputs.len();
+        fs::write(path.with_extension("dot"), DotPrinter::print(&self.expr.ast, num_inputs))?;
+        if let Some(minimized) = &self.minimized {
+            fs::write(path.with_extension("minimized.dot"), DotPrinter::print(&minimized.ast, num_inputs))?;
+        }
+        let expected: Vec<(&str, &[f64])> = self.engines.iter()
+            .filter(|e| e.engine == "Rev" || e.engine == "Fwd")
+            .map(|e| (e.engine.as_str(), e.jacobian.as_slice()))
+            .collect();
+        fs::write(path.with_extension("py"), PyTorchScriptPrinter::print(&self.expr.ast, &self.inputs, &expected))?;
+        fs::write(path.with_extension("c"), CPrinter::program(&self.expr.ast, &self.inputs))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::error::FuzzError;
+    use crate::failure_classifier::FailureClass;
+    use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator};
+    use crate::gt_calculators::DualNumberCalculator;
+    use crate::oracles::{Oracle, OracleContext, OracleSelection};
+
+    #[derive(Clone)]
+    struct AlwaysFails;
+
+    impl Oracle for AlwaysFails {
+        fn name(&self) -> &str { "always_fails" }
+        fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+            Err(FuzzError::OracleMismatch {
+                oracle: "Always".to_string(),
+                inputs: ctx.engine.inputs.clone(),
+                output: 0,
+                index: 0,
+                class: FailureClass::SignFlip,
+                details: "test".to_string(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_failure_report_json() {
+        let expr = SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::num(f64::NAN));
+        let mut oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        oracles.register(AlwaysFails);
+        let calc = AdPyUnified::new(expr.clone(), 1, 1);
+        let outcome = run_ad_tests(&[2.0], calc, &oracles, &[DualNumberCalculator.boxed()]).unwrap();
+
+        let report = FailureReport::new(&expr, None, &outcome, &oracles).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["oracle"]["name"], "always_fails");
+        assert_eq!(json["oracle"]["class"], "SignFlip");
+        assert_eq!(json["expr"]["infix"], "(x_0 * NaN)");
+        assert_eq!(json["inputs"][0], 2.0);
+        assert_eq!(json["symbolic_gradient"][0], "NaN");
+        assert_eq!(json["engines"][2]["engine"], "Dual");
+        assert!(json["engines"][0]["value"].is_null());
+        assert!(json["expr"]["ast"]["BinOp"].is_array());
+
+        let dir = std::env::temp_dir().join(format!("failure-report-{}", process::id()));
+        let path = report.write_to(&dir).unwrap();
+        let dot = fs::read_to_string(path.with_extension("dot")).unwrap();
+        let script = fs::read_to_string(path.with_extension("py")).unwrap();
+        let c_source = fs::read_to_string(path.with_extension("c")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(dot.starts_with("digraph expr {"));
+        assert!(dot.contains("[label=\"x_0\", shape=box]") && dot.contains("[label=\"*\"]"));
+        assert!(script.contains("x_0 = torch.tensor(2.0, dtype=torch.float64, requires_grad=True)"));
+        assert!(script.contains("out = torch.mul(x_0, torch.tensor(float('nan'), dtype=torch.float64))"));
+        assert!(script.contains("out.backward()") && script.contains("\"Rev\": ["));
+        assert!(c_source.contains("return (x[0] * NAN);") && c_source.contains("double x[N] = {2.0};"));
+
+        assert!(oracles.tolerances("rev_fwd").unwrap().abs_tol.is_some());
+        assert!(oracles.tolerances("missing").is_none());
+    }
+}
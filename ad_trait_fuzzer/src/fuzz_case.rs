@@ -0,0 +1,321 @@
+// src/fuzz_case.rs
+
+// How the fuzz targets turn raw fuzzer bytes into test cases: a header byte sizes the inputs
+// region (see `FuzzInputSplitter`), the rest seeds the AST generator. A `harness::Harness` runs the whole decode -> generate ->
+// evaluate -> check pipeline for one input, `run_fuzz_iteration` the one of a config; the fuzz
+// targets only act on its outcome (crash reports, campaign stats), and `adfuzz replay` prints
+// it, so artifacts replay exactly.
+
+use std::hash::Hasher;
+use std::sync::OnceLock;
+
+use arbitrary::Error as ArbitraryError;
+
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::ast_generator::{generate_from_bytes, AstGenConfig, GeneratedExpr};
+use crate::error::FuzzError;
+use crate::expr_codec::StableHasher;
+use crate::fuzz_config::FuzzConfig;
+use crate::fuzz_harness::{GroundTruthCalculator, HarnessMode, TestOutcome};
+use crate::gt_calculators::{CachedGroundTruth, DefaultGroundTruth};
+use crate::harness::Harness;
+use crate::input_decoder::{
+    ArbitraryInputDecoder, DyadicInputDecoder, ExtremeInputDecoder, FuzzInputDecoder, FuzzInputSplitter, GeneralInputDecoder, InputDecoding,
+    InputLayout, PerturbedInputDecoder, SanitizedInputDecoder,
+};
+use crate::oracles::FuzzingOracles;
+
+/// Bytes between the starts of consecutive expressions in one fuzz input.
+pub const TEST_STRIDE: usize = 32;
+
+/// Random points `InputSynthesizer` tries per expression with `config.inputs.synthesize`.
+pub const SYNTHESIS_ATTEMPTS: usize = 32;
+
+/// The decoder `config.inputs.decoder` names, sized to `config.ast.max_variables` inputs.
+pub fn config_decoder(config: &FuzzConfig) -> Box<dyn FuzzInputDecoder> {
+    let input_length = config.ast.max_variables;
+    let domain = &config.inputs;
+    match domain.decoder {
+        InputDecoding::Sanitized => Box::new(SanitizedInputDecoder {
+            input_length,
+            min_magnitude: domain.min_magnitude,
+            max_magnitude: domain.max_magnitude.min(domain.max_abs),
+            max_magnitude_first: domain.max_magnitude.min(domain.max_abs_first),
+            positive_first: !domain.allow_non_positive,
+            special_values: domain.special_values,
+        }),
+        InputDecoding::General => Box::new(GeneralInputDecoder { input_length, domains: domain.domains.clone() }),
+        InputDecoding::Extreme => Box::new(ExtremeInputDecoder { input_length }),
+        InputDecoding::Dyadic => Box::new(DyadicInputDecoder {
+            input_length,
+            max_numerator: domain.dyadic_max_numerator,
+            max_exponent: domain.dyadic_max_exponent,
+            max_abs: domain.max_abs,
+            max_abs_first: domain.max_abs_first,
+            positive_first: !domain.allow_non_positive,
+        }),
+        InputDecoding::Arbitrary => Box::new(ArbitraryInputDecoder {
+            min_magnitude: domain.min_magnitude,
+            max_magnitude: domain.max_magnitude,
+            domains: (0..input_length).map(|i| domain.interval(i)).collect(),
+        }),
+    }
+}
+
+/// Inputs decoded from `data` as laid out by `config.inputs.layout`, and the bytes left for AST
+/// generation. `None` if `data` is too short: empty, or with the prefix layout shorter than the
+/// inputs. The inputs are not yet checked against `config.inputs`. With
+/// `config.inputs.perturbation_step`, `x` then `x + h` (twice as many values).
+pub fn decode_inputs<'a>(data: &'a [u8], config: &FuzzConfig) -> Option<(Vec<f64>, &'a [u8])> {
+    decode_inputs_with(&*config_decoder(config), data, config)
+}
+
+/// `decode_inputs` with `decoder` in place of the one `config.inputs.decoder` names.
+pub fn decode_inputs_with<'a>(decoder: &dyn FuzzInputDecoder, data: &'a [u8], config: &FuzzConfig) -> Option<(Vec<f64>, &'a [u8])> {
+    let perturbed;
+    let decoder: &dyn FuzzInputDecoder = match config.inputs.perturbation_step {
+        Some(step) => {
+            perturbed = PerturbedInputDecoder { inner: decoder, step };
+            &perturbed
+        }
+        None => decoder,
+    };
+    match config.inputs.layout {
+        InputLayout::Header => FuzzInputSplitter::default().decode(decoder, data).ok(),
+        InputLayout::Prefix => {
+            let (inputs, consumed) = decoder.decode_prefix(data).ok()?;
+            Some((inputs, &data[consumed..]))
+        }
+    }
+}
+
+/// Expression number `i` of a fuzz input. Each starts `TEST_STRIDE` bytes further into
+/// `ast_data`, or at its start once the data runs out.
+pub fn generate_test(ast_data: &[u8], i: usize, config: &AstGenConfig) -> Result<GeneratedExpr, ArbitraryError> {
+    let offset = i * TEST_STRIDE;
+    let test_data = if offset < ast_data.len() {
+        &ast_data[offset..]
+    } else {
+        ast_data
+    };
+    generate_from_bytes(test_data, config.clone())
+}
+
+/// Seed for the special points of expression `i` of a fuzz input: a function of the bytes, so
+/// the input replays with the same substitutions, whichever build replays it.
+pub(crate) fn special_point_seed(data: &[u8], i: usize) -> u64 {
+    let mut hasher = StableHasher::default();
+    hasher.write(data);
+    hasher.write_usize(i);
+    hasher.finish()
+}
+
+/// Seed for the synthesized inputs of expression `i`, see `special_point_seed`.
+pub(crate) fn synthesis_seed(data: &[u8], i: usize) -> u64 {
+    special_point_seed(data, i).rotate_left(16)
+}
+
+/// Seed for the random sweep points of expression `i`, see `special_point_seed`.
+pub(crate) fn sweep_seed(data: &[u8], i: usize) -> u64 {
+    special_point_seed(data, i).rotate_left(32)
+}
+
+/// The finite constants in `expr`, each once.
+pub(crate) fn expr_constants(expr: &SimpleExpr) -> Vec<f64> {
+    fn walk(expr: &SimpleExpr, out: &mut Vec<f64>) {
+        match expr {
+            Expr::Number(_, c) if c.is_finite() && !out.contains(c) => out.push(*c),
+            Expr::UnOp(_, _, e) | Expr::Cast(_, _, e) => walk(e, out),
+            Expr::BinOp(_, _, l, r) => {
+                walk(l, out);
+                walk(r, out);
+            }
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk(expr, &mut out);
+    out
+}
+
+/// What one fuzz input turned into.
+pub enum IterationOutcome {
+    /// Too short to hold the inputs, see `decode_inputs`.
+    TooShort,
+    /// The decoded inputs are outside `config.inputs` (never with `config.inputs.synthesize`).
+    InputsRejected(Vec<f64>),
+    /// One `CaseResult` per `config.num_generated_tests`, in order, each tested expression
+    /// followed by one per sweep point (see `FuzzCase::point`).
+    Ran {
+        inputs: Vec<f64>,
+        /// The campaign's oracles, before `FuzzingOracles::specialize`.
+        oracles: FuzzingOracles,
+        cases: Vec<CaseResult>,
+    },
+}
+
+/// An expression that was handed to the harness.
+pub struct FuzzCase {
+    pub expr: SimpleExpr,
+    /// The inputs it was evaluated at, one per variable.
+    pub inputs: Vec<f64>,
+    /// The oracles specialized for `expr`, as run.
+    pub oracles: FuzzingOracles,
+    /// 0 for the decoded point, then 1.. for the points of `config.inputs.sweep`, then the
+    /// out-of-domain probes of `FuzzingOracles::probe_domain`.
+    pub point: usize,
+}
+
+pub enum CaseResult {
+    /// The bytes did not make an expression.
+    NotGenerated(ArbitraryError),
+    /// Constant, or without variables: nothing to differentiate. Also an expression with more
+    /// variables than inputs were decoded, see `harness::ExprGenerator`.
+    Skipped(GeneratedExpr),
+    /// `config.inputs.synthesize` found no inputs the expression is defined at.
+    Unsatisfiable(GeneratedExpr),
+    /// The harness could not run it, e.g. `FuzzError::Timeout`.
+    Error(FuzzCase, FuzzError),
+    Tested(FuzzCase, Box<TestOutcome>),
+    /// Not generated: `config.max_iteration` ran out on the cases before.
+    OverBudget,
+}
+
+impl IterationOutcome {
+    /// No case failed (rejected and skipped inputs pass).
+    pub fn passed(&self) -> bool {
+        match self {
+            IterationOutcome::Ran { cases, .. } => cases.iter().all(|case| match case {
+                CaseResult::Tested(_, outcome) => outcome.passed(),
+                _ => true,
+            }),
+            _ => true,
+        }
+    }
+}
+
+/// Runs one fuzz input through the `Harness` of `config` (see `HarnessBuilder` for the
+/// defaults), against the `DefaultGroundTruth` (PyTorch with the `pytorch` feature), every case
+/// whatever `config.mode`. The ground truths are memoized for the whole process, in a cache of
+/// `config.gt_cache_size` entries (the size of the first call's config). Only fails if the
+/// harness can't be built from `config`.
+pub fn run_fuzz_iteration(data: &[u8], config: &FuzzConfig) -> Result<IterationOutcome, FuzzError> {
+    static REFEREE: OnceLock<[Box<dyn GroundTruthCalculator>; 1]> = OnceLock::new();
+    let referee = REFEREE.get_or_init(|| [CachedGroundTruth::new(DefaultGroundTruth::default(), config.gt_cache_size).boxed()]);
+    let harness = Harness::builder()
+        .config(config.clone())
+        .ground_truths(referee.to_vec())
+        .mode(HarnessMode::Continuous)
+        .build()?;
+    Ok(harness.run(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gt_calculators::DualNumberCalculator;
+    use crate::input_decoder::{PointSweep, SweepMode};
+    use crate::oracles::OracleSelection;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_fuzz_iteration() {
+        let mut config = FuzzConfig { num_generated_tests: 4, ..FuzzConfig::default() };
+        config.inputs.decoder = InputDecoding::General;
+        config.inputs.layout = InputLayout::Prefix;
+        let run = |data: &[u8], config: &FuzzConfig| Harness::builder()
+            .config(config.clone())
+            .ground_truths(vec![DualNumberCalculator.boxed()])
+            .mode(HarnessMode::Continuous)
+            .build()
+            .unwrap()
+            .run(data);
+        let too_short = run(&[0u8; 3], &config);
+        assert!(matches!(too_short, IterationOutcome::TooShort));
+
+        // Every input window decodes to 0x3f3f3f3f3f3f3f3f, about 0.49
+        let mut data = vec![0x3fu8; 16];
+        data.extend((0..200u8).map(|b| b.wrapping_mul(37)));
+        match run(&data, &config) {
+            IterationOutcome::Ran { inputs, cases, .. } => {
+                assert_eq!(inputs, vec![f64::from_bits(0x3f3f3f3f3f3f3f3f); 2]);
+                assert_eq!(cases.len(), 4);
+            }
+            _ => panic!("inputs should decode and be accepted"),
+        }
+
+        // The same inputs and AST bytes behind a header asking for two slots
+        let mut header_config = config.clone();
+        header_config.inputs.layout = InputLayout::Header;
+        assert!(matches!(run(&[], &header_config), IterationOutcome::TooShort));
+        let with_header: Vec<u8> = std::iter::once(2).chain(data.iter().copied()).collect();
+        match (run(&with_header, &header_config), run(&data, &config)) {
+            (IterationOutcome::Ran { inputs, cases, .. }, IterationOutcome::Ran { inputs: prefix_inputs, cases: prefix_cases, .. }) => {
+                assert_eq!(inputs, prefix_inputs);
+                assert_eq!(cases.len(), prefix_cases.len());
+            }
+            _ => panic!("inputs should decode and be accepted"),
+        }
+
+        // Every accepted grid point is a case of its own, after the decoded one
+        let mut sweep_config = config.clone();
+        sweep_config.inputs.sweep = PointSweep { mode: SweepMode::Grid(2), spread: 2.0 };
+        match run(&data, &sweep_config) {
+            IterationOutcome::Ran { cases, .. } => {
+                let points: Vec<usize> = cases
+                    .iter()
+                    .filter_map(|c| match c {
+                        CaseResult::Tested(case, _) | CaseResult::Error(case, _) => Some(case.point),
+                        _ => None,
+                    })
+                    .collect();
+                // 2 steps per input: 2 or 4 points after each decoded one
+                assert!(points.windows(2).all(|w| w[1] == w[0] + 1 || w[1] == 0));
+                assert!(points.iter().all(|&p| p <= 4) && points.contains(&4));
+            }
+            _ => panic!("inputs should decode and be accepted"),
+        }
+
+        // x_0 = 0 is rejected when decoding comes first, synthesized away by default
+        let mut zeros = vec![0u8; 16];
+        zeros.extend(&data[16..]);
+        let mut decode_first = config.clone();
+        decode_first.inputs.synthesize = false;
+        assert!(matches!(run(&zeros, &decode_first), IterationOutcome::InputsRejected(_)));
+        let synth_config = config.clone();
+        match run(&zeros, &synth_config) {
+            IterationOutcome::Ran { cases, .. } => {
+                for case in cases {
+                    if let CaseResult::Tested(case, _) = case {
+                        assert!(synth_config.inputs.accepts(&case.inputs));
+                    }
+                }
+            }
+            _ => panic!("synthesized inputs are never rejected"),
+        }
+
+        // Perturbation pairs: twice the bytes, the same number of inputs, and a secant verdict
+        let mut pair_config = config.clone();
+        pair_config.inputs.perturbation_step = Some(1e-7);
+        pair_config.oracle_selection = OracleSelection::SECANT;
+        let mut pair_data = vec![0x3fu8; 16];
+        pair_data.extend(&data);
+        match run(&pair_data, &pair_config) {
+            IterationOutcome::Ran { inputs, cases, .. } => {
+                assert_eq!(inputs.len(), 2);
+                for case in cases {
+                    if let CaseResult::Tested(_, outcome) = case {
+                        assert!(outcome.verdicts.iter().any(|v| v.oracle == "secant" && v.result.is_ok()));
+                    }
+                }
+            }
+            _ => panic!("inputs should decode and be accepted"),
+        }
+
+        let config = FuzzConfig { max_iteration: Some(Duration::from_nanos(1)), ..config };
+        match run(&data, &config) {
+            IterationOutcome::Ran { cases, .. } => assert!(cases.iter().all(|c| matches!(c, CaseResult::OverBudget))),
+            _ => panic!("inputs should decode and be accepted"),
+        }
+    }
+}
@@ -0,0 +1,628 @@
+// src/fuzz_config.rs
+
+// Campaign settings shared by the fuzz targets, read from a TOML file (`fuzz_config.toml`):
+//
+//     mode = "continuous"          # or "panic" (default)
+//     tests = 4                    # expressions per fuzz input
+//     oracles = "rev_fwd,fwd_gt"   # FUZZ_ORACLE syntax
+//     oracle_config = "oracles.toml"
+//     report_dir = "reports"       # JSON report per failure, see `FailureReport`
+//     eval_timeout_ms = 2000       # per evaluation stage, see `fuzz_harness::with_timeout`
+//     max_iteration_millis = 5000  # per fuzz input, see `fuzz_harness::set_iteration_budget`
+//     tape_budget_nodes = 100000   # Reverse AD tape limits, see `tape_stats::TapeBudget`
+//     tape_budget_bytes = 67108864
+//     checkpoint = "campaign.ckpt" # Continuous mode state, resumed from if it exists
+//     checkpoint_interval_s = 60
+//     notify_command = "..."       # run / POSTed to on each new failure bucket, see `notifier`
+//     notify_url = "https://..."
+//     printers = "infix,sexpr,ssa" # crash report formats, see `ast_evaluator::printer`
+//
+//     [ast]                        # AstGenConfig fields
+//     max_depth = 4
+//     allow_log = true
+//     coverage_bias = true         # steer generation to uncovered operator pairs, see `expr_coverage`
+//
+//     [inputs]
+//     max_abs_first = 1e10         # |x_0| bound
+//     max_abs = 100.0              # |x_i| bound for the other inputs
+//     allow_non_positive = false   # keep x_0 <= 0 (domain oracle)
+//     domains = [[1e-300, 10.0]]   # per variable: x_0 in [1e-300, 10], see `InputDomain::domains`
+//     layout = "header"            # or "prefix", see `FuzzInputSplitter`
+//     min_magnitude = 1e-6         # log-uniform range of the "sanitized" decoder
+//     max_magnitude = 1e6
+//     dyadic_max_numerator = 64    # k / 2^n range of the "dyadic" decoder
+//     dyadic_max_exponent = 8
+//     special_point_rate = 0.1     # chance per input of a special point, see `inject_special_points`
+//     sweep = "random:8"           # or "grid:3", more points per expression, see `PointSweep`
+//     sweep_spread = 2.0
+//     synthesize = false           # decode and reject instead of picking inputs per expression
+//     perturbation_step = 1e-7     # decode x and x + h for the secant oracle, see `PerturbedInputDecoder`
+//
+//     [tolerances]                 # every oracle, like ORACLE_ABS_TOL / ORACLE_REL_TOL / ORACLE_ULPS
+//     abs_tol = 1e-12
+//     rel_tol = 1e-9
+//
+// Environment variables override the file (see README).
+
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::ast_evaluator::{printer, PRINTER_NAMES};
+use crate::ast_generator::AstGenConfig;
+use crate::error::FuzzError;
+use crate::fuzz_harness::HarnessMode;
+use crate::input_decoder::{InputDecoding, InputLayout, PointSweep};
+use crate::oracles::{FuzzingOracles, OracleConfig, OracleSelection, ToleranceOverride};
+use crate::tape_stats::TapeBudget;
+
+/// File read by `FuzzConfig::load` when `FUZZ_CONFIG` is not set.
+pub const DEFAULT_CONFIG_FILE: &str = "fuzz_config.toml";
+
+/// Which decoded inputs are worth a test case; the rest are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputDomain {
+    pub max_abs_first: f64,
+    pub max_abs: f64,
+    /// Keep non-positive `x_0`, so the domain oracle sees log/sqrt of negatives.
+    pub allow_non_positive: bool,
+    /// Interval of each variable, in place of the bounds above: the general decoder maps into
+    /// it, and `accepts` checks it. Variables past the end use the bounds above.
+    pub domains: Vec<RangeInclusive<f64>>,
+    /// How the fuzzer bytes become inputs, see `fuzz_case::decode_inputs`.
+    pub decoder: InputDecoding,
+    /// Which of the fuzzer bytes are inputs, see `FuzzInputSplitter`.
+    pub layout: InputLayout,
+    /// Magnitude range of `SanitizedInputDecoder`, within the bounds above.
+    pub min_magnitude: f64,
+    pub max_magnitude: f64,
+    /// Let `SanitizedInputDecoder` substitute `NICE_VALUES` now and then.
+    pub special_values: bool,
+    /// Range of `DyadicInputDecoder`: `k / 2^n` with `k <= dyadic_max_numerator`,
+    /// `n <= dyadic_max_exponent`.
+    pub dyadic_max_numerator: u32,
+    pub dyadic_max_exponent: u32,
+    /// Chance that an input of a test case is replaced by a special point or one of the
+    /// expression's constants, see `input_decoder::inject_special_points`.
+    pub special_point_rate: f64,
+    /// Points around the decoded one each expression is also tested at.
+    pub sweep: PointSweep,
+    /// Generate each expression first and synthesize its inputs from the decoded ones, see
+    /// `InputSynthesizer` (default). `false` decodes first and rejects inputs outside the domain.
+    pub synthesize: bool,
+    /// Decode perturbation pairs with this relative step for the `secant` oracle, see
+    /// `PerturbedInputDecoder`.
+    pub perturbation_step: Option<f64>,
+}
+
+impl Default for InputDomain {
+    fn default() -> Self {
+        InputDomain {
+            max_abs_first: 1e10,
+            max_abs: 100.0,
+            allow_non_positive: false,
+            domains: Vec::new(),
+            decoder: InputDecoding::Sanitized,
+            layout: InputLayout::Header,
+            min_magnitude: 1e-6,
+            max_magnitude: 1e6,
+            special_values: false,
+            dyadic_max_numerator: 64,
+            dyadic_max_exponent: 8,
+            special_point_rate: 0.0,
+            sweep: PointSweep::default(),
+            synthesize: true,
+            perturbation_step: None,
+        }
+    }
+}
+
+impl InputDomain {
+    /// Interval of input `i` as `accepts` checks it: `domains[i]`, else the bounds above
+    /// (`x_0 > 0` as `[f64::MIN_POSITIVE, max_abs_first]`).
+    pub fn interval(&self, i: usize) -> RangeInclusive<f64> {
+        match self.domains.get(i) {
+            Some(domain) => domain.clone(),
+            None if i == 0 && !self.allow_non_positive => f64::MIN_POSITIVE..=self.max_abs_first,
+            None if i == 0 => -self.max_abs_first..=self.max_abs_first,
+            None => -self.max_abs..=self.max_abs,
+        }
+    }
+
+    pub fn accepts(&self, inputs: &[f64]) -> bool {
+        if !inputs.iter().all(|x| x.is_finite()) {
+            return false;
+        }
+        inputs.iter().enumerate().all(|(i, x)| match self.domains.get(i) {
+            Some(domain) => domain.contains(x),
+            None if i == 0 => (*x > 0.0 || self.allow_non_positive) && x.abs() <= self.max_abs_first,
+            None => x.abs() <= self.max_abs,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub mode: HarnessMode,
+    pub num_generated_tests: usize,
+    pub oracle_selection: OracleSelection,
+    pub ast: AstGenConfig,
+    /// Seed for the RPN generator of `fuzz_target_1` (`test_generator::RpnGenConfig::seed`):
+    /// every fuzzer input is then tested on the same functions. From entropy if unset.
+    pub rpn_seed: Option<u64>,
+    pub inputs: InputDomain,
+    pub tolerances: ToleranceOverride,
+    /// `oracles.toml` applied after the tolerances, see `OracleConfig`.
+    pub oracle_config: Option<PathBuf>,
+    /// Directory for JSON failure reports; none are written if unset.
+    pub report_dir: Option<PathBuf>,
+    /// Wall-clock limit for each evaluation stage; the fuzz targets pass it to
+    /// `fuzz_harness::set_eval_timeout`.
+    pub eval_timeout: Option<Duration>,
+    /// Wall-clock limit for a whole fuzz input (every generated test and ground truth), see
+    /// `fuzz_harness::set_iteration_budget`. Tests left when it runs out are not run.
+    pub max_iteration: Option<Duration>,
+    /// Entries of the memoized PyTorch ground truth `run_fuzz_iteration` uses, see
+    /// `gt_calculators::CachedGroundTruth`; 0 disables it.
+    pub gt_cache_size: usize,
+    /// Limits on the Reverse AD tape of each test case; the fuzz targets pass it to
+    /// `tape_stats::set_tape_budget`.
+    pub tape_budget: TapeBudget,
+    /// Continuous mode: the campaign state is saved here every `checkpoint_interval` and on
+    /// exit, and loaded back when a campaign starts, see `CampaignReport::save_checkpoint`.
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+    /// Shell command run for each new failure bucket, see `notifier::notify`.
+    pub notify_command: Option<String>,
+    /// Webhook the notice of each new failure bucket is POSTed to.
+    pub notify_url: Option<String>,
+    /// Formats the crash report prints the expression in, registry names of `printer`.
+    pub printers: Vec<String>,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        FuzzConfig {
+            mode: HarnessMode::PanicOnFirstError,
+            num_generated_tests: 1,
+            oracle_selection: OracleSelection::ALL,
+            ast: AstGenConfig { max_depth: 4, ..AstGenConfig::default() },
+            rpn_seed: None,
+            inputs: InputDomain::default(),
+            tolerances: ToleranceOverride::default(),
+            oracle_config: None,
+            report_dir: None,
+            eval_timeout: None,
+            max_iteration: None,
+            gt_cache_size: 4096,
+            tape_budget: TapeBudget::default(),
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(60),
+            notify_command: None,
+            notify_url: None,
+            printers: ["infix", "sexpr", "ssa"].map(String::from).to_vec(),
+        }
+    }
+}
+
+fn invalid(msg: String) -> FuzzError {
+    FuzzError::InvalidConfig(msg)
+}
+
+/// 0 disables the timeout.
+fn timeout_from_ms(ms: u64) -> Option<Duration> {
+    if ms == 0 { None } else { Some(Duration::from_millis(ms)) }
+}
+
+/// 0 disables the limit.
+fn limit(n: usize) -> Option<usize> {
+    if n == 0 { None } else { Some(n) }
+}
+
+fn parse_mode(s: &str) -> Option<HarnessMode> {
+    if s.eq_ignore_ascii_case("continuous") {
+        Some(HarnessMode::Continuous)
+    } else if s.eq_ignore_ascii_case("panic") {
+        Some(HarnessMode::PanicOnFirstError)
+    } else {
+        None
+    }
+}
+
+fn as_table<'a>(item: &'a Item, ctx: &str) -> Result<&'a Table, FuzzError> {
+    item.as_table().ok_or_else(|| invalid(format!("'{}' must be a table", ctx)))
+}
+
+fn as_float(item: &Item, ctx: &str) -> Result<f64, FuzzError> {
+    item.as_float()
+        .or_else(|| item.as_integer().map(|i| i as f64))
+        .ok_or_else(|| invalid(format!("{} must be a number", ctx)))
+}
+
+fn as_usize(item: &Item, ctx: &str) -> Result<usize, FuzzError> {
+    match item.as_integer() {
+        Some(i) if i >= 0 => Ok(i as usize),
+        _ => Err(invalid(format!("{} must be a non-negative integer", ctx))),
+    }
+}
+
+fn as_u32(item: &Item, ctx: &str) -> Result<u32, FuzzError> {
+    u32::try_from(as_usize(item, ctx)?).map_err(|_| invalid(format!("{} is too large", ctx)))
+}
+
+fn as_rate(item: &Item, ctx: &str) -> Result<f64, FuzzError> {
+    match as_float(item, ctx)? {
+        rate if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => Err(invalid(format!("{} must be between 0 and 1", ctx))),
+    }
+}
+
+/// 0 turns perturbation pairs off.
+fn perturbation_step(step: f64) -> Option<f64> {
+    if step == 0.0 { None } else { Some(step) }
+}
+
+/// A finite factor of at least 1.
+fn as_spread(item: &Item, ctx: &str) -> Result<f64, FuzzError> {
+    match as_float(item, ctx)? {
+        spread if spread.is_finite() && spread >= 1.0 => Ok(spread),
+        _ => Err(invalid(format!("{} must be a finite number >= 1", ctx))),
+    }
+}
+
+/// `[[lo, hi], ...]`, finite with `lo <= hi`.
+fn as_domains(item: &Item, ctx: &str) -> Result<Vec<RangeInclusive<f64>>, FuzzError> {
+    let err = || invalid(format!("{} must be a list of [lo, hi] pairs with finite lo <= hi", ctx));
+    let array = item.as_array().ok_or_else(err)?;
+    array.iter().map(|pair| {
+        let pair = pair.as_array().filter(|p| p.len() == 2).ok_or_else(err)?;
+        let bound = |i: usize| pair.get(i).and_then(|v| v.as_float().or_else(|| v.as_integer().map(|n| n as f64)));
+        match (bound(0), bound(1)) {
+            (Some(lo), Some(hi)) if lo.is_finite() && hi.is_finite() && lo <= hi => Ok(lo..=hi),
+            _ => Err(err()),
+        }
+    }).collect()
+}
+
+/// `FUZZ_INPUT_DOMAINS` syntax: `lo..hi` per variable, comma-separated.
+fn parse_domains(s: &str) -> Option<Vec<RangeInclusive<f64>>> {
+    s.split(',').map(|range| {
+        let (lo, hi) = range.trim().split_once("..")?;
+        let (lo, hi): (f64, f64) = (lo.trim().parse().ok()?, hi.trim().parse().ok()?);
+        (lo.is_finite() && hi.is_finite() && lo <= hi).then_some(lo..=hi)
+    }).collect()
+}
+
+/// Comma-separated printer names, each known to `printer`.
+fn parse_printers(s: &str) -> Result<Vec<String>, FuzzError> {
+    s.split(',').map(str::trim).filter(|name| !name.is_empty()).map(|name| match printer::<()>(name) {
+        Some(_) => Ok(name.to_string()),
+        None => Err(invalid(format!("unknown printer '{}', expected one of {}", name, PRINTER_NAMES.join(", ")))),
+    }).collect()
+}
+
+fn as_bool(item: &Item, ctx: &str) -> Result<bool, FuzzError> {
+    item.as_bool().ok_or_else(|| invalid(format!("{} must be a boolean", ctx)))
+}
+
+fn as_str<'a>(item: &'a Item, ctx: &str) -> Result<&'a str, FuzzError> {
+    item.as_str().ok_or_else(|| invalid(format!("{} must be a string", ctx)))
+}
+
+/// Environment variable `name`, parsed. Unset is `None`; unparsable is an error.
+fn read_env<T>(name: &str, parse: impl Fn(&str) -> Option<T>) -> Result<Option<T>, FuzzError> {
+    match env::var(name) {
+        Ok(val) => parse(&val).map(Some).ok_or_else(|| invalid(format!("{}={} is not valid", name, val))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// `true`/`false`, `1`/`0` or `yes`/`no`; anything else is an error, not a silent `false`.
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+impl FuzzConfig {
+    /// `FUZZ_CONFIG` (or `fuzz_config.toml` if it exists), then the environment overrides.
+    pub fn load() -> Result<Self, FuzzError> {
+        match env::var("FUZZ_CONFIG") {
+            Ok(path) => Self::from_file(path),
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => Self::from_file(DEFAULT_CONFIG_FILE),
+            Err(_) => Self::from_env(),
+        }
+    }
+
+    /// Settings from `path`, then the environment overrides.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, FuzzError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text)?.with_env_overrides()
+    }
+
+    /// Defaults, then the environment overrides.
+    pub fn from_env() -> Result<Self, FuzzError> {
+        Self::default().with_env_overrides()
+    }
+
+    /// Settings from the text of a config file, without environment overrides.
+    pub fn parse(text: &str) -> Result<Self, FuzzError> {
+        let doc: DocumentMut = text.parse().map_err(|e| invalid(format!("{}", e)))?;
+        let mut config = FuzzConfig::default();
+
+        for (key, item) in doc.iter() {
+            match key {
+                "mode" => {
+                    config.mode = parse_mode(as_str(item, key)?)
+                        .ok_or_else(|| invalid("mode must be \"panic\" or \"continuous\"".to_string()))?;
+                }
+                "tests" => config.num_generated_tests = as_usize(item, key)?,
+                "rpn_seed" => config.rpn_seed = Some(as_usize(item, key)? as u64),
+                "oracles" => {
+                    config.oracle_selection = as_str(item, key)?.parse()
+                        .map_err(|e| invalid(format!("oracles: {}", e)))?;
+                }
+                "oracle_config" => config.oracle_config = Some(PathBuf::from(as_str(item, key)?)),
+                "report_dir" => config.report_dir = Some(PathBuf::from(as_str(item, key)?)),
+                "eval_timeout_ms" => config.eval_timeout = timeout_from_ms(as_usize(item, key)? as u64),
+                "max_iteration_millis" => config.max_iteration = timeout_from_ms(as_usize(item, key)? as u64),
+                "gt_cache_size" => config.gt_cache_size = as_usize(item, key)?,
+                "tape_budget_nodes" => config.tape_budget.max_nodes = limit(as_usize(item, key)?),
+                "tape_budget_bytes" => config.tape_budget.max_bytes = limit(as_usize(item, key)?),
+                "checkpoint" => config.checkpoint = Some(PathBuf::from(as_str(item, key)?)),
+                "checkpoint_interval_s" => config.checkpoint_interval = Duration::from_secs(as_usize(item, key)? as u64),
+                "notify_command" => config.notify_command = Some(as_str(item, key)?.to_string()),
+                "notify_url" => config.notify_url = Some(as_str(item, key)?.to_string()),
+                "printers" => config.printers = parse_printers(as_str(item, key)?)?,
+                "ast" => {
+                    let ast = &mut config.ast;
+                    for (k, v) in as_table(item, key)?.iter() {
+                        let ctx = format!("[ast] {}", k);
+                        match k {
+                            "max_depth" => ast.max_depth = as_usize(v, &ctx)?,
+                            "max_variables" => ast.max_variables = as_usize(v, &ctx)?,
+                            "allow_division" => ast.allow_division = as_bool(v, &ctx)?,
+                            "allow_power" => ast.allow_power = as_bool(v, &ctx)?,
+                            "allow_log" => ast.allow_log = as_bool(v, &ctx)?,
+                            "allow_cast" => ast.allow_cast = as_bool(v, &ctx)?,
+                            "coverage_bias" => ast.coverage_bias = as_bool(v, &ctx)?,
+                            "grammar" => ast.grammar = as_str(v, &ctx)?.parse().map_err(|e| invalid(format!("{}: {}", ctx, e)))?,
+                            _ => return Err(invalid(format!("unknown key '{}' in [ast]", k))),
+                        }
+                    }
+                }
+                "inputs" => {
+                    let inputs = &mut config.inputs;
+                    for (k, v) in as_table(item, key)?.iter() {
+                        let ctx = format!("[inputs] {}", k);
+                        match k {
+                            "max_abs_first" => inputs.max_abs_first = as_float(v, &ctx)?,
+                            "max_abs" => inputs.max_abs = as_float(v, &ctx)?,
+                            "allow_non_positive" => inputs.allow_non_positive = as_bool(v, &ctx)?,
+                            "domains" => inputs.domains = as_domains(v, &ctx)?,
+                            "decoder" => inputs.decoder = as_str(v, &ctx)?.parse().map_err(|e| invalid(format!("{}: {}", ctx, e)))?,
+                            "layout" => inputs.layout = as_str(v, &ctx)?.parse().map_err(|e| invalid(format!("{}: {}", ctx, e)))?,
+                            "min_magnitude" => inputs.min_magnitude = as_float(v, &ctx)?,
+                            "max_magnitude" => inputs.max_magnitude = as_float(v, &ctx)?,
+                            "special_values" => inputs.special_values = as_bool(v, &ctx)?,
+                            "dyadic_max_numerator" => inputs.dyadic_max_numerator = as_u32(v, &ctx)?,
+                            "dyadic_max_exponent" => inputs.dyadic_max_exponent = as_u32(v, &ctx)?,
+                            "special_point_rate" => inputs.special_point_rate = as_rate(v, &ctx)?,
+                            "sweep" => inputs.sweep.mode = as_str(v, &ctx)?.parse().map_err(|e| invalid(format!("{}: {}", ctx, e)))?,
+                            "sweep_spread" => inputs.sweep.spread = as_spread(v, &ctx)?,
+                            "synthesize" => inputs.synthesize = as_bool(v, &ctx)?,
+                            "perturbation_step" => {
+                                inputs.perturbation_step = match as_float(v, &ctx)? {
+                                    step if step.is_finite() && step >= 0.0 => perturbation_step(step),
+                                    _ => return Err(invalid(format!("{} must be a finite number >= 0", ctx))),
+                                };
+                            }
+                            _ => return Err(invalid(format!("unknown key '{}' in [inputs]", k))),
+                        }
+                    }
+                }
+                "tolerances" => {
+                    let tolerances = &mut config.tolerances;
+                    for (k, v) in as_table(item, key)?.iter() {
+                        let ctx = format!("[tolerances] {}", k);
+                        match k {
+                            "abs_tol" => tolerances.abs_tol = Some(as_float(v, &ctx)?),
+                            "rel_tol" => tolerances.rel_tol = Some(as_float(v, &ctx)?),
+                            "ulps" => tolerances.ulps = Some(as_usize(v, &ctx)? as u64),
+                            _ => return Err(invalid(format!("unknown key '{}' in [tolerances]", k))),
+                        }
+                    }
+                }
+                _ => return Err(invalid(format!("unknown key '{}'", key))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Applies `FUZZ_MODE`, `FUZZ_TESTS`, `FUZZ_RPN_SEED`, `FUZZ_ORACLE`, `AST_*`, `FUZZ_DOMAIN_INPUTS`,
+    /// `FUZZ_INPUT_DOMAINS`, `FUZZ_INPUT_DECODER`, `FUZZ_INPUT_LAYOUT`, `FUZZ_SPECIAL_POINT_RATE`,
+    /// `FUZZ_SWEEP`, `FUZZ_SWEEP_SPREAD`, `FUZZ_PERTURBATION_STEP`, `FUZZ_SYNTHESIZE_INPUTS`, `ORACLE_CONFIG`, `FUZZ_REPORT_DIR`,
+    /// `FUZZ_EVAL_TIMEOUT_MS`, `FUZZ_MAX_ITERATION_MILLIS`, `FUZZ_GT_CACHE_SIZE`, `FUZZ_TAPE_BUDGET_NODES`,
+    /// `FUZZ_TAPE_BUDGET_BYTES`, `FUZZ_CHECKPOINT`,
+    /// `FUZZ_CHECKPOINT_INTERVAL_S`, `FUZZ_NOTIFY_COMMAND`, `FUZZ_NOTIFY_URL` and `FUZZ_PRINTERS`. The `ORACLE_*` tolerance variables are applied by `oracles()`.
+    pub fn with_env_overrides(mut self) -> Result<Self, FuzzError> {
+        if let Some(mode) = read_env("FUZZ_MODE", parse_mode)? {
+            self.mode = mode;
+        }
+        if let Some(tests) = read_env("FUZZ_TESTS", |s| s.parse().ok())? {
+            self.num_generated_tests = tests;
+        }
+        if let Some(seed) = read_env("FUZZ_RPN_SEED", |s| s.parse().ok())? {
+            self.rpn_seed = Some(seed);
+        }
+        // Typos are rejected up front instead of silently running nothing
+        if let Ok(val) = env::var("FUZZ_ORACLE") {
+            self.oracle_selection = val.parse()
+                .map_err(|e| invalid(format!("FUZZ_ORACLE: {}", e)))?;
+        }
+
+        let ast = &mut self.ast;
+        if let Some(depth) = read_env("AST_MAX_DEPTH", |s| s.parse().ok())? {
+            ast.max_depth = depth;
+        }
+        if let Some(vars) = read_env("AST_MAX_VARIABLES", |s| s.parse().ok())? {
+            ast.max_variables = vars;
+        }
+        if let Ok(val) = env::var("AST_GRAMMAR") {
+            ast.grammar = val.parse().map_err(|e| invalid(format!("AST_GRAMMAR: {}", e)))?;
+        }
+        let flags: [(&str, &mut bool); 5] = [
+            ("AST_ALLOW_DIVISION", &mut ast.allow_division),
+            ("AST_ALLOW_POWER", &mut ast.allow_power),
+            ("AST_ALLOW_LOG", &mut ast.allow_log),
+            ("AST_ALLOW_CAST", &mut ast.allow_cast),
+            ("AST_COVERAGE_BIAS", &mut ast.coverage_bias),
+        ];
+        for (name, flag) in flags {
+            if let Some(val) = read_env(name, parse_bool)? {
+                *flag = val;
+            }
+        }
+
+        if let Some(val) = read_env("FUZZ_DOMAIN_INPUTS", parse_bool)? {
+            self.inputs.allow_non_positive = val;
+        }
+        if let Some(domains) = read_env("FUZZ_INPUT_DOMAINS", parse_domains)? {
+            self.inputs.domains = domains;
+        }
+        if let Ok(val) = env::var("FUZZ_INPUT_DECODER") {
+            self.inputs.decoder = val.parse().map_err(|e| invalid(format!("FUZZ_INPUT_DECODER: {}", e)))?;
+        }
+        if let Ok(val) = env::var("FUZZ_INPUT_LAYOUT") {
+            self.inputs.layout = val.parse().map_err(|e| invalid(format!("FUZZ_INPUT_LAYOUT: {}", e)))?;
+        }
+        if let Some(rate) = read_env("FUZZ_SPECIAL_POINT_RATE", |s| s.parse().ok().filter(|r| (0.0..=1.0).contains(r)))? {
+            self.inputs.special_point_rate = rate;
+        }
+        if let Ok(val) = env::var("FUZZ_SWEEP") {
+            self.inputs.sweep.mode = val.parse().map_err(|e| invalid(format!("FUZZ_SWEEP: {}", e)))?;
+        }
+        if let Some(spread) = read_env("FUZZ_SWEEP_SPREAD", |s| s.parse().ok().filter(|s: &f64| s.is_finite() && *s >= 1.0))? {
+            self.inputs.sweep.spread = spread;
+        }
+        if let Some(val) = read_env("FUZZ_SYNTHESIZE_INPUTS", parse_bool)? {
+            self.inputs.synthesize = val;
+        }
+        if let Some(step) = read_env("FUZZ_PERTURBATION_STEP", |s| s.parse().ok().filter(|s: &f64| s.is_finite() && *s >= 0.0))? {
+            self.inputs.perturbation_step = perturbation_step(step);
+        }
+        if let Ok(path) = env::var("ORACLE_CONFIG") {
+            self.oracle_config = Some(PathBuf::from(path));
+        }
+        if let Ok(dir) = env::var("FUZZ_REPORT_DIR") {
+            self.report_dir = Some(PathBuf::from(dir));
+        }
+        if let Some(ms) = read_env("FUZZ_EVAL_TIMEOUT_MS", |s| s.parse().ok())? {
+            self.eval_timeout = timeout_from_ms(ms);
+        }
+        if let Some(ms) = read_env("FUZZ_MAX_ITERATION_MILLIS", |s| s.parse().ok())? {
+            self.max_iteration = timeout_from_ms(ms);
+        }
+        if let Some(n) = read_env("FUZZ_GT_CACHE_SIZE", |s| s.parse().ok())? {
+            self.gt_cache_size = n;
+        }
+        if let Some(n) = read_env("FUZZ_TAPE_BUDGET_NODES", |s| s.parse().ok())? {
+            self.tape_budget.max_nodes = limit(n);
+        }
+        if let Some(n) = read_env("FUZZ_TAPE_BUDGET_BYTES", |s| s.parse().ok())? {
+            self.tape_budget.max_bytes = limit(n);
+        }
+        if let Ok(path) = env::var("FUZZ_CHECKPOINT") {
+            self.checkpoint = Some(PathBuf::from(path));
+        }
+        if let Some(secs) = read_env("FUZZ_CHECKPOINT_INTERVAL_S", |s| s.parse().ok())? {
+            self.checkpoint_interval = Duration::from_secs(secs);
+        }
+        if let Ok(command) = env::var("FUZZ_NOTIFY_COMMAND") {
+            self.notify_command = Some(command);
+        }
+        if let Ok(url) = env::var("FUZZ_NOTIFY_URL") {
+            self.notify_url = Some(url);
+        }
+        if let Ok(val) = env::var("FUZZ_PRINTERS") {
+            self.printers = parse_printers(&val).map_err(|e| invalid(format!("FUZZ_PRINTERS: {}", e)))?;
+        }
+        Ok(self)
+    }
+
+    /// Oracles for this campaign: selection and tolerances from the config, the `ORACLE_*`
+    /// environment variables on top, then `oracle_config` if set.
+    pub fn oracles(&self) -> Result<FuzzingOracles, FuzzError> {
+        let mut oracles = FuzzingOracles::builder()
+            .selection(self.oracle_selection)
+            .with_overrides(&self.tolerances)
+            .with_env_overrides()?
+            .build();
+        if let Some(path) = &self.oracle_config {
+            OracleConfig::load(path)?.apply(&mut oracles);
+        }
+        Ok(oracles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fuzz_config() {
+        let config = FuzzConfig::parse(r#"
+            mode = "continuous"
+            tests = 3
+            oracles = "rev_fwd,primal"
+            eval_timeout_ms = 250
+            tape_budget_nodes = 5000
+            checkpoint = "campaign.ckpt"
+            printers = "infix, latex"
+
+            [ast]
+            max_depth = 6
+            allow_log = true
+
+            [inputs]
+            allow_non_positive = true
+            domains = [[-1, 1]]
+
+            [tolerances]
+            rel_tol = 1e-6
+        "#).unwrap();
+
+        assert!(matches!(config.mode, HarnessMode::Continuous));
+        assert_eq!(config.num_generated_tests, 3);
+        assert_eq!(config.oracle_selection, OracleSelection::REV_FWD | OracleSelection::PRIMAL);
+        assert_eq!(config.ast.max_depth, 6);
+        assert!(config.ast.allow_log && config.ast.allow_division);
+        assert!(config.inputs.accepts(&[-1.0, 2.0]));
+        assert!(!config.inputs.accepts(&[1.0, 200.0]));
+        assert!(!config.inputs.accepts(&[-2.0, 2.0]));
+        assert_eq!(parse_domains("1e-300..10, -5..5"), Some(vec![1e-300..=10.0, -5.0..=5.0]));
+        assert!(FuzzConfig::parse("[inputs]\ndomains = [[2, 1]]").is_err());
+        assert_eq!(config.tolerances, ToleranceOverride { rel_tol: Some(1e-6), ..Default::default() });
+        assert_eq!(config.eval_timeout, Some(Duration::from_millis(250)));
+        assert_eq!(config.tape_budget, TapeBudget { max_nodes: Some(5000), max_bytes: None });
+        assert_eq!(config.checkpoint, Some(PathBuf::from("campaign.ckpt")));
+        assert_eq!(config.checkpoint_interval, Duration::from_secs(60));
+        assert_eq!(config.printers, ["infix", "latex"]);
+        assert!(FuzzConfig::parse("printers = \"infix,pdf\"").is_err());
+
+        assert_eq!(["1", "YES", "true"].map(parse_bool), [Some(true); 3]);
+        assert_eq!(["0", "no", "False"].map(parse_bool), [Some(false); 3]);
+        assert_eq!(parse_bool("ture"), None);
+
+        assert!(FuzzConfig::parse("[ast]\nmax_dpeth = 3").is_err());
+        assert!(FuzzConfig::parse("oracles = \"rev_fwd,typo\"").is_err());
+    }
+}
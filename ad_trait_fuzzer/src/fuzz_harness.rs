@@ -1,144 +1,790 @@
-// src/fuzz_harness.rs
-
-use ad_trait::AD;
-use ad_trait::function_engine::FunctionEngine;
-use ad_trait::differentiable_function::{ForwardAD, ReverseAD}; 
-use ad_trait::differentiable_function::DifferentiableFunctionTrait;
-use ad_trait::forward_ad::adfn::adfn;
-use ad_trait::reverse_ad::adr::adr;
-use core::slice::SlicePattern;
-use tch::Tensor; 
-use std::error::Error;
-
-use crate::oracles::{FuzzingOracles, EngineResults, GroundTruth};
-
-// --- CORE TRAITS (Defining the Interface for a Test Case) ---
-
-pub trait Calculator: Clone
-{
-    fn eval_expr<T: AD + PartialEq>(&self, _: &[T]) -> T;
-    fn num_inputs(&self) -> usize; 
-    fn num_outputs(&self) -> usize;
-}
-
-// The methods were likely missing in your local file causing E0407, ensure they are present.
-pub trait PyTorchComputable: Clone
-{
-    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, Box<dyn Error>>;
-    fn num_inputs(&self) -> usize;
-    fn num_outputs(&self) -> usize;
-}
-
-/// Defines the interface for calculating a derivative using an external oracle.
-pub trait GroundTruthCalculator {
-    fn name(&self) -> &'static str;
-    
-    fn calculate<G: Calculator + PyTorchComputable>(&self, calc: &G, inputs: &[f64]) -> Result<Vec<f64>, Box<dyn Error>>;
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum HarnessMode {
-    PanicOnFirstError,
-    Continuous,
-}
-
-#[derive(Debug, Clone)]
-pub struct FuzzConfig {
-    pub mode: HarnessMode,
-    pub num_generated_tests: usize,
-    pub oracle_selection: String,
-}
-
-// --- ADAPTER Struct (Connects Calculator to ad-trait) ---
-
-#[derive(Clone)]
-pub struct SimpleADFunction<T: AD, G: Calculator>
-{
-    placeholder : T,
-    expression: G
-}
-
-impl<T: AD, G: Calculator> DifferentiableFunctionTrait<T> for SimpleADFunction<T, G>
-{
-    const NAME: &'static str = "SimpleFunc";
-    fn call(&self, inputs: &[T], _freeze: bool) -> Vec<T>
-    {
-        vec![self.expression.eval_expr(inputs.as_slice())]
-    }
-
-    fn num_inputs(&self) -> usize { self.expression.num_inputs() }
-    fn num_outputs(&self) -> usize { self.expression.num_outputs() } 
-}
-
-impl<T: AD, G: Calculator> SimpleADFunction<T, G> {
-    pub fn to_other_ad_type<T2: AD>(&self) -> SimpleADFunction<T2, G> {
-        SimpleADFunction { placeholder: self.placeholder.to_other_ad_type::<T2>(),
-                           expression: self.expression.clone() }
-    }
-}
-
-// --- ORACLE DRIVER (The Engine) ---
-
-pub fn run_ad_tests<G: Calculator + PyTorchComputable + 'static, T: GroundTruthCalculator>(
-    inputs: &[f64],
-    calc: G,
-    oracles: &FuzzingOracles,
-    gt_calculators: &[T],
-    mode: HarnessMode, 
-) -> Result<(), Box<dyn Error>> {
-    // FIX E0034: Disambiguate the num_inputs call by specifying the trait.
-    if inputs.len() != PyTorchComputable::num_inputs(&calc) || inputs.len() < 1 {
-        print!("Input length mismatch: expected {}, got {}", PyTorchComputable::num_inputs(&calc), inputs.len());
-        println!("Exiting due to input error!!");
-        return Ok(());
-    }
-
-    // 1. Compute AD results
-    let func_standard = SimpleADFunction { placeholder: 0.0, expression: calc.clone() };
-
-    let func_rev_derivative = func_standard.to_other_ad_type::<adr>();
-    let rev_engine = FunctionEngine::new(func_standard.clone(), func_rev_derivative, ReverseAD::new());
-    let (_f_res_rev, reverse_jacobian) = rev_engine.derivative(&inputs); 
-
-    let func_fwd_derivative = func_standard.to_other_ad_type::<adfn<1>>();
-    let fwd_engine = FunctionEngine::new(func_standard.clone(), func_fwd_derivative, ForwardAD::new());
-    let (_f_res_fwd, forward_jacobian) = fwd_engine.derivative(&inputs); 
-
-    // 2. Compute ALL Ground Truths
-    let mut ground_truths = Vec::new();
-    for gt_calc in gt_calculators {
-        if let Ok(jacobian) = gt_calc.calculate(&calc, &inputs) {
-            ground_truths.push(GroundTruth { name: gt_calc.name(), jacobian });
-        }
-    }
-
-    // 3. Collect Engine Results
-    let engine_results = EngineResults {
-        inputs: inputs.to_vec(),
-        reverse: reverse_jacobian.into_iter().map(|d| (*d).into()).collect::<Vec<f64>>(), 
-        forward: forward_jacobian.into_iter().map(|d| (*d).into()).collect::<Vec<f64>>(), 
-    };
-
-    println!("Engine Results: {:?}", engine_results);
-    // 4. Run all Oracle Checks and return the result
-    oracles.check_all(&engine_results, &ground_truths, mode)
-}
-
-pub fn run_custom_test<G: Calculator + PyTorchComputable + 'static, T: GroundTruthCalculator>(
-    inputs: &[f64],
-    calc: G,
-    gt_calculators: &[T],
-) -> Result<(), Box<dyn Error>> {
-    use crate::oracles::FuzzingOracles;
-    
-    let oracles = FuzzingOracles::new("all".to_string());
-    let result = run_ad_tests(&inputs, calc, &oracles, gt_calculators, HarnessMode::PanicOnFirstError);
-    
-    // Print result regardless of pass/fail
-    match &result {
-        Ok(_) => println!("Test PASSED"),
-        Err(e) => println!("Test FAILED: {}", e),
-    }
-    
-    result
-}
+// src/fuzz_harness.rs
+
+use ad_trait::AD;
+use ad_trait::function_engine::FunctionEngine;
+use ad_trait::differentiable_function::{ForwardAD, ReverseAD}; 
+use ad_trait::differentiable_function::DifferentiableFunctionTrait;
+use ad_trait::forward_ad::adfn::adfn;
+use ad_trait::reverse_ad::adr::adr;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(feature = "pytorch")]
+use tch::Tensor; 
+
+use crate::ast_canonical::canonicalize;
+use crate::ast_evaluator::{Dual, MainBackend};
+use crate::ast_expr::SimpleExpr;
+use crate::ast_parser::parse_infix;
+use crate::error::FuzzError;
+use crate::oracles::{FuzzingOracles, EngineResults, EngineTiming, GroundTruth, NanPolicy, OracleSelection, OracleVerdict, Outcome, Severity};
+use crate::tape_stats::{measure_peak, tape_budget, tape_nodes, TapeStats};
+
+// --- CORE TRAITS (Defining the Interface for a Test Case) ---
+
+pub trait Calculator: Clone
+{
+    fn eval_expr<T: AD + PartialEq>(&self, _: &[T]) -> T;
+    fn num_inputs(&self) -> usize; 
+    fn num_outputs(&self) -> usize;
+
+    /// All `num_outputs()` outputs. Override for functions with more than one output;
+    /// `eval_expr` stays the first output.
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        vec![self.eval_expr(inputs)]
+    }
+
+    /// Evaluate with any `MainBackend` (e.g. the in-crate `Dual`), for referees outside ad_trait.
+    fn eval_backend<B: MainBackend>(&self, _inputs: &[B]) -> Result<B, FuzzError> {
+        Err(FuzzError::UnsupportedExpr("Calculator does not support generic backends".to_string()))
+    }
+
+    /// `other` computes the same function, so `run_ad_tests` may reuse the AD engines it built
+    /// for `self`, see `AdEngines`. The default never reuses them.
+    fn same_function(&self, _other: &Self) -> bool {
+        false
+    }
+
+    /// `self` with the variables `x_i` and `x_j` exchanged in its expression, for the permutation
+    /// oracle. `None`, the default, if there is no expression to rewrite.
+    fn relabeled(&self, _pair: (usize, usize)) -> Option<Self> {
+        None
+    }
+
+    /// `self` with the `index`-th constant of its expression (pre-order) replaced by `value`, for
+    /// the sensitivity oracle. `None`, the default, if there is no expression to rewrite.
+    fn with_constant(&self, _index: usize, _value: f64) -> Option<Self> {
+        None
+    }
+}
+
+/// What a ground truth sees of the function under test. Object safe, so that
+/// `GroundTruthCalculator::calculate` is too.
+pub trait PyTorchComputable
+{
+    #[cfg(feature = "pytorch")]
+    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, FuzzError>;
+    fn num_inputs(&self) -> usize;
+    fn num_outputs(&self) -> usize;
+
+    /// Infix source of the expression, for ground truths that evaluate it out of process.
+    fn infix_expr(&self) -> Option<String> { None }
+
+    /// The expression in canonical form (see `ast_canonical`), what `CachedGroundTruth` keys
+    /// on. By default `infix_expr` parsed and canonicalized on every call.
+    fn canonical_expr(&self) -> Option<Arc<SimpleExpr>> {
+        let (expr, _) = parse_infix(&self.infix_expr()?).ok()?;
+        Some(Arc::new(canonicalize(&expr)))
+    }
+
+    /// Evaluate with the in-crate `Dual` numbers, for `DualNumberCalculator`; usually
+    /// `Calculator::eval_backend`.
+    fn eval_dual(&self, _inputs: &[Dual]) -> Result<Dual, FuzzError> {
+        Err(FuzzError::UnsupportedExpr("Calculator does not support dual numbers".to_string()))
+    }
+}
+
+/// Defines the interface for calculating a derivative using an external oracle.
+///
+/// Object safe: the harness takes `&[Box<dyn GroundTruthCalculator>]`, so one run can mix
+/// calculators of different types (`boxed()` wraps one).
+pub trait GroundTruthCalculator: CloneGroundTruth + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError>;
+
+    fn boxed(self) -> Box<dyn GroundTruthCalculator> where Self: Sized + 'static {
+        Box::new(self)
+    }
+}
+
+/// `Clone` for boxed ground truths, so the harness can move a copy onto a timeout thread.
+/// Implemented for every calculator that is `Clone`.
+pub trait CloneGroundTruth {
+    fn clone_box(&self) -> Box<dyn GroundTruthCalculator>;
+}
+
+impl<T: GroundTruthCalculator + Clone + 'static> CloneGroundTruth for T {
+    fn clone_box(&self) -> Box<dyn GroundTruthCalculator> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn GroundTruthCalculator> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HarnessMode {
+    PanicOnFirstError,
+    Continuous,
+}
+
+pub use crate::fuzz_config::FuzzConfig;
+
+// --- ADAPTER Struct (Connects Calculator to ad-trait) ---
+
+#[derive(Clone)]
+pub struct SimpleADFunction<T: AD, G: Calculator>
+{
+    placeholder : T,
+    expression: G
+}
+
+impl<T: AD, G: Calculator> DifferentiableFunctionTrait<T> for SimpleADFunction<T, G>
+{
+    const NAME: &'static str = "SimpleFunc";
+    fn call(&self, inputs: &[T], _freeze: bool) -> Vec<T>
+    {
+        self.expression.eval_outputs(inputs)
+    }
+
+    fn num_inputs(&self) -> usize { self.expression.num_inputs() }
+    fn num_outputs(&self) -> usize { self.expression.num_outputs() } 
+}
+
+impl<T: AD, G: Calculator> SimpleADFunction<T, G> {
+    pub fn to_other_ad_type<T2: AD>(&self) -> SimpleADFunction<T2, G> {
+        SimpleADFunction { placeholder: self.placeholder.to_other_ad_type::<T2>(),
+                           expression: self.expression.clone() }
+    }
+}
+
+// --- PANIC ISOLATION ---
+
+thread_local! {
+    /// Inside `catch_backend_panic` on this thread.
+    static IN_BACKEND: Cell<bool> = const { Cell::new(false) };
+    /// Location of the last panic caught on this thread.
+    static PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// libFuzzer's panic hook aborts the process before `catch_unwind` can see the panic, so wrap
+/// whatever hook is installed and skip it for panics `catch_backend_panic` will handle.
+fn install_panic_filter() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_BACKEND.with(Cell::get) {
+                let location = info.location().map(|l| format!("{}:{}", l.file(), l.line()));
+                PANIC_LOCATION.with(|loc| *loc.borrow_mut() = location);
+            } else {
+                previous(info);
+            }
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string());
+    match PANIC_LOCATION.with(|loc| loc.borrow_mut().take()) {
+        Some(location) => format!("{} (at {})", message, location),
+        None => message,
+    }
+}
+
+/// Runs `f`, turning a panic into `FuzzError::BackendPanic` so it is reported like an oracle
+/// failure instead of aborting the fuzzer. Needs unwinding panics (the default profile).
+pub fn catch_backend_panic<R>(backend: &str, f: impl FnOnce() -> R) -> Result<R, FuzzError> {
+    install_panic_filter();
+    let outer = IN_BACKEND.with(|flag| flag.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    IN_BACKEND.with(|flag| flag.set(outer));
+    result.map_err(|payload| FuzzError::BackendPanic {
+        backend: backend.to_string(),
+        message: panic_message(payload.as_ref()),
+    })
+}
+
+// --- EVALUATION TIMEOUT ---
+
+/// Limit for each evaluation stage in milliseconds, 0 for none.
+static EVAL_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the wall-clock limit `run_ad_tests` (and `with_timeout`) puts on each evaluation stage.
+/// `None` (the default) runs everything on the calling thread without a limit.
+pub fn set_eval_timeout(limit: Option<Duration>) {
+    let ms = limit.map_or(0, |d| (d.as_millis() as u64).max(1));
+    EVAL_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn eval_timeout() -> Option<Duration> {
+    match EVAL_TIMEOUT_MS.load(Ordering::Relaxed) {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    }
+}
+
+// --- ITERATION BUDGET ---
+
+thread_local! {
+    /// Deadline of the fuzz input running on this thread, and the budget it was set from.
+    static ITERATION_BUDGET: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+}
+
+/// Gives the fuzz input about to run on this thread `budget` of wall-clock time in total (`None`
+/// for no limit). Until it is reset, `with_timeout` waits at most for what is left of it and
+/// `run_ad_tests` skips the ground truths it has no time left for.
+pub fn set_iteration_budget(budget: Option<Duration>) {
+    ITERATION_BUDGET.with(|b| b.set(budget.map(|budget| (Instant::now() + budget, budget))));
+}
+
+/// The budget set by `set_iteration_budget`, if it has run out.
+pub fn iteration_budget_exceeded() -> Option<Duration> {
+    ITERATION_BUDGET.with(Cell::get).filter(|(deadline, _)| Instant::now() >= *deadline).map(|(_, budget)| budget)
+}
+
+fn remaining_budget() -> Option<Duration> {
+    ITERATION_BUDGET.with(Cell::get).map(|(deadline, _)| deadline.saturating_duration_since(Instant::now()))
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Workers left running a stage that timed out, see `with_timeout`.
+static STUCK_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Past this many stuck workers, `with_timeout` fails stages without starting another.
+pub const MAX_STUCK_WORKERS: usize = 8;
+
+/// Number of workers still running a stage that timed out.
+pub fn stuck_workers() -> usize {
+    STUCK_WORKERS.load(Ordering::Relaxed)
+}
+
+/// Thread `with_timeout` runs stages on. One per calling thread, kept between calls so what a
+/// stage leaves in thread-locals (the `ENGINES` of the last function) is there for the next.
+struct Worker {
+    jobs: mpsc::Sender<Job>,
+    /// Set when a stage times out and the worker is left to finish it.
+    abandoned: Arc<AtomicBool>,
+}
+
+impl Worker {
+    fn spawn() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let abandoned = Arc::new(AtomicBool::new(false));
+        let flag = abandoned.clone();
+        // Ends once its `Worker` is dropped and the job it is on returns
+        thread::spawn(move || {
+            queue.into_iter().for_each(|job| job());
+            if flag.load(Ordering::Relaxed) {
+                STUCK_WORKERS.fetch_sub(1, Ordering::Relaxed);
+            }
+        });
+        Worker { jobs, abandoned }
+    }
+
+    /// Queues `job` on the worker in `slot`, starting a new one if there is none yet or it is gone.
+    fn submit(slot: &RefCell<Option<Worker>>, job: Job) {
+        let mut worker = slot.borrow_mut();
+        let job = match worker.as_ref() {
+            Some(w) => w.jobs.send(job).err().map(|e| e.0),
+            None => Some(job),
+        };
+        if let Some(job) = job {
+            let fresh = Worker::spawn();
+            fresh.jobs.send(job).expect("a new worker takes jobs");
+            *worker = Some(fresh);
+        }
+    }
+}
+
+thread_local! {
+    /// This thread's `Worker`, started by the first `with_timeout` that needs one.
+    static WORKER: RefCell<Option<Worker>> = const { RefCell::new(None) };
+    /// This thread's ground truth worker, see `run_ad_tests`. Kept between cases like `WORKER`,
+    /// and so is the `with_timeout` worker it starts.
+    static GROUND_TRUTH_WORKER: RefCell<Option<Worker>> = const { RefCell::new(None) };
+    /// When the stage running on this worker thread times out, see `stage_deadline`.
+    static STAGE_DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// When the `with_timeout` stage running on this thread is abandoned, if it has a limit. Stages
+/// that wait on something outside the process (the subprocess ground truths) stop waiting, and
+/// clean up after themselves, by then.
+pub fn stage_deadline() -> Option<Instant> {
+    STAGE_DEADLINE.with(Cell::get)
+}
+
+/// Runs `f` under `catch_backend_panic`, on this thread's worker thread, which is given at most
+/// `eval_timeout()`, or what is left of the iteration budget if that is less.
+///
+/// A thread cannot be killed: on timeout `FuzzError::Timeout` is returned and the worker is left
+/// running detached until `f` returns (the next call starts a new one). A stage that never
+/// returns leaks its thread for the rest of the process, so once `MAX_STUCK_WORKERS` are stuck
+/// every call fails with a `Timeout` straight away instead of leaking more.
+pub fn with_timeout<R: Send + 'static>(stage: &str, f: impl FnOnce() -> R + Send + 'static) -> Result<R, FuzzError> {
+    let limit = match [eval_timeout(), remaining_budget()].into_iter().flatten().min() {
+        Some(limit) => limit,
+        None => return catch_backend_panic(stage, f),
+    };
+    let stuck = stuck_workers();
+    if stuck >= MAX_STUCK_WORKERS {
+        return Err(FuzzError::Timeout { stage: format!("{} (not started: {} workers stuck)", stage, stuck), limit });
+    }
+    let deadline = Instant::now() + limit;
+    let (tx, rx) = mpsc::channel();
+    let backend = stage.to_string();
+    let job: Job = Box::new(move || {
+        STAGE_DEADLINE.with(|d| d.set(Some(deadline)));
+        let result = catch_backend_panic(&backend, f);
+        STAGE_DEADLINE.with(|d| d.set(None));
+        // The receiver is gone after a timeout; nothing left to report to
+        let _ = tx.send(result);
+    });
+    WORKER.with(|worker| Worker::submit(worker, job));
+    match rx.recv_timeout(limit) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            if let Some(worker) = WORKER.with(|worker| worker.borrow_mut().take()) {
+                STUCK_WORKERS.fetch_add(1, Ordering::Relaxed);
+                worker.abandoned.store(true, Ordering::Relaxed);
+            }
+            Err(FuzzError::Timeout { stage: stage.to_string(), limit })
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => unreachable!("the worker always sends a result"),
+    }
+}
+
+// --- ORACLE DRIVER (The Engine) ---
+
+/// Runs Reverse and Forward AD on `calc` at `inputs`. Also used by the metamorphic oracles to
+/// differentiate transformed versions of the same function.
+///
+/// ad_trait returns an m x n Jacobian; it is flattened row by row (see `EngineResults::reverse`).
+pub fn compute_engine_results<G: Calculator>(calc: &G, inputs: &[f64]) -> EngineResults {
+    compute_engine_results_with_tape(calc, inputs).0
+}
+
+/// Reverse AD alone: the Jacobian of `calc` at `inputs`, flattened like `EngineResults::reverse`.
+pub fn reverse_jacobian<G: Calculator>(calc: &G, inputs: &[f64]) -> Vec<f64> {
+    let func = SimpleADFunction { placeholder: 0.0, expression: calc.clone() };
+    let engine = FunctionEngine::new(func.clone(), func.to_other_ad_type::<adr>(), ReverseAD::new());
+    engine.derivative(inputs).1.transpose().iter().copied().collect()
+}
+
+/// Forward AD alone, like `reverse_jacobian`.
+pub fn forward_jacobian<G: Calculator>(calc: &G, inputs: &[f64]) -> Vec<f64> {
+    let func = SimpleADFunction { placeholder: 0.0, expression: calc.clone() };
+    let engine = FunctionEngine::new(func.clone(), func.to_other_ad_type::<adfn<1>>(), ForwardAD::new());
+    engine.derivative(inputs).1.transpose().iter().copied().collect()
+}
+
+/// `compute_engine_results`, and the size of the Reverse AD tape, see `tape_stats`. The peak
+/// memory covers the reverse pass only.
+pub fn compute_engine_results_with_tape<G: Calculator>(calc: &G, inputs: &[f64]) -> (EngineResults, TapeStats) {
+    AdEngines::new(calc).results_with_tape(inputs)
+}
+
+// --- ENGINE CACHE ---
+
+/// The Reverse and Forward AD engines of one calculator. Building them is a large part of a
+/// test case, and they only depend on the function: a generated expression is tested at many
+/// points (sweep, probes, shrinking), so `run_ad_tests` keeps the last ones per thread (the
+/// `with_timeout` worker's, with a timeout or budget set) and reuses them while
+/// `Calculator::same_function` holds.
+pub struct AdEngines<G: Calculator> {
+    calc: G,
+    reverse: FunctionEngine<SimpleADFunction<f64, G>, SimpleADFunction<adr, G>, ReverseAD>,
+    forward: FunctionEngine<SimpleADFunction<f64, G>, SimpleADFunction<adfn<1>, G>, ForwardAD>,
+}
+
+impl<G: Calculator> AdEngines<G> {
+    pub fn new(calc: &G) -> Self {
+        let func_standard = SimpleADFunction { placeholder: 0.0, expression: calc.clone() };
+        let func_rev_derivative = func_standard.to_other_ad_type::<adr>();
+        let func_fwd_derivative = func_standard.to_other_ad_type::<adfn<1>>();
+        AdEngines {
+            calc: calc.clone(),
+            reverse: FunctionEngine::new(func_standard.clone(), func_rev_derivative, ReverseAD::new()),
+            forward: FunctionEngine::new(func_standard, func_fwd_derivative, ForwardAD::new()),
+        }
+    }
+
+    /// Both engines at `inputs`, and the size of the Reverse AD tape.
+    pub fn results_with_tape(&self, inputs: &[f64]) -> (EngineResults, TapeStats) {
+        let start = Instant::now();
+        let ((f_res_rev, reverse_jacobian), peak_bytes) = measure_peak(|| self.reverse.derivative(inputs));
+        let reverse_time = start.elapsed();
+        let tape = TapeStats { nodes: tape_nodes(&self.calc, inputs), peak_bytes };
+
+        let start = Instant::now();
+        let (f_res_fwd, forward_jacobian) = self.forward.derivative(inputs); 
+        let forward_time = start.elapsed();
+
+        let engine = EngineResults {
+            inputs: inputs.to_vec(),
+            // nalgebra iterates column-major; the transpose's columns are the Jacobian's rows
+            reverse: reverse_jacobian.transpose().iter().copied().collect::<Vec<f64>>(), 
+            forward: forward_jacobian.transpose().iter().copied().collect::<Vec<f64>>(), 
+            reverse_primal: f_res_rev,
+            forward_primal: f_res_fwd,
+            timing: EngineTiming { reverse: reverse_time, forward: forward_time },
+        };
+        (engine, tape)
+    }
+}
+
+thread_local! {
+    /// The engines `run_ad_tests` last built on this thread. They hold a clone of their
+    /// calculator, so whatever `same_function` compares (e.g. the address of a shared
+    /// expression) can't be reused by another function while they are cached.
+    static ENGINES: RefCell<Option<Rc<dyn Any>>> = const { RefCell::new(None) };
+}
+
+/// The cached engines if they were built for the same function as `calc`, new ones otherwise.
+pub fn cached_engines<G: Calculator + 'static>(calc: &G) -> Rc<AdEngines<G>> {
+    ENGINES.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let cached = cache.clone().and_then(|engines| engines.downcast::<AdEngines<G>>().ok());
+        if let Some(engines) = cached.filter(|engines| engines.calc.same_function(calc)) {
+            return engines;
+        }
+        let engines = Rc::new(AdEngines::new(calc));
+        *cache = Some(engines.clone());
+        engines
+    })
+}
+
+/// Wall-clock time spent in each stage of `run_ad_tests`. `ad` and `ground_truths` run at the
+/// same time, so their sum is more than the case took.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timing {
+    pub ad: Duration,
+    pub ground_truths: Duration,
+    pub oracles: Duration,
+}
+
+/// Everything `run_ad_tests` computed for one test case. Whether a failure should panic,
+/// be counted, or be ignored is up to the caller.
+#[derive(Debug)]
+pub struct TestOutcome {
+    pub engine_results: EngineResults,
+    pub ground_truths: Vec<GroundTruth>,
+    /// Ground truth calculators that returned an error, with the error.
+    pub failed_ground_truths: Vec<(&'static str, FuzzError)>,
+    /// One verdict per registered oracle, in run order.
+    pub verdicts: Vec<OracleVerdict>,
+    pub timing: Timing,
+    /// Size of the Reverse AD tape; unknown when the engines panicked.
+    pub tape: TapeStats,
+}
+
+impl TestOutcome {
+    /// No verdict is an `Outcome::Fail`.
+    pub fn passed(&self) -> bool {
+        !self.verdicts.iter().any(OracleVerdict::is_failure)
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &FuzzError> {
+        self.verdicts.iter().filter(|v| v.is_failure()).filter_map(|v| v.result.as_ref().err())
+    }
+
+    /// Mismatches from oracles with `Severity::Warn`, and borderline ones (see `Outcome`).
+    pub fn warnings(&self) -> impl Iterator<Item = &FuzzError> {
+        self.verdicts.iter().filter(|v| v.outcome() == Outcome::Warn).filter_map(|v| v.result.as_ref().err())
+    }
+
+    pub fn first_failure(&self) -> Option<&FuzzError> {
+        self.failures().next()
+    }
+
+    /// A test case whose AD engines panicked: no results, one failing "backend" verdict.
+    fn backend_panic(inputs: &[f64], error: FuzzError) -> Self {
+        TestOutcome {
+            engine_results: EngineResults {
+                inputs: inputs.to_vec(),
+                reverse: Vec::new(),
+                forward: Vec::new(),
+                reverse_primal: Vec::new(),
+                forward_primal: Vec::new(),
+                timing: EngineTiming::default(),
+            },
+            ground_truths: Vec::new(),
+            failed_ground_truths: Vec::new(),
+            verdicts: vec![backend_verdict(error)],
+            timing: Timing::default(),
+            tape: TapeStats::default(),
+        }
+    }
+
+    /// The first failure, as `run_ad_tests` used to return it.
+    pub fn into_result(self) -> Result<(), FuzzError> {
+        match self.verdicts.into_iter().find(OracleVerdict::is_failure) {
+            Some(verdict) => verdict.result,
+            None => Ok(()),
+        }
+    }
+}
+
+/// A caught engine panic, reported as a failing verdict so it goes through the same reporting
+/// path as oracle mismatches.
+fn backend_verdict(error: FuzzError) -> OracleVerdict {
+    OracleVerdict { oracle: "backend".to_string(), severity: Severity::Fail, result: Err(error), borderline: false, elapsed: Duration::ZERO }
+}
+
+/// Under `NanPolicy::Fail`, a ground truth that reports NaN fails the test case like a panic:
+/// the reference itself broke. A failing "nan_policy" verdict naming the first NaN entry.
+fn nan_ground_truth_verdict(policy: NanPolicy, engine: &EngineResults, gt: &GroundTruth) -> Option<OracleVerdict> {
+    if policy != NanPolicy::Fail {
+        return None;
+    }
+    let entry = match gt.jacobian.iter().position(|v| v.is_nan()) {
+        Some(i) => {
+            let (output, input) = engine.partial(i);
+            format!("d/dx_{} of output {}", input, output)
+        }
+        None if gt.value.is_some_and(f64::is_nan) => "the value".to_string(),
+        None => return None,
+    };
+    let error = FuzzError::backend(gt.name, format!("returned NaN for {} (NaN policy: fail)", entry));
+    Some(OracleVerdict { oracle: "nan_policy".to_string(), severity: Severity::Fail, result: Err(error), borderline: false, elapsed: Duration::ZERO })
+}
+
+/// A Reverse AD pass over the `tape_budget()`: a failing "tape" verdict, like a panic.
+fn tape_verdict(tape: &TapeStats) -> Option<OracleVerdict> {
+    let error = tape_budget().check(tape).err()?;
+    Some(OracleVerdict { oracle: "tape".to_string(), severity: Severity::Fail, result: Err(error), borderline: false, elapsed: Duration::ZERO })
+}
+
+/// Each ground truth calculator's name and result, in order.
+type GroundTruthResults = Vec<(&'static str, Result<GroundTruth, FuzzError>)>;
+
+/// Every ground truth of `calc` at `inputs` in order, each under `with_timeout`, and the time
+/// they took. The ones the iteration budget has no time left for fail with a `Timeout`.
+fn compute_ground_truths<G>(calc: &G, inputs: &[f64], gt_calculators: &[Box<dyn GroundTruthCalculator>]) -> (GroundTruthResults, Duration)
+where
+    G: Calculator + PyTorchComputable + Send + 'static,
+{
+    let start = Instant::now();
+    let results = gt_calculators.iter().map(|gt_calc| {
+        let result = match iteration_budget_exceeded() {
+            Some(budget) => {
+                let stage = format!("{} (iteration budget)", gt_calc.name());
+                Err(FuzzError::Timeout { stage, limit: budget })
+            }
+            None => {
+                let (gt, calc, gt_inputs) = (gt_calc.clone(), calc.clone(), inputs.to_vec());
+                with_timeout(gt_calc.name(), move || gt.calculate(&calc, &gt_inputs)).and_then(|r| r)
+            }
+        };
+        (gt_calc.name(), result)
+    }).collect();
+    (results, start.elapsed())
+}
+
+/// Runs `compute_ground_truths` on this thread's ground truth worker, under this thread's
+/// iteration budget; the receiver gets the result, or the panic it unwound with.
+fn start_ground_truths<G>(calc: &G, inputs: &[f64], gt_calculators: &[Box<dyn GroundTruthCalculator>]) -> mpsc::Receiver<thread::Result<(GroundTruthResults, Duration)>>
+where
+    G: Calculator + PyTorchComputable + Send + 'static,
+{
+    let (calc, gt_inputs, gt_calculators) = (calc.clone(), inputs.to_vec(), gt_calculators.to_vec());
+    let budget = ITERATION_BUDGET.with(Cell::get);
+    let (tx, rx) = mpsc::channel();
+    let job: Job = Box::new(move || {
+        ITERATION_BUDGET.with(|b| b.set(budget));
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| compute_ground_truths(&calc, &gt_inputs, &gt_calculators)));
+        let _ = tx.send(result);
+    });
+    GROUND_TRUTH_WORKER.with(|worker| Worker::submit(worker, job));
+    rx
+}
+
+/// Runs the AD engines, every ground truth calculator and every oracle on `calc` at `inputs`.
+/// The ground truths run on this thread's ground truth worker, one per calling thread kept
+/// between cases, while the AD engines run on this one, so libtorch dispatch overlaps with the
+/// Rust work; they are joined before the oracles. Only fails if the
+/// test case can't be run at all (wrong number of inputs, or the AD engines hit the
+/// `eval_timeout()` or the iteration budget). A ground truth that times out, or is skipped
+/// because the iteration budget ran out, is reported like any other failed one; a panic in any
+/// engine becomes a failing "backend" verdict, and so does a NaN ground truth under
+/// `NanPolicy::Fail` (as "nan_policy") and a tape over the `tape_budget()` (as "tape").
+pub fn run_ad_tests<G>(
+    inputs: &[f64],
+    calc: G,
+    oracles: &FuzzingOracles,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+) -> Result<TestOutcome, FuzzError>
+where
+    G: Calculator + PyTorchComputable + Send + 'static,
+{
+    // FIX E0034: Disambiguate the num_inputs call by specifying the trait.
+    let expected = PyTorchComputable::num_inputs(&calc);
+    if inputs.len() != expected || inputs.is_empty() {
+        return Err(FuzzError::InputMismatch { expected, got: inputs.len() });
+    }
+    let mut timing = Timing::default();
+
+    // 1. Start ALL Ground Truths on the ground truth worker, if there are any
+    let ground_truth_worker = (!gt_calculators.is_empty()).then(|| start_ground_truths(&calc, inputs, gt_calculators));
+    // Joined on every path, so a case's ground truths never overlap with the next case
+    let join_ground_truths = |worker: Option<mpsc::Receiver<thread::Result<_>>>| match worker {
+        Some(rx) => rx.recv().expect("the ground truth worker always sends a result").unwrap_or_else(|payload| panic::resume_unwind(payload)),
+        None => (Vec::new(), Duration::ZERO),
+    };
+
+    // 2. Compute AD results
+    let start = Instant::now();
+    let engine_results = {
+        let (calc, inputs) = (calc.clone(), inputs.to_vec());
+        with_timeout("AD (Rev/Fwd)", move || cached_engines(&calc).results_with_tape(&inputs))
+    };
+    let (engine_results, tape) = match engine_results {
+        Ok(results) => results,
+        Err(e) => {
+            join_ground_truths(ground_truth_worker);
+            return match e {
+                FuzzError::BackendPanic { .. } => Ok(TestOutcome::backend_panic(inputs, e)),
+                e => Err(e),
+            };
+        }
+    };
+    timing.ad = start.elapsed();
+
+    // 3. Join the Ground Truths
+    let (gt_results, gt_time) = join_ground_truths(ground_truth_worker);
+    timing.ground_truths = gt_time;
+    let mut ground_truths = Vec::new();
+    let mut failed_ground_truths = Vec::new();
+    let mut backend_verdicts: Vec<OracleVerdict> = tape_verdict(&tape).into_iter().collect();
+    for (name, result) in gt_results {
+        match result {
+            Ok(gt) => {
+                backend_verdicts.extend(nan_ground_truth_verdict(oracles.nan_policy, &engine_results, &gt));
+                ground_truths.push(gt);
+            }
+            Err(e @ FuzzError::BackendPanic { .. }) => backend_verdicts.push(backend_verdict(e)),
+            // Kept for the domain oracle: an error can be the expected behavior
+            Err(e) => failed_ground_truths.push((name, e)),
+        }
+    }
+
+    // 4. Report Engine Results
+    println!("Engine Results: {:?}", engine_results);
+
+    // 5. Run all Oracle Checks
+    let start = Instant::now();
+    let mut verdicts = backend_verdicts;
+    verdicts.extend(oracles.verdicts_all(&calc, &engine_results, &ground_truths, &failed_ground_truths));
+    timing.oracles = start.elapsed();
+
+    // Warnings stay in the verdicts, see `TestOutcome::warnings`
+    Ok(TestOutcome { engine_results, ground_truths, failed_ground_truths, verdicts, timing, tape })
+}
+
+pub fn run_custom_test<G: Calculator + PyTorchComputable + Send + 'static>(
+    inputs: &[f64],
+    calc: G,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+) -> Result<(), FuzzError> {
+    let oracles = FuzzingOracles::new(OracleSelection::ALL);
+    let outcome = run_ad_tests(inputs, calc, &oracles, gt_calculators);
+    for warning in outcome.iter().flat_map(TestOutcome::warnings) {
+        println!("Warning: {}", warning);
+    }
+    let result = outcome.and_then(TestOutcome::into_result);
+    
+    // Print result regardless of pass/fail
+    match &result {
+        Ok(_) => println!("Test PASSED"),
+        Err(e) => println!("Test FAILED: {}", e),
+    }
+    
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_expr::SimpleExpr;
+    use crate::gt_calculators::{CachedGroundTruth, DualNumberCalculator, SymbolicGroundTruthCalculator};
+
+    #[test]
+    fn test_backend_panic_becomes_verdict() {
+        // x_3 is unbound with one input, which makes AdEvaluator::eval_expr panic
+        let calc = AdPyUnified::new(SimpleExpr::sin(SimpleExpr::var("x_3")), 1, 1);
+        let oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        let outcome = run_ad_tests(&[0.5], calc, &oracles, &[DualNumberCalculator.boxed()]).unwrap();
+        let error = outcome.first_failure().unwrap();
+        assert_eq!(error.kind(), "BackendPanic");
+        assert_eq!(outcome.verdicts[0].oracle, "backend");
+
+        let caught = catch_backend_panic("test", || panic!("boom"));
+        assert!(matches!(caught, Err(FuzzError::BackendPanic { ref message, .. }) if message.starts_with("boom (at ")));
+    }
+
+    #[test]
+    fn test_mixed_ground_truths() {
+        // Calculators of different types in one run, each reported under its own name
+        let calc = AdPyUnified::new(SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::var("x_1")), 2, 1);
+        let oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        let gt_calculators = [DualNumberCalculator.boxed(), CachedGroundTruth::new(SymbolicGroundTruthCalculator, 4).boxed()];
+        let outcome = run_ad_tests(&[0.5, 2.0], calc, &oracles, &gt_calculators).unwrap();
+        let names: Vec<&str> = outcome.ground_truths.iter().map(|gt| gt.name).collect();
+        assert_eq!(names, ["Dual", "Symbolic"]);
+        assert!(outcome.ground_truths.iter().all(|gt| gt.jacobian == [2.0, 0.5]));
+
+        // Every case's ground truths run on the same worker, not on this thread
+        #[derive(Clone)]
+        struct ThreadProbe(Arc<std::sync::Mutex<Vec<thread::ThreadId>>>);
+        impl GroundTruthCalculator for ThreadProbe {
+            fn name(&self) -> &'static str {
+                "probe"
+            }
+            fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+                self.0.lock().unwrap().push(thread::current().id());
+                DualNumberCalculator.calculate(calc, inputs)
+            }
+        }
+        let probe = ThreadProbe(Arc::default());
+        let calc = AdPyUnified::new(SimpleExpr::var("x_0"), 1, 1);
+        for x in [0.5, 2.0] {
+            run_ad_tests(&[x], calc.clone(), &oracles, &[probe.clone().boxed()]).unwrap();
+        }
+        let threads = probe.0.lock().unwrap().clone();
+        assert_eq!(threads.len(), 2);
+        assert!(threads[0] == threads[1] && threads[0] != thread::current().id());
+    }
+
+    #[test]
+    fn test_engine_cache() {
+        // Clones of a calculator share their engines, an equal but separate expression does not
+        let expr = SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::sin(SimpleExpr::var("x_1")));
+        let calc = AdPyUnified::new(expr.clone(), 2, 1);
+        let engines = cached_engines(&calc);
+        assert!(Rc::ptr_eq(&engines, &cached_engines(&calc.clone())));
+        let other = AdPyUnified::new(expr, 2, 1);
+        assert!(!Rc::ptr_eq(&engines, &cached_engines(&other)));
+
+        // Reused engines give what new ones give, at every point
+        for x in [[0.5, 2.0], [-1.5, 0.25]] {
+            let (cached, _) = cached_engines(&other).results_with_tape(&x);
+            let fresh = compute_engine_results(&other, &x);
+            assert_eq!((cached.reverse, cached.forward), (fresh.reverse, fresh.forward));
+        }
+
+        // With a budget the engines are built on the `with_timeout` worker, and kept there
+        set_iteration_budget(Some(Duration::from_secs(60)));
+        let oracles = FuzzingOracles::new(OracleSelection::REV_FWD);
+        run_ad_tests(&[0.5, 2.0], calc.clone(), &oracles, &[]).unwrap();
+        let probe = calc.clone();
+        let kept = with_timeout("probe", move || {
+            let cached = ENGINES.with(|cache| cache.borrow().clone()).and_then(|e| e.downcast::<AdEngines<AdPyUnified<()>>>().ok());
+            cached.is_some_and(|engines| engines.calc.same_function(&probe))
+        });
+        set_iteration_budget(None);
+        assert!(kept.unwrap());
+    }
+}
@@ -1,69 +1,817 @@
-// src/gt_calculators.rs
-
-use tch::{Tensor, Kind};
-use std::error::Error;
-use core::convert::TryFrom; 
-use crate::fuzz_harness::{GroundTruthCalculator, PyTorchComputable, Calculator}; 
-
-/// Concrete implementation for calculating Ground Truth via PyTorch.
-#[derive(Clone)]
-pub struct PyTorchGroundTruthCalculator;
-
-impl GroundTruthCalculator for PyTorchGroundTruthCalculator {
-    fn name(&self) -> &'static str { "PyTorch" }
-
-    // G is a generic type for the function (e.g., RpnEvaluator)
-    fn calculate<G: Calculator + PyTorchComputable>(&self, calc: &G, inputs: &[f64]) -> Result<Vec<f64>, Box<dyn Error>> {
-        let mut tensors: Vec<Tensor> = Vec::new();
-        for &val in inputs {
-            tensors.push(
-                Tensor::from(val)
-                    .set_requires_grad(true) // Inputs always require grad
-                    .to_kind(Kind::Double)
-            );
-        }
-        
-        // 1. Compute PyTorch output
-        let outputs = calc.compute_pytorch(&tensors)?; 
-        if outputs.is_empty() { return Err("PyTorch function returned no output.".into()); }
-        
-        // Assuming scalar output
-        if outputs[0].numel() != 1 {
-            return Err("PyTorch output is not a scalar, skipping derivative calculation.".into());
-        }
-
-        // Check if the output requires a gradient. If not, the function evaluated
-        // to a constant (derivative must be zero). This prevents the E0599 panic.
-        if !outputs[0].requires_grad() {
-            let zero_gradients = vec![0.0; inputs.len()];
-            return Ok(zero_gradients);
-        }
-
-        // 2. Run backpropagation
-        outputs[0].backward(); 
-        
-        // 3. Extract gradients
-        let mut gradients = Vec::new();
-        for tensor in &tensors {
-            let grad_tensor = tensor.grad();
-
-            // Use numel() > 0 to check if a gradient was actually computed.
-            let grad = if grad_tensor.numel() > 0 {
-                
-                // Convert the scalar tensor value to f64
-                match f64::try_from(grad_tensor.double_value(&[])) {
-                    Ok(val) => val,
-                    Err(_) => {
-                        panic!("Warning: PyTorch gradient conversion failed unexpectedly. Assuming 0.0");
-                    }
-                }
-            } else {
-                // Derivative is 0 if no gradient was stored
-                0.0
-            };
-            gradients.push(grad);
-        }
-        
-        Ok(gradients)
-    }
-}
+// src/gt_calculators.rs
+
+#[cfg(feature = "pytorch")]
+use tch::{Tensor, Kind};
+use evalexpr_jit::Equation;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::ast_expr::SimpleExpr;
+use crate::ast_evaluator::{evaluate, Dual, Env, JuliaPrinter};
+use crate::ast_parser::parse_infix;
+use crate::error::FuzzError;
+use crate::fuzz_harness::{stage_deadline, GroundTruthCalculator, PyTorchComputable};
+use crate::oracles::{GroundTruth, BASELINE_FORWARD, BASELINE_REVERSE};
+use crate::symbolic_diff::{derivative, symbolic_gradient};
+
+/// The referee of the fuzz targets and `adfuzz`: PyTorch with the `pytorch` feature, closed-form
+/// derivatives without it, so the crate fuzzes on machines without libtorch.
+#[cfg(feature = "pytorch")]
+pub type DefaultGroundTruth = PyTorchGroundTruthCalculator;
+#[cfg(not(feature = "pytorch"))]
+pub type DefaultGroundTruth = SymbolicGroundTruthCalculator;
+
+/// An engine the fuzzer can run, for discovery (`adfuzz list-backends`).
+#[derive(Debug, Clone)]
+pub struct BackendInfo {
+    /// The engine's name in reports: `GroundTruthCalculator::name` for ground truths.
+    pub name: &'static str,
+    /// `false` for the engines under test, Reverse and Forward AD.
+    pub ground_truth: bool,
+    /// Reports a Hessian, for the `hessian` oracle.
+    pub hessian: bool,
+    /// Compiled into this build.
+    pub available: bool,
+    pub requires: Option<&'static str>,
+}
+
+/// Every engine the crate knows of, compiled in or not: ad_trait's two engines, then the ground
+/// truths. Names match `GroundTruth::name`.
+pub fn backends() -> Vec<BackendInfo> {
+    let backend = |name, ground_truth, hessian, available, requires| BackendInfo { name, ground_truth, hessian, available, requires };
+    vec![
+        backend("Rev", false, false, true, None),
+        backend("Fwd", false, false, true, None),
+        backend("PyTorch", true, true, cfg!(feature = "pytorch"), Some("the `pytorch` feature and libtorch")),
+        backend(DualNumberCalculator.name(), true, false, true, None),
+        backend(EvalexprJitCalculator.name(), true, true, true, None),
+        backend(SymbolicGroundTruthCalculator.name(), true, true, true, None),
+        backend("PyTorch (subprocess)", true, true, true, Some("python3 with torch installed")),
+        backend("ForwardDiff.jl", true, false, true, Some("julia with ForwardDiff.jl in the default environment")),
+        backend("Zygote.jl", true, false, true, Some("julia with Zygote.jl in the default environment")),
+        backend(BASELINE_REVERSE, true, false, cfg!(feature = "ab"), Some("the `ab` feature")),
+        backend(BASELINE_FORWARD, true, false, cfg!(feature = "ab"), Some("the `ab` feature")),
+    ]
+}
+
+/// Concrete implementation for calculating Ground Truth via PyTorch.
+#[cfg(feature = "pytorch")]
+#[derive(Clone, Default)]
+pub struct PyTorchGroundTruthCalculator {
+    hessians: bool,
+}
+
+#[cfg(feature = "pytorch")]
+impl PyTorchGroundTruthCalculator {
+    /// Also report the Hessian of the first output (see `calculate_hessian`), for the `hessian`
+    /// oracle.
+    pub fn with_hessians(mut self) -> Self {
+        self.hessians = true;
+        self
+    }
+
+    /// One leaf tensor per input, all requiring a gradient.
+    fn input_tensors(inputs: &[f64]) -> Vec<Tensor> {
+        inputs.iter().map(|&val| Tensor::from(val).set_requires_grad(true).to_kind(Kind::Double)).collect()
+    }
+
+    /// A gradient entry as a number. Undefined/empty: the output does not depend on this input.
+    fn scalar(grad: &Tensor) -> f64 {
+        if grad.defined() && grad.numel() > 0 { grad.double_value(&[]) } else { 0.0 }
+    }
+
+    /// Hessian of the first output, `hessian[i][j] = d^2 f / dx_i dx_j`, by double backward:
+    /// the gradient is taken with `create_graph`, so each of its entries is itself on the graph,
+    /// and row `i` is the gradient of entry `i`, like `torch.autograd.gradcheck`'s gradgradcheck.
+    pub fn calculate_hessian(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<Vec<Vec<f64>>, FuzzError> {
+        let tensors = Self::input_tensors(inputs);
+        let outputs = calc.compute_pytorch(&tensors)?;
+        let out = outputs.first().ok_or_else(|| FuzzError::backend(self.name(), "PyTorch function returned no output."))?;
+        if out.numel() != 1 {
+            return Err(FuzzError::backend(self.name(), "PyTorch output is not a scalar, skipping Hessian calculation."));
+        }
+        let n = inputs.len();
+        if !out.requires_grad() {
+            return Ok(vec![vec![0.0; n]; n]);
+        }
+
+        let grads = Tensor::f_run_backward(&[out], &tensors, true, true)
+            .map_err(|e| FuzzError::backend(self.name(), e))?;
+        let mut hessian = Vec::with_capacity(n);
+        for grad in &grads {
+            // Not on the graph (undefined, or constant in every input): the row is zero
+            if !grad.defined() || grad.numel() == 0 || !grad.requires_grad() {
+                hessian.push(vec![0.0; n]);
+                continue;
+            }
+            let row = Tensor::f_run_backward(&[grad], &tensors, true, false)
+                .map_err(|e| FuzzError::backend(self.name(), e))?;
+            hessian.push(row.iter().map(Self::scalar).collect());
+        }
+        Ok(hessian)
+    }
+}
+
+#[cfg(feature = "pytorch")]
+impl GroundTruthCalculator for PyTorchGroundTruthCalculator {
+    fn name(&self) -> &'static str { "PyTorch" }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        let tensors = Self::input_tensors(inputs);
+        
+        // 1. Compute PyTorch output
+        let outputs = calc.compute_pytorch(&tensors)?; 
+        if outputs.is_empty() { return Err(FuzzError::backend(self.name(), "PyTorch function returned no output.")); }
+        
+        // Each output must be a scalar; output k fills row k of the Jacobian
+        if outputs.iter().any(|out| out.numel() != 1) {
+            return Err(FuzzError::backend(self.name(), "PyTorch output is not a scalar, skipping derivative calculation."));
+        }
+
+        let value = outputs[0].double_value(&[]);
+        let mut jacobian = Vec::with_capacity(outputs.len() * inputs.len());
+        for out in &outputs {
+            // Check if the output requires a gradient. If not, the function evaluated
+            // to a constant (derivative must be zero). This prevents the E0599 panic.
+            if !out.requires_grad() {
+                jacobian.extend(std::iter::repeat(0.0).take(inputs.len()));
+                continue;
+            }
+
+            // 2. Run backpropagation for this output only; keep the graph for the next one
+            let grads = Tensor::f_run_backward(&[out], &tensors, true, false)
+                .map_err(|e| FuzzError::backend(self.name(), e))?;
+
+            // 3. Extract gradients
+            jacobian.extend(grads.iter().map(Self::scalar));
+        }
+
+        let hessian = if self.hessians { Some(self.calculate_hessian(calc, inputs)?) } else { None };
+        Ok(GroundTruth { name: self.name(), jacobian, value: Some(value), hessian })
+    }
+}
+
+/// Ground Truth from the in-crate `Dual` numbers: one forward pass per input.
+///
+/// Independent of both ad_trait and PyTorch, and small enough to audit when they disagree.
+#[derive(Clone)]
+pub struct DualNumberCalculator;
+
+impl GroundTruthCalculator for DualNumberCalculator {
+    fn name(&self) -> &'static str { "Dual" }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("Dual numbers only support single-output functions".to_string()));
+        }
+        let mut gradients = Vec::with_capacity(inputs.len());
+        let mut value = None;
+        for i in 0..inputs.len() {
+            let duals: Vec<Dual> = inputs.iter().enumerate()
+                .map(|(j, &val)| if i == j { Dual::variable(val) } else { Dual::constant(val) })
+                .collect();
+            let result = calc.eval_dual(&duals)?;
+            value = Some(result.val);
+            gradients.push(result.der);
+        }
+        Ok(GroundTruth { name: self.name(), jacobian: gradients, value, hessian: None })
+    }
+}
+
+/// Ground Truth from evalexpr-jit: the infix expression is JIT-compiled with symbolic first and
+/// second derivatives. No PyTorch involved, so Rev/Fwd can be checked against it directly.
+#[derive(Clone)]
+pub struct EvalexprJitCalculator;
+
+impl GroundTruthCalculator for EvalexprJitCalculator {
+    fn name(&self) -> &'static str { "evalexpr-jit" }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("evalexpr-jit only supports single-output functions".to_string()));
+        }
+        let expr = calc.infix_expr()
+            .ok_or_else(|| FuzzError::UnsupportedExpr("Calculator does not expose an infix expression".to_string()))?;
+        // Equation::new orders variables alphabetically (x_10 before x_2); pin x_i to input i
+        let variables: HashMap<String, u32> = (0..inputs.len()).map(|i| (format!("x_{}", i), i as u32)).collect();
+        let equation = Equation::from_var_map(expr, &variables).map_err(|e| FuzzError::backend(self.name(), e))?;
+
+        let value = equation.eval(&inputs.to_vec()).map_err(|e| FuzzError::backend(self.name(), e))?;
+        let jacobian = equation.gradient(inputs).map_err(|e| FuzzError::backend(self.name(), e))?;
+        let hessian = equation.hessian(inputs).map_err(|e| FuzzError::backend(self.name(), e))?;
+        Ok(GroundTruth { name: self.name(), jacobian, value: Some(value), hessian: Some(hessian) })
+    }
+}
+
+/// Ground Truth from closed-form derivatives, see `symbolic_diff`: the gradient and Hessian are
+/// expressions built by the differentiation rules and evaluated in f64, with no AD anywhere.
+#[derive(Clone, Default)]
+pub struct SymbolicGroundTruthCalculator;
+
+impl GroundTruthCalculator for SymbolicGroundTruthCalculator {
+    fn name(&self) -> &'static str { "Symbolic" }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("Symbolic derivatives only support single-output functions".to_string()));
+        }
+        let expr = calc.infix_expr()
+            .ok_or_else(|| FuzzError::UnsupportedExpr("Calculator does not expose an infix expression".to_string()))?;
+        let (expr, _) = parse_infix(&expr)?;
+        let env: Env<f64> = inputs.iter().enumerate().map(|(i, &x)| (format!("x_{}", i), x)).collect();
+
+        let value = evaluate(&expr, &env)?;
+        let gradient = symbolic_gradient(&expr, inputs.len())?;
+        let jacobian = gradient.iter().map(|d| evaluate(d, &env)).collect::<Result<Vec<f64>, FuzzError>>()?;
+        let hessian = gradient.iter()
+            .map(|d| (0..inputs.len()).map(|j| evaluate(&derivative(d, &format!("x_{}", j))?, &env)).collect())
+            .collect::<Result<Vec<Vec<f64>>, FuzzError>>()?;
+        Ok(GroundTruth { name: self.name(), jacobian, value: Some(value), hessian: Some(hessian) })
+    }
+}
+
+/// Source of the Python worker, run with `python -u -c`.
+const PYTORCH_WORKER_SRC: &str = include_str!("../python/pytorch_worker.py");
+
+/// One request line sent to the worker. Floats are sent as strings so NaN/Inf survive JSON.
+#[derive(Serialize)]
+struct WorkerRequest<'a> {
+    expr: &'a str,
+    inputs: Vec<String>,
+    hessian: bool,
+}
+
+/// One response line read back from the worker.
+#[derive(Deserialize)]
+struct WorkerResponse {
+    value: Option<String>,
+    gradients: Option<Vec<String>>,
+    hessian: Option<Vec<Vec<String>>>,
+    error: Option<String>,
+}
+
+struct WorkerProcess {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines of the worker's stdout, read on a thread of their own so `exchange` can stop waiting.
+    lines: mpsc::Receiver<std::io::Result<String>>,
+}
+
+impl WorkerProcess {
+    /// Starts `command` with piped stdin/stdout. `backend` names the calculator in errors.
+    fn spawn(command: &mut Command, backend: &'static str) -> Result<Self, FuzzError> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| FuzzError::backend(backend, format!("Failed to start worker '{}': {}", command.get_program().to_string_lossy(), e)))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| FuzzError::backend(backend, "Worker has no stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| FuzzError::backend(backend, "Worker has no stdout"))?;
+
+        // Ends at EOF, i.e. once the worker exits or is killed
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let mut line = String::new();
+                match stdout.read_line(&mut line) {
+                    Ok(0) => break,
+                    read => if tx.send(read.map(|_| line)).is_err() { break },
+                }
+            }
+        });
+
+        Ok(WorkerProcess { child, stdin, lines })
+    }
+
+    /// Sends one request line and reads one response line. Gives up after `read_timeout`, or at
+    /// the `stage_deadline` of the `with_timeout` it runs under if that is sooner: the caller then
+    /// drops the worker, which kills it.
+    fn exchange(&mut self, request: &str, backend: &'static str, read_timeout: Option<Duration>) -> Result<String, FuzzError> {
+        let error = |e: std::io::Error| FuzzError::backend(backend, e);
+        self.stdin.write_all(request.as_bytes()).map_err(error)?;
+        self.stdin.write_all(b"\n").map_err(error)?;
+        self.stdin.flush().map_err(error)?;
+
+        let deadline = read_timeout.map(|timeout| Instant::now() + timeout);
+        let received = match stage_deadline().into_iter().chain(deadline).min() {
+            Some(deadline) => self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => self.lines.recv().map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(line) => line.map_err(error),
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(FuzzError::backend(backend, "Worker did not answer in time")),
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                let status = self.child.wait().map_err(error)?;
+                Err(FuzzError::backend(backend, format!("Worker died ({})", status)))
+            }
+        }
+    }
+}
+
+impl Drop for WorkerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn worker_error(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> FuzzError {
+    FuzzError::backend("PyTorch (subprocess)", source)
+}
+
+/// Ground Truth via PyTorch running in a separate, long-lived Python process.
+///
+/// The expression is shipped as an infix string over stdin/stdout (one JSON object per line).
+/// If libtorch aborts or throws, only the worker dies: the failure is returned as an error
+/// for this test case and a fresh worker is spawned on the next call. So does a worker that
+/// does not answer within the read timeout (`DEFAULT_READ_TIMEOUT`, generous enough for the
+/// first request to wait for `import torch`).
+///
+/// Clones share the worker, so `run_ad_tests` can move one onto a timeout thread.
+#[derive(Clone)]
+pub struct SubprocessPyTorchCalculator {
+    python: String,
+    hessians: bool,
+    read_timeout: Option<Duration>,
+    worker: Arc<Mutex<Option<WorkerProcess>>>,
+}
+
+impl SubprocessPyTorchCalculator {
+    pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new(python: impl Into<String>) -> Self {
+        SubprocessPyTorchCalculator {
+            python: python.into(),
+            hessians: false,
+            read_timeout: Some(Self::DEFAULT_READ_TIMEOUT),
+            worker: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// How long to wait for each response; `None` waits for as long as the stage may take.
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Also ask the worker for the Hessian (double backward), for the `hessian` oracle.
+    pub fn with_hessians(mut self) -> Self {
+        self.hessians = true;
+        self
+    }
+
+    fn spawn_worker(&self) -> Result<WorkerProcess, FuzzError> {
+        WorkerProcess::spawn(Command::new(&self.python).arg("-u").arg("-c").arg(PYTORCH_WORKER_SRC), "PyTorch (subprocess)")
+    }
+
+    fn round_trip(&self, worker: &mut WorkerProcess, request: &str) -> Result<WorkerResponse, FuzzError> {
+        let line = worker.exchange(request, "PyTorch (subprocess)", self.read_timeout)?;
+        serde_json::from_str(&line).map_err(worker_error)
+    }
+}
+
+impl Default for SubprocessPyTorchCalculator {
+    fn default() -> Self {
+        Self::new("python3")
+    }
+}
+
+impl GroundTruthCalculator for SubprocessPyTorchCalculator {
+    fn name(&self) -> &'static str { "PyTorch (subprocess)" }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("The PyTorch worker only supports single-output functions".to_string()));
+        }
+        let expr = calc.infix_expr()
+            .ok_or_else(|| FuzzError::UnsupportedExpr("Calculator does not expose an infix expression".to_string()))?;
+        let request = serde_json::to_string(&WorkerRequest {
+            expr: &expr,
+            inputs: inputs.iter().map(|v| format!("{:?}", v)).collect(),
+            hessian: self.hessians,
+        }).map_err(worker_error)?;
+
+        // Held by an abandoned request after a timeout: fail fast instead of queueing behind it
+        let mut guard = match self.worker.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => return Err(worker_error("PyTorch worker is still busy with a request that timed out")),
+            Err(TryLockError::Poisoned(_)) => return Err(worker_error("PyTorch worker lock poisoned")),
+        };
+        if guard.is_none() {
+            *guard = Some(self.spawn_worker()?);
+        }
+
+        let response = match self.round_trip(guard.as_mut().unwrap(), &request) {
+            Ok(response) => response,
+            Err(e) => {
+                // Drop the broken worker so the next call starts a fresh one.
+                *guard = None;
+                return Err(e);
+            }
+        };
+
+        if let Some(e) = response.error {
+            return Err(worker_error(format!("PyTorch worker error: {}", e)));
+        }
+
+        let gradients = response.gradients.ok_or_else(|| worker_error("PyTorch worker returned no gradients"))?;
+        if gradients.len() != inputs.len() {
+            return Err(worker_error(format!("PyTorch worker returned {} gradients, expected {}", gradients.len(), inputs.len())));
+        }
+
+        let parse = |s: &String| s.parse::<f64>()
+            .map_err(|e| worker_error(format!("Bad number '{}' from PyTorch worker: {}", s, e)));
+        let jacobian = gradients.iter().map(parse).collect::<Result<Vec<f64>, FuzzError>>()?;
+        let value = response.value.as_ref().map(parse).transpose()?;
+        let hessian = match &response.hessian {
+            Some(rows) => Some(rows.iter()
+                .map(|row| row.iter().map(parse).collect::<Result<Vec<f64>, FuzzError>>())
+                .collect::<Result<Vec<Vec<f64>>, FuzzError>>()?),
+            None => None,
+        };
+
+        Ok(GroundTruth { name: self.name(), jacobian, value, hessian })
+    }
+}
+
+/// Source of the Julia worker, run with `julia -e`.
+const JULIA_WORKER_SRC: &str = include_str!("../julia/ad_worker.jl");
+
+/// Which Julia AD package `SubprocessJuliaCalculator` asks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JuliaAd {
+    ForwardDiff,
+    Zygote,
+}
+
+/// Ground Truth from ForwardDiff.jl or Zygote.jl in a long-lived Julia process, a referee outside
+/// both the Rust and the Python ecosystems. The infix expression is parsed back and rendered with
+/// `JuliaPrinter`; requests and responses are one plain-text line each (see
+/// `julia/ad_worker.jl`). Julia throws `DomainError` where the other engines return NaN, which
+/// comes back as a `FuzzError::DomainError` for the domain oracle; other exceptions are backend
+/// failures, like a PyTorch exception.
+///
+/// The package must be installed in the default Julia environment. The first request waits for
+/// Julia to start and compile, so give it a generous `FUZZ_EVAL_TIMEOUT_MS`, if any. Clones share
+/// the worker, see `SubprocessPyTorchCalculator`.
+#[derive(Clone)]
+pub struct SubprocessJuliaCalculator {
+    julia: String,
+    ad: JuliaAd,
+    worker: Arc<Mutex<Option<WorkerProcess>>>,
+}
+
+impl SubprocessJuliaCalculator {
+    pub fn new(julia: impl Into<String>, ad: JuliaAd) -> Self {
+        SubprocessJuliaCalculator { julia: julia.into(), ad, worker: Arc::new(Mutex::new(None)) }
+    }
+
+    fn spawn_worker(&self) -> Result<WorkerProcess, FuzzError> {
+        let package = match self.ad {
+            JuliaAd::ForwardDiff => "forwarddiff",
+            JuliaAd::Zygote => "zygote",
+        };
+        // With -e, ARGS are the arguments after the script source
+        WorkerProcess::spawn(Command::new(&self.julia).arg("-e").arg(JULIA_WORKER_SRC).arg(package), self.name())
+    }
+}
+
+impl Default for SubprocessJuliaCalculator {
+    fn default() -> Self {
+        Self::new("julia", JuliaAd::ForwardDiff)
+    }
+}
+
+impl GroundTruthCalculator for SubprocessJuliaCalculator {
+    fn name(&self) -> &'static str {
+        match self.ad {
+            JuliaAd::ForwardDiff => "ForwardDiff.jl",
+            JuliaAd::Zygote => "Zygote.jl",
+        }
+    }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        if calc.num_outputs() != 1 {
+            return Err(FuzzError::UnsupportedExpr("The Julia worker only supports single-output functions".to_string()));
+        }
+        let infix = calc.infix_expr()
+            .ok_or_else(|| FuzzError::UnsupportedExpr("Calculator does not expose an infix expression".to_string()))?;
+        let (expr, _) = parse_infix(&infix)?;
+        let inputs_field: Vec<String> = inputs.iter().map(|v| format!("{:?}", v)).collect();
+        let request = format!("{}\t{}", JuliaPrinter::print(&expr, inputs.len()), inputs_field.join(" "));
+
+        let mut guard = match self.worker.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => return Err(FuzzError::backend(self.name(), "Julia worker is still busy with a request that timed out")),
+            Err(TryLockError::Poisoned(_)) => return Err(FuzzError::backend(self.name(), "Julia worker lock poisoned")),
+        };
+        if guard.is_none() {
+            *guard = Some(self.spawn_worker()?);
+        }
+        let line = match guard.as_mut().unwrap().exchange(&request, self.name(), None) {
+            Ok(line) => line,
+            Err(e) => {
+                *guard = None;
+                return Err(e);
+            }
+        };
+
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("ok") => {}
+            Some("error") => {
+                let message = line.trim_end().trim_start_matches("error").trim_start();
+                if message.starts_with("DomainError") {
+                    return Err(FuzzError::DomainError(format!("{}: {}", self.name(), message)));
+                }
+                return Err(FuzzError::backend(self.name(), format!("Julia worker error: {}", message)));
+            }
+            _ => return Err(FuzzError::backend(self.name(), format!("Bad response from Julia worker: {}", line.trim_end()))),
+        }
+        let numbers = fields
+            .map(|s| s.parse::<f64>().map_err(|e| FuzzError::backend(self.name(), format!("Bad number '{}' from Julia worker: {}", s, e))))
+            .collect::<Result<Vec<f64>, FuzzError>>()?;
+        if numbers.len() != inputs.len() + 1 {
+            return Err(FuzzError::backend(self.name(), format!("Julia worker returned {} gradients, expected {}", numbers.len().saturating_sub(1), inputs.len())));
+        }
+        Ok(GroundTruth { name: self.name(), jacobian: numbers[1..].to_vec(), value: Some(numbers[0]), hessian: None })
+    }
+}
+
+// --- MEMOIZED GROUND TRUTHS ---
+
+/// Lookups in every `CachedGroundTruth` of the process, for the campaign report.
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Hits and misses of a ground truth cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups() == 0 { 0.0 } else { self.hits as f64 / self.lookups() as f64 }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hits of {} lookups ({:.1}%)", self.hits, self.lookups(), 100.0 * self.hit_rate())
+    }
+}
+
+/// Totals of every `CachedGroundTruth` in the process.
+pub fn ground_truth_cache_stats() -> CacheStats {
+    CacheStats { hits: CACHE_HITS.load(Ordering::Relaxed), misses: CACHE_MISSES.load(Ordering::Relaxed) }
+}
+
+/// Canonical expression (see `ast_canonical`) and the bits of the inputs. The whole expression,
+/// not a hash of it, so two expressions never share an entry.
+type CacheKey = (Arc<SimpleExpr>, Vec<u64>);
+
+/// Least recently used entries go first: `order` maps the tick of each entry's last use to it.
+struct LruCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<CacheKey, (GroundTruth, u64)>,
+    order: BTreeMap<u64, CacheKey>,
+    stats: CacheStats,
+}
+
+impl LruCache {
+    fn get(&mut self, key: &CacheKey) -> Option<GroundTruth> {
+        self.tick += 1;
+        let (gt, used) = self.entries.get_mut(key)?;
+        let key = self.order.remove(used).expect("every entry is in the order");
+        *used = self.tick;
+        self.order.insert(self.tick, key);
+        Some(gt.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, gt: GroundTruth) {
+        self.tick += 1;
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.insert(self.tick, key.clone());
+        if let Some((_, replaced)) = self.entries.insert(key, (gt, self.tick)) {
+            self.order.remove(&replaced);
+        }
+    }
+}
+
+/// `inner` with an LRU cache of its results, keyed by the canonical expression
+/// (`PyTorchComputable::canonical_expr`) and the exact inputs: once a campaign's corpus
+/// converges the same expression/input pairs come back, and their PyTorch backward pass is
+/// skipped. `canonicalize` keeps values and gradients bit for bit, so `x_0 - x_1` and
+/// `-x_1 + x_0` share an entry. Only results are cached, errors are recomputed (a timeout may
+/// not happen again). Calculators without an expression, and a capacity of 0, bypass the cache.
+///
+/// Clones share the cache, so a static one lasts the whole campaign.
+#[derive(Clone)]
+pub struct CachedGroundTruth<T> {
+    inner: T,
+    cache: Arc<Mutex<LruCache>>,
+}
+
+impl<T: GroundTruthCalculator> CachedGroundTruth<T> {
+    pub fn new(inner: T, capacity: usize) -> Self {
+        let cache = LruCache { capacity, tick: 0, entries: HashMap::new(), order: BTreeMap::new(), stats: CacheStats::default() };
+        CachedGroundTruth { inner, cache: Arc::new(Mutex::new(cache)) }
+    }
+
+    /// Lookups in this cache and its clones.
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().map_or_else(|poisoned| poisoned.into_inner().stats, |cache| cache.stats)
+    }
+
+    fn key(calc: &dyn PyTorchComputable, inputs: &[f64]) -> Option<CacheKey> {
+        Some((calc.canonical_expr()?, inputs.iter().map(|x| x.to_bits()).collect()))
+    }
+}
+
+impl<T: GroundTruthCalculator + Clone + 'static> GroundTruthCalculator for CachedGroundTruth<T> {
+    fn name(&self) -> &'static str { self.inner.name() }
+
+    fn calculate(&self, calc: &dyn PyTorchComputable, inputs: &[f64]) -> Result<GroundTruth, FuzzError> {
+        let capacity = self.cache.lock().map_or(0, |cache| cache.capacity);
+        let cacheable = capacity > 0 && calc.num_outputs() == 1;
+        let key = match cacheable.then(|| Self::key(calc, inputs)).flatten() {
+            Some(key) => key,
+            None => return self.inner.calculate(calc, inputs),
+        };
+        // Not held during the calculation: a ground truth that times out keeps running
+        if let Ok(mut cache) = self.cache.lock() {
+            if let Some(gt) = cache.get(&key) {
+                cache.stats.hits += 1;
+                CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+                return Ok(gt);
+            }
+            cache.stats.misses += 1;
+            CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+        }
+        let gt = self.inner.calculate(calc, inputs)?;
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(key, gt.clone());
+        }
+        Ok(gt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+
+    #[test]
+    fn test_evalexpr_jit_matches_dual() {
+        // x_1 first, so alphabetical variable order would not be enough
+        let expr = SimpleExpr::mul(SimpleExpr::var("x_1"), SimpleExpr::sin(SimpleExpr::var("x_0")));
+        let calc = AdPyUnified::new(expr, 2, 1);
+        let inputs = [0.5, 3.0];
+        let jit = EvalexprJitCalculator.calculate(&calc, &inputs).unwrap();
+        let dual = DualNumberCalculator.calculate(&calc, &inputs).unwrap();
+        assert!((jit.value.unwrap() - dual.value.unwrap()).abs() < 1e-12);
+        for (a, b) in jit.jacobian.iter().zip(&dual.jacobian) {
+            assert!((a - b).abs() < 1e-12, "{:?} vs {:?}", jit.jacobian, dual.jacobian);
+        }
+        assert_eq!(jit.hessian.unwrap()[0][1], 0.5f64.cos());
+    }
+
+    #[cfg(feature = "pytorch")]
+    #[test]
+    fn test_pytorch_hessian_matches_forward_over_reverse() {
+        // sin(x_0) * x_1 + x_0 * x_0: a cross term and a zero entry
+        let (x, y) = (SimpleExpr::var("x_0"), SimpleExpr::var("x_1"));
+        let expr = SimpleExpr::add(SimpleExpr::mul(SimpleExpr::sin(x.clone()), y), SimpleExpr::mul(x.clone(), x));
+        let calc = AdPyUnified::new(expr, 2, 1);
+        let inputs = [0.5, 3.0];
+        let torch = PyTorchGroundTruthCalculator::default().with_hessians().calculate(&calc, &inputs).unwrap();
+        let expected = crate::oracles::forward_over_reverse_hessian(&calc, &inputs).unwrap();
+        for (row, expected) in torch.hessian.unwrap().iter().zip(&expected) {
+            for (a, b) in row.iter().zip(expected) {
+                assert!((a - b).abs() < 1e-12, "{} vs {}", a, b);
+            }
+        }
+        assert!(PyTorchGroundTruthCalculator::default().calculate(&calc, &inputs).unwrap().hessian.is_none());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pytorch_worker_protocol() {
+        use std::os::unix::fs::PermissionsExt;
+        // Stands in for python: checks the request encoding and answers with non-finite values,
+        // in CRLF lines like a worker on Windows would write; other inputs hang
+        let script = std::env::temp_dir().join(format!("fake_python_{}", std::process::id()));
+        std::fs::write(&script, concat!(
+            "#!/bin/sh\nwhile read line; do\n  case \"$line\" in\n",
+            "    *'\"expr\":\"(x_0 * inf)\",\"inputs\":[\"NaN\",\"-inf\"]'*) printf '%s\\r\\n' '{\"value\": \"nan\", \"gradients\": [\"inf\", \"-inf\"]}' ;;\n",
+            "    *) exec sleep 600 ;;\n  esac\ndone\n",
+        )).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let worker = SubprocessPyTorchCalculator::new(script.to_string_lossy()).with_read_timeout(Some(Duration::from_millis(300)));
+        let calc = AdPyUnified::new(SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::num(f64::INFINITY)), 2, 1);
+        let gt = worker.calculate(&calc, &[f64::NAN, f64::NEG_INFINITY]).unwrap();
+        assert!(gt.value.unwrap().is_nan());
+        assert_eq!(gt.jacobian, [f64::INFINITY, f64::NEG_INFINITY]);
+
+        // A worker that stops answering is killed after the read timeout, even outside `with_timeout`
+        let error = worker.calculate(&calc, &[1.0, 2.0]).unwrap_err();
+        assert!(error.to_string().contains("did not answer"), "{}", error);
+        assert!(worker.worker.try_lock().unwrap().is_none());
+        let _ = std::fs::remove_file(&script);
+    }
+
+    #[test]
+    fn test_julia_request() {
+        let (expr, n) = parse_infix("(x_1 ^ -0.5) * bool(ln(x_0))").unwrap();
+        assert_eq!(JuliaPrinter::print(&expr, n), "x -> ((x[2] ^ (-0.5)) * (v -> ifelse(v != 0, one(v), zero(v)))(log(x[1])))");
+
+        // Without julia the test case gets an error, not a panic
+        let calc = AdPyUnified::new(expr, n, 1);
+        let missing = SubprocessJuliaCalculator::new("/nonexistent/julia", JuliaAd::Zygote);
+        let error = missing.calculate(&calc, &[1.5, 2.0]).unwrap_err();
+        assert_eq!(error.kind(), "BackendFailure");
+        assert!(error.to_string().contains("Zygote.jl"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            use std::time::Duration;
+            use crate::fuzz_harness::{set_iteration_budget, with_timeout};
+            let worker = |name: &str, source: &str| {
+                let script = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+                std::fs::write(&script, source).unwrap();
+                std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+                script
+            };
+
+            // Julia's DomainError is one for the domain oracle, other exceptions are not
+            let script = worker("domain_julia", "#!/bin/sh\nwhile read line; do\n  case \"$line\" in\n    *-1.5*) echo 'error DomainError with -1.5: log was called with a negative real argument' ;;\n    *) echo 'error MethodError: no method matching foo' ;;\n  esac\ndone\n");
+            let domain = SubprocessJuliaCalculator::new(script.to_string_lossy(), JuliaAd::ForwardDiff);
+            let error = domain.calculate(&calc, &[-1.5, 2.0]).unwrap_err();
+            assert_eq!(error.kind(), "DomainError");
+            assert!(error.to_string().contains("ForwardDiff.jl: DomainError with -1.5"));
+            assert_eq!(domain.calculate(&calc, &[1.5, 2.0]).unwrap_err().kind(), "BackendFailure");
+            let _ = std::fs::remove_file(&script);
+
+            // A worker that hangs is killed when its stage times out, and frees the calculator
+            let script = worker("hung_julia", "#!/bin/sh\nexec sleep 600\n");
+            let hung = SubprocessJuliaCalculator::new(script.to_string_lossy(), JuliaAd::ForwardDiff);
+            let (job, job_calc) = (hung.clone(), calc.clone());
+            set_iteration_budget(Some(Duration::from_millis(300)));
+            let result = with_timeout("Julia", move || job.calculate(&job_calc, &[1.5, 2.0]));
+            set_iteration_budget(None);
+            assert!(result.and_then(|gt| gt).is_err());
+            let freed = (0..100).any(|_| {
+                std::thread::sleep(Duration::from_millis(50));
+                hung.worker.try_lock().is_ok_and(|worker| worker.is_none())
+            });
+            let _ = std::fs::remove_file(&script);
+            assert!(freed);
+        }
+    }
+
+    #[test]
+    fn test_cached_ground_truth() {
+        let calc = |expr| AdPyUnified::new(expr, 2, 1);
+        let (x, y) = (SimpleExpr::var("x_0"), SimpleExpr::var("x_1"));
+        let cached = CachedGroundTruth::new(DualNumberCalculator, 2);
+
+        // The same function in another form is a hit, other inputs are not
+        let first = cached.calculate(&calc(SimpleExpr::sub(x.clone(), y.clone())), &[1.5, 2.0]).unwrap();
+        let again = cached.clone().calculate(&calc(SimpleExpr::add(SimpleExpr::neg(y.clone()), x.clone())), &[1.5, 2.0]).unwrap();
+        assert_eq!((first.jacobian, first.value), (again.jacobian, again.value));
+        cached.calculate(&calc(SimpleExpr::sub(x.clone(), y.clone())), &[1.5, -2.0]).unwrap();
+        assert_eq!(cached.stats(), CacheStats { hits: 1, misses: 2 });
+
+        // Two entries: a third evicts the least recently used, [1.5, -2.0]
+        cached.calculate(&calc(SimpleExpr::sub(x.clone(), y.clone())), &[1.5, 2.0]).unwrap();
+        cached.calculate(&calc(SimpleExpr::mul(x.clone(), y.clone())), &[1.5, 2.0]).unwrap();
+        cached.calculate(&calc(SimpleExpr::sub(x.clone(), y.clone())), &[1.5, 2.0]).unwrap();
+        cached.calculate(&calc(SimpleExpr::sub(x.clone(), y.clone())), &[1.5, -2.0]).unwrap();
+        assert_eq!(cached.stats(), CacheStats { hits: 3, misses: 4 });
+
+        // Canonicalized once per expression, not per lookup
+        let diff = calc(SimpleExpr::sub(x, y));
+        assert!(Arc::ptr_eq(&diff.canonical_expr().unwrap(), &diff.clone().canonical_expr().unwrap()));
+    }
+}
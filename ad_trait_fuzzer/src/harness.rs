@@ -0,0 +1,309 @@
+// src/harness.rs
+
+// The decode -> generate -> evaluate -> check pipeline of one fuzz input as a reusable object.
+// `Harness::builder()` takes each stage separately, defaulting to what `config` (a
+// `FuzzConfig`) describes, so a fuzz target or example can swap one stage, e.g. a custom input
+// decoder or expression generator, and keep the rest of the pipeline:
+//
+//     let harness = Harness::builder()
+//         .config(config)
+//         .decoder(ExtremeInputDecoder { input_length: 2 })
+//         .ground_truths(vec![DualNumberCalculator.boxed()])
+//         .build()?;
+//     let outcome = harness.run(data);
+//
+// `fuzz_case::run_fuzz_iteration` is the harness every default stage builds.
+
+use arbitrary::Error as ArbitraryError;
+
+use crate::ast_evaluator::constant_value;
+use crate::ast_evaluator::unified::AdPyUnified;
+use crate::ast_generator::{AstGenConfig, GeneratedExpr};
+use crate::domain_analysis::out_of_domain_points;
+use crate::error::FuzzError;
+use crate::expr_coverage;
+use crate::fuzz_case::{
+    config_decoder, decode_inputs_with, expr_constants, generate_test, special_point_seed, sweep_seed, synthesis_seed, CaseResult, FuzzCase,
+    IterationOutcome, SYNTHESIS_ATTEMPTS,
+};
+use crate::fuzz_config::FuzzConfig;
+use crate::fuzz_harness::{iteration_budget_exceeded, run_ad_tests, set_iteration_budget, GroundTruthCalculator, HarnessMode};
+use crate::gt_calculators::{CachedGroundTruth, DefaultGroundTruth};
+use crate::input_decoder::{inject_special_points, FuzzInputDecoder};
+use crate::input_synthesizer::InputSynthesizer;
+use crate::oracles::FuzzingOracles;
+
+/// Expression number `i` of a fuzz input, from the bytes left after the inputs.
+pub trait ExprGenerator {
+    fn generate(&self, ast_data: &[u8], i: usize) -> Result<GeneratedExpr, ArbitraryError>;
+}
+
+/// The AST generator, see `fuzz_case::generate_test`.
+impl ExprGenerator for AstGenConfig {
+    fn generate(&self, ast_data: &[u8], i: usize) -> Result<GeneratedExpr, ArbitraryError> {
+        generate_test(ast_data, i, self)
+    }
+}
+
+impl<F: Fn(&[u8], usize) -> Result<GeneratedExpr, ArbitraryError>> ExprGenerator for F {
+    fn generate(&self, ast_data: &[u8], i: usize) -> Result<GeneratedExpr, ArbitraryError> {
+        self(ast_data, i)
+    }
+}
+
+/// One fuzz pipeline, built once and run on every input. See `HarnessBuilder` for the stages.
+pub struct Harness {
+    config: FuzzConfig,
+    decoder: Box<dyn FuzzInputDecoder>,
+    generator: Box<dyn ExprGenerator>,
+    oracles: FuzzingOracles,
+    ground_truths: Vec<Box<dyn GroundTruthCalculator>>,
+    mode: HarnessMode,
+}
+
+/// Stages of a `Harness`. Each one left unset comes from the config (`FuzzConfig::default()`
+/// unless `config` is called), which also holds the settings no stage owns: the input layout
+/// and domain, sweep, special points, `num_generated_tests` and `max_iteration`.
+pub struct HarnessBuilder {
+    config: FuzzConfig,
+    decoder: Option<Box<dyn FuzzInputDecoder>>,
+    generator: Option<Box<dyn ExprGenerator>>,
+    oracles: Option<FuzzingOracles>,
+    ground_truths: Option<Vec<Box<dyn GroundTruthCalculator>>>,
+    mode: Option<HarnessMode>,
+}
+
+impl Harness {
+    pub fn builder() -> HarnessBuilder {
+        HarnessBuilder {
+            config: FuzzConfig::default(),
+            decoder: None,
+            generator: None,
+            oracles: None,
+            ground_truths: None,
+            mode: None,
+        }
+    }
+
+    pub fn config(&self) -> &FuzzConfig { &self.config }
+
+    pub fn oracles(&self) -> &FuzzingOracles { &self.oracles }
+
+    pub fn mode(&self) -> HarnessMode { self.mode }
+
+    /// Decodes the inputs, generates `config.num_generated_tests` expressions (with
+    /// `config.inputs.synthesize`, synthesizes inputs for each from the decoded ones) and runs
+    /// each through `run_ad_tests` with the harness's ground truths as referees, at the decoded
+    /// point and then at each point of `config.inputs.sweep` the input domain accepts (and the
+    /// probes of `FuzzingOracles::probe_domain`), within `config.max_iteration` if set (sweep
+    /// points left when it runs out are dropped). In `HarnessMode::PanicOnFirstError`, nothing
+    /// runs after the first failing case: the caller panics on it.
+    pub fn run(&self, data: &[u8]) -> IterationOutcome {
+        let config = &self.config;
+        let (mut inputs, ast_data) = match decode_inputs_with(&*self.decoder, data, config) {
+            Some(decoded) => decoded,
+            None => return IterationOutcome::TooShort,
+        };
+        // x + h - x of each input of a perturbation pair, kept when special points or the sweep move x
+        let steps: Option<Vec<f64>> = config.inputs.perturbation_step.map(|_| {
+            let perturbed = inputs.split_off(inputs.len() / 2);
+            perturbed.iter().zip(&inputs).map(|(xh, x)| xh - x).collect()
+        });
+        // Always true for the sanitized decoder, which decodes within the domain; the synthesizer
+        // only starts from the decoded inputs
+        if !config.inputs.synthesize && !config.inputs.accepts(&inputs) {
+            return IterationOutcome::InputsRejected(inputs);
+        }
+
+        let mut cases = Vec::with_capacity(config.num_generated_tests);
+        set_iteration_budget(config.max_iteration);
+        for i in 0..config.num_generated_tests {
+            if iteration_budget_exceeded().is_some() {
+                cases.push(CaseResult::OverBudget);
+                continue;
+            }
+            let generated = match self.generator.generate(ast_data, i) {
+                Ok(generated) => generated,
+                Err(e) => {
+                    cases.push(CaseResult::NotGenerated(e));
+                    continue;
+                }
+            };
+            expr_coverage::record(&generated.expr);
+
+            // Constant expressions have zero gradient everywhere; not worth a PyTorch dispatch
+            if generated.num_inputs == 0 || generated.num_inputs > inputs.len() || constant_value(&generated.expr).is_some() {
+                cases.push(CaseResult::Skipped(generated));
+                continue;
+            }
+            let mut case_inputs = inputs[..generated.num_inputs].to_vec();
+            if config.inputs.synthesize {
+                let synthesizer = InputSynthesizer { domain: &config.inputs, attempts: SYNTHESIS_ATTEMPTS };
+                match synthesizer.synthesize(&generated.expr, &case_inputs, synthesis_seed(data, i)) {
+                    Some(synthesized) => case_inputs = synthesized,
+                    None => {
+                        cases.push(CaseResult::Unsatisfiable(generated));
+                        continue;
+                    }
+                }
+            }
+            let calc = AdPyUnified::new(generated.expr, generated.num_inputs, 1);
+            let rate = config.inputs.special_point_rate;
+            if rate > 0.0 {
+                let constants = expr_constants(calc.get_expr());
+                let accept = |x: &[f64]| config.inputs.accepts(x);
+                inject_special_points(&mut case_inputs, &constants, rate, special_point_seed(data, i), accept);
+            }
+            let sweep = config.inputs.sweep.points(&case_inputs, sweep_seed(data, i));
+            // Deliberately outside the domain, so not filtered
+            let probes = if self.oracles.probe_domain { out_of_domain_points(calc.get_expr(), &case_inputs) } else { Vec::new() };
+            let points = std::iter::once(case_inputs).chain(sweep.into_iter().filter(|p| config.inputs.accepts(p))).chain(probes);
+            let case_oracles = self.oracles.specialize(calc.get_expr());
+            for (point, point_inputs) in points.enumerate() {
+                if point > 0 && iteration_budget_exceeded().is_some() {
+                    break;
+                }
+                let oracles = match &steps {
+                    Some(steps) => case_oracles.with_perturbation(&point_inputs.iter().zip(steps).map(|(x, h)| x + h).collect::<Vec<_>>()),
+                    None => case_oracles.clone(),
+                };
+                let case = FuzzCase {
+                    inputs: point_inputs,
+                    oracles,
+                    expr: calc.get_expr().clone(),
+                    point,
+                };
+                expr_coverage::record_inputs(&case.expr, &case.inputs);
+                let result = match run_ad_tests(&case.inputs, calc.clone(), &case.oracles, &self.ground_truths) {
+                    Ok(outcome) => CaseResult::Tested(case, Box::new(outcome)),
+                    Err(e) => CaseResult::Error(case, e),
+                };
+                let failed = matches!(&result, CaseResult::Tested(_, outcome) if !outcome.passed());
+                cases.push(result);
+                if failed && matches!(self.mode, HarnessMode::PanicOnFirstError) {
+                    set_iteration_budget(None);
+                    return IterationOutcome::Ran { inputs, oracles: self.oracles.clone(), cases };
+                }
+            }
+        }
+        set_iteration_budget(None);
+        IterationOutcome::Ran { inputs, oracles: self.oracles.clone(), cases }
+    }
+}
+
+impl HarnessBuilder {
+    /// Settings of every stage left unset, and of the pipeline itself.
+    pub fn config(mut self, config: FuzzConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Decodes the inputs, laid out and perturbed as `config.inputs` says. Default: the one
+    /// `config.inputs.decoder` names, see `fuzz_case::config_decoder`.
+    pub fn decoder(mut self, decoder: impl FuzzInputDecoder + 'static) -> Self {
+        self.decoder = Some(Box::new(decoder));
+        self
+    }
+
+    /// Makes the expressions. Default: the AST generator with `config.ast`.
+    pub fn generator(mut self, generator: impl ExprGenerator + 'static) -> Self {
+        self.generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Default: `config.oracles()`.
+    pub fn oracles(mut self, oracles: FuzzingOracles) -> Self {
+        self.oracles = Some(oracles);
+        self
+    }
+
+    /// The referees. Default: the `DefaultGroundTruth`, memoized in a cache of
+    /// `config.gt_cache_size` entries.
+    pub fn ground_truths(mut self, ground_truths: Vec<Box<dyn GroundTruthCalculator>>) -> Self {
+        self.ground_truths = Some(ground_truths);
+        self
+    }
+
+    /// Default: `config.mode`.
+    pub fn mode(mut self, mode: HarnessMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Fails if the oracles can't be built from the config, or the decoder's settings are
+    /// invalid.
+    pub fn build(self) -> Result<Harness, FuzzError> {
+        let config = self.config;
+        let decoder = self.decoder.unwrap_or_else(|| config_decoder(&config));
+        decoder.validate().map_err(|e| FuzzError::InvalidConfig(e.to_string()))?;
+        let oracles = match self.oracles {
+            Some(oracles) => oracles,
+            None => config.oracles()?,
+        };
+        let ground_truths = self.ground_truths
+            .unwrap_or_else(|| vec![CachedGroundTruth::new(DefaultGroundTruth::default(), config.gt_cache_size).boxed()]);
+        Ok(Harness {
+            decoder,
+            generator: self.generator.unwrap_or_else(|| Box::new(config.ast.clone())),
+            oracles,
+            ground_truths,
+            mode: self.mode.unwrap_or(config.mode),
+            config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+    use crate::gt_calculators::DualNumberCalculator;
+    use crate::input_decoder::{InputDecoding, InputLayout, TwoInputDecoder};
+    use crate::oracles::OracleSelection;
+
+    #[test]
+    fn test_harness_builder() {
+        let mut config = FuzzConfig { num_generated_tests: 3, ..FuzzConfig::default() };
+        config.inputs.decoder = InputDecoding::General;
+        config.inputs.layout = InputLayout::Prefix;
+        let mut data = vec![0x3fu8; 16];
+        data.extend((0..200u8).map(|b| b.wrapping_mul(37)));
+
+        // A custom decoder and generator: x_0 * x_1 for every expression
+        let product = |_: &[u8], _: usize| -> Result<GeneratedExpr, ArbitraryError> {
+            Ok(GeneratedExpr {
+                expr: SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::var("x_1")),
+                used_vars: [0, 1].into(),
+                num_inputs: 2,
+            })
+        };
+        let harness = Harness::builder()
+            .config(config.clone())
+            .decoder(TwoInputDecoder)
+            .generator(product)
+            .oracles(FuzzingOracles::new(OracleSelection::REV_FWD))
+            .ground_truths(vec![DualNumberCalculator.boxed()])
+            .mode(HarnessMode::Continuous)
+            .build()
+            .unwrap();
+        assert!(matches!(harness.run(&[0u8; 3]), IterationOutcome::TooShort));
+        // Reusable: the same input gives the same cases
+        for _ in 0..2 {
+            match harness.run(&data) {
+                IterationOutcome::Ran { cases, .. } => {
+                    assert_eq!(cases.len(), 3);
+                    assert!(cases.iter().all(|c| matches!(c, CaseResult::Tested(case, outcome) if case.inputs.len() == 2 && outcome.passed())));
+                }
+                _ => panic!("inputs should decode and be accepted"),
+            }
+        }
+
+        // Unset stages come from the config
+        let harness = Harness::builder().config(config).build().unwrap();
+        assert!(matches!(harness.mode(), HarnessMode::PanicOnFirstError));
+        assert_eq!(harness.config().num_generated_tests, 3);
+        let mut invalid = FuzzConfig::default();
+        invalid.inputs.min_magnitude = 0.0;
+        assert!(matches!(Harness::builder().config(invalid).build(), Err(FuzzError::InvalidConfig(_))));
+    }
+}
@@ -1,61 +1,812 @@
-// src/input_decoder.rs
-
-use core::convert::TryInto;
-use std::error::Error;
-
-/// Defines the interface for converting raw fuzzer bytes into numerical inputs (f64).
-pub trait FuzzInputDecoder {
-    /// The exact number of f64 inputs this decoder expects to produce.
-    fn num_inputs(&self) -> usize;
-    
-    /// The minimum number of bytes required to satisfy the input needs (NUM_INPUTS * 8).
-    fn min_bytes(&self) -> usize { self.num_inputs() * 8 }
-    
-    /// Attempts to read and decode f64 inputs from the raw byte slice.
-    fn decode(&self, data: &[u8]) -> Result<Vec<f64>, Box<dyn Error>>;
-}
-
-/// A concrete decoder for functions that require exactly two f64 inputs (x and y).
-pub struct TwoInputDecoder;
-
-impl FuzzInputDecoder for TwoInputDecoder {
-    fn num_inputs(&self) -> usize { 2 }
-
-    fn decode(&self, data: &[u8]) -> Result<Vec<f64>, Box<dyn Error>> {
-        if data.len() < self.min_bytes() {
-            return Err("Not enough data to decode inputs".into());
-        }
-        println!("data length: {}", data.len());
-        // Decode x
-        let x_bytes: [u8; 8] = data[0..8].try_into().map_err(|_| "Failed to slice x bytes")?;
-        let x = f64::from_le_bytes(x_bytes);
-
-        // Decode y
-        let y_bytes: [u8; 8] = data[8..16].try_into().map_err(|_| "Failed to slice y bytes")?;
-        let y = f64::from_le_bytes(y_bytes);
-
-        Ok(vec![x, y])
-    }
-}
-
-pub struct GeneralInputDecoder
-{
-    pub input_length: usize
-}
-
-impl FuzzInputDecoder for GeneralInputDecoder
-{
-    fn num_inputs(&self) -> usize { self.input_length }
-
-    fn decode(&self, data: &[u8]) -> Result<Vec<f64>, Box<dyn Error>>
-    {
-        let mut ret_val: Vec<f64> = vec![];
-        ret_val.resize(self.input_length, 0.0);
-        for (i, el) in ret_val.iter_mut().enumerate()
-        {
-            let bytes: [u8; 8] = data[i..(8 + i)].try_into().map_err(|_| "Failed to slice bytes")?;
-            *el = f64::from_le_bytes(bytes);
-        } 
-        return Ok(ret_val);
-    }
-}
+// src/input_decoder.rs
+
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use arbitrary::Unstructured;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+/// Bytes per decoded input.
+pub const BYTES_PER_INPUT: usize = 8;
+
+/// Why fuzzer bytes did not decode into inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// The decoder needs `needed` bytes, only `available` are left.
+    NotEnoughData { needed: usize, available: usize },
+    /// The decoder's settings can't produce inputs, e.g. an empty magnitude range.
+    InvalidSettings(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::NotEnoughData { needed, available } => {
+                write!(f, "{} bytes needed to decode the inputs, {} available", needed, available)
+            }
+            DecodeError::InvalidSettings(msg) => write!(f, "invalid decoder settings: {}", msg),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Turns raw fuzzer bytes into f64 inputs, `BYTES_PER_INPUT` bytes each. Implementors decode
+/// one chunk; `decode` and `decode_prefix` do the bounds checks and bookkeeping.
+pub trait FuzzInputDecoder {
+    /// The exact number of f64 inputs this decoder produces.
+    fn num_inputs(&self) -> usize;
+
+    /// Input `index` from its chunk of bytes.
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64;
+
+    /// Checks the decoder's settings before any bytes are consumed.
+    fn validate(&self) -> Result<(), DecodeError> { Ok(()) }
+
+    /// The most bytes `decode` consumes; exactly this many for decoders of fixed chunks.
+    fn num_bytes(&self) -> usize { self.num_inputs() * BYTES_PER_INPUT }
+
+    /// Decodes the inputs from the front of `u`, consuming `num_bytes()`. Nothing is consumed
+    /// on error.
+    fn decode(&self, u: &mut Unstructured) -> Result<Vec<f64>, DecodeError> {
+        decode_chunks(self, u)
+    }
+
+    /// The inputs decoded from the front of `data`, and the number of bytes they took.
+    fn decode_prefix(&self, data: &[u8]) -> Result<(Vec<f64>, usize), DecodeError> {
+        let mut u = Unstructured::new(data);
+        let inputs = self.decode(&mut u)?;
+        Ok((inputs, data.len() - u.len()))
+    }
+}
+
+/// Input slots `FuzzInputSplitter::default()` can give the inputs region.
+pub const MAX_INPUT_SLOTS: usize = 16;
+
+/// Splits a fuzz input into an inputs region and an AST region. Byte 0 is a header: the inputs
+/// region is the next `header % (max_slots + 1)` slots of `BYTES_PER_INPUT` bytes, the AST
+/// region everything after. Where the AST bytes start depends on the header alone, not on how
+/// many variables are decoded, so a corpus entry generates the same expressions whatever
+/// `max_variables` is, and only a mutation of the header moves them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzInputSplitter {
+    pub max_slots: usize,
+}
+
+impl Default for FuzzInputSplitter {
+    fn default() -> Self {
+        FuzzInputSplitter { max_slots: MAX_INPUT_SLOTS }
+    }
+}
+
+impl FuzzInputSplitter {
+    /// The inputs region and the AST region of `data`; `None` if there is no header byte. The
+    /// inputs region is cut short if `data` ends inside it.
+    pub fn split<'a>(&self, data: &'a [u8]) -> Option<(&'a [u8], &'a [u8])> {
+        let (&header, rest) = data.split_first()?;
+        let len = (header as usize % (self.max_slots + 1)) * BYTES_PER_INPUT;
+        Some(rest.split_at(len.min(rest.len())))
+    }
+
+    /// The inputs `decoder` makes of the inputs region, and the AST region. Inputs past the end
+    /// of the region decode from zero bytes.
+    pub fn decode<'a>(&self, decoder: &dyn FuzzInputDecoder, data: &'a [u8]) -> Result<(Vec<f64>, &'a [u8]), DecodeError> {
+        let (region, ast) = self.split(data).ok_or(DecodeError::NotEnoughData { needed: 1, available: 0 })?;
+        let mut bytes = region.to_vec();
+        bytes.resize(bytes.len().max(decoder.num_bytes()), 0);
+        let (inputs, _) = decoder.decode_prefix(&bytes)?;
+        Ok((inputs, ast))
+    }
+}
+
+/// `FuzzInputDecoder::decode` as provided: `decode_chunk` on each chunk.
+fn decode_chunks<D: FuzzInputDecoder + ?Sized>(decoder: &D, u: &mut Unstructured) -> Result<Vec<f64>, DecodeError> {
+    decoder.validate()?;
+    let needed = decoder.num_bytes();
+    if u.len() < needed {
+        return Err(DecodeError::NotEnoughData { needed, available: u.len() });
+    }
+    let bytes = u.bytes(needed).expect("length checked above");
+    Ok(bytes.chunks_exact(BYTES_PER_INPUT).enumerate().map(|(i, chunk)| {
+        decoder.decode_chunk(i, chunk.try_into().expect("chunks_exact"))
+    }).collect())
+}
+
+/// Perturbation pairs: the `n` inputs of `inner`, then `x + h` for a small step `h` the fuzzer
+/// picks, `2n` values from `2n` chunks. Chunk `n + i` sets `h_i = step * t * max(1, |x_i|)`,
+/// `t` in `[-1, -0.5]` or `[0.5, 1]`, so every input moves. The `secant` oracle checks the AD
+/// gradient against `f(x + h) - f(x)`.
+pub struct PerturbedInputDecoder<'a> {
+    pub inner: &'a dyn FuzzInputDecoder,
+    pub step: f64,
+}
+
+impl FuzzInputDecoder for PerturbedInputDecoder<'_> {
+    fn num_inputs(&self) -> usize { 2 * self.inner.num_inputs() }
+
+    fn validate(&self) -> Result<(), DecodeError> {
+        if !(self.step > 0.0 && self.step.is_finite()) {
+            return Err(DecodeError::InvalidSettings(format!("perturbation step {} is not positive and finite", self.step)));
+        }
+        self.inner.validate()
+    }
+
+    /// `x_i` for the first `n` chunks, then `t` of `h_i`.
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64 {
+        let n = self.inner.num_inputs();
+        if index < n {
+            return self.inner.decode_chunk(index, chunk);
+        }
+        let t = 0.5 + 0.5 * (u64::from_le_bytes(*chunk) >> 11) as f64 / (1u64 << 53) as f64;
+        if chunk[0] & 0x01 != 0 { -t } else { t }
+    }
+
+    fn decode(&self, u: &mut Unstructured) -> Result<Vec<f64>, DecodeError> {
+        let mut values = decode_chunks(self, u)?;
+        let n = self.inner.num_inputs();
+        for i in 0..n {
+            let x = values[i];
+            values[n + i] = x + self.step * values[n + i] * x.abs().max(1.0);
+        }
+        Ok(values)
+    }
+}
+
+/// A concrete decoder for functions that require exactly two f64 inputs (x and y).
+pub struct TwoInputDecoder;
+
+impl FuzzInputDecoder for TwoInputDecoder {
+    fn num_inputs(&self) -> usize { 2 }
+
+    fn decode_chunk(&self, _: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64 {
+        f64::from_le_bytes(*chunk)
+    }
+}
+
+/// Raw f64 bits. Variable i is mapped into `domains[i]` if given (see `map_into`); variables
+/// past the end of `domains` are returned as decoded, NaN and infinities included.
+pub struct GeneralInputDecoder
+{
+    pub input_length: usize,
+    pub domains: Vec<RangeInclusive<f64>>,
+}
+
+/// `x` if it lies in `domain`, otherwise a point of `domain` picked by the bits of `x`, so NaN,
+/// infinities and out-of-range values still spread over the whole interval.
+pub fn map_into(x: f64, domain: &RangeInclusive<f64>) -> f64 {
+    if domain.contains(&x) {
+        return x;
+    }
+    let (lo, hi) = (*domain.start(), *domain.end());
+    let t = (x.to_bits() >> 11) as f64 / (1u64 << 53) as f64;
+    // Not lo + t * (hi - lo): the width overflows for domains like [-MAX, MAX]
+    (lo * (1.0 - t) + hi * t).clamp(lo, hi)
+}
+
+impl FuzzInputDecoder for GeneralInputDecoder
+{
+    fn num_inputs(&self) -> usize { self.input_length }
+
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64
+    {
+        let x = f64::from_le_bytes(*chunk);
+        match self.domains.get(index) {
+            Some(domain) => map_into(x, domain),
+            None => x,
+        }
+    }
+}
+
+/// Which decoder `fuzz_case::decode_inputs` uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputDecoding {
+    /// `SanitizedInputDecoder`: finite, log-uniform magnitudes within the input domain.
+    Sanitized,
+    /// `GeneralInputDecoder`: raw f64 bits.
+    General,
+    /// `ExtremeInputDecoder`: subnormals, values near `f64::MAX` and around overflow thresholds.
+    Extreme,
+    /// `DyadicInputDecoder`: small `k / 2^n`, exact in binary.
+    Dyadic,
+    /// `ArbitraryInputDecoder`: drawn with `arbitrary`, like the expressions.
+    Arbitrary,
+}
+
+impl FromStr for InputDecoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "sanitized" => Ok(InputDecoding::Sanitized),
+            "general" => Ok(InputDecoding::General),
+            "extreme" => Ok(InputDecoding::Extreme),
+            "dyadic" => Ok(InputDecoding::Dyadic),
+            "arbitrary" => Ok(InputDecoding::Arbitrary),
+            _ => Err(format!("unknown decoder '{}' (expected \"sanitized\", \"general\", \"extreme\", \"dyadic\" or \"arbitrary\")", s)),
+        }
+    }
+}
+
+/// Where `fuzz_case::decode_inputs` finds the input bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputLayout {
+    /// `FuzzInputSplitter`: a header byte sizes the inputs region.
+    Header,
+    /// The first `max_variables * BYTES_PER_INPUT` bytes, how artifacts from before the
+    /// splitter decode.
+    Prefix,
+}
+
+impl FromStr for InputLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "header" => Ok(InputLayout::Header),
+            "prefix" => Ok(InputLayout::Prefix),
+            _ => Err(format!("unknown input layout '{}' (expected \"header\" or \"prefix\")", s)),
+        }
+    }
+}
+
+/// Exact values `SanitizedInputDecoder` substitutes with `special_values`: the arguments where
+/// hand-written derivatives are easy to check (before the sign is applied).
+pub const NICE_VALUES: [f64; 8] = [
+    1.0,
+    0.5,
+    2.0,
+    10.0,
+    std::f64::consts::PI,
+    std::f64::consts::FRAC_PI_2,
+    std::f64::consts::E,
+    std::f64::consts::LN_2,
+];
+
+/// Finite inputs in the input domain, so none are thrown away by `InputDomain::accepts`. Per
+/// 8-byte chunk: bit 7 of byte 0 is the sign, bytes 1..8 pick a magnitude log-uniformly in
+/// `[min_magnitude, max_magnitude]` (`max_magnitude_first` for x_0), so every order of
+/// magnitude is about as likely. With `special_values`, a low nibble of 0 in byte 0 (1 in 16)
+/// picks one of `NICE_VALUES` with byte 1 instead.
+pub struct SanitizedInputDecoder
+{
+    pub input_length: usize,
+    pub min_magnitude: f64,
+    pub max_magnitude: f64,
+    pub max_magnitude_first: f64,
+    /// Ignore the sign bit for x_0 (see `InputDomain::allow_non_positive`).
+    pub positive_first: bool,
+    pub special_values: bool,
+}
+
+impl SanitizedInputDecoder
+{
+    fn magnitude(&self, chunk: &[u8; BYTES_PER_INPUT], max: f64) -> f64
+    {
+        if self.special_values && chunk[0] & 0x0f == 0 {
+            return NICE_VALUES[chunk[1] as usize % NICE_VALUES.len()].clamp(self.min_magnitude, max);
+        }
+        let mut bytes = [0u8; 8];
+        bytes[..7].copy_from_slice(&chunk[1..]);
+        let t = u64::from_le_bytes(bytes) as f64 / (1u64 << 56) as f64;
+        let (lo, hi) = (self.min_magnitude.ln(), max.ln());
+        (lo + t * (hi - lo)).exp().clamp(self.min_magnitude, max)
+    }
+}
+
+impl FuzzInputDecoder for SanitizedInputDecoder
+{
+    fn num_inputs(&self) -> usize { self.input_length }
+
+    fn validate(&self) -> Result<(), DecodeError>
+    {
+        let valid = |max: f64| self.min_magnitude > 0.0 && self.min_magnitude <= max && max.is_finite();
+        if !valid(self.max_magnitude) || !valid(self.max_magnitude_first) {
+            return Err(DecodeError::InvalidSettings(format!(
+                "magnitude range [{}, {}] (x_0: {}) is not a positive, finite range",
+                self.min_magnitude, self.max_magnitude, self.max_magnitude_first
+            )));
+        }
+        Ok(())
+    }
+
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64
+    {
+        let first = index == 0;
+        let magnitude = self.magnitude(chunk, if first { self.max_magnitude_first } else { self.max_magnitude });
+        let negative = chunk[0] & 0x80 != 0 && !(first && self.positive_first);
+        if negative { -magnitude } else { magnitude }
+    }
+}
+
+/// Inputs where derivative bugs cluster and byte decoding practically never lands: signed
+/// zeros, ±1, ±epsilon and subnormals (the smallest one and the largest half of the range).
+pub const SPECIAL_POINTS: [f64; 10] = [
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    f64::EPSILON,
+    -f64::EPSILON,
+    5e-324,
+    -5e-324,
+    1.1125369292536007e-308,
+    -1.1125369292536007e-308,
+];
+
+/// Replaces each input, with probability `rate`, by one of `SPECIAL_POINTS` or `constants`
+/// (the expression's own constants, where `x - c` is zero and `c / x` is one). Replacements
+/// `accept` rejects are undone. Deterministic in `seed`, so saved inputs replay exactly.
+pub fn inject_special_points(inputs: &mut [f64], constants: &[f64], rate: f64, seed: u64, accept: impl Fn(&[f64]) -> bool) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let candidates: Vec<f64> = SPECIAL_POINTS.iter().chain(constants).copied().collect();
+    for i in 0..inputs.len() {
+        if !rng.gen_bool(rate.clamp(0.0, 1.0)) {
+            continue;
+        }
+        let decoded = inputs[i];
+        inputs[i] = *candidates.choose(&mut rng).expect("SPECIAL_POINTS is not empty");
+        if !accept(inputs) {
+            inputs[i] = decoded;
+        }
+    }
+}
+
+/// Largest `N` of `random:N` and `grid:N`, and the points a grid sweep is cut off at, see
+/// `PointSweep::points`. Every point is a full test case.
+pub const MAX_SWEEP_POINTS: usize = 256;
+
+/// How many points around the decoded one each expression is also tested at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepMode {
+    /// Only the decoded point.
+    Off,
+    /// This many points, at random.
+    Random(usize),
+    /// This many steps per input, every combination.
+    Grid(usize),
+}
+
+impl FromStr for SweepMode {
+    type Err = String;
+
+    /// `off`, `random:N` or `grid:N`, `N` up to `MAX_SWEEP_POINTS`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let err = || format!("unknown sweep '{}' (expected \"off\", \"random:N\" or \"grid:N\" with 0 < N <= {})", s, MAX_SWEEP_POINTS);
+        let s = s.to_ascii_lowercase();
+        if s == "off" {
+            return Ok(SweepMode::Off);
+        }
+        let (kind, n) = s.split_once(':').ok_or_else(err)?;
+        let n: usize = n.trim().parse().ok().filter(|&n| n > 0 && n <= MAX_SWEEP_POINTS).ok_or_else(err)?;
+        match kind.trim() {
+            "random" => Ok(SweepMode::Random(n)),
+            "grid" => Ok(SweepMode::Grid(n)),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Extra points an expression is tested at: each input of the decoded point scaled by a factor
+/// in `[1 / spread, spread]`, so signs and orders of magnitude (and with them the input domain)
+/// are mostly kept. Each point is a test case of its own; only the AD engines carry over (see
+/// `fuzz_harness::AdEngines`), ground truths and evalexpr-jit are set up again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointSweep {
+    pub mode: SweepMode,
+    pub spread: f64,
+}
+
+impl Default for PointSweep {
+    fn default() -> Self {
+        PointSweep { mode: SweepMode::Off, spread: 2.0 }
+    }
+}
+
+impl PointSweep {
+    /// The points around `base`, not including it. Random factors are log-uniform and
+    /// deterministic in `seed`; grid factors are geometric steps from `1 / spread` to `spread`,
+    /// the first `MAX_SWEEP_POINTS` combinations.
+    pub fn points(&self, base: &[f64], seed: u64) -> Vec<Vec<f64>> {
+        let ln_spread = self.spread.ln();
+        let mut points: Vec<Vec<f64>> = match self.mode {
+            SweepMode::Off => return Vec::new(),
+            SweepMode::Random(n) => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                (0..n).map(|_| base.iter().map(|x| x * (rng.gen_range(-1.0..=1.0) * ln_spread).exp()).collect()).collect()
+            }
+            SweepMode::Grid(steps) => {
+                let factors: Vec<f64> = (0..steps).map(|k| match steps {
+                    1 => 1.0,
+                    _ => ((2.0 * k as f64 / (steps - 1) as f64 - 1.0) * ln_spread).exp(),
+                }).collect();
+                let total = steps.saturating_pow(base.len() as u32).min(MAX_SWEEP_POINTS);
+                (0..total).map(|mut p| base.iter().map(|x| {
+                    let factor = factors[p % steps];
+                    p /= steps;
+                    x * factor
+                }).collect()).collect()
+            }
+        };
+        points.retain(|p| p.as_slice() != base);
+        points
+    }
+}
+
+/// Small dyadic rationals `k / 2^n`, `1 <= k <= max_numerator` and `n <= max_exponent`, so sums,
+/// products and squares of inputs stay exact and an oracle mismatch is more likely a derivative
+/// rule than rounding. Per 8-byte chunk: bit 7 of byte 0 is the sign, byte 1 picks `n`, bytes
+/// 2..6 pick `k`. Halved until within `max_abs` (`max_abs_first` for x_0), which keeps them dyadic.
+pub struct DyadicInputDecoder
+{
+    pub input_length: usize,
+    pub max_numerator: u32,
+    pub max_exponent: u32,
+    pub max_abs: f64,
+    pub max_abs_first: f64,
+    /// Ignore the sign bit for x_0 (see `InputDomain::allow_non_positive`).
+    pub positive_first: bool,
+}
+
+impl FuzzInputDecoder for DyadicInputDecoder
+{
+    fn num_inputs(&self) -> usize { self.input_length }
+
+    fn validate(&self) -> Result<(), DecodeError>
+    {
+        if self.max_numerator == 0 || self.max_exponent > 1000 {
+            return Err(DecodeError::InvalidSettings(format!(
+                "dyadic numerator 1..={} and exponent 0..={} are out of range", self.max_numerator, self.max_exponent
+            )));
+        }
+        if !(self.max_abs > 0.0 && self.max_abs_first > 0.0) {
+            return Err(DecodeError::InvalidSettings(format!(
+                "dyadic bounds {} (x_0: {}) are not positive", self.max_abs, self.max_abs_first
+            )));
+        }
+        Ok(())
+    }
+
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64
+    {
+        let first = index == 0;
+        let n = chunk[1] as u32 % (self.max_exponent + 1);
+        let k = 1 + u32::from_le_bytes([chunk[2], chunk[3], chunk[4], chunk[5]]) % self.max_numerator;
+        let max = if first { self.max_abs_first } else { self.max_abs };
+        let mut magnitude = k as f64 / 2f64.powi(n as i32);
+        while magnitude > max && magnitude > f64::MIN_POSITIVE {
+            magnitude /= 2.0;
+        }
+        let negative = chunk[0] & 0x80 != 0 && !(first && self.positive_first);
+        if negative { -magnitude } else { magnitude }
+    }
+}
+
+/// Most bytes `ArbitraryInputDecoder` draws one input from: the kind byte and raw `f64` bits.
+pub const ARBITRARY_INPUT_BYTES: usize = 1 + 8;
+
+/// Inputs drawn from an `Unstructured` with `arbitrary`, like the expressions are, so a
+/// mutated byte changes one choice instead of scattering the bits of an f64. Per input, one
+/// byte picks the kind: one of `SPECIAL_POINTS` or `NICE_VALUES`, a small integer, raw `f64`
+/// bits, or (half the time) a sign and a log-uniform magnitude in `[min_magnitude,
+/// max_magnitude]`. The value is then mapped into its interval of `domains` (see `map_into`),
+/// so every input is accepted. Takes 1 to `ARBITRARY_INPUT_BYTES` bytes per input, at least one
+/// each; inputs past the end of the data take the first choice of each draw.
+pub struct ArbitraryInputDecoder
+{
+    pub min_magnitude: f64,
+    pub max_magnitude: f64,
+    /// Interval of each input, one per input, see `InputDomain::interval`.
+    pub domains: Vec<RangeInclusive<f64>>,
+}
+
+impl ArbitraryInputDecoder
+{
+    fn draw(&self, index: usize, u: &mut Unstructured) -> arbitrary::Result<f64>
+    {
+        let x = match u.int_in_range(0u8..=7)? {
+            0 => *u.choose(&SPECIAL_POINTS)?,
+            1 => *u.choose(&NICE_VALUES)?,
+            2 => u.int_in_range(-64i32..=64)? as f64,
+            3 => u.arbitrary::<f64>()?,
+            _ => {
+                let negative: bool = u.arbitrary()?;
+                let t = u.int_in_range(0..=u32::MAX)? as f64 / u32::MAX as f64;
+                let (lo, hi) = (self.min_magnitude.ln(), self.max_magnitude.ln());
+                let magnitude = (lo + t * (hi - lo)).exp().clamp(self.min_magnitude, self.max_magnitude);
+                if negative { -magnitude } else { magnitude }
+            }
+        };
+        Ok(map_into(x, &self.domains[index]))
+    }
+}
+
+impl FuzzInputDecoder for ArbitraryInputDecoder
+{
+    fn num_inputs(&self) -> usize { self.domains.len() }
+
+    fn num_bytes(&self) -> usize { self.num_inputs() * ARBITRARY_INPUT_BYTES }
+
+    fn validate(&self) -> Result<(), DecodeError>
+    {
+        if !(self.min_magnitude > 0.0 && self.min_magnitude <= self.max_magnitude && self.max_magnitude.is_finite()) {
+            return Err(DecodeError::InvalidSettings(format!(
+                "magnitude range [{}, {}] is not a positive, finite range", self.min_magnitude, self.max_magnitude
+            )));
+        }
+        if let Some((i, domain)) = self.domains.iter().enumerate().find(|(_, d)| d.is_empty() || !d.start().is_finite() || !d.end().is_finite()) {
+            return Err(DecodeError::InvalidSettings(format!("interval {:?} of x_{} is empty or not finite", domain, i)));
+        }
+        Ok(())
+    }
+
+    /// One input drawn from its chunk alone, for wrappers that decode fixed chunks like
+    /// `PerturbedInputDecoder`.
+    fn decode_chunk(&self, index: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64
+    {
+        self.draw(index, &mut Unstructured::new(chunk)).expect("draws from fixed, non-empty ranges")
+    }
+
+    fn decode(&self, u: &mut Unstructured) -> Result<Vec<f64>, DecodeError>
+    {
+        self.validate()?;
+        if u.len() < self.num_inputs() {
+            return Err(DecodeError::NotEnoughData { needed: self.num_inputs(), available: u.len() });
+        }
+        Ok((0..self.num_inputs()).map(|i| self.draw(i, u).expect("draws from fixed, non-empty ranges")).collect())
+    }
+}
+
+/// Magnitudes where f64 arithmetic changes regime: the subnormal boundary, and the arguments
+/// above/below which exp, x^2 and x^3 overflow or underflow.
+pub const OVERFLOW_THRESHOLDS: [f64; 8] = [
+    f64::MIN_POSITIVE,
+    709.782712893384,           // exp(x) overflows above
+    -745.1332191019411,         // exp(x) underflows to 0 below
+    1.3407807929942596e154,     // x^2 overflows above (sqrt(MAX))
+    1.4916681462400413e-154,    // x^2 is subnormal below (sqrt(MIN_POSITIVE))
+    5.643803094122362e102,      // x^3 overflows above (cbrt(MAX))
+    1.0,
+    f64::EPSILON,
+];
+
+/// Inputs for the extreme-magnitude target. Byte 0 of each 8-byte chunk picks the regime and
+/// sign, bytes 1..8 the value within it:
+/// - subnormal
+/// - in [2^1023, f64::MAX]
+/// - within 128 ulps of one of `OVERFLOW_THRESHOLDS`
+pub struct ExtremeInputDecoder
+{
+    pub input_length: usize
+}
+
+impl FuzzInputDecoder for ExtremeInputDecoder
+{
+    fn num_inputs(&self) -> usize { self.input_length }
+
+    fn decode_chunk(&self, _: usize, chunk: &[u8; BYTES_PER_INPUT]) -> f64
+    {
+        let tag = chunk[0];
+        let mut bytes = [0u8; 8];
+        bytes[..7].copy_from_slice(&chunk[1..]);
+        let bits = u64::from_le_bytes(bytes);
+        let mantissa = bits & ((1 << 52) - 1);
+        let magnitude = match tag & 0x3 {
+            0 => f64::from_bits(mantissa.max(1)),
+            1 => f64::from_bits((0x7fe << 52) | mantissa),
+            _ => {
+                let threshold = OVERFLOW_THRESHOLDS[(bits >> 52) as usize % OVERFLOW_THRESHOLDS.len()];
+                let ulps = (mantissa & 0xff) as i64 - 128;
+                f64::from_bits((threshold.to_bits() as i64 + ulps) as u64)
+            }
+        };
+        if tag & 0x80 != 0 { -magnitude } else { magnitude }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extreme_decoder() {
+        let decoder = ExtremeInputDecoder { input_length: 3 };
+        let mut data = vec![0u8; 24];
+        data[0] = 0x80;                         // negative subnormal
+        data[1] = 0x05;
+        data[8] = 0x01;                         // near MAX
+        data[16] = 0x02;                        // threshold 1 (exp overflow), +0 ulps
+        data[17] = 0x80;
+        data[23] = 0x10;
+        let inputs = decoder.decode_prefix(&data).unwrap().0;
+        assert_eq!(inputs[0], -f64::from_bits(5));
+        assert!(inputs[1] >= 2f64.powi(1023) && inputs[1].is_finite());
+        assert_eq!(inputs[2], OVERFLOW_THRESHOLDS[1]);
+        assert_eq!(decoder.decode_prefix(&data[..20]), Err(DecodeError::NotEnoughData { needed: 24, available: 20 }));
+
+        // Exactly num_bytes() are consumed, the rest is left for the AST
+        let mut u = Unstructured::new(&[0u8; 30]);
+        decoder.decode(&mut u).unwrap();
+        assert_eq!(u.len(), 6);
+    }
+
+    #[test]
+    fn test_arbitrary_decoder() {
+        let decoder = ArbitraryInputDecoder { min_magnitude: 1e-6, max_magnitude: 1e6, domains: vec![1e-3..=10.0, -2.0..=2.0] };
+        // Every kind of value lands in its interval, from 1 to ARBITRARY_INPUT_BYTES bytes each
+        for kind in 0..8u8 {
+            let data: Vec<u8> = [kind, 0xa7, 0x13, 0xff, 0x80, 0x3c, 0x55, 0x09, 0xee].iter().cycle().take(40).copied().collect();
+            let mut u = Unstructured::new(&data);
+            let inputs = decoder.decode(&mut u).unwrap();
+            assert!(inputs.iter().zip(&decoder.domains).all(|(x, domain)| domain.contains(x)));
+            assert!((2..=decoder.num_bytes()).contains(&(data.len() - u.len())));
+        }
+        assert_eq!(decoder.decode_prefix(&[0]), Err(DecodeError::NotEnoughData { needed: 2, available: 1 }));
+
+        // Chunk by chunk under a wrapper
+        let perturbed = PerturbedInputDecoder { inner: &decoder, step: 1e-7 };
+        assert_eq!(perturbed.decode_prefix(&[0x45; 32]).unwrap().0.len(), 4);
+        assert!(ArbitraryInputDecoder { min_magnitude: 0.0, ..decoder }.validate().is_err());
+    }
+
+    #[test]
+    fn test_splitter() {
+        let splitter = FuzzInputSplitter::default();
+        assert_eq!(splitter.split(&[]), None);
+        let mut data = vec![2u8];
+        data.extend(1.5f64.to_le_bytes());
+        data.extend(2.5f64.to_le_bytes());
+        data.extend([7, 8, 9]);
+        assert_eq!(splitter.split(&data), Some((&data[1..17], &data[17..])));
+
+        // More variables: the missing one is zero, the AST region does not move
+        let two = GeneralInputDecoder { input_length: 2, domains: Vec::new() };
+        let three = GeneralInputDecoder { input_length: 3, domains: Vec::new() };
+        assert_eq!(splitter.decode(&two, &data), Ok((vec![1.5, 2.5], &[7u8, 8, 9][..])));
+        assert_eq!(splitter.decode(&three, &data), Ok((vec![1.5, 2.5, 0.0], &[7u8, 8, 9][..])));
+
+        // A region running past the end of the data is cut short
+        assert_eq!(splitter.split(&[16, 1, 2]), Some((&[1u8, 2][..], &[][..])));
+    }
+
+    #[test]
+    fn test_general_decoder_domains() {
+        let decoder = GeneralInputDecoder { input_length: 2, domains: vec![f64::MIN_POSITIVE..=10.0] };
+        // NaN bits: x_0 is mapped into its domain, x_1 has none and stays NaN
+        let inputs = decoder.decode_prefix(&[0xff; 16]).unwrap().0;
+        assert!(inputs[0] >= f64::MIN_POSITIVE && inputs[0] <= 10.0);
+        assert!(inputs[1].is_nan());
+        // In range: unchanged
+        let decoder = GeneralInputDecoder { input_length: 1, ..decoder };
+        assert_eq!(decoder.decode_prefix(&5.0f64.to_le_bytes()).unwrap(), (vec![5.0], 8));
+
+        for bits in [f64::NAN.to_bits(), f64::NEG_INFINITY.to_bits(), (-3.0f64).to_bits(), 1e300f64.to_bits(), 0] {
+            let x = map_into(f64::from_bits(bits), &(f64::MIN_POSITIVE..=10.0));
+            assert!((f64::MIN_POSITIVE..=10.0).contains(&x), "{:e}", x);
+        }
+        assert!(map_into(f64::NAN, &(-f64::MAX..=f64::MAX)).is_finite());
+    }
+
+    #[test]
+    fn test_inject_special_points() {
+        let decoded = [0.3, 0.7, 1.3];
+        let is_special = |x: f64| SPECIAL_POINTS.iter().any(|p| p.to_bits() == x.to_bits()) || x == 42.0;
+        for seed in 0..32 {
+            let mut inputs = decoded;
+            inject_special_points(&mut inputs, &[42.0], 1.0, seed, |_| true);
+            assert!(inputs.iter().all(|&x| is_special(x)));
+
+            let mut again = decoded;
+            inject_special_points(&mut again, &[42.0], 1.0, seed, |_| true);
+            assert_eq!(inputs.map(f64::to_bits), again.map(f64::to_bits));
+
+            // Rejected replacements are undone
+            let mut positive = decoded;
+            inject_special_points(&mut positive, &[], 1.0, seed, |x| x[0] > 0.0);
+            assert!(positive[0] > 0.0);
+        }
+        let mut untouched = decoded;
+        inject_special_points(&mut untouched, &[42.0], 0.0, 7, |_| true);
+        assert_eq!(untouched, decoded);
+    }
+
+    #[test]
+    fn test_perturbed_decoder() {
+        let inner = GeneralInputDecoder { input_length: 2, domains: Vec::new() };
+        let decoder = PerturbedInputDecoder { inner: &inner, step: 1e-6 };
+        let mut data = Vec::new();
+        data.extend(3.0f64.to_le_bytes());
+        data.extend((-200.0f64).to_le_bytes());
+        data.extend([0u8; 8]);
+        data.extend([0xffu8; 8]);
+        let (inputs, consumed) = decoder.decode_prefix(&data).unwrap();
+        assert_eq!(consumed, 32);
+        assert_eq!(&inputs[..2], &[3.0, -200.0]);
+        // Zero bytes: +step / 2 relative, 0xff: about -step relative
+        assert!((inputs[2] - 3.0 - 1.5e-6).abs() < 1e-12);
+        assert!((inputs[3] + 200.0 + 2e-4).abs() < 1e-9);
+        assert!(PerturbedInputDecoder { inner: &inner, step: 0.0 }.decode_prefix(&data).is_err());
+    }
+
+    #[test]
+    fn test_dyadic_decoder() {
+        let decoder = DyadicInputDecoder {
+            input_length: 3,
+            max_numerator: 64,
+            max_exponent: 8,
+            max_abs: 100.0,
+            max_abs_first: 0.3,
+            positive_first: true,
+        };
+        for seed in 0..=255u8 {
+            let data: Vec<u8> = (0..24u8).map(|i| seed.wrapping_mul(57).wrapping_add(i.wrapping_mul(13))).collect();
+            let inputs = decoder.decode_prefix(&data).unwrap().0;
+            assert!(inputs[0] > 0.0 && inputs[0] <= 0.3);
+            for x in inputs {
+                // k / 2^n with k < 2^7: at most 7 significant bits
+                assert_eq!(x.to_bits() & ((1 << 46) - 1), 0, "{} is not a small dyadic", x);
+                assert!(x.abs() <= 100.0);
+            }
+        }
+        // Sign, n = 2, k = 1 + 4: x_1 = -5/4
+        let mut data = [0u8; 24];
+        data[8] = 0x80;
+        data[9] = 2;
+        data[10] = 4;
+        assert_eq!(decoder.decode_prefix(&data).unwrap().0[1], -1.25);
+        assert!(DyadicInputDecoder { max_numerator: 0, ..decoder }.decode_prefix(&data).is_err());
+    }
+
+    #[test]
+    fn test_point_sweep() {
+        assert_eq!("grid:3".parse(), Ok(SweepMode::Grid(3)));
+        assert!("grid:0".parse::<SweepMode>().is_err());
+        assert!("spiral:3".parse::<SweepMode>().is_err());
+        assert_eq!("random:256".parse::<SweepMode>(), Ok(SweepMode::Random(256)));
+        assert!("random:257".parse::<SweepMode>().is_err() && "grid:100000".parse::<SweepMode>().is_err());
+
+        let base = [1.0, -4.0];
+        let grid = PointSweep { mode: SweepMode::Grid(3), spread: 2.0 };
+        let points = grid.points(&base, 0);
+        // 3 x 3 combinations of 0.5, 1 and 2, without the base point
+        assert_eq!(points.len(), 8);
+        assert!(points.contains(&vec![0.5, -8.0]) && points.contains(&vec![2.0, -4.0]));
+
+        let random = PointSweep { mode: SweepMode::Random(20), spread: 10.0 };
+        let points = random.points(&base, 7);
+        assert_eq!(points, random.points(&base, 7));
+        assert!(points.iter().all(|p| p[0] >= 0.1 - 1e-12 && p[0] <= 10.0 + 1e-12 && p[1] < 0.0));
+        assert!(PointSweep::default().points(&base, 0).is_empty());
+    }
+
+    #[test]
+    fn test_sanitized_decoder() {
+        let decoder = SanitizedInputDecoder {
+            input_length: 3,
+            min_magnitude: 1e-6,
+            max_magnitude: 1e6,
+            max_magnitude_first: 1e2,
+            positive_first: true,
+            special_values: true,
+        };
+        for seed in 0..=255u8 {
+            let data: Vec<u8> = (0..24u8).map(|i| seed.wrapping_mul(31).wrapping_add(i.wrapping_mul(97))).collect();
+            let inputs = decoder.decode_prefix(&data).unwrap().0;
+            assert!(inputs[0] >= 1e-6 && inputs[0] <= 1e2);
+            assert!(inputs[1..].iter().all(|x| x.abs() >= 1e-6 && x.abs() <= 1e6));
+        }
+        // A low nibble of 0 picks a nice value; 0x81 then 0xff bytes: negative, near the top
+        let mut data = vec![0u8; 24];
+        data[8] = 0x81;
+        data[9..16].fill(0xff);
+        let inputs = decoder.decode_prefix(&data).unwrap().0;
+        assert_eq!(inputs[0], NICE_VALUES[0]);
+        assert!(inputs[1] < -9.9e5);
+        assert_eq!(inputs[2], NICE_VALUES[0]);
+        let invalid = SanitizedInputDecoder { min_magnitude: 0.0, ..decoder };
+        assert!(matches!(invalid.decode_prefix(&data), Err(DecodeError::InvalidSettings(_))));
+    }
+}
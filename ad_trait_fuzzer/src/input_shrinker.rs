@@ -0,0 +1,107 @@
+// src/input_shrinker.rs
+
+// Delta debugging over the inputs of a failing case, the counterpart of `ast_shrinker` for the
+// point instead of the expression. Decoded inputs are arbitrary doubles like
+// 0.30000000000000004 or 1.2345678901234567e3; each one is replaced by the simplest value that
+// still fails the same way: 0, ±1, its integer part, then the value rounded to 1, 2, ... 16
+// significant digits. Simpler means an integer over a fraction, then fewer significant digits,
+// then smaller magnitude.
+
+use crate::ast_evaluator::unified::AdPyUnified;
+use crate::ast_expr::SimpleExpr;
+use crate::ast_shrinker::same_failure;
+use crate::error::FuzzError;
+use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator, TestOutcome};
+use crate::oracles::FuzzingOracles;
+
+/// Upper bound on `still_fails` calls, like `ast_shrinker::MAX_SHRINK_ATTEMPTS`.
+pub const MAX_INPUT_SHRINK_ATTEMPTS: usize = 200;
+
+/// Significant decimal digits of the shortest representation of `x` that parses back to it.
+fn significant_digits(x: f64) -> usize {
+    let repr = format!("{:e}", x.abs());
+    let mantissa = repr.split('e').next().unwrap_or(&repr);
+    mantissa.chars().filter(char::is_ascii_digit).count()
+}
+
+/// Sort key, see the top of the file (the bits of a non-negative double order like its value).
+fn complexity(x: f64) -> (bool, usize, u64) {
+    (x.fract() != 0.0, significant_digits(x), x.abs().to_bits())
+}
+
+/// Values simpler than `x` to try in its place, simplest first.
+fn candidates(x: f64) -> Vec<f64> {
+    if !x.is_finite() {
+        return Vec::new();
+    }
+    let mut values = vec![0.0, 1.0_f64.copysign(x), x.trunc(), x.round()];
+    values.extend((1..=16).map(|digits| format!("{:.*e}", digits - 1, x).parse::<f64>().unwrap_or(x)));
+    values.retain(|v| complexity(*v) < complexity(x));
+    values.sort_by_key(|v| complexity(*v));
+    values.dedup();
+    values
+}
+
+/// Simplifies `inputs` one coordinate at a time while `still_fails` holds, trying at most
+/// `MAX_INPUT_SHRINK_ATTEMPTS` candidates. Returns `inputs` itself if no simpler point fails.
+pub fn shrink_inputs(inputs: &[f64], mut still_fails: impl FnMut(&[f64]) -> bool) -> Vec<f64> {
+    let mut current = inputs.to_vec();
+    let mut attempts = 0;
+    'pass: loop {
+        for i in 0..current.len() {
+            for candidate in candidates(current[i]) {
+                if attempts == MAX_INPUT_SHRINK_ATTEMPTS {
+                    return current;
+                }
+                attempts += 1;
+                let mut point = current.clone();
+                point[i] = candidate;
+                if still_fails(&point) {
+                    current = point;
+                    continue 'pass;
+                }
+            }
+        }
+        return current;
+    }
+}
+
+/// Shrinks the inputs of `expr`, which failed the oracles with `error`, keeping only points where
+/// it fails the same way (same oracle and failure class).
+pub fn shrink_input_failure(
+    expr: &SimpleExpr,
+    inputs: &[f64],
+    oracles: &FuzzingOracles,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+    error: &FuzzError,
+) -> Vec<f64> {
+    let oracles = oracles.specialize(expr);
+    shrink_inputs(inputs, |point| {
+        let calc = AdPyUnified::new(expr.clone(), point.len(), 1);
+        match run_ad_tests(point, calc, &oracles, gt_calculators).and_then(TestOutcome::into_result) {
+            Err(e) => same_failure(&e, error),
+            Ok(()) => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shrink_inputs() {
+        // Fails wherever x_0 > 2.5 and x_1 is negative
+        let shrunk = shrink_inputs(&[3.718281828459045, -0.30000000000000004], |x| x[0] > 2.5 && x[1] < 0.0);
+        assert_eq!(shrunk, vec![3.0, -1.0]);
+
+        // Only digits that matter are kept
+        let shrunk = shrink_inputs(&[1.2345678901234567], |x| (x[0] - 1.2345).abs() < 1e-3);
+        assert_eq!(shrunk, vec![1.235]);
+
+        // Non-finite inputs are kept as they are
+        let shrunk = shrink_inputs(&[f64::NAN, 0.5], |x| x[0].is_nan());
+        assert!(shrunk[0].is_nan());
+        assert_eq!(shrunk[1], 0.0);
+    }
+}
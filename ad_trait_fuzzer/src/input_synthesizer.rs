@@ -0,0 +1,211 @@
+// src/input_synthesizer.rs
+
+// Inputs chosen for an expression instead of decoded blindly and rejected: the expression is
+// generated first, `domain_requirements` reads off what each variable has to satisfy (log and
+// sqrt arguments, divisors, bases of fractional powers), and `InputSynthesizer` searches for a
+// point in the input domain where the expression is finite and stays finite close by. Candidates
+// are also clamped into the safe ranges of `domain_analysis`, which sees through arithmetic
+// (`log(x_0 - 1)`) where `domain_requirements` does not.
+
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ast_evaluator::{evaluate, Env};
+use crate::ast_expr::{Expr, Op1, Op2};
+use crate::domain_analysis::{analyze_domains, Interval};
+use crate::fuzz_config::InputDomain;
+
+/// Relative distance of the neighbour a synthesized point has to be finite at too, so points
+/// right next to a pole or a domain boundary are not picked. Absolute for inputs at 0.
+const NEIGHBOURHOOD: f64 = 1e-6;
+
+/// What a variable must satisfy for the expression to be defined, from the operators applied
+/// to it directly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VarRequirement {
+    /// Argument of `log`.
+    pub positive: bool,
+    /// Argument of `sqrt`, or base of a power with a non-integer constant exponent.
+    pub non_negative: bool,
+    /// Divisor.
+    pub nonzero: bool,
+}
+
+impl VarRequirement {
+    pub fn holds(&self, x: f64) -> bool {
+        !(self.positive && x <= 0.0 || self.non_negative && x < 0.0 || self.nonzero && x == 0.0)
+    }
+
+    /// `x` moved to where the requirement holds, keeping its magnitude.
+    fn adjust(&self, x: f64, min_magnitude: f64) -> f64 {
+        let x = if self.positive || self.non_negative { x.abs() } else { x };
+        if (self.positive || self.nonzero) && x == 0.0 { min_magnitude } else { x }
+    }
+}
+
+/// Requirements of each variable `x_i` of `expr`, by index. Only direct arguments are analyzed
+/// (`log(x_0)`, not `log(x_0 - 1)`); `InputSynthesizer` evaluates the expression for the rest.
+pub fn domain_requirements<T>(expr: &Expr<T>) -> HashMap<usize, VarRequirement> {
+    fn var_index<T>(expr: &Expr<T>) -> Option<usize> {
+        match expr {
+            Expr::Id(_, name) => name.strip_prefix("x_")?.parse().ok(),
+            _ => None,
+        }
+    }
+    fn walk<T>(expr: &Expr<T>, out: &mut HashMap<usize, VarRequirement>) {
+        match expr {
+            Expr::UnOp(_, op, e) => {
+                if let Some(i) = var_index(e) {
+                    let req = out.entry(i).or_default();
+                    match op {
+                        Op1::Log => req.positive = true,
+                        Op1::Sqrt => req.non_negative = true,
+                        _ => {}
+                    }
+                }
+                walk(e, out);
+            }
+            Expr::BinOp(_, op, l, r) => {
+                match (op, var_index(l), var_index(r)) {
+                    (Op2::Div, _, Some(i)) => out.entry(i).or_default().nonzero = true,
+                    (Op2::Pow, Some(i), _) => {
+                        let req = out.entry(i).or_default();
+                        match r.as_ref() {
+                            Expr::Number(_, e) if e.fract() == 0.0 => req.nonzero |= *e < 0.0,
+                            _ => req.non_negative = true,
+                        }
+                    }
+                    _ => {}
+                }
+                walk(l, out);
+                walk(r, out);
+            }
+            Expr::Cast(_, _, e) => walk(e, out),
+            _ => {}
+        }
+    }
+    let mut out = HashMap::new();
+    walk(expr, &mut out);
+    out
+}
+
+/// Finds inputs for one expression within an `InputDomain`.
+pub struct InputSynthesizer<'a> {
+    pub domain: &'a InputDomain,
+    /// Random candidates tried after the decoded point.
+    pub attempts: usize,
+}
+
+impl InputSynthesizer<'_> {
+    /// The first candidate the domain accepts, that meets the expression's requirements, and
+    /// where the expression is finite at and right next to: `decoded` as is, `decoded` adjusted
+    /// to the requirements, the adjusted point clamped into the safe ranges of
+    /// `analyze_domains`, then `attempts` random points (deterministic in `seed`), clamped the
+    /// same way. `None` if none qualifies.
+    pub fn synthesize<T>(&self, expr: &Expr<T>, decoded: &[f64], seed: u64) -> Option<Vec<f64>> {
+        let requirements = domain_requirements(expr);
+        let requirement = |i: usize| requirements.get(&i).copied().unwrap_or_default();
+        let safe = analyze_domains(expr);
+        let clamp = |x: Vec<f64>| -> Vec<f64> {
+            x.iter().enumerate()
+                .map(|(i, &v)| safe.get(&format!("x_{}", i)).unwrap_or(&Interval::ENTIRE).clamp(v))
+                .collect()
+        };
+        let qualifies = |x: &[f64]| {
+            self.domain.accepts(x)
+                && x.iter().enumerate().all(|(i, &v)| requirement(i).holds(v))
+                && finite_at(expr, x)
+                && finite_near(expr, x)
+        };
+
+        if qualifies(decoded) {
+            return Some(decoded.to_vec());
+        }
+        let adjusted: Vec<f64> = decoded.iter().enumerate()
+            .map(|(i, &x)| requirement(i).adjust(x, self.domain.min_magnitude))
+            .collect();
+        if qualifies(&adjusted) {
+            return Some(adjusted);
+        }
+        let clamped = clamp(adjusted);
+        if qualifies(&clamped) {
+            return Some(clamped);
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..self.attempts).map(|_| {
+            clamp((0..decoded.len()).map(|i| self.random_input(&mut rng, i, requirement(i))).collect())
+        }).find(|x| qualifies(x))
+    }
+
+    /// Log-uniform magnitude in `[min_magnitude, max_magnitude]` within the bounds of input
+    /// `i`, with a random sign unless `requirement` or the domain fixes it.
+    fn random_input(&self, rng: &mut StdRng, i: usize, requirement: VarRequirement) -> f64 {
+        let domain = self.domain;
+        if let Some(range) = domain.domains.get(i) {
+            return rng.gen_range(*range.start()..=*range.end());
+        }
+        let bound = if i == 0 { domain.max_abs_first } else { domain.max_abs };
+        let (lo, hi) = (domain.min_magnitude, domain.max_magnitude.min(bound).max(domain.min_magnitude));
+        let magnitude = (rng.gen_range(lo.ln()..=hi.ln())).exp();
+        let positive = requirement.positive || requirement.non_negative || (i == 0 && !domain.allow_non_positive);
+        if positive || rng.gen_bool(0.5) { magnitude } else { -magnitude }
+    }
+}
+
+/// Finite at `inputs` moved away from 0 by `NEIGHBOURHOOD`. Inputs at 0 (a clamp into a point
+/// range like `sqrt(-abs(x_0))`'s gives one) have no direction to keep: either side will do.
+fn finite_near<T>(expr: &Expr<T>, inputs: &[f64]) -> bool {
+    let moved = |sign: f64| -> Vec<f64> {
+        inputs.iter().map(|&v| if v == 0.0 { sign * NEIGHBOURHOOD } else { v * (1.0 + NEIGHBOURHOOD) }).collect()
+    };
+    finite_at(expr, &moved(1.0)) || (inputs.contains(&0.0) && finite_at(expr, &moved(-1.0)))
+}
+
+fn finite_at<T>(expr: &Expr<T>, inputs: &[f64]) -> bool {
+    let env: Env<f64> = inputs.iter().enumerate().map(|(i, &x)| (format!("x_{}", i), x)).collect();
+    matches!(evaluate(expr, &env), Ok(v) if v.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_synthesize() {
+        // log(x_0) / x_1 + sqrt(x_1)
+        let expr = SimpleExpr::add(
+            SimpleExpr::div(SimpleExpr::log(SimpleExpr::var("x_0")), SimpleExpr::var("x_1")),
+            SimpleExpr::sqrt(SimpleExpr::var("x_1")),
+        );
+        let requirements = domain_requirements(&expr);
+        assert_eq!(requirements[&0], VarRequirement { positive: true, ..VarRequirement::default() });
+        assert_eq!(requirements[&1], VarRequirement { non_negative: true, nonzero: true, ..VarRequirement::default() });
+
+        let domain = InputDomain { allow_non_positive: true, ..InputDomain::default() };
+        let synthesizer = InputSynthesizer { domain: &domain, attempts: 16 };
+        // Already fine: kept as is
+        assert_eq!(synthesizer.synthesize(&expr, &[2.0, 3.0], 0), Some(vec![2.0, 3.0]));
+        // Signs flipped into the domain
+        assert_eq!(synthesizer.synthesize(&expr, &[-2.0, -3.0], 0), Some(vec![2.0, 3.0]));
+        // x_1 = 0: a random point
+        let x = synthesizer.synthesize(&expr, &[2.0, 0.0], 0).unwrap();
+        assert!(x[0] > 0.0 && x[1] > 0.0);
+        assert_eq!(synthesizer.synthesize(&expr, &[2.0, 0.0], 0), Some(x));
+
+        // log(x_0 - 1) is only seen by the domain analysis: clamped to just above 1
+        let shifted = SimpleExpr::log(SimpleExpr::sub(SimpleExpr::var("x_0"), SimpleExpr::num(1.0)));
+        let x = synthesizer.synthesize(&shifted, &[0.5], 0).unwrap();
+        assert!(x[0] > 1.0);
+
+        // Only finite at x_0 = 0, an isolated point: nothing qualifies
+        let nan = SimpleExpr::sqrt(SimpleExpr::neg(SimpleExpr::abs(SimpleExpr::var("x_0"))));
+        assert_eq!(synthesizer.synthesize(&nan, &[1.0], 0), None);
+        // sqrt(-x_0) at 0 is finite on the negative side
+        let edge = SimpleExpr::sqrt(SimpleExpr::neg(SimpleExpr::var("x_0")));
+        assert!(finite_near(&edge, &[0.0]) && !finite_near(&nan, &[0.0]));
+    }
+}
@@ -1,6 +1,5 @@
 // src/lib.rs
 
-#![feature(slice_pattern)]
 //! Core library for the Automatic Differentiation (AD) fuzzing harness.
 //!
 //! This crate contains all the modular components for:
@@ -9,12 +8,54 @@
 //! 3. Evaluating test cases using various AD types.
 //! 4. Calculating ground truth derivatives (via PyTorch).
 //! 5. Running and comparing results via a set of Oracles.
+//!
+//! Failures across all stages are reported through `error::FuzzError`; oracle mismatches are
+//! tagged with a `failure_classifier::FailureClass`.
 
+pub mod error;
+pub mod failure_classifier;
+pub mod failure_report;
+pub mod campaign_report;
+pub mod calibration;
+pub mod smt_export;
+pub mod symbolic_diff;
+pub mod notifier;
 pub mod input_decoder;
+pub mod input_synthesizer;
+pub mod input_shrinker;
+pub mod domain_analysis;
 pub mod oracles;
 pub mod fuzz_harness;
+pub mod tape_stats;
+pub mod fuzz_config;
+pub mod fuzz_case;
+pub mod harness;
 pub mod gt_calculators;
+pub mod test_definition;
+pub mod rpn_evaluator;
+pub mod test_generator;
 
 pub mod ast_expr;
 pub mod ast_evaluator;
 pub mod ast_generator;
+pub mod ast_parser;
+pub mod ast_shrinker;
+pub mod ast_localizer;
+pub mod ast_typecheck;
+pub mod ast_dead_code;
+pub mod ast_lower;
+pub mod ast_visitor;
+pub mod ast_rewrite;
+pub mod ast_canonical;
+pub mod expr_codec;
+pub mod expr_coverage;
+pub mod corpus_min;
+
+#[cfg(feature = "ab")]
+pub mod ab_harness;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+pub use fuzz_case::{run_fuzz_iteration, IterationOutcome};
+pub use harness::Harness;
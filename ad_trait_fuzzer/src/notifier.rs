@@ -0,0 +1,153 @@
+// src/notifier.rs
+
+// Tells someone outside the terminal about each new failure bucket of a long campaign. Two
+// sinks, both optional: a command run through `sh -c` (the JSON notice on stdin, a one-line
+// summary in $FUZZ_FAILURE_SUMMARY, e.g. `notify-send "$FUZZ_FAILURE_SUMMARY"`), and a webhook
+// URL the notice is POSTed to with curl. The notice carries a `text` field, so Slack-style
+// incoming webhooks show the summary as is.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+
+use serde_json::{json, Value};
+
+use crate::ast_evaluator::InfixPrinter;
+use crate::ast_expr::SimpleExpr;
+use crate::error::FuzzError;
+use crate::failure_classifier::oracle_kind;
+use crate::fuzz_config::FuzzConfig;
+
+/// Seconds curl waits for the webhook before giving up.
+const WEBHOOK_TIMEOUT_SECS: &str = "10";
+
+/// The first failure of a new bucket.
+pub struct FailureNotice<'a> {
+    pub expr: &'a SimpleExpr,
+    pub inputs: &'a [f64],
+    pub error: &'a FuzzError,
+    /// Buckets seen so far, this one included.
+    pub distinct_failures: usize,
+    /// JSON report of the failure, if one was written.
+    pub report: Option<&'a Path>,
+}
+
+impl FailureNotice<'_> {
+    pub fn summary(&self) -> String {
+        let class = self.error.failure_class().map(|c| c.to_string()).unwrap_or_else(|| self.error.kind().to_string());
+        format!(
+            "New failure #{} ({}, {}): {} at {:?}",
+            self.distinct_failures, oracle_kind(self.error), class,
+            InfixPrinter::print(self.expr, self.inputs.len()), self.inputs,
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "text": self.summary(),
+            "oracle": oracle_kind(self.error),
+            "class": self.error.failure_class().map(|c| c.to_string()),
+            "kind": self.error.kind(),
+            "error": self.error.to_string(),
+            "expression": InfixPrinter::print(self.expr, self.inputs.len()),
+            "inputs": self.inputs,
+            "distinct_failures": self.distinct_failures,
+            "report": self.report.map(|p| p.display().to_string()),
+        })
+    }
+}
+
+/// Sends `notice` to the sinks configured in `config`, if any, on a background thread so a slow
+/// webhook does not stall the fuzzer; join the returned thread before exiting to not lose the
+/// notice. Delivery errors are printed, never fatal.
+pub fn notify(config: &FuzzConfig, notice: &FailureNotice) -> Option<JoinHandle<()>> {
+    if config.notify_command.is_none() && config.notify_url.is_none() {
+        return None;
+    }
+    let command = config.notify_command.clone();
+    let url = config.notify_url.clone();
+    let summary = notice.summary();
+    let payload = notice.to_json().to_string();
+    Some(thread::spawn(move || {
+        if let Some(command) = command {
+            if let Err(e) = run_command(&command, &summary, &payload) {
+                eprintln!("Failure notification command '{}' failed: {}", command, e);
+            }
+        }
+        if let Some(url) = url {
+            if let Err(e) = post_webhook(&url, &payload) {
+                eprintln!("Failure notification to {} failed: {}", url, e);
+            }
+        }
+    }))
+}
+
+/// Runs `command` with `payload` on stdin and `summary` in `FUZZ_FAILURE_SUMMARY`.
+fn run_command(command: &str, summary: &str, payload: &str) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FUZZ_FAILURE_SUMMARY", summary)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    // A command that ignores stdin may exit before reading it; that is not an error
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(payload.as_bytes());
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() { Ok(()) } else { Err(status.to_string()) }
+}
+
+fn post_webhook(url: &str, payload: &str) -> Result<(), String> {
+    let command = format!(
+        "curl -sS --fail -m {} -X POST -H 'Content-Type: application/json' --data-binary @- \"$FUZZ_NOTIFY_URL\"",
+        WEBHOOK_TIMEOUT_SECS,
+    );
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FUZZ_NOTIFY_URL", url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(payload.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if status.success() { Ok(()) } else { Err(format!("curl {}", status)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::failure_classifier::FailureClass;
+
+    #[test]
+    fn test_notify_command() {
+        let error = FuzzError::OracleMismatch {
+            oracle: "Rev vs Fwd".to_string(),
+            inputs: vec![0.5],
+            output: 0,
+            index: 0,
+            class: FailureClass::SignFlip,
+            details: String::new(),
+        };
+        let expr = SimpleExpr::sin(SimpleExpr::var("x_0"));
+        let notice = FailureNotice { expr: &expr, inputs: &[0.5], error: &error, distinct_failures: 3, report: None };
+        assert!(notice.summary().starts_with("New failure #3 (Rev vs Fwd, SignFlip): "));
+
+        let out = std::env::temp_dir().join(format!("notify-{}.json", std::process::id()));
+        let command = format!("cat > {} && test -n \"$FUZZ_FAILURE_SUMMARY\"", out.display());
+        run_command(&command, &notice.summary(), &notice.to_json().to_string()).unwrap();
+        let delivered: Value = serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        std::fs::remove_file(&out).unwrap();
+        assert_eq!(delivered["oracle"], "Rev vs Fwd");
+        assert_eq!(delivered["class"], "SignFlip");
+        assert_eq!(delivered["inputs"][0], 0.5);
+
+        assert!(run_command("exit 3", "", "").is_err());
+    }
+}
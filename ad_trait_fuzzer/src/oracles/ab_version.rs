@@ -0,0 +1,111 @@
+// src/oracles/ab_version.rs
+
+// Differential check of two ad_trait versions. The baseline (e.g. the last release) runs as a
+// pair of ground truths, see `ab_harness::BaselineGroundTruth`, and this oracle holds the
+// version under test to them engine by engine: Reverse AD against the baseline's Reverse AD,
+// Forward AD against its Forward AD. Any other ground truth is ignored, so `rev_gt`/`fwd_gt` can
+// still run next to it.
+
+use super::{ADType, ADVsGroundTruthCheck, Comparison, NanPolicy, Oracle, OracleContext, PointCheck};
+use crate::error::FuzzError;
+
+/// `GroundTruth::name` of the baseline's Reverse and Forward AD Jacobians.
+pub const BASELINE_REVERSE: &str = "Baseline Rev";
+pub const BASELINE_FORWARD: &str = "Baseline Fwd";
+
+/// AbVersionCheck: same comparison as `rev_gt`/`fwd_gt`, with tighter defaults since both sides
+/// run the same formulas unless a release changed them. Passes when no baseline ran.
+#[derive(Clone)]
+pub struct AbVersionCheck {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub comparison: Comparison,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for AbVersionCheck {
+    fn default() -> Self {
+        AbVersionCheck {
+            abs_tolerance: 1e-14,
+            rel_tolerance: 1e-12,
+            comparison: Comparison::Hybrid,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl AbVersionCheck {
+    fn engine_check(&self, ad_type: ADType) -> ADVsGroundTruthCheck {
+        ADVsGroundTruthCheck {
+            ad_type,
+            abs_tolerance: self.abs_tolerance,
+            rel_tolerance: self.rel_tolerance,
+            comparison: self.comparison,
+            nan_policy: self.nan_policy,
+        }
+    }
+}
+
+impl Oracle for AbVersionCheck {
+    fn name(&self) -> &str { "ab" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for gt in ctx.ground_truths {
+            let check = match gt.name {
+                BASELINE_REVERSE => self.engine_check(ADType::Reverse),
+                BASELINE_FORWARD => self.engine_check(ADType::Forward),
+                _ => continue,
+            };
+            for i in 0..ctx.engine.reverse.len() {
+                check.check_at(ctx.engine, Some(gt), i)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn comparison_mut(&mut self) -> Option<&mut Comparison> { Some(&mut self.comparison) }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain_analysis::Interval;
+    use crate::oracles::{EngineResults, EngineTiming, GroundTruth, InputTransform};
+
+    #[test]
+    fn test_ab_version_check() {
+        let engine = EngineResults {
+            inputs: vec![1.0, 2.0],
+            reverse: vec![3.0, 4.0],
+            forward: vec![3.0, 4.5],
+            reverse_primal: vec![0.0],
+            forward_primal: vec![0.0],
+            timing: EngineTiming::default(),
+        };
+        let gt = |name, jacobian: Vec<f64>| GroundTruth { name, jacobian, value: None, hessian: None };
+        let rerun = |_: InputTransform, _: &[f64]| -> EngineResults { unreachable!() };
+        let run = |ground_truths: &[GroundTruth]| AbVersionCheck::default().check(&OracleContext {
+            engine: &engine,
+            ground_truths,
+            failed_ground_truths: &[],
+            function: &|_: &[f64]| vec![0.0],
+            rerun: &rerun,
+            gradient_enclosure: &|region: &[Interval]| Ok(vec![Interval::ENTIRE; region.len()]),
+            vector_products: &|_, _, _| (Vec::new(), Vec::new()),
+            hessian: &|_| Err(FuzzError::UnsupportedExpr("no Hessian".to_string())),
+            with_constant: &|_, _, _| None,
+        });
+
+        // Each engine is held to its own counterpart; other ground truths don't count
+        assert!(run(&[gt(BASELINE_REVERSE, vec![3.0, 4.0]), gt("Symbolic", vec![0.0, 0.0])]).is_ok());
+        assert!(run(&[]).is_ok());
+        let err = run(&[gt(BASELINE_FORWARD, vec![3.0, 4.0])]).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, index: 1, .. } if oracle == "Fwd vs Baseline Fwd"));
+    }
+}
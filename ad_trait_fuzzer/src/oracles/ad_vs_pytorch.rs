@@ -1,7 +1,8 @@
 // src/oracles/ad_vs_pytorch.rs
 
-use super::{EngineResults, Oracle, GroundTruth};
-use std::error::Error;
+use super::{EngineResults, Oracle, OracleContext, PointCheck, GroundTruth, Comparison, NanPolicy, infinity_verdict, nan_mismatch, ulp_mismatch};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
 
 /// Defines which AD type should be compared against the ground truth.
 #[derive(Clone)]
@@ -15,46 +16,108 @@ pub enum ADType {
 #[derive(Clone)]
 pub struct ADVsGroundTruthCheck {
     pub ad_type: ADType, 
+    pub abs_tolerance: f64, // Absolute threshold, used when ground truth is near zero.
+    pub rel_tolerance: f64, // Relative threshold, 1e-9 = 1 part per billion.
+    pub comparison: Comparison,
+    pub nan_policy: NanPolicy,
+}
+
+impl ADVsGroundTruthCheck {
+    pub fn new(ad_type: ADType) -> Self {
+        ADVsGroundTruthCheck {
+            ad_type,
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            comparison: Comparison::Hybrid,
+            nan_policy: NanPolicy::default(),
+        }
+    }
 }
 
 impl Oracle for ADVsGroundTruthCheck {
-    /// Tolerance constant for trait satisfaction. The actual tolerances are defined below.
-    const TOLERANCE: f64 = 1e-6; 
-    
-    fn check(&self, engine: &EngineResults, gt: Option<&GroundTruth>, i: usize) -> Result<(), Box<dyn Error>> {
-        
-        // Define tolerances as local constants inside the function scope.
-        const ABS_TOLERANCE: f64 = 1e-12; // Absolute threshold, used when ground truth is near zero.
-        const REL_TOLERANCE: f64 = 1e-9;  // Relative threshold, 1 part per billion.
-        
-        // Ensure a Ground Truth value was provided for this check
-        let gt = gt.ok_or("AD vs Ground Truth check requires a ground truth input.")?;
+    fn name(&self) -> &str {
+        match self.ad_type {
+            ADType::Reverse => "rev_gt",
+            ADType::Forward => "fwd_gt",
+        }
+    }
+
+    /// Checks every partial derivative against every ground truth.
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for gt in ctx.ground_truths {
+            for i in 0..ctx.engine.reverse.len() {
+                self.check_at(ctx.engine, Some(gt), i)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn comparison_mut(&mut self) -> Option<&mut Comparison> { Some(&mut self.comparison) }
 
-        let (ad_val, ad_name) = match self.ad_type {
-            ADType::Reverse => (engine.reverse[i], "Reverse AD"),
-            ADType::Forward => (engine.forward[i], "Forward AD"),
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+impl PointCheck for ADVsGroundTruthCheck {
+    fn check_at(&self, engine: &EngineResults, gt: Option<&GroundTruth>, i: usize) -> Result<(), FuzzError> {
+        let abs_tolerance = self.abs_tolerance;
+        let rel_tolerance = self.rel_tolerance;
+        
+        let (ad_val, ad_name, ad_short) = match self.ad_type {
+            ADType::Reverse => (engine.reverse[i], "Reverse AD", "Rev"),
+            ADType::Forward => (engine.forward[i], "Forward AD", "Fwd"),
         };
+        // Ensure a Ground Truth value was provided for this check
+        let gt = gt.ok_or_else(|| FuzzError::MissingGroundTruth { oracle: format!("{} vs Ground Truth", ad_short) })?;
         
+        let (output, input) = engine.partial(i);
         let gt_val = gt.jacobian[i];
         let gt_name = gt.name;
 
-        // Skip check if ground truth is not finite (e.g., NaN, Inf)
-        if !gt_val.is_finite() {
+        let nan_verdict = self.nan_policy.verdict(ad_val, gt_val);
+        if nan_verdict == Some(true) {
+            return Ok(());
+        }
+
+        if let Comparison::Ulps(max_ulps) = self.comparison {
+            let mismatch = match nan_verdict {
+                Some(_) => Some(nan_mismatch(self.nan_policy, ad_name, ad_val, gt_name, gt_val)),
+                None => ulp_mismatch(ad_name, ad_val, gt_name, gt_val, max_ulps),
+            };
+            return match mismatch {
+                Some(details) => Err(FuzzError::OracleMismatch {
+                    oracle: format!("{} vs {}", ad_short, gt_name),
+                    inputs: engine.inputs.clone(),
+                    output,
+                    index: input,
+                    class: FailureClass::of(ad_val, gt_val),
+                    details,
+                }),
+                None => Ok(()),
+            };
+        }
+
+        // Same infinity on both sides agrees; the tolerance math below can't tell
+        let inf_verdict = infinity_verdict(ad_val, gt_val);
+        if inf_verdict == Some(true) {
             return Ok(());
         }
 
         let diff = (ad_val - gt_val).abs();
         
-        // 1. Calculate the scaled threshold: max(ABS_TOLERANCE, |GT| * REL_TOLERANCE)
-        let scaled_rel_threshold = gt_val.abs() * REL_TOLERANCE;
-        let threshold = ABS_TOLERANCE.max(scaled_rel_threshold);
+        // 1. Calculate the scaled threshold: max(abs_tolerance, |GT| * rel_tolerance)
+        let scaled_rel_threshold = gt_val.abs() * rel_tolerance;
+        let threshold = abs_tolerance.max(scaled_rel_threshold);
 
         // 2. Perform the Hybrid check: Fail only if difference is greater than the threshold
-        if diff > threshold || (ad_val.is_nan() != gt_val.is_nan()) {
+        if diff > threshold || nan_verdict == Some(false) || inf_verdict == Some(false) {
             let relative_diff = diff / gt_val.abs();
             let percent_diff = (relative_diff * 100.0).min(100.0);
             
-            Err(format!(
+            let details = format!(
                 "{} vs {} failed! (Hybrid Tolerance Check)\n\
                 {}: {:.10e}, {}: {:.10e}\n\
                 Absolute Diff: {:.10e}\n\
@@ -64,10 +127,43 @@ impl Oracle for ADVsGroundTruthCheck {
                 ad_name, ad_val, gt_name, gt_val,
                 diff, 
                 relative_diff, percent_diff,
-                threshold, ABS_TOLERANCE, scaled_rel_threshold
-            ).into())
+                threshold, abs_tolerance, scaled_rel_threshold
+            );
+            Err(FuzzError::OracleMismatch {
+                oracle: format!("{} vs {}", ad_short, gt_name),
+                inputs: engine.inputs.clone(),
+                output,
+                index: input,
+                class: FailureClass::of(ad_val, gt_val),
+                details,
+            })
         } else {
             Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracles::EngineTiming;
+
+    #[test]
+    fn test_missing_ground_truth() {
+        let engine = EngineResults {
+            inputs: vec![1.0],
+            reverse: vec![2.0],
+            forward: vec![2.0],
+            reverse_primal: vec![1.0],
+            forward_primal: vec![1.0],
+            timing: EngineTiming::default(),
+        };
+        let check = ADVsGroundTruthCheck::new(ADType::Forward);
+        let error = check.check_at(&engine, None, 0).unwrap_err();
+        assert!(matches!(error, FuzzError::MissingGroundTruth { ref oracle } if oracle == "Fwd vs Ground Truth"));
+        assert_eq!(error.kind(), "MissingGroundTruth");
+
+        let gt = GroundTruth { name: "Exact", jacobian: vec![2.0], value: None, hessian: None };
+        assert!(check.check_at(&engine, Some(&gt), 0).is_ok());
+    }
+}
@@ -0,0 +1,384 @@
+// src/oracles/builder.rs
+
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+use super::{FuzzingOracles, Oracle, Severity, ToleranceOverride, ReverseVsForwardCheck, ADVsGroundTruthCheck, ADType, OracleSelection, Comparison, NonFiniteConsistencyCheck, PrimalValueCheck, GradCheckOracle, TranslationInvarianceCheck, ChainRuleScalingCheck, PermutationCheck, MajorityVoteCheck, DomainConsistencyCheck, HessianCheck, SecantCheck, IdentityCheck, GradientBoundCheck, ConstantSensitivityCheck, TimingCheck, AbVersionCheck, VectorProductCheck, NanPolicy};
+use crate::error::FuzzError;
+
+/// A built-in oracle as the builder configures it, for discovery (`adfuzz list-oracles`).
+#[derive(Debug, Clone)]
+pub struct OracleInfo {
+    pub name: &'static str,
+    pub flag: OracleSelection,
+    /// Part of `all`; `false` for the opt-in oracles.
+    pub in_all: bool,
+    /// (abs, rel), for oracles with tolerances.
+    pub tolerances: Option<(f64, f64)>,
+    pub comparison: Option<Comparison>,
+    pub requires: Option<&'static str>,
+}
+
+/// Builder for `FuzzingOracles`, used to tune tolerances per campaign and per oracle.
+///
+/// ```ignore
+/// let oracles = FuzzingOracles::builder()
+///     .selection(OracleSelection::REV_FWD | OracleSelection::REV_GT)
+///     .tolerances(1e-10, 1e-7)           // every oracle
+///     .rev_gt_tolerances(1e-8, 1e-6)     // then loosen one of them
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct FuzzingOraclesBuilder {
+    selection: OracleSelection,
+    reverse_vs_forward: ReverseVsForwardCheck,
+    reverse_vs_gt: ADVsGroundTruthCheck,
+    forward_vs_gt: ADVsGroundTruthCheck,
+    primal: PrimalValueCheck,
+    grad_check: GradCheckOracle,
+    translation: TranslationInvarianceCheck,
+    scaling: ChainRuleScalingCheck,
+    majority: MajorityVoteCheck,
+    timing: TimingCheck,
+    nan_policy: NanPolicy,
+    custom: Vec<Box<dyn Oracle>>,
+}
+
+impl FuzzingOraclesBuilder {
+    pub fn new() -> Self {
+        FuzzingOraclesBuilder {
+            selection: OracleSelection::ALL,
+            reverse_vs_forward: ReverseVsForwardCheck::default(),
+            reverse_vs_gt: ADVsGroundTruthCheck::new(ADType::Reverse),
+            forward_vs_gt: ADVsGroundTruthCheck::new(ADType::Forward),
+            primal: PrimalValueCheck::default(),
+            grad_check: GradCheckOracle::default(),
+            translation: TranslationInvarianceCheck::default(),
+            scaling: ChainRuleScalingCheck::default(),
+            majority: MajorityVoteCheck::default(),
+            timing: TimingCheck::default(),
+            nan_policy: NanPolicy::default(),
+            custom: Vec::new(),
+        }
+    }
+
+    pub fn selection(mut self, selection: OracleSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Set the same absolute/relative tolerance on every oracle.
+    pub fn tolerances(self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.rev_fwd_tolerances(abs_tolerance, rel_tolerance)
+            .rev_gt_tolerances(abs_tolerance, rel_tolerance)
+            .fwd_gt_tolerances(abs_tolerance, rel_tolerance)
+            .primal_tolerances(abs_tolerance, rel_tolerance)
+            .majority_tolerances(abs_tolerance, rel_tolerance)
+    }
+
+    pub fn rev_fwd_tolerances(mut self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.reverse_vs_forward.abs_tolerance = abs_tolerance;
+        self.reverse_vs_forward.rel_tolerance = rel_tolerance;
+        self
+    }
+
+    pub fn rev_gt_tolerances(mut self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.reverse_vs_gt.abs_tolerance = abs_tolerance;
+        self.reverse_vs_gt.rel_tolerance = rel_tolerance;
+        self
+    }
+
+    pub fn fwd_gt_tolerances(mut self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.forward_vs_gt.abs_tolerance = abs_tolerance;
+        self.forward_vs_gt.rel_tolerance = rel_tolerance;
+        self
+    }
+
+    pub fn primal_tolerances(mut self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.primal.abs_tolerance = abs_tolerance;
+        self.primal.rel_tolerance = rel_tolerance;
+        self
+    }
+
+    pub fn majority_tolerances(mut self, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.majority.abs_tolerance = abs_tolerance;
+        self.majority.rel_tolerance = rel_tolerance;
+        self
+    }
+
+    /// Finite-difference gradient check: base tolerances (the FD error estimate is added on top)
+    /// and the relative step size.
+    pub fn grad_check(mut self, abs_tolerance: f64, rel_tolerance: f64, step_scale: f64) -> Self {
+        self.grad_check = GradCheckOracle { abs_tolerance, rel_tolerance, step_scale, ..self.grad_check };
+        self
+    }
+
+    /// Translation-invariance oracle: offset `c` used for `g(x) = f(x + c)` and its tolerances.
+    pub fn translation(mut self, offset: f64, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.translation = TranslationInvarianceCheck { offset, abs_tolerance, rel_tolerance, ..self.translation };
+        self
+    }
+
+    /// Chain-rule scaling oracle: factor `a` for `h(x) = f(a * x)`, the fraction of inputs it runs on,
+    /// and its tolerances.
+    pub fn scaling(mut self, factor: f64, sample_rate: f64, abs_tolerance: f64, rel_tolerance: f64) -> Self {
+        self.scaling = ChainRuleScalingCheck { factor, sample_rate, abs_tolerance, rel_tolerance, ..self.scaling };
+        self
+    }
+
+    /// Timing oracle: slowdown of one engine over the other, per sweep, that fails a case, and how
+    /// many times a suspicious case is re-measured.
+    pub fn timing(mut self, max_ratio: f64, repeats: usize) -> Self {
+        self.timing = TimingCheck { max_ratio, repeats, ..self.timing };
+        self
+    }
+
+    /// Set the same comparison strategy on every oracle.
+    pub fn comparison(self, comparison: Comparison) -> Self {
+        self.rev_fwd_comparison(comparison)
+            .rev_gt_comparison(comparison)
+            .fwd_gt_comparison(comparison)
+    }
+
+    pub fn rev_fwd_comparison(mut self, comparison: Comparison) -> Self {
+        self.reverse_vs_forward.comparison = comparison;
+        self
+    }
+
+    pub fn rev_gt_comparison(mut self, comparison: Comparison) -> Self {
+        self.reverse_vs_gt.comparison = comparison;
+        self
+    }
+
+    pub fn fwd_gt_comparison(mut self, comparison: Comparison) -> Self {
+        self.forward_vs_gt.comparison = comparison;
+        self
+    }
+
+    /// How every oracle that compares values, and the harness for ground truths, treats NaN.
+    pub fn nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
+
+    /// Set whichever of abs/rel tolerance `overrides` has on `rev_fwd`, `rev_gt`, `fwd_gt`,
+    /// `primal` and `majority`, and its ULPs on the first three, like `ORACLE_ABS_TOL` /
+    /// `ORACLE_REL_TOL` / `ORACLE_ULPS` do. The other oracles keep theirs; set them per oracle in
+    /// an `oracles.toml`.
+    pub fn with_overrides(mut self, overrides: &ToleranceOverride) -> Self {
+        if let Some(ulps) = overrides.ulps {
+            self = self.comparison(Comparison::Ulps(ulps));
+        }
+        let checks: [(&mut f64, &mut f64); 5] = [
+            (&mut self.reverse_vs_forward.abs_tolerance, &mut self.reverse_vs_forward.rel_tolerance),
+            (&mut self.reverse_vs_gt.abs_tolerance, &mut self.reverse_vs_gt.rel_tolerance),
+            (&mut self.forward_vs_gt.abs_tolerance, &mut self.forward_vs_gt.rel_tolerance),
+            (&mut self.primal.abs_tolerance, &mut self.primal.rel_tolerance),
+            (&mut self.majority.abs_tolerance, &mut self.majority.rel_tolerance),
+        ];
+        for (abs, rel) in checks {
+            if let Some(val) = overrides.abs_tol {
+                *abs = val;
+            }
+            if let Some(val) = overrides.rel_tol {
+                *rel = val;
+            }
+        }
+        self
+    }
+
+    /// Apply tolerance overrides from the environment.
+    ///
+    /// `ORACLE_ABS_TOL` / `ORACLE_REL_TOL` apply to those five oracles (see `with_overrides`), then
+    /// `ORACLE_<REV_FWD|REV_GT|FWD_GT|PRIMAL|MAJORITY>_ABS_TOL` / `_REL_TOL` override a single one.
+    /// `ORACLE_ULPS` / `ORACLE_<NAME>_ULPS` switch to ULP comparison with that many ULPs allowed.
+    /// `ORACLE_SCALING_RATE` sets the fraction of inputs the scaling oracle runs on.
+    /// `ORACLE_TIMING_RATIO` sets the slowdown the timing oracle allows.
+    /// `ORACLE_NAN_POLICY` (`skip`, `equal` or `fail`) sets the `NanPolicy`.
+    /// A value that does not parse is an `InvalidConfig` error, not a silent default.
+    pub fn with_env_overrides(self) -> Result<Self, FuzzError> {
+        self.with_vars(|name| env::var(name).ok())
+    }
+
+    /// `with_env_overrides` with the variables from `lookup`.
+    fn with_vars(mut self, lookup: impl Fn(&str) -> Option<String>) -> Result<Self, FuzzError> {
+        let read = |name: &str| -> Result<Option<f64>, FuzzError> { parse_var(&lookup, name) };
+        let read_ulps = |name: &str| -> Result<Option<u64>, FuzzError> { parse_var(&lookup, name) };
+
+        if let Some(policy) = parse_var(&lookup, "ORACLE_NAN_POLICY")? {
+            self.nan_policy = policy;
+        }
+
+        if let Some(rate) = read("ORACLE_SCALING_RATE")? {
+            self.scaling.sample_rate = rate;
+        }
+
+        if let Some(ratio) = read("ORACLE_TIMING_RATIO")? {
+            self.timing.max_ratio = ratio;
+        }
+
+        self = self.with_overrides(&ToleranceOverride {
+            abs_tol: read("ORACLE_ABS_TOL")?,
+            rel_tol: read("ORACLE_REL_TOL")?,
+            ulps: read_ulps("ORACLE_ULPS")?,
+        });
+        let comparisons: [(&str, &mut Comparison); 3] = [
+            ("REV_FWD", &mut self.reverse_vs_forward.comparison),
+            ("REV_GT", &mut self.reverse_vs_gt.comparison),
+            ("FWD_GT", &mut self.forward_vs_gt.comparison),
+        ];
+        for (name, comparison) in comparisons {
+            if let Some(ulps) = read_ulps(&format!("ORACLE_{}_ULPS", name))? {
+                *comparison = Comparison::Ulps(ulps);
+            }
+        }
+
+        let checks: [(&str, &mut f64, &mut f64); 5] = [
+            ("REV_FWD", &mut self.reverse_vs_forward.abs_tolerance, &mut self.reverse_vs_forward.rel_tolerance),
+            ("REV_GT", &mut self.reverse_vs_gt.abs_tolerance, &mut self.reverse_vs_gt.rel_tolerance),
+            ("FWD_GT", &mut self.forward_vs_gt.abs_tolerance, &mut self.forward_vs_gt.rel_tolerance),
+            ("PRIMAL", &mut self.primal.abs_tolerance, &mut self.primal.rel_tolerance),
+            ("MAJORITY", &mut self.majority.abs_tolerance, &mut self.majority.rel_tolerance),
+        ];
+        for (name, abs, rel) in checks {
+            if let Some(val) = read(&format!("ORACLE_{}_ABS_TOL", name))? {
+                *abs = val;
+            }
+            if let Some(val) = read(&format!("ORACLE_{}_REL_TOL", name))? {
+                *rel = val;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds a user-defined check, run after the built-in ones.
+    pub fn register(mut self, oracle: impl Oracle + 'static) -> Self {
+        self.custom.push(Box::new(oracle));
+        self
+    }
+
+    /// The configured built-in oracle for a single selection flag.
+    pub(crate) fn builtin(&self, flag: OracleSelection) -> Option<Box<dyn Oracle>> {
+        Some(match flag {
+            OracleSelection::PRIMAL => Box::new(self.primal.clone()),
+            OracleSelection::NON_FINITE => Box::new(NonFiniteConsistencyCheck::default()),
+            OracleSelection::DOMAIN => Box::new(DomainConsistencyCheck),
+            OracleSelection::HESSIAN => Box::new(HessianCheck::default()),
+            OracleSelection::SECANT => Box::new(SecantCheck::default()),
+            OracleSelection::IDENTITY => Box::new(IdentityCheck::default()),
+            OracleSelection::GRAD_BOUND => Box::new(GradientBoundCheck::default()),
+            OracleSelection::SENSITIVITY => Box::new(ConstantSensitivityCheck::default()),
+            OracleSelection::MAJORITY => Box::new(self.majority.clone()),
+            OracleSelection::REV_FWD => Box::new(self.reverse_vs_forward.clone()),
+            OracleSelection::REV_GT => Box::new(self.reverse_vs_gt.clone()),
+            OracleSelection::FWD_GT => Box::new(self.forward_vs_gt.clone()),
+            OracleSelection::GRAD_CHECK => Box::new(self.grad_check.clone()),
+            OracleSelection::TRANSLATION => Box::new(self.translation.clone()),
+            OracleSelection::SCALING => Box::new(self.scaling.clone()),
+            OracleSelection::TIMING => Box::new(self.timing.clone()),
+            OracleSelection::AB_VERSION => Box::new(AbVersionCheck::default()),
+            OracleSelection::VJP_JVP => Box::new(VectorProductCheck::default()),
+            OracleSelection::PERMUTATION => Box::new(PermutationCheck::default()),
+            _ => return None,
+        })
+    }
+
+    /// Every built-in oracle, selected or not, with this builder's settings, in bit order.
+    pub fn catalog(&self) -> Vec<OracleInfo> {
+        OracleSelection::known().iter().filter_map(|&(name, flag)| {
+            let mut oracle = self.builtin(flag)?;
+            Some(OracleInfo {
+                name,
+                flag,
+                in_all: OracleSelection::ALL.contains(flag),
+                tolerances: oracle.tolerances_mut().map(|(abs, rel)| (*abs, *rel)),
+                comparison: oracle.comparison_mut().map(|comparison| *comparison),
+                requires: flag.requirement(),
+            })
+        }).collect()
+    }
+
+    pub fn build(self) -> FuzzingOracles {
+        // Out-of-domain behavior and primal values first (evaluation vs derivative bug), then NaN/Inf
+        // agreement and the majority vote so a mismatch is reported with the most specific label.
+        const RUN_ORDER: [OracleSelection; 19] = [
+            OracleSelection::DOMAIN,
+            OracleSelection::PRIMAL,
+            OracleSelection::NON_FINITE,
+            OracleSelection::MAJORITY,
+            OracleSelection::REV_FWD,
+            OracleSelection::REV_GT,
+            OracleSelection::FWD_GT,
+            OracleSelection::VJP_JVP,
+            OracleSelection::AB_VERSION,
+            OracleSelection::GRAD_BOUND,
+            OracleSelection::GRAD_CHECK,
+            OracleSelection::SECANT,
+            OracleSelection::HESSIAN,
+            OracleSelection::TRANSLATION,
+            OracleSelection::SCALING,
+            OracleSelection::PERMUTATION,
+            OracleSelection::IDENTITY,
+            OracleSelection::SENSITIVITY,
+            OracleSelection::TIMING,
+        ];
+
+        let mut oracles = FuzzingOracles::empty();
+        for flag in RUN_ORDER {
+            if self.selection.contains(flag) {
+                if let Some(oracle) = self.builtin(flag) {
+                    oracles.register_boxed(oracle, Severity::Fail);
+                }
+            }
+        }
+        for oracle in self.custom {
+            oracles.register_boxed(oracle, Severity::Fail);
+        }
+        oracles.set_nan_policy(self.nan_policy);
+        oracles
+    }
+}
+
+impl Default for FuzzingOraclesBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `name` from `lookup`, parsed; `None` if it is not set.
+fn parse_var<T: FromStr>(lookup: impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, FuzzError>
+where
+    T::Err: Display,
+{
+    match lookup(name) {
+        Some(value) => value.trim().parse().map(Some)
+            .map_err(|e| FuzzError::InvalidConfig(format!("{}={} is not valid: {}", name, value, e))),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |pairs: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+            move |name: &str| vars.get(name).cloned()
+        };
+        let builder = FuzzingOracles::builder()
+            .with_vars(vars(&[("ORACLE_REL_TOL", "1e-6"), ("ORACLE_REV_GT_ULPS", "8"), ("ORACLE_NAN_POLICY", "fail")]))
+            .unwrap();
+        assert_eq!(builder.reverse_vs_forward.rel_tolerance, 1e-6);
+        assert_eq!(builder.reverse_vs_gt.comparison, Comparison::Ulps(8));
+        assert_eq!(builder.nan_policy, NanPolicy::Fail);
+
+        for (name, value) in [("ORACLE_ABS_TOL", "1e-6x"), ("ORACLE_FWD_GT_ULPS", "-1"), ("ORACLE_NAN_POLICY", "never")] {
+            let error = FuzzingOracles::builder().with_vars(vars(&[(name, value)])).err().unwrap();
+            assert_eq!(error.kind(), "InvalidConfig");
+            assert!(error.to_string().contains(name), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// src/oracles/comparison.rs
+
+/// How an oracle decides whether two derivative values agree.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Comparison {
+    /// `|a - b| <= max(abs_tolerance, |reference| * rel_tolerance)`
+    #[default]
+    Hybrid,
+    /// At most this many representable doubles apart. Scale-free, so it behaves the same for
+    /// gradients of 1e-300 and 1e+300 (exp/pow chains span that range easily).
+    Ulps(u64),
+}
+
+/// Map the bits of an f64 onto a line where adjacent doubles are adjacent integers.
+/// -0.0 and +0.0 both map to 0.
+fn ordered_bits(x: f64) -> i64 {
+    let bits = x.to_bits() as i64;
+    if bits < 0 { i64::MIN - bits } else { bits }
+}
+
+/// Number of representable doubles between `a` and `b`.
+/// NaN is 0 ULPs from NaN and infinitely far (u64::MAX) from anything else; so is each
+/// infinity, which would otherwise be 1 ULP from `f64::MAX`.
+pub fn ulp_distance(a: f64, b: f64) -> u64 {
+    if let Some(agrees) = infinity_verdict(a, b) {
+        return if agrees { 0 } else { u64::MAX };
+    }
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => 0,
+        (true, false) | (false, true) => u64::MAX,
+        _ => ordered_bits(a).abs_diff(ordered_bits(b)),
+    }
+}
+
+/// `Some(agrees)` if `a` or `b` is ±Inf (and neither is NaN, see `NanPolicy`): the same
+/// infinity agrees, anything else does not. `None` if both are finite and the tolerance math
+/// decides, which can't: `Inf - Inf` is NaN, and an infinite reference makes the relative
+/// threshold infinite too.
+pub fn infinity_verdict(a: f64, b: f64) -> Option<bool> {
+    if a.is_nan() || b.is_nan() || (a.is_finite() && b.is_finite()) {
+        return None;
+    }
+    Some(a == b)
+}
+
+/// Failure details when `a` and `b` are more than `max_ulps` apart, `None` if they agree.
+pub fn ulp_mismatch(a_name: &str, a: f64, b_name: &str, b: f64, max_ulps: u64) -> Option<String> {
+    let ulps = ulp_distance(a, b);
+    if ulps <= max_ulps {
+        return None;
+    }
+    Some(format!(
+        "{} vs {} failed! (ULP Check)\n\
+        {}: {:.17e}, {}: {:.17e}\n\
+        ULP Distance: {} (max allowed: {})",
+        a_name, b_name,
+        a_name, a, b_name, b,
+        ulps, max_ulps
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ulp_distance() {
+        assert_eq!(ulp_distance(1.0, 1.0), 0);
+        assert_eq!(ulp_distance(1.0, 1.0 + f64::EPSILON), 1);
+        assert_eq!(ulp_distance(0.0, -0.0), 0);
+        assert_eq!(ulp_distance(-f64::MIN_POSITIVE, f64::MIN_POSITIVE), 2 * ulp_distance(0.0, f64::MIN_POSITIVE));
+        assert_eq!(ulp_distance(f64::NAN, 1.0), u64::MAX);
+        assert_eq!(ulp_distance(f64::NAN, f64::NAN), 0);
+        assert_eq!(ulp_distance(f64::INFINITY, f64::INFINITY), 0);
+        assert_eq!(ulp_distance(f64::INFINITY, f64::MAX), u64::MAX);
+
+        assert_eq!(infinity_verdict(f64::NEG_INFINITY, f64::NEG_INFINITY), Some(true));
+        assert_eq!(infinity_verdict(f64::INFINITY, f64::NEG_INFINITY), Some(false));
+        assert_eq!(infinity_verdict(1e308, f64::INFINITY), Some(false));
+        assert_eq!(infinity_verdict(f64::INFINITY, f64::NAN), None);
+    }
+}
@@ -0,0 +1,402 @@
+// src/oracles/config.rs
+
+// Per-oracle settings loaded from a TOML file (`oracles.toml`):
+//
+//     [defaults]            # every oracle with tolerances
+//     abs_tol = 1e-12
+//     rel_tol = 1e-9
+//     nan_policy = "skip"   # "skip", "equal" (default) or "fail", see `NanPolicy`
+//     borderline_factor = 10  # mismatches within 10x the tolerances warn (default), 1: never
+//
+//     [rev_gt]              # any FUZZ_ORACLE name
+//     enabled = true
+//     severity = "warn"     # "fail" (default) or "warn"
+//     rel_tol = 1e-6
+//     ulps = 8              # rev_fwd / rev_gt / fwd_gt only
+//
+//     [rev_gt.pow]          # only for expressions using pow/sqrt
+//     rel_tol = 1e-4
+//
+//     [domain]
+//     probe = true          # also test just outside the safe input ranges
+//
+// Operator classes: trig (sin/cos/tan), pow (pow/sqrt), log (log/exp), non_smooth (abs/casts).
+
+use std::fs;
+use std::path::Path;
+
+use toml_edit::{DocumentMut, Item, Table};
+
+use super::{FuzzingOracles, FuzzingOraclesBuilder, NanPolicy, Oracle, OracleSelection, Comparison};
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr};
+use crate::ast_visitor::for_each_node;
+use crate::error::FuzzError;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Fail,
+    Warn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpClass {
+    Trig,
+    Pow,
+    Log,
+    NonSmooth,
+}
+
+impl OpClass {
+    const NAMES: &'static [(&'static str, OpClass)] = &[
+        ("trig", OpClass::Trig),
+        ("pow", OpClass::Pow),
+        ("log", OpClass::Log),
+        ("non_smooth", OpClass::NonSmooth),
+    ];
+
+    fn from_name(name: &str) -> Option<OpClass> {
+        Self::NAMES.iter().find(|(n, _)| *n == name).map(|(_, class)| *class)
+    }
+
+    /// The table name in `oracles.toml`, e.g. "non_smooth".
+    pub fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(_, class)| *class == self).map(|(n, _)| *n).unwrap()
+    }
+
+    /// Operator classes used anywhere in `expr`.
+    pub fn of_expr<Tag>(expr: &Expr<Tag>) -> Vec<OpClass> {
+        let mut classes = Vec::new();
+        collect_classes(expr, &mut classes);
+        classes
+    }
+}
+
+fn collect_classes<Tag>(expr: &Expr<Tag>, classes: &mut Vec<OpClass>) {
+    for_each_node(expr, |e| {
+        let class = match e {
+            Expr::UnOp(_, op, _) => match op {
+                Op1::Sin | Op1::Cos | Op1::Tan => OpClass::Trig,
+                Op1::Sqrt => OpClass::Pow,
+                Op1::Log | Op1::Exp => OpClass::Log,
+                Op1::Abs => OpClass::NonSmooth,
+                Op1::Neg => return,
+            },
+            Expr::BinOp(_, Op2::Pow, ..) => OpClass::Pow,
+            Expr::Cast(..) => OpClass::NonSmooth,
+            _ => return,
+        };
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    });
+}
+
+/// Tolerance settings; unset fields keep the oracle's current value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToleranceOverride {
+    pub abs_tol: Option<f64>,
+    pub rel_tol: Option<f64>,
+    pub ulps: Option<u64>,
+}
+
+/// Tolerances for one oracle that only apply when the expression uses `class`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpOverride {
+    pub oracle: String,
+    pub class: OpClass,
+    pub tolerances: ToleranceOverride,
+}
+
+#[derive(Debug, Clone, Default)]
+struct OracleSettings {
+    enabled: Option<bool>,
+    severity: Option<Severity>,
+    tolerances: ToleranceOverride,
+    /// `[domain]` only, see `FuzzingOracles::probe_domain`.
+    probe: Option<bool>,
+}
+
+/// Parsed `oracles.toml`. Apply it to a `FuzzingOracles` with `apply`.
+#[derive(Debug, Clone, Default)]
+pub struct OracleConfig {
+    defaults: ToleranceOverride,
+    nan_policy: Option<NanPolicy>,
+    borderline_factor: Option<f64>,
+    oracles: Vec<(String, OracleSelection, OracleSettings)>,
+    op_overrides: Vec<OpOverride>,
+}
+
+fn invalid(msg: String) -> FuzzError {
+    FuzzError::InvalidConfig(msg)
+}
+
+fn read_float(table: &Table, key: &str, ctx: &str) -> Result<Option<f64>, FuzzError> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(item) => item.as_float()
+            .or_else(|| item.as_integer().map(|i| i as f64))
+            .map(Some)
+            .ok_or_else(|| invalid(format!("[{}] {} must be a number", ctx, key))),
+    }
+}
+
+fn read_tolerances(table: &Table, ctx: &str) -> Result<ToleranceOverride, FuzzError> {
+    let ulps = match table.get("ulps") {
+        None => None,
+        Some(item) => match item.as_integer() {
+            Some(u) if u >= 0 => Some(u as u64),
+            _ => return Err(invalid(format!("[{}] ulps must be a non-negative integer", ctx))),
+        },
+    };
+    Ok(ToleranceOverride {
+        abs_tol: read_float(table, "abs_tol", ctx)?,
+        rel_tol: read_float(table, "rel_tol", ctx)?,
+        ulps,
+    })
+}
+
+fn as_table<'a>(item: &'a Item, ctx: &str) -> Result<&'a Table, FuzzError> {
+    item.as_table().ok_or_else(|| invalid(format!("'{}' must be a table", ctx)))
+}
+
+impl OracleConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FuzzError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| invalid(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, FuzzError> {
+        let doc: DocumentMut = text.parse().map_err(|e| invalid(format!("{}", e)))?;
+        let mut config = OracleConfig::default();
+
+        for (name, item) in doc.iter() {
+            let table = as_table(item, name)?;
+
+            if name == "defaults" {
+                for (key, value) in table.iter() {
+                    match key {
+                        "abs_tol" | "rel_tol" | "ulps" => {}
+                        "nan_policy" => {
+                            let policy = value.as_str().and_then(|s| s.parse().ok())
+                                .ok_or_else(|| invalid("[defaults] nan_policy must be \"skip\", \"equal\" or \"fail\"".to_string()))?;
+                            config.nan_policy = Some(policy);
+                        }
+                        "borderline_factor" => {
+                            config.borderline_factor = match read_float(table, key, name)? {
+                                Some(f) if f >= 1.0 => Some(f),
+                                _ => return Err(invalid("[defaults] borderline_factor must be at least 1".to_string())),
+                            };
+                        }
+                        _ => return Err(invalid(format!("unknown key '{}' in [defaults]", key))),
+                    }
+                }
+                config.defaults = read_tolerances(table, name)?;
+                continue;
+            }
+
+            let name = &name.to_ascii_lowercase();
+            let flag = OracleSelection::from_name(name)
+                .ok_or_else(|| invalid(format!("unknown oracle [{}]", name)))?;
+            let mut settings = OracleSettings { tolerances: read_tolerances(table, name)?, ..Default::default() };
+
+            for (key, value) in table.iter() {
+                match key {
+                    "abs_tol" | "rel_tol" | "ulps" => {}
+                    "enabled" => {
+                        settings.enabled = Some(value.as_bool()
+                            .ok_or_else(|| invalid(format!("[{}] enabled must be a boolean", name)))?);
+                    }
+                    "probe" if flag == OracleSelection::DOMAIN => {
+                        settings.probe = Some(value.as_bool()
+                            .ok_or_else(|| invalid(format!("[{}] probe must be a boolean", name)))?);
+                    }
+                    "severity" => {
+                        settings.severity = Some(match value.as_str() {
+                            Some("fail") => Severity::Fail,
+                            Some("warn") => Severity::Warn,
+                            _ => return Err(invalid(format!("[{}] severity must be \"fail\" or \"warn\"", name))),
+                        });
+                    }
+                    _ => {
+                        let class = OpClass::from_name(key)
+                            .ok_or_else(|| invalid(format!("unknown key '{}' in [{}]", key, name)))?;
+                        let ctx = format!("{}.{}", name, key);
+                        config.op_overrides.push(OpOverride {
+                            oracle: name.to_string(),
+                            class,
+                            tolerances: read_tolerances(as_table(value, &ctx)?, &ctx)?,
+                        });
+                    }
+                }
+            }
+            config.oracles.push((name.to_string(), flag, settings));
+        }
+        Ok(config)
+    }
+
+    /// Applies defaults, then per-oracle settings, and stores the operator-class overrides for
+    /// `FuzzingOracles::specialize`. `enabled = true` registers a missing built-in oracle.
+    pub fn apply(&self, oracles: &mut FuzzingOracles) {
+        for (name, flag, settings) in &self.oracles {
+            match settings.enabled {
+                Some(true) if !oracles.contains(name) => {
+                    if let Some(oracle) = FuzzingOraclesBuilder::new().builtin(*flag) {
+                        oracles.register_boxed(oracle, Severity::Fail);
+                    }
+                }
+                Some(false) => {
+                    oracles.remove(name);
+                }
+                _ => {}
+            }
+        }
+
+        for oracle in oracles.iter_mut() {
+            apply_tolerances(oracle.as_mut(), &self.defaults);
+        }
+        for (name, _, settings) in &self.oracles {
+            if let Some(oracle) = oracles.get_mut(name) {
+                apply_tolerances(oracle, &settings.tolerances);
+            }
+            if let Some(severity) = settings.severity {
+                oracles.set_severity(name, severity);
+            }
+            if let Some(probe) = settings.probe {
+                oracles.probe_domain = probe;
+            }
+        }
+        oracles.op_overrides.extend(self.op_overrides.iter().cloned());
+        if let Some(factor) = self.borderline_factor {
+            oracles.borderline_factor = factor;
+        }
+        // Also reaches oracles registered above with `enabled = true`
+        oracles.set_nan_policy(self.nan_policy.unwrap_or(oracles.nan_policy));
+    }
+}
+
+fn apply_tolerances(oracle: &mut dyn Oracle, tolerances: &ToleranceOverride) {
+    if let Some((abs, rel)) = oracle.tolerances_mut() {
+        if let Some(val) = tolerances.abs_tol {
+            *abs = val;
+        }
+        if let Some(val) = tolerances.rel_tol {
+            *rel = val;
+        }
+    }
+    if let (Some(ulps), Some(comparison)) = (tolerances.ulps, oracle.comparison_mut()) {
+        *comparison = Comparison::Ulps(ulps);
+    }
+}
+
+impl FuzzingOracles {
+    /// Copy of the oracles for `expr`: oracles that inspect the expression get it, and the
+    /// operator-class overrides that match it are applied. When several classes match the same
+    /// oracle, the loosest value wins.
+    pub fn specialize(&self, expr: &SimpleExpr) -> FuzzingOracles {
+        let mut specialized = self.clone();
+        for oracle in specialized.iter_mut() {
+            if let Some(slot) = oracle.expression_mut() {
+                *slot = Some(expr.clone());
+            }
+        }
+        if self.op_overrides.is_empty() {
+            return specialized;
+        }
+
+        let classes = OpClass::of_expr(expr);
+        for oracle in specialized.iter_mut() {
+            let mut merged: Option<ToleranceOverride> = None;
+            for o in self.op_overrides.iter().filter(|o| o.oracle == oracle.name() && classes.contains(&o.class)) {
+                let m = merged.get_or_insert_with(ToleranceOverride::default);
+                m.abs_tol = max_opt(m.abs_tol, o.tolerances.abs_tol, f64::max);
+                m.rel_tol = max_opt(m.rel_tol, o.tolerances.rel_tol, f64::max);
+                m.ulps = max_opt(m.ulps, o.tolerances.ulps, u64::max);
+            }
+            if let Some(m) = merged {
+                apply_tolerances(oracle.as_mut(), &m);
+            }
+        }
+        specialized
+    }
+
+    /// Current tolerances of oracle `name`, for reports. `ulps` is only set in ULP mode.
+    pub fn tolerances(&self, name: &str) -> Option<ToleranceOverride> {
+        let mut oracle = self.oracles.iter().find(|(o, _)| o.name() == name)?.0.clone();
+        let mut tolerances = ToleranceOverride::default();
+        if let Some((abs, rel)) = oracle.tolerances_mut() {
+            tolerances.abs_tol = Some(*abs);
+            tolerances.rel_tol = Some(*rel);
+        }
+        if let Some(Comparison::Ulps(ulps)) = oracle.comparison_mut() {
+            tolerances.ulps = Some(*ulps);
+        }
+        Some(tolerances)
+    }
+}
+
+fn max_opt<T>(a: Option<T>, b: Option<T>, max: fn(T, T) -> T) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(max(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [defaults]
+        rel_tol = 1e-8
+        nan_policy = "skip"
+        borderline_factor = 4
+
+        [rev_gt]
+        severity = "warn"
+        abs_tol = 1e-6
+
+        [rev_gt.pow]
+        rel_tol = 1e-4
+
+        [scaling]
+        enabled = false
+
+        [domain]
+        probe = true
+    "#;
+
+    #[test]
+    fn test_apply_and_specialize() {
+        fn tolerances(oracles: &mut FuzzingOracles, name: &str) -> (f64, f64) {
+            let (abs, rel) = oracles.get_mut(name).unwrap().tolerances_mut().unwrap();
+            (*abs, *rel)
+        }
+
+        let mut oracles = FuzzingOracles::new(OracleSelection::ALL);
+        OracleConfig::parse(CONFIG).unwrap().apply(&mut oracles);
+
+        assert_eq!(tolerances(&mut oracles, "rev_fwd").1, 1e-8);
+        assert_eq!(tolerances(&mut oracles, "rev_gt"), (1e-6, 1e-8));
+        assert!(!oracles.contains("scaling"));
+        assert!(oracles.probe_domain);
+        assert_eq!(oracles.nan_policy, NanPolicy::Skip);
+        assert_eq!(oracles.borderline_factor, 4.0);
+        assert_eq!(oracles.get_mut("non_finite").unwrap().nan_policy_mut().copied(), Some(NanPolicy::Skip));
+
+        let mut trig = oracles.specialize(&SimpleExpr::sin(SimpleExpr::var("x_0")));
+        assert_eq!(tolerances(&mut trig, "rev_gt").1, 1e-8);
+        let mut pow = oracles.specialize(&SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)));
+        assert_eq!(tolerances(&mut pow, "rev_gt").1, 1e-4);
+    }
+
+    #[test]
+    fn test_unknown_key_is_rejected() {
+        assert!(OracleConfig::parse("[rev_gt]\nabs_tolerance = 1.0\n").is_err());
+        assert!(OracleConfig::parse("[rev_gtt]\nabs_tol = 1.0\n").is_err());
+        assert!(OracleConfig::parse("[rev_gt]\nprobe = true\n").is_err());
+        assert!(OracleConfig::parse("[defaults]\nnan_policy = \"ignore\"\n").is_err());
+        assert!(OracleConfig::parse("[defaults]\nborderline_factor = 0.5\n").is_err());
+    }
+}
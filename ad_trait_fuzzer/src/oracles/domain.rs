@@ -0,0 +1,112 @@
+// src/oracles/domain.rs
+
+use super::{Oracle, OracleContext, FiniteClass};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// What an engine did with the test case: its value class, the worst class among its partial
+/// derivatives, or an error (ground truth calculators only).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DomainBehavior {
+    Value { value: FiniteClass, gradient: FiniteClass },
+    Error,
+}
+
+impl DomainBehavior {
+    fn of(value: Option<f64>, gradient: &[f64]) -> Self {
+        // NaN dominates Inf dominates finite
+        let worst = gradient.iter().map(|d| FiniteClass::of(*d)).fold(FiniteClass::Finite, |acc, c| {
+            match (acc, c) {
+                (FiniteClass::NaN, _) | (_, FiniteClass::NaN) => FiniteClass::NaN,
+                (FiniteClass::Finite, c) => c,
+                (acc, _) => acc,
+            }
+        });
+        DomainBehavior::Value {
+            // A ground truth that does not report its value agrees on it with everyone
+            value: value.map(FiniteClass::of).unwrap_or(FiniteClass::Finite),
+            gradient: worst,
+        }
+    }
+
+    /// Representative values, so a disagreement can be classified like any other.
+    fn representative(&self) -> (f64, f64) {
+        fn rep(class: FiniteClass) -> f64 {
+            match class {
+                FiniteClass::Finite => 1.0,
+                FiniteClass::NaN => f64::NAN,
+                FiniteClass::PosInf => f64::INFINITY,
+                FiniteClass::NegInf => f64::NEG_INFINITY,
+            }
+        }
+        match self {
+            DomainBehavior::Value { value, gradient } => (rep(*value), rep(*gradient)),
+            DomainBehavior::Error => (f64::NAN, f64::NAN),
+        }
+    }
+
+    fn in_domain(&self) -> bool {
+        matches!(self, DomainBehavior::Value { value: FiniteClass::Finite, gradient: FiniteClass::Finite })
+    }
+}
+
+/// DomainConsistencyCheck: When any engine leaves a function's domain (log or sqrt of a negative,
+/// 0 to a negative power, ...), every engine must show the same behavior: same value class
+/// (NaN/±Inf), same gradient class, or all failing with `FuzzError::DomainError`.
+///
+/// Only runs when at least one engine is out of the domain, so it never duplicates the
+/// tolerance oracles on ordinary inputs.
+#[derive(Clone, Default)]
+pub struct DomainConsistencyCheck;
+
+impl Oracle for DomainConsistencyCheck {
+    fn name(&self) -> &str { "domain" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        let engine = ctx.engine;
+        let mut behaviors = vec![
+            ("Rev", DomainBehavior::of(engine.reverse_primal.first().copied(), &engine.reverse)),
+            ("Fwd", DomainBehavior::of(engine.forward_primal.first().copied(), &engine.forward)),
+        ];
+        for gt in ctx.ground_truths {
+            behaviors.push((gt.name, DomainBehavior::of(gt.value, &gt.jacobian)));
+        }
+        // Other failures (unsupported expression, dead worker, ...) say nothing about the domain
+        let domain_errors: Vec<&(&'static str, FuzzError)> = ctx.failed_ground_truths.iter()
+            .filter(|(_, e)| matches!(e, FuzzError::DomainError(_)))
+            .collect();
+        for (name, _) in &domain_errors {
+            behaviors.push((name, DomainBehavior::Error));
+        }
+
+        if behaviors.iter().all(|(_, b)| b.in_domain()) {
+            return Ok(());
+        }
+
+        let (first_name, first) = behaviors[0];
+        if let Some((name, other)) = behaviors.iter().find(|(_, b)| *b != first) {
+            let (a, b) = (other.representative(), first.representative());
+            let class = if FiniteClass::of(a.0) != FiniteClass::of(b.0) {
+                FailureClass::of(a.0, b.0)
+            } else {
+                FailureClass::of(a.1, b.1)
+            };
+            let mut summary: Vec<String> = behaviors.iter().map(|(n, b)| format!("{}: {:?}", n, b)).collect();
+            for (n, e) in &domain_errors {
+                summary.push(format!("{} error: {}", n, e));
+            }
+            return Err(FuzzError::OracleMismatch {
+                oracle: format!("Domain {} vs {}", first_name, name),
+                inputs: engine.inputs.clone(),
+                output: 0,
+                index: 0,
+                class,
+                details: format!(
+                    "Engines disagree on behavior outside the function's domain!\n{}",
+                    summary.join("\n")
+                ),
+            });
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 // src/oracles/evalexpr_vs_pytorch.rs
 
-use super::GroundTruth;
-use std::error::Error;
+use super::{GroundTruth, NanPolicy, infinity_verdict};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
 use crate::ast_evaluator::EvalexprEvaluator;
 
 pub struct EvalexprVsPyTorchCheck {
     abs_tolerance: f64,
     rel_tolerance: f64,
+    nan_policy: NanPolicy,
 }
 
 impl EvalexprVsPyTorchCheck {
@@ -14,8 +16,14 @@ impl EvalexprVsPyTorchCheck {
         EvalexprVsPyTorchCheck {
             abs_tolerance: 1e-12,
             rel_tolerance: 1e-9,
+            nan_policy: NanPolicy::default(),
         }
     }
+
+    pub fn nan_policy(mut self, policy: NanPolicy) -> Self {
+        self.nan_policy = policy;
+        self
+    }
     
     pub fn check_derivative(
         &self,
@@ -23,16 +31,18 @@ impl EvalexprVsPyTorchCheck {
         inputs: &[f64],
         gt: &GroundTruth,
         var_index: usize,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), FuzzError> {
         let gt_val = gt.jacobian[var_index];
         
-        if !gt_val.is_finite() {
-            return Ok(());
-        }
-        
         // evalexpr-jit derivative
         let deriv_fn = evalexpr_eval.derivative(var_index)?;
         let evalexpr_val = deriv_fn(inputs);
+
+        let nan_verdict = self.nan_policy.verdict(evalexpr_val, gt_val);
+        let inf_verdict = infinity_verdict(evalexpr_val, gt_val);
+        if nan_verdict == Some(true) || inf_verdict == Some(true) {
+            return Ok(());
+        }
         
         let diff = (evalexpr_val - gt_val).abs();
         
@@ -40,11 +50,18 @@ impl EvalexprVsPyTorchCheck {
         let scaled_rel_threshold = gt_val.abs() * self.rel_tolerance;
         let threshold = self.abs_tolerance.max(scaled_rel_threshold);
         
-        if diff > threshold || (evalexpr_val.is_nan() != gt_val.is_nan()){
-            return Err(format!(
-                "evalexpr-jit vs {} derivative mismatch for x_{}: evalexpr-jit = {}, {} = {}, diff = {} (threshold = {})",
-                gt.name, var_index, evalexpr_val, gt.name, gt_val, diff, threshold
-            ).into());
+        if diff > threshold || nan_verdict == Some(false) || inf_verdict == Some(false) {
+            return Err(FuzzError::OracleMismatch {
+                oracle: format!("evalexpr-jit vs {}", gt.name),
+                inputs: inputs.to_vec(),
+                output: 0,
+                index: var_index,
+                class: FailureClass::of(evalexpr_val, gt_val),
+                details: format!(
+                    "evalexpr-jit vs {} derivative mismatch for x_{}: evalexpr-jit = {}, {} = {}, diff = {} (threshold = {})",
+                    gt.name, var_index, evalexpr_val, gt.name, gt_val, diff, threshold
+                ),
+            });
         }
         
         Ok(())
@@ -55,7 +72,7 @@ impl EvalexprVsPyTorchCheck {
         evalexpr_eval: &EvalexprEvaluator<()>,
         inputs: &[f64],
         ground_truths: &[GroundTruth],
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), FuzzError> {
         let num_inputs = evalexpr_eval.num_inputs;
         
         for gt in ground_truths {
@@ -0,0 +1,168 @@
+// src/oracles/grad_check.rs
+
+use super::{EngineResults, Oracle, OracleContext, NanPolicy};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// GradCheckOracle: Compares the AD gradients against central finite differences, modeled on
+/// `torch.autograd.gradcheck`. Needs no external ground truth and catches derivative rules
+/// that Reverse and Forward AD get wrong *together*.
+///
+/// The step is scaled per input (`h = step_scale * max(1, |x_i|)`), and the tolerance grows
+/// with the estimated truncation error (difference between steps `h` and `2h`) and the rounding
+/// error of the difference quotient (`eps * |f| / h`).
+#[derive(Clone)]
+pub struct GradCheckOracle {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub step_scale: f64,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for GradCheckOracle {
+    fn default() -> Self {
+        GradCheckOracle {
+            abs_tolerance: 1e-5, // gradcheck defaults
+            rel_tolerance: 1e-3,
+            step_scale: 6.0555e-6, // cbrt(f64::EPSILON), optimal for central differences
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+/// Finite-difference estimate of df/dx_i at `x` plus the error bound that goes with it.
+struct FdEstimate {
+    central: f64,
+    error_bound: f64,
+    one_sided_gap: f64,
+}
+
+impl GradCheckOracle {
+    /// `None` if `f` is not finite at one of the points.
+    fn estimate<F: Fn(&[f64]) -> Result<f64, FuzzError>>(&self, f: &F, x: &[f64], i: usize) -> Result<Option<FdEstimate>, FuzzError> {
+        let h = self.step_scale * x[i].abs().max(1.0);
+        let at = |offset: f64| {
+            let mut shifted = x.to_vec();
+            shifted[i] += offset;
+            f(&shifted)
+        };
+
+        let f0 = f(x)?;
+        let (fp1, fm1) = (at(h)?, at(-h)?);
+        let (fp2, fm2) = (at(2.0 * h)?, at(-2.0 * h)?);
+        if ![f0, fp1, fm1, fp2, fm2].iter().all(|v| v.is_finite()) {
+            return Ok(None);
+        }
+
+        let central = (fp1 - fm1) / (2.0 * h);
+        let central_2h = (fp2 - fm2) / (4.0 * h);
+        let forward = (fp1 - f0) / h;
+        let backward = (f0 - fm1) / h;
+
+        let truncation = (central - central_2h).abs();
+        let rounding = f64::EPSILON * f0.abs().max(1.0) / h;
+        Ok(Some(FdEstimate {
+            central,
+            error_bound: truncation + rounding,
+            one_sided_gap: (forward - backward).abs(),
+        }))
+    }
+
+    /// Runs the check for every (output, input) pair. `f` evaluates the function in plain f64;
+    /// it returning fewer outputs than the engines is a `BackendFailure`, not a skipped pair.
+    pub fn check_function<F: Fn(&[f64]) -> Vec<f64>>(&self, f: F, engine: &EngineResults) -> Result<(), FuzzError> {
+        let x = &engine.inputs;
+        for k in 0..engine.num_outputs() {
+            let f_k = |x: &[f64]| {
+                let outputs = f(x);
+                outputs.get(k).copied().ok_or_else(|| FuzzError::backend(
+                    "f64 evaluation",
+                    format!("returned {} outputs, the engines {}", outputs.len(), engine.num_outputs()),
+                ))
+            };
+            for i in 0..x.len() {
+                let est = match self.estimate(&f_k, x, i)? {
+                    Some(est) => est,
+                    None => continue, // non-finite neighbourhood, nothing to compare
+                };
+
+                let threshold = self.abs_tolerance + self.rel_tolerance * est.central.abs() + est.error_bound;
+
+                // Left and right slopes disagree: kink or jump (abs at 0, trunc), not differentiable here
+                if est.one_sided_gap > threshold {
+                    continue;
+                }
+
+                let flat = k * x.len() + i;
+                for (name, ad_val) in [("Rev", engine.reverse[flat]), ("Fwd", engine.forward[flat])] {
+                    let nan_verdict = self.nan_policy.verdict(ad_val, est.central);
+                    if nan_verdict == Some(true) {
+                        continue;
+                    }
+                    let diff = (ad_val - est.central).abs();
+                    if diff > threshold || nan_verdict == Some(false) {
+                        return Err(FuzzError::OracleMismatch {
+                            oracle: format!("GradCheck {} vs FiniteDiff", name),
+                            inputs: x.clone(),
+                            output: k,
+                            index: i,
+                            class: FailureClass::of(ad_val, est.central),
+                            details: format!(
+                                "Gradient check failed! (Central Differences)\n\
+                                {}: {:.10e}, FiniteDiff: {:.10e}\n\
+                                Absolute Diff: {:.10e}\n\
+                                Tolerance Threshold: {:.10e} (Abs:{:.10e} + Rel:{:.10e} * |fd| + FD error estimate:{:.10e})",
+                                name, ad_val, est.central,
+                                diff,
+                                threshold, self.abs_tolerance, self.rel_tolerance, est.error_bound
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for GradCheckOracle {
+    fn name(&self) -> &str { "grad_check" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_function(ctx.function, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_parser::parse_infix;
+    use crate::fuzz_harness::compute_engine_results;
+
+    #[test]
+    fn test_grad_check() {
+        let (expr, n) = parse_infix("x_0 * exp(x_1) - sin(x_0)").unwrap();
+        let calc = AdPyUnified::new(expr, n, 1);
+        let f = |x: &[f64]| vec![x[0] * x[1].exp() - x[0].sin()];
+        let mut engine = compute_engine_results(&calc, &[2.0, 0.5]);
+        let check = GradCheckOracle::default();
+        assert!(check.check_function(f, &engine).is_ok());
+
+        // A missing output is an error, not a pair to skip
+        let err = check.check_function(|_: &[f64]| Vec::new(), &engine).unwrap_err();
+        assert_eq!(err.kind(), "BackendFailure");
+
+        // Both engines wrong together: only the finite differences notice
+        engine.reverse[1] *= 1.01;
+        engine.forward[1] *= 1.01;
+        let err = check.check_function(f, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, index: 1, .. } if oracle == "GradCheck Rev vs FiniteDiff"));
+    }
+}
@@ -0,0 +1,198 @@
+// src/oracles/gradient_bound.rs
+
+// Gradient-magnitude sanity check: forward-mode AD over intervals (`IntervalDual`) encloses each
+// partial derivative of the function over a small box around the inputs, and no engine may
+// report a partial larger than that. A Lipschitz-style bound, cheap and needing no ground truth,
+// so it still runs when PyTorch fails; catches gradients that are impossibly large for the
+// expression, not small errors.
+
+use super::{Oracle, OracleContext};
+use crate::ast_evaluator::MainBackend;
+use crate::ast_expr::Type;
+use crate::domain_analysis::Interval;
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+use crate::fuzz_harness::Calculator;
+
+/// Range of a value and of its derivative along one input over a box, see `gradient_enclosure`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntervalDual {
+    pub val: Interval,
+    pub der: Interval,
+}
+
+impl IntervalDual {
+    fn constant(val: Interval) -> Self {
+        IntervalDual { val, der: Interval::point(0.0) }
+    }
+
+    /// `f(val)` with derivative `f'(val) * der`.
+    fn chain(self, val: Interval, derivative: Interval) -> Self {
+        IntervalDual { val, der: derivative.mul(self.der) }
+    }
+}
+
+impl MainBackend for IntervalDual {
+    fn from_f64(val: f64) -> Self { IntervalDual::constant(Interval::point(val)) }
+    fn zero() -> Self { IntervalDual::from_f64(0.0) }
+    fn one() -> Self { IntervalDual::from_f64(1.0) }
+
+    fn neg(self) -> Self { IntervalDual { val: self.val.neg(), der: self.der.neg() } }
+    fn sin(self) -> Self { self.chain(self.val.sin(), self.val.cos()) }
+    fn cos(self) -> Self { self.chain(self.val.cos(), self.val.sin().neg()) }
+    fn tan(self) -> Self {
+        let cos = self.val.cos();
+        self.chain(self.val.tan(), Interval::one().div(cos.mul(cos)))
+    }
+    fn exp(self) -> Self { self.chain(self.val.exp(), self.val.exp()) }
+    fn log(self) -> Self { self.chain(self.val.log(), Interval::one().div(self.val)) }
+    fn sqrt(self) -> Self {
+        let sqrt = self.val.sqrt();
+        self.chain(sqrt, Interval::one().div(Interval::from_f64(2.0).mul(sqrt)))
+    }
+    fn abs(self) -> Self {
+        // sign(val) * der; any subgradient where val spans 0
+        let der = match (self.val.lo >= 0.0, self.val.hi <= 0.0) {
+            (true, _) => self.der,
+            (_, true) => self.der.neg(),
+            _ => {
+                let m = self.der.lo.abs().max(self.der.hi.abs());
+                Interval::new(-m, m)
+            }
+        };
+        IntervalDual { val: self.val.abs(), der }
+    }
+
+    fn add(self, other: Self) -> Self { IntervalDual { val: self.val.add(other.val), der: self.der.add(other.der) } }
+    fn sub(self, other: Self) -> Self { IntervalDual { val: self.val.sub(other.val), der: self.der.sub(other.der) } }
+    fn mul(self, other: Self) -> Self {
+        IntervalDual { val: self.val.mul(other.val), der: self.der.mul(other.val).add(self.val.mul(other.der)) }
+    }
+    fn div(self, other: Self) -> Self {
+        let val = self.val.div(other.val);
+        IntervalDual { val, der: self.der.sub(val.mul(other.der)).div(other.val) }
+    }
+    fn pow(self, other: Self) -> Self {
+        let val = self.val.pow(other.val);
+        // b a^(b-1) a' + a^b ln(a) b'; a constant exponent has no ln(a) term
+        let base_term = other.val.mul(self.val.pow(other.val.sub(Interval::one()))).mul(self.der);
+        let der = if other.der == Interval::point(0.0) {
+            base_term
+        } else {
+            base_term.add(val.mul(self.val.log()).mul(other.der))
+        };
+        IntervalDual { val, der }
+    }
+
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            // Piecewise constant
+            Type::Int | Type::Bool => IntervalDual::constant(self.val.cast(ty)),
+        }
+    }
+}
+
+/// Enclosure of each partial derivative of the first output of `calc` over `region`, one
+/// interval per input.
+pub fn gradient_enclosure<G: Calculator>(calc: &G, region: &[Interval]) -> Result<Vec<Interval>, FuzzError> {
+    (0..region.len())
+        .map(|i| {
+            let inputs: Vec<IntervalDual> = region.iter().enumerate()
+                .map(|(j, &val)| IntervalDual { val, der: Interval::point(if i == j { 1.0 } else { 0.0 }) })
+                .collect();
+            calc.eval_backend(&inputs).map(|out| out.der)
+        })
+        .collect()
+}
+
+/// GradientBoundCheck: Reverse and Forward AD partials of the first output must not exceed, in
+/// magnitude, the largest value `gradient_enclosure` allows over the box
+/// `x_i ± radius * max(1, |x_i|)`. Unbounded enclosures (a pole or a domain edge in the box) and
+/// non-finite partials are skipped.
+#[derive(Clone)]
+pub struct GradientBoundCheck {
+    pub radius: f64,
+    pub abs_tolerance: f64,
+    /// Interval endpoints are not rounded outward, so the bound gets some slack.
+    pub rel_tolerance: f64,
+}
+
+impl Default for GradientBoundCheck {
+    fn default() -> Self {
+        GradientBoundCheck {
+            radius: 1e-3,
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-6,
+        }
+    }
+}
+
+impl Oracle for GradientBoundCheck {
+    fn name(&self) -> &str { "grad_bound" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        let engine = ctx.engine;
+        let region: Vec<Interval> = engine.inputs.iter()
+            .map(|x| {
+                let r = self.radius * x.abs().max(1.0);
+                Interval::new(x - r, x + r)
+            })
+            .collect();
+        // Expressions the interval backend can't evaluate have no bound
+        let Ok(bounds) = (ctx.gradient_enclosure)(&region) else { return Ok(()) };
+
+        for (input, bound) in bounds.iter().enumerate() {
+            let limit = bound.lo.abs().max(bound.hi.abs());
+            if bound.is_empty() || !limit.is_finite() {
+                continue;
+            }
+            let threshold = limit * (1.0 + self.rel_tolerance) + self.abs_tolerance;
+            for (name, partial) in [("Rev", engine.reverse[input]), ("Fwd", engine.forward[input])] {
+                if partial.is_finite() && partial.abs() > threshold {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Gradient Bound {}", name),
+                        inputs: engine.inputs.clone(),
+                        output: 0,
+                        index: input,
+                        class: FailureClass::of(partial, limit.copysign(partial)),
+                        details: format!(
+                            "Partial derivative larger than any value of it near the inputs!\n\
+                            {}: {:.10e}, range of d/dx_{} over x ± {:e} * max(1, |x|): [{:.10e}, {:.10e}]",
+                            name, partial, input, self.radius, bound.lo, bound.hi
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_gradient_enclosure() {
+        // f = x_0^2 * sin(x_1): df/dx_0 = 2 x_0 sin(x_1) ∈ 2 [1, 2] [-1, 1]
+        let calc = AdPyUnified::new(
+            SimpleExpr::mul(SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)), SimpleExpr::sin(SimpleExpr::var("x_1"))),
+            2,
+            1,
+        );
+        let bounds = gradient_enclosure(&calc, &[Interval::new(1.0, 2.0), Interval::new(0.0, 3.0)]).unwrap();
+        assert_eq!(bounds[0], Interval::new(-4.0, 4.0));
+        assert!(bounds[1].contains(4.0 * 0.0_f64.cos()) && bounds[1].contains(4.0 * 3.0_f64.cos()));
+
+        // Constant exponent: no ln of a negative base
+        let square = AdPyUnified::new(SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)), 1, 1);
+        assert_eq!(gradient_enclosure(&square, &[Interval::new(-3.0, -1.0)]).unwrap(), vec![Interval::new(-6.0, -2.0)]);
+    }
+}
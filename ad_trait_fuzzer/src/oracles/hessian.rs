@@ -0,0 +1,325 @@
+// src/oracles/hessian.rs
+
+use std::cell::RefCell;
+
+use super::{EngineResults, GroundTruth, InputTransform, Oracle, OracleContext};
+use crate::ast_evaluator::{Dual, MainBackend};
+use crate::ast_expr::Type;
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+use crate::fuzz_harness::Calculator;
+
+thread_local! {
+    /// Tape of the `RevDual`s recorded on this thread: per node, its operands and the partial
+    /// derivatives to them.
+    static TAPE: RefCell<Vec<Vec<(usize, Dual)>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Reverse-mode variable whose values are forward-mode `Dual`s, for
+/// `forward_over_reverse_hessian`. Like `Dual`, small and independent from ad_trait.
+#[derive(Clone, Copy, Debug)]
+pub struct RevDual {
+    pub val: Dual,
+    /// Index on the tape, `None` for constants.
+    node: Option<usize>,
+}
+
+impl RevDual {
+    fn constant(val: Dual) -> Self {
+        RevDual { val, node: None }
+    }
+
+    /// New node for `val`, with `partials` to the operands it is computed from. Constant if none
+    /// of them is on the tape.
+    fn record(val: Dual, partials: &[(RevDual, Dual)]) -> Self {
+        let operands: Vec<(usize, Dual)> = partials.iter().filter_map(|(x, d)| x.node.map(|n| (n, *d))).collect();
+        if operands.is_empty() {
+            return RevDual::constant(val);
+        }
+        let node = TAPE.with(|tape| {
+            let mut tape = tape.borrow_mut();
+            tape.push(operands);
+            tape.len() - 1
+        });
+        RevDual { val, node: Some(node) }
+    }
+
+    fn variable(val: Dual) -> Self {
+        let node = TAPE.with(|tape| {
+            let mut tape = tape.borrow_mut();
+            tape.push(Vec::new());
+            tape.len() - 1
+        });
+        RevDual { val, node: Some(node) }
+    }
+}
+
+impl MainBackend for RevDual {
+    fn from_f64(val: f64) -> Self { RevDual::constant(Dual::constant(val)) }
+    fn zero() -> Self { RevDual::from_f64(0.0) }
+    fn one() -> Self { RevDual::from_f64(1.0) }
+
+    fn neg(self) -> Self { RevDual::record(self.val.neg(), &[(self, Dual::constant(-1.0))]) }
+    fn sin(self) -> Self { RevDual::record(self.val.sin(), &[(self, self.val.cos())]) }
+    fn cos(self) -> Self { RevDual::record(self.val.cos(), &[(self, self.val.sin().neg())]) }
+    fn tan(self) -> Self {
+        let c = self.val.cos();
+        RevDual::record(self.val.tan(), &[(self, Dual::one().div(c.mul(c)))])
+    }
+    fn exp(self) -> Self {
+        let e = self.val.exp();
+        RevDual::record(e, &[(self, e)])
+    }
+    fn log(self) -> Self { RevDual::record(self.val.log(), &[(self, Dual::one().div(self.val))]) }
+    fn sqrt(self) -> Self {
+        let s = self.val.sqrt();
+        RevDual::record(s, &[(self, Dual::constant(0.5).div(s))])
+    }
+    // Subgradient 0 at the kink, like `Dual`
+    fn abs(self) -> Self {
+        let sign = if self.val.val > 0.0 { 1.0 } else if self.val.val < 0.0 { -1.0 } else { 0.0 };
+        RevDual::record(self.val.abs(), &[(self, Dual::constant(sign))])
+    }
+
+    fn add(self, other: Self) -> Self {
+        RevDual::record(self.val.add(other.val), &[(self, Dual::one()), (other, Dual::one())])
+    }
+    fn sub(self, other: Self) -> Self {
+        RevDual::record(self.val.sub(other.val), &[(self, Dual::one()), (other, Dual::constant(-1.0))])
+    }
+    fn mul(self, other: Self) -> Self {
+        RevDual::record(self.val.mul(other.val), &[(self, other.val), (other, self.val)])
+    }
+    fn div(self, other: Self) -> Self {
+        let q = self.val.div(other.val);
+        RevDual::record(q, &[(self, Dual::one().div(other.val)), (other, q.neg().div(other.val))])
+    }
+
+    // Only the operands on the tape get a partial, so a constant exponent on a negative base does
+    // not pick up ln(a) = NaN
+    fn pow(self, other: Self) -> Self {
+        let val = self.val.pow(other.val);
+        let mut partials = Vec::with_capacity(2);
+        if self.node.is_some() {
+            partials.push((self, other.val.mul(self.val.pow(other.val.sub(Dual::one())))));
+        }
+        if other.node.is_some() {
+            partials.push((other, val.mul(self.val.log())));
+        }
+        RevDual::record(val, &partials)
+    }
+
+    // Int and Bool are piecewise constant: off the tape
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            _ => RevDual::constant(self.val.cast(ty)),
+        }
+    }
+}
+
+/// Hessian of `calc`'s first output at `x` by forward-over-reverse: column `j` is the forward
+/// derivative along `e_j` of the reverse-mode gradient, one tape per column. Fails if `calc`
+/// cannot be evaluated with generic backends.
+pub fn forward_over_reverse_hessian<G: Calculator>(calc: &G, x: &[f64]) -> Result<Vec<Vec<f64>>, FuzzError> {
+    let n = x.len();
+    let mut hessian = vec![vec![0.0; n]; n];
+    for j in 0..n {
+        TAPE.with(|tape| tape.borrow_mut().clear());
+        // The inputs are nodes 0..n
+        let inputs: Vec<RevDual> = x.iter().enumerate()
+            .map(|(i, &val)| RevDual::variable(Dual::new(val, if i == j { 1.0 } else { 0.0 })))
+            .collect();
+        let out = calc.eval_backend(&inputs)?;
+        let tape = TAPE.with(|tape| std::mem::take(&mut *tape.borrow_mut()));
+        let Some(last) = out.node else { continue };
+
+        let mut adjoints = vec![Dual::zero(); last + 1];
+        adjoints[last] = Dual::one();
+        for node in (0..=last).rev() {
+            let adjoint = adjoints[node];
+            for &(operand, partial) in &tape[node] {
+                adjoints[operand] = adjoints[operand].add(adjoint.mul(partial));
+            }
+        }
+        for (row, adjoint) in hessian.iter_mut().zip(&adjoints) {
+            row[j] = adjoint.der;
+        }
+    }
+    Ok(hessian)
+}
+
+/// HessianCheck: Compares a ground truth Hessian (when the calculator reports one) against
+/// central differences of the Reverse AD gradient, `H[:, j] ~ (grad(x + h e_j) - grad(x - h e_j)) / 2h`,
+/// and, where the function supports generic backends, against `forward_over_reverse_hessian`.
+///
+/// First-order oracles cannot see a wrong second derivative rule until it shows up in a
+/// composed gradient; this catches it directly.
+#[derive(Clone)]
+pub struct HessianCheck {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub step_scale: f64,
+}
+
+impl Default for HessianCheck {
+    fn default() -> Self {
+        HessianCheck {
+            abs_tolerance: 1e-4,
+            rel_tolerance: 1e-3,
+            step_scale: 6.0555e-6, // cbrt(f64::EPSILON)
+        }
+    }
+}
+
+impl HessianCheck {
+    /// Column `j` of the Hessian from the engine gradients at `x +- h e_j`.
+    fn ad_column(&self, ctx: &OracleContext, j: usize) -> Vec<f64> {
+        let x = &ctx.engine.inputs;
+        let h = self.step_scale * x[j].abs().max(1.0);
+        let mut plus = x.clone();
+        plus[j] += h;
+        let mut minus = x.clone();
+        minus[j] -= h;
+
+        let (g_plus, g_minus) = ((ctx.rerun)(InputTransform::Identity, &plus), (ctx.rerun)(InputTransform::Identity, &minus));
+        // Only the first n entries (output 0): ground truth Hessians are of the first output
+        g_plus.reverse.iter().zip(&g_minus.reverse).take(x.len()).map(|(p, m)| (p - m) / (2.0 * h)).collect()
+    }
+
+    fn check_gt(&self, ctx: &OracleContext, engine: &EngineResults, gt: &GroundTruth, hessian: &[Vec<f64>], reference: Option<&[Vec<f64>]>) -> Result<(), FuzzError> {
+        let n = engine.inputs.len();
+        for j in 0..n {
+            let column = self.ad_column(ctx, j);
+            for (i, (ad_val, gt_row)) in column.iter().zip(hessian).enumerate() {
+                self.compare(engine, gt, "Rev", (i, j), *ad_val, gt_row[j])?;
+            }
+        }
+        if let Some(reference) = reference {
+            for (i, (row, gt_row)) in reference.iter().zip(hessian).enumerate() {
+                for (j, (ad_val, gt_val)) in row.iter().zip(gt_row).enumerate() {
+                    self.compare(engine, gt, "Fwd-over-Rev", (i, j), *ad_val, *gt_val)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compare(&self, engine: &EngineResults, gt: &GroundTruth, name: &str, (i, j): (usize, usize), ad_val: f64, gt_val: f64) -> Result<(), FuzzError> {
+        if !ad_val.is_finite() || !gt_val.is_finite() {
+            return Ok(()); // NonFinite/Domain oracles own blow-ups
+        }
+        let threshold = self.abs_tolerance.max(gt_val.abs() * self.rel_tolerance);
+        if (ad_val - gt_val).abs() <= threshold {
+            return Ok(());
+        }
+        let what = match name {
+            "Rev" => "Rev (central differences of the gradient)",
+            other => other,
+        };
+        Err(FuzzError::OracleMismatch {
+            oracle: format!("Hessian {} vs {}", name, gt.name),
+            inputs: engine.inputs.clone(),
+            output: 0,
+            index: i,
+            class: FailureClass::of(ad_val, gt_val),
+            details: format!(
+                "Hessian mismatch at d^2f/dx_{}dx_{}!\n\
+                {}: {:.10e}, {}: {:.10e}\n\
+                Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e})",
+                i, j, what, ad_val, gt.name, gt_val,
+                (ad_val - gt_val).abs(), self.abs_tolerance, self.rel_tolerance
+            ),
+        })
+    }
+}
+
+impl Oracle for HessianCheck {
+    fn name(&self) -> &str { "hessian" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        let n = ctx.engine.inputs.len();
+        let mut reference = None;
+        for gt in ctx.ground_truths {
+            let hessian = match &gt.hessian {
+                Some(hessian) => hessian,
+                None => continue,
+            };
+            if hessian.len() != n || hessian.iter().any(|row| row.len() != n) {
+                return Err(FuzzError::OracleMismatch {
+                    oracle: format!("Hessian Rev vs {}", gt.name),
+                    inputs: ctx.engine.inputs.clone(),
+                    output: 0,
+                    index: 0,
+                    class: FailureClass::ShapeMismatch,
+                    details: format!("{} Hessian is not {}x{}", gt.name, n, n),
+                });
+            }
+            // Computed once, for the first ground truth with a Hessian
+            if reference.is_none() {
+                reference = Some((ctx.hessian)(&ctx.engine.inputs).ok());
+            }
+            self.check_gt(ctx, ctx.engine, gt, hessian, reference.as_ref().and_then(|r| r.as_deref()))?;
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::fuzz_harness::compute_engine_results;
+    use crate::domain_analysis::Interval;
+    use crate::ast_parser::parse_infix;
+    use crate::fuzz_harness::GroundTruthCalculator;
+    use crate::gt_calculators::SymbolicGroundTruthCalculator;
+    use crate::oracles::{gradient_enclosure, run_transformed, vector_products};
+
+    #[test]
+    fn test_hessian_against_exact() {
+        // f = x_0^2 * x_1, H = [[2 x_1, 2 x_0], [2 x_0, 0]]
+        let calc = AdPyUnified::new(
+            SimpleExpr::mul(SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(2.0)), SimpleExpr::var("x_1")),
+            2,
+            1,
+        );
+        let x = [1.5, -2.0];
+        let engine = compute_engine_results(&calc, &x);
+        let rerun = |t: InputTransform, x: &[f64]| run_transformed(&calc, t, x);
+        let check = |hessian: Vec<Vec<f64>>| {
+            let gt = GroundTruth { name: "Exact", jacobian: engine.reverse.clone(), value: None, hessian: Some(hessian) };
+            HessianCheck::default().check(&OracleContext {
+                engine: &engine,
+                ground_truths: &[gt],
+                failed_ground_truths: &[],
+                function: &|_: &[f64]| Vec::new(),
+                rerun: &rerun,
+                gradient_enclosure: &|region: &[Interval]| gradient_enclosure(&calc, region),
+                vector_products: &|x, u, v| vector_products(&calc, x, u, v),
+                hessian: &|x| forward_over_reverse_hessian(&calc, x),
+                with_constant: &|_, _, _| None,
+            })
+        };
+
+        assert!(check(vec![vec![-4.0, 3.0], vec![3.0, 0.0]]).is_ok());
+        assert!(check(vec![vec![-4.0, -3.0], vec![-3.0, 0.0]]).is_err());
+
+        // Forward-over-reverse matches the closed-form Hessian through every rule
+        let (expr, n) = parse_infix("sin(x_0) * x_1 / sqrt(x_0 + exp(x_1)) + ln(x_0) ^ x_1 - tan(abs(x_1)) * cos(x_0)").unwrap();
+        let calc = AdPyUnified::new(expr, n, 1);
+        let x = [1.7, -0.6];
+        let symbolic = SymbolicGroundTruthCalculator.calculate(&calc, &x).unwrap().hessian.unwrap();
+        let hessian = forward_over_reverse_hessian(&calc, &x).unwrap();
+        for (row, expected) in hessian.iter().zip(&symbolic) {
+            for (a, b) in row.iter().zip(expected) {
+                assert!((a - b).abs() <= 1e-12 * b.abs().max(1.0), "{} vs {}", a, b);
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// src/oracles/identity.rs
+
+// Algebraic-identity oracle: the output u = f(x) is fed through an identity such as
+// sin(u)^2 + cos(u)^2 = 1 on the tape, and each engine's derivative of the composition must be
+// what the identity implies: 0 for a constant, u' for one that reduces to u. Only the engine
+// under test is involved, so a systematic error in e.g. the derivative of `sin`, which a ground
+// truth could share, still shows up as a nonzero gradient.
+
+use std::fmt;
+
+use ad_trait::AD;
+
+use super::{EngineResults, InputTransform, Oracle, OracleContext};
+use crate::ast_evaluator::MainBackend;
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+use crate::fuzz_harness::Calculator;
+
+/// Identities in one variable `u` that the engines must differentiate consistently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlgebraicIdentity {
+    /// sin(u)^2 + cos(u)^2 = 1
+    Pythagorean,
+    /// exp(u) * exp(-u) = 1
+    ExpInverse,
+    /// tan(u) * cos(u) - sin(u) = 0
+    TanQuotient,
+    /// sqrt(u * u) - |u| = 0
+    SqrtSquare,
+    /// ln(exp(u)) = u
+    LogExp,
+    /// u^2 / u = u
+    PowQuotient,
+}
+
+impl AlgebraicIdentity {
+    pub const ALL: [AlgebraicIdentity; 6] = [
+        AlgebraicIdentity::Pythagorean,
+        AlgebraicIdentity::ExpInverse,
+        AlgebraicIdentity::TanQuotient,
+        AlgebraicIdentity::SqrtSquare,
+        AlgebraicIdentity::LogExp,
+        AlgebraicIdentity::PowQuotient,
+    ];
+
+    /// The left-hand side at `u`.
+    pub fn apply<B: MainBackend>(self, u: B) -> B {
+        let two = || B::from_f64(2.0);
+        match self {
+            AlgebraicIdentity::Pythagorean => u.clone().sin().pow(two()).add(u.cos().pow(two())),
+            AlgebraicIdentity::ExpInverse => u.clone().exp().mul(u.neg().exp()),
+            AlgebraicIdentity::TanQuotient => u.clone().tan().mul(u.clone().cos()).sub(u.sin()),
+            AlgebraicIdentity::SqrtSquare => u.clone().mul(u.clone()).sqrt().sub(u.abs()),
+            AlgebraicIdentity::LogExp => u.exp().log(),
+            AlgebraicIdentity::PowQuotient => u.clone().pow(two()).div(u),
+        }
+    }
+
+    /// The right-hand side if it is a constant, `None` if it is `u`.
+    pub fn constant(self) -> Option<f64> {
+        match self {
+            AlgebraicIdentity::Pythagorean | AlgebraicIdentity::ExpInverse => Some(1.0),
+            AlgebraicIdentity::TanQuotient | AlgebraicIdentity::SqrtSquare => Some(0.0),
+            AlgebraicIdentity::LogExp | AlgebraicIdentity::PowQuotient => None,
+        }
+    }
+
+    /// Whether every intermediate stays finite and differentiable at `u`.
+    pub fn applies(self, u: f64) -> bool {
+        u.is_finite() && match self {
+            AlgebraicIdentity::Pythagorean => true,
+            AlgebraicIdentity::ExpInverse => u.abs() < 300.0,
+            // Terms of size 1 / cos(u) cancel
+            AlgebraicIdentity::TanQuotient => u.cos().abs() > 1e-3,
+            // u * u neither underflows nor overflows
+            AlgebraicIdentity::SqrtSquare | AlgebraicIdentity::PowQuotient => u.abs() > 1e-150 && u.abs() < 1e150,
+            AlgebraicIdentity::LogExp => u.abs() < 700.0,
+        }
+    }
+}
+
+impl fmt::Display for AlgebraicIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AlgebraicIdentity::Pythagorean => "sin(u)^2 + cos(u)^2 = 1",
+            AlgebraicIdentity::ExpInverse => "exp(u) * exp(-u) = 1",
+            AlgebraicIdentity::TanQuotient => "tan(u) * cos(u) - sin(u) = 0",
+            AlgebraicIdentity::SqrtSquare => "sqrt(u * u) - |u| = 0",
+            AlgebraicIdentity::LogExp => "ln(exp(u)) = u",
+            AlgebraicIdentity::PowQuotient => "u^2 / u = u",
+        })
+    }
+}
+
+/// `identity(f(x))`, each output of `f` fed through the identity on the tape.
+#[derive(Clone)]
+pub struct Composed<G: Calculator> {
+    pub inner: G,
+    pub identity: AlgebraicIdentity,
+}
+
+impl<G: Calculator> Calculator for Composed<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        self.identity.apply(self.inner.eval_expr(inputs))
+    }
+
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        self.inner.eval_outputs(inputs).into_iter().map(|u| self.identity.apply(u)).collect()
+    }
+
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        self.inner.eval_backend(inputs).map(|u| self.identity.apply(u))
+    }
+}
+
+/// IdentityCheck: for each identity in `identities` that applies at every output, checks the
+/// value and the derivative of `identity(f(x))` against the right-hand side, separately for
+/// Reverse and Forward AD. The tolerance grows with `|u|` and `|u'|`, the size of the terms that
+/// cancel.
+#[derive(Clone)]
+pub struct IdentityCheck {
+    pub identities: Vec<AlgebraicIdentity>,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+}
+
+impl Default for IdentityCheck {
+    fn default() -> Self {
+        IdentityCheck {
+            identities: AlgebraicIdentity::ALL.to_vec(),
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+        }
+    }
+}
+
+impl IdentityCheck {
+    pub fn check_rerun(&self, rerun: &dyn Fn(InputTransform, &[f64]) -> EngineResults, engine: &EngineResults) -> Result<(), FuzzError> {
+        let tolerance = |scale: f64| self.abs_tolerance + self.rel_tolerance * scale;
+        let mismatch = |identity: AlgebraicIdentity, name: &str, what: &str, output: usize, index: usize, got: f64, expected: f64, scale: f64| FuzzError::OracleMismatch {
+            oracle: format!("Identity {}", name),
+            inputs: engine.inputs.clone(),
+            output,
+            index,
+            class: FailureClass::of(got, expected),
+            details: format!(
+                "Algebraic identity violated: {}\n\
+                {}: {:.10e}, expected {:.10e}\n\
+                Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e} of {:.10e})",
+                identity, what, got, expected,
+                (got - expected).abs(), self.abs_tolerance, self.rel_tolerance, scale
+            ),
+        };
+        for &identity in &self.identities {
+            if !engine.reverse_primal.iter().chain(&engine.forward_primal).all(|u| identity.applies(*u)) {
+                continue;
+            }
+            let composed = rerun(InputTransform::Compose(identity), &engine.inputs);
+
+            let values = [
+                ("Rev", &composed.reverse_primal, &engine.reverse_primal),
+                ("Fwd", &composed.forward_primal, &engine.forward_primal),
+            ];
+            for (name, got, original) in values {
+                for (output, (&got, &u)) in got.iter().zip(original).enumerate() {
+                    let expected = identity.constant().unwrap_or(u);
+                    let scale = u.abs().max(1.0);
+                    if (got - expected).abs() > tolerance(scale) {
+                        return Err(mismatch(identity, name, &format!("value at u = {:.10e}", u), output, 0, got, expected, scale));
+                    }
+                }
+            }
+
+            for i in 0..composed.reverse.len() {
+                let (output, input) = engine.partial(i);
+                let pairs = [
+                    ("Rev", composed.reverse[i], engine.reverse[i]),
+                    ("Fwd", composed.forward[i], engine.forward[i]),
+                ];
+                for (name, got, du) in pairs {
+                    // Non-finite derivatives are left to `NonFiniteConsistencyCheck`
+                    if !got.is_finite() || !du.is_finite() {
+                        continue;
+                    }
+                    let expected = if identity.constant().is_some() { 0.0 } else { du };
+                    if (got - expected).abs() > tolerance(du.abs()) {
+                        return Err(mismatch(identity, name, "d/dx identity(f(x))", output, input, got, expected, du.abs()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for IdentityCheck {
+    fn name(&self) -> &str { "identity" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_rerun(ctx.rerun, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_expr::SimpleExpr;
+    use crate::fuzz_harness::compute_engine_results;
+    use crate::oracles::run_transformed;
+
+    #[test]
+    fn test_identities() {
+        for u in [-2.5, -0.3, 0.7, 4.0] {
+            for identity in AlgebraicIdentity::ALL {
+                let expected = identity.constant().unwrap_or(u);
+                assert!((identity.apply(u) - expected).abs() < 1e-12, "{} at {}", identity, u);
+            }
+        }
+
+        let calc = AdPyUnified::new(SimpleExpr::mul(SimpleExpr::sin(SimpleExpr::var("x_0")), SimpleExpr::var("x_1")), 2, 1);
+        let x = [0.8, -1.7];
+        let engine = compute_engine_results(&calc, &x);
+        let rerun = |t: InputTransform, x: &[f64]| run_transformed(&calc, t, x);
+        assert!(IdentityCheck::default().check_rerun(&rerun, &engine).is_ok());
+
+        // An engine whose derivative of the composition is off by 1e-3
+        let broken = |t: InputTransform, x: &[f64]| {
+            let mut results = run_transformed(&calc, t, x);
+            results.forward[1] += 1e-3;
+            results
+        };
+        let err = IdentityCheck::default().check_rerun(&broken, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, index: 1, .. } if oracle == "Identity Fwd"));
+    }
+}
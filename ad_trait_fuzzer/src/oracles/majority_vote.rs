@@ -0,0 +1,137 @@
+// src/oracles/majority_vote.rs
+
+use super::{EngineResults, Oracle, OracleContext, GroundTruth, NanPolicy, infinity_verdict};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// MajorityVoteCheck: Compares Reverse AD, Forward AD and every ground truth at once and names
+/// the engine(s) that disagree with the majority, instead of failing on the first pairwise
+/// mismatch (which cannot tell which side of the pair is wrong).
+///
+/// Needs at least three voters; with fewer it does nothing and the pairwise oracles decide.
+#[derive(Clone)]
+pub struct MajorityVoteCheck {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    /// Under `NanPolicy::Skip`, NaN voters abstain.
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for MajorityVoteCheck {
+    fn default() -> Self {
+        MajorityVoteCheck {
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl MajorityVoteCheck {
+    fn agrees(&self, a: f64, b: f64) -> bool {
+        if let Some(passes) = self.nan_policy.verdict(a, b).or_else(|| infinity_verdict(a, b)) {
+            return passes;
+        }
+        if a == b {
+            return true;
+        }
+        let threshold = self.abs_tolerance.max(a.abs().max(b.abs()) * self.rel_tolerance);
+        (a - b).abs() <= threshold
+    }
+
+    pub fn check_at(&self, engine: &EngineResults, ground_truths: &[GroundTruth], i: usize) -> Result<(), FuzzError> {
+        let (output, input) = engine.partial(i);
+        let mut votes = vec![("Rev", engine.reverse[i]), ("Fwd", engine.forward[i])];
+        for gt in ground_truths {
+            votes.push((gt.name, gt.jacobian[i]));
+        }
+        if self.nan_policy == NanPolicy::Skip {
+            votes.retain(|(_, v)| !v.is_nan());
+        }
+        if votes.len() < 3 {
+            return Ok(());
+        }
+
+        // The voter that agrees with the most others stands for the majority
+        let support: Vec<usize> = votes.iter()
+            .map(|&(_, a)| votes.iter().filter(|&&(_, b)| self.agrees(a, b)).count())
+            .collect();
+        let (leader, &best) = support.iter().enumerate().max_by_key(|&(_, s)| *s).unwrap();
+        if best == votes.len() {
+            return Ok(());
+        }
+
+        let leader_val = votes[leader].1;
+        let outlier_votes: Vec<(&str, f64)> = votes.iter()
+            .filter(|&&(_, v)| !self.agrees(v, leader_val))
+            .copied()
+            .collect();
+        let outliers: Vec<&str> = outlier_votes.iter().map(|&(name, _)| name).collect();
+        let summary: Vec<String> = votes.iter().map(|(n, v)| format!("{}: {:.10e}", n, v)).collect();
+
+        let has_majority = best * 2 > votes.len();
+        let (oracle, verdict) = if has_majority {
+            (
+                format!("Majority Vote (outlier: {})", outliers.join(", ")),
+                format!("{} of {} engines agree on {:.10e}; implicated: {}", best, votes.len(), leader_val, outliers.join(", ")),
+            )
+        } else {
+            (
+                "Majority Vote (no majority)".to_string(),
+                format!("No value is shared by a majority of the {} engines", votes.len()),
+            )
+        };
+
+        Err(FuzzError::OracleMismatch {
+            oracle,
+            inputs: engine.inputs.clone(),
+            output,
+            index: input,
+            class: FailureClass::of(outlier_votes[0].1, leader_val),
+            details: format!(
+                "Engines disagree!\n{}\n{}\n(Abs tol: {:.10e}, Rel tol: {:.10e})",
+                summary.join(", "), verdict, self.abs_tolerance, self.rel_tolerance
+            ),
+        })
+    }
+}
+
+impl Oracle for MajorityVoteCheck {
+    fn name(&self) -> &str { "majority" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for i in 0..ctx.engine.reverse.len() {
+            self.check_at(ctx.engine, ctx.ground_truths, i)?;
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracles::EngineTiming;
+
+    #[test]
+    fn test_outlier_is_named() {
+        let engine = EngineResults {
+            inputs: vec![1.0],
+            reverse: vec![2.0],
+            forward: vec![-2.0],
+            reverse_primal: vec![1.0],
+            forward_primal: vec![1.0],
+            timing: EngineTiming::default(),
+        };
+        let gt = GroundTruth { name: "PyTorch", jacobian: vec![2.0], value: None, hessian: None };
+        match MajorityVoteCheck::default().check_at(&engine, &[gt], 0) {
+            Err(FuzzError::OracleMismatch { oracle, .. }) => assert_eq!(oracle, "Majority Vote (outlier: Fwd)"),
+            other => panic!("Expected a mismatch, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,485 @@
+// src/oracles/metamorphic.rs
+
+// Metamorphic oracles: differentiate a transformed copy of the function and check the relation
+// the derivatives must satisfy. They only need the engine under test, no ground truth.
+
+use std::hash::Hasher;
+
+use ad_trait::AD;
+
+use super::{infinity_verdict, AlgebraicIdentity, Composed, EngineResults, NanPolicy, Oracle, OracleContext};
+use crate::ast_evaluator::MainBackend;
+use crate::ast_expr::Expr;
+use crate::ast_visitor::{fold_children, ExprFolder};
+use crate::error::FuzzError;
+use crate::expr_codec::StableHasher;
+use crate::failure_classifier::FailureClass;
+use crate::fuzz_harness::{Calculator, compute_engine_results};
+
+/// `g(x) = f(x + c)`. The offset is added on the tape, as a constant, before `f` runs.
+#[derive(Clone)]
+pub struct Shifted<G: Calculator> {
+    pub inner: G,
+    pub offset: f64,
+}
+
+impl<G: Calculator> Calculator for Shifted<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        let shifted: Vec<T> = inputs.iter().map(|x| *x + T::constant(self.offset)).collect();
+        self.inner.eval_expr(&shifted)
+    }
+
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        let shifted: Vec<T> = inputs.iter().map(|x| *x + T::constant(self.offset)).collect();
+        self.inner.eval_outputs(&shifted)
+    }
+
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        let shifted: Vec<B> = inputs.iter().map(|x| x.clone().add(B::from_f64(self.offset))).collect();
+        self.inner.eval_backend(&shifted)
+    }
+}
+
+/// `h(x) = f(a * x)`. The factor is multiplied in on the tape, as a constant.
+#[derive(Clone)]
+pub struct Scaled<G: Calculator> {
+    pub inner: G,
+    pub factor: f64,
+}
+
+impl<G: Calculator> Calculator for Scaled<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        let scaled: Vec<T> = inputs.iter().map(|x| T::constant(self.factor) * *x).collect();
+        self.inner.eval_expr(&scaled)
+    }
+
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        let scaled: Vec<T> = inputs.iter().map(|x| T::constant(self.factor) * *x).collect();
+        self.inner.eval_outputs(&scaled)
+    }
+
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        let scaled: Vec<B> = inputs.iter().map(|x| B::from_f64(self.factor).mul(x.clone())).collect();
+        self.inner.eval_backend(&scaled)
+    }
+}
+
+/// `expr` with the variables `x_i` and `x_j` exchanged wherever they are read, bound or assigned:
+/// `f(x)` becomes `g(y)`, with `g(swapped x) = f(x)`.
+pub fn swap_variables<Tag>(expr: Expr<Tag>, (i, j): (usize, usize)) -> Expr<Tag> {
+    struct Swap(String, String);
+
+    impl Swap {
+        fn rename(&self, name: String) -> String {
+            if name == self.0 { self.1.clone() } else if name == self.1 { self.0.clone() } else { name }
+        }
+    }
+
+    impl<Tag> ExprFolder<Tag> for Swap {
+        fn fold(&mut self, expr: Expr<Tag>) -> Expr<Tag> {
+            match fold_children(self, expr) {
+                Expr::Id(t, name) => Expr::Id(t, self.rename(name)),
+                Expr::Set(t, name, e) => Expr::Set(t, self.rename(name), e),
+                Expr::Let(t, bindings, body) => {
+                    Expr::Let(t, bindings.into_iter().map(|(name, e)| (self.rename(name), e)).collect(), body)
+                }
+                other => other,
+            }
+        }
+    }
+
+    Swap(format!("x_{}", i), format!("x_{}", j)).fold(expr)
+}
+
+/// `g(y) = f(y with y_i and y_j exchanged)`, for calculators `Calculator::relabeled` cannot
+/// rewrite: the inputs are exchanged on the tape instead of in the expression.
+#[derive(Clone)]
+pub struct Swapped<G: Calculator> {
+    pub inner: G,
+    pub pair: (usize, usize),
+}
+
+impl<G: Calculator> Swapped<G> {
+    fn swap<T: Clone>(&self, inputs: &[T]) -> Vec<T> {
+        let mut swapped = inputs.to_vec();
+        swapped.swap(self.pair.0, self.pair.1);
+        swapped
+    }
+}
+
+impl<G: Calculator> Calculator for Swapped<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        self.inner.eval_expr(&self.swap(inputs))
+    }
+
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        self.inner.eval_outputs(&self.swap(inputs))
+    }
+
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        self.inner.eval_backend(&self.swap(inputs))
+    }
+}
+
+/// Input transformation applied on the tape before the function under test runs, or, for
+/// `Compose`, an identity applied to its outputs after.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputTransform {
+    Identity,
+    /// `x -> x + c`
+    Shift(f64),
+    /// `x -> a * x`
+    Scale(f64),
+    /// `x_i <-> x_j`, renamed in the expression, see `Calculator::relabeled`
+    Swap(usize, usize),
+    /// `f(x) -> identity(f(x))`, see `IdentityCheck`
+    Compose(AlgebraicIdentity),
+}
+
+/// Reverse and Forward AD results for `calc` composed with `transform`, at `inputs`.
+pub fn run_transformed<G: Calculator>(calc: &G, transform: InputTransform, inputs: &[f64]) -> EngineResults {
+    match transform {
+        InputTransform::Identity => compute_engine_results(calc, inputs),
+        InputTransform::Shift(offset) => compute_engine_results(&Shifted { inner: calc.clone(), offset }, inputs),
+        InputTransform::Scale(factor) => compute_engine_results(&Scaled { inner: calc.clone(), factor }, inputs),
+        InputTransform::Swap(i, j) => match calc.relabeled((i, j)) {
+            Some(relabeled) => compute_engine_results(&relabeled, inputs),
+            None => compute_engine_results(&Swapped { inner: calc.clone(), pair: (i, j) }, inputs),
+        },
+        InputTransform::Compose(identity) => compute_engine_results(&Composed { inner: calc.clone(), identity }, inputs),
+    }
+}
+
+/// Relative/absolute agreement used by the metamorphic checks. NaN follows `nan_policy`,
+/// infinities `infinity_verdict`.
+fn agrees(a: f64, b: f64, abs_tolerance: f64, rel_tolerance: f64, nan_policy: NanPolicy) -> bool {
+    if let Some(passes) = nan_policy.verdict(a, b).or_else(|| infinity_verdict(a, b)) {
+        return passes;
+    }
+    if a == b {
+        return true;
+    }
+    (a - b).abs() <= abs_tolerance.max(b.abs() * rel_tolerance)
+}
+
+/// TranslationInvarianceCheck: for `g(x) = f(x + c)`, checks `g'(x) == f'(x + c)` separately for
+/// Reverse and Forward AD. Catches bugs in how constant offsets are recorded on the tape.
+#[derive(Clone)]
+pub struct TranslationInvarianceCheck {
+    pub offset: f64,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for TranslationInvarianceCheck {
+    fn default() -> Self {
+        TranslationInvarianceCheck {
+            offset: 0.5,
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl TranslationInvarianceCheck {
+    pub fn check_rerun(&self, rerun: &dyn Fn(InputTransform, &[f64]) -> EngineResults, engine: &EngineResults) -> Result<(), FuzzError> {
+        let shifted_inputs: Vec<f64> = engine.inputs.iter().map(|x| x + self.offset).collect();
+        // f'(x + c), straight from the engines
+        let at_shifted = rerun(InputTransform::Identity, &shifted_inputs);
+        // g'(x), with the shift on the tape
+        let through_g = rerun(InputTransform::Shift(self.offset), &engine.inputs);
+
+        for i in 0..through_g.reverse.len() {
+            let (output, input) = engine.partial(i);
+            let pairs = [
+                ("Rev", through_g.reverse[i], at_shifted.reverse[i]),
+                ("Fwd", through_g.forward[i], at_shifted.forward[i]),
+            ];
+            for (name, g_der, f_der) in pairs {
+                if !agrees(g_der, f_der, self.abs_tolerance, self.rel_tolerance, self.nan_policy) {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Translation {}", name),
+                        inputs: engine.inputs.clone(),
+                        output,
+                        index: input,
+                        class: FailureClass::of(g_der, f_der),
+                        details: format!(
+                            "Translation invariance violated (c = {})!\n\
+                            g'(x) = d/dx f(x + c): {:.10e}, f'(x + c): {:.10e}\n\
+                            Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e})",
+                            self.offset, g_der, f_der,
+                            (g_der - f_der).abs(), self.abs_tolerance, self.rel_tolerance
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for TranslationInvarianceCheck {
+    fn name(&self) -> &str { "translation" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_rerun(ctx.rerun, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+/// ChainRuleScalingCheck: for `h(x) = f(a * x)`, checks `h'(x) == a * f'(a * x)` separately for
+/// Reverse and Forward AD. Catches constant-scaling bugs in adjoint accumulation.
+///
+/// Costs two extra AD runs, so it only runs on a `sample_rate` fraction of inputs. The choice is
+/// a hash of the inputs, so a reproduced crash input makes the same choice.
+#[derive(Clone)]
+pub struct ChainRuleScalingCheck {
+    pub factor: f64,
+    pub sample_rate: f64,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for ChainRuleScalingCheck {
+    fn default() -> Self {
+        ChainRuleScalingCheck {
+            factor: 1.5,
+            sample_rate: 0.25,
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl ChainRuleScalingCheck {
+    fn sampled(&self, inputs: &[f64]) -> bool {
+        if self.sample_rate >= 1.0 {
+            return true;
+        }
+        let mut hasher = StableHasher::default();
+        for x in inputs {
+            hasher.write_u64(x.to_bits());
+        }
+        (hasher.finish() as f64 / u64::MAX as f64) < self.sample_rate
+    }
+
+    pub fn check_rerun(&self, rerun: &dyn Fn(InputTransform, &[f64]) -> EngineResults, engine: &EngineResults) -> Result<(), FuzzError> {
+        if !self.sampled(&engine.inputs) {
+            return Ok(());
+        }
+
+        let scaled_inputs: Vec<f64> = engine.inputs.iter().map(|x| self.factor * x).collect();
+        // f'(a * x), straight from the engines
+        let at_scaled = rerun(InputTransform::Identity, &scaled_inputs);
+        // h'(x), with the scaling on the tape
+        let through_h = rerun(InputTransform::Scale(self.factor), &engine.inputs);
+
+        for i in 0..through_h.reverse.len() {
+            let (output, input) = engine.partial(i);
+            let pairs = [
+                ("Rev", through_h.reverse[i], self.factor * at_scaled.reverse[i]),
+                ("Fwd", through_h.forward[i], self.factor * at_scaled.forward[i]),
+            ];
+            for (name, h_der, expected) in pairs {
+                if !agrees(h_der, expected, self.abs_tolerance, self.rel_tolerance, self.nan_policy) {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Scaling {}", name),
+                        inputs: engine.inputs.clone(),
+                        output,
+                        index: input,
+                        class: FailureClass::of(h_der, expected),
+                        details: format!(
+                            "Chain rule scaling violated (a = {})!\n\
+                            h'(x) = d/dx f(a * x): {:.10e}, a * f'(a * x): {:.10e}\n\
+                            Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e})",
+                            self.factor, h_der, expected,
+                            (h_der - expected).abs(), self.abs_tolerance, self.rel_tolerance
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for ChainRuleScalingCheck {
+    fn name(&self) -> &str { "scaling" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_rerun(ctx.rerun, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+/// PermutationCheck: for `g`, `f` with `x_i` and `x_j` relabeled (see `swap_variables`), checks that
+/// `g` at the swapped inputs has the value of `f` and the gradient of `f` with the two columns
+/// exchanged, separately for Reverse and Forward AD. The same operations run in the same order,
+/// so by default the results must match exactly. Catches index-mapping bugs in how inputs are
+/// wired to the function and registered as variables on the tape.
+#[derive(Clone)]
+pub struct PermutationCheck {
+    pub pair: (usize, usize),
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for PermutationCheck {
+    fn default() -> Self {
+        PermutationCheck {
+            pair: (0, 1),
+            abs_tolerance: 0.0,
+            rel_tolerance: 0.0,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl PermutationCheck {
+    pub fn check_rerun(&self, rerun: &dyn Fn(InputTransform, &[f64]) -> EngineResults, engine: &EngineResults) -> Result<(), FuzzError> {
+        let (i, j) = self.pair;
+        let n = engine.inputs.len();
+        if i == j || i.max(j) >= n {
+            return Ok(());
+        }
+        let mut swapped_inputs = engine.inputs.clone();
+        swapped_inputs.swap(i, j);
+        let through_g = rerun(InputTransform::Swap(i, j), &swapped_inputs);
+        let mismatch = |name: &str, output, input, got: f64, expected: f64, what: &str| FuzzError::OracleMismatch {
+            oracle: format!("Permutation {}", name),
+            inputs: engine.inputs.clone(),
+            output,
+            index: input,
+            class: FailureClass::of(got, expected),
+            details: format!(
+                "Permutation invariance violated (x_{} <-> x_{})!\n\
+                {}: {:.10e}, expected: {:.10e}\n\
+                Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e})",
+                i, j, what, got, expected,
+                (got - expected).abs(), self.abs_tolerance, self.rel_tolerance
+            ),
+        };
+
+        let values = [
+            ("Rev", &through_g.reverse_primal, &engine.reverse_primal),
+            ("Fwd", &through_g.forward_primal, &engine.forward_primal),
+        ];
+        for (name, g_values, f_values) in values {
+            for (output, (g, f)) in g_values.iter().zip(f_values.iter()).enumerate() {
+                if !agrees(*g, *f, self.abs_tolerance, self.rel_tolerance, self.nan_policy) {
+                    return Err(mismatch(name, output, 0, *g, *f, "g(swapped x)"));
+                }
+            }
+        }
+        for k in 0..through_g.reverse.len().min(engine.reverse.len()) {
+            let (output, input) = engine.partial(k);
+            // d g / d y_i is d f / d x_j and the other way around
+            let source = output * n + if input == i { j } else if input == j { i } else { input };
+            let pairs = [
+                ("Rev", through_g.reverse[k], engine.reverse[source]),
+                ("Fwd", through_g.forward[k], engine.forward[source]),
+            ];
+            for (name, g_der, f_der) in pairs {
+                if !agrees(g_der, f_der, self.abs_tolerance, self.rel_tolerance, self.nan_policy) {
+                    return Err(mismatch(name, output, input, g_der, f_der, &format!("d g / d x_{}", input)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for PermutationCheck {
+    fn name(&self) -> &str { "permutation" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_rerun(ctx.rerun, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_evaluator::InfixPrinter;
+    use crate::ast_parser::parse_infix;
+
+    #[test]
+    fn test_permutation_check() {
+        let (expr, n) = parse_infix("x_0 * exp(x_1) - x_2").unwrap();
+        let calc = AdPyUnified::new(expr, n, 1);
+        let relabeled = calc.relabeled((2, 0)).unwrap();
+        assert_eq!(InfixPrinter::print(relabeled.get_expr(), n), InfixPrinter::print(&parse_infix("x_2 * exp(x_1) - x_0").unwrap().0, n));
+
+        let engine = compute_engine_results(&calc, &[2.0, 0.5, -1.0]);
+        let check = PermutationCheck::default();
+        let rerun = |t: InputTransform, x: &[f64]| run_transformed(&calc, t, x);
+        assert!(check.check_rerun(&rerun, &engine).is_ok());
+        assert!(PermutationCheck { pair: (2, 0), ..check.clone() }.check_rerun(&rerun, &engine).is_ok());
+
+        // Inputs wired to the wrong variables: the swap is lost between harness and tape
+        let unswapped = |_: InputTransform, x: &[f64]| compute_engine_results(&calc, x);
+        let err = check.check_rerun(&unswapped, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, .. } if oracle.starts_with("Permutation")));
+    }
+
+    #[test]
+    fn test_translation_and_scaling_checks() {
+        let (expr, n) = parse_infix("x_0 * exp(x_1) - x_0 * x_0").unwrap();
+        let calc = AdPyUnified::new(expr, n, 1);
+        let engine = compute_engine_results(&calc, &[2.0, 0.5]);
+        let rerun = |t: InputTransform, x: &[f64]| run_transformed(&calc, t, x);
+        let translation = TranslationInvarianceCheck::default();
+        let scaling = ChainRuleScalingCheck { sample_rate: 1.0, ..ChainRuleScalingCheck::default() };
+        assert!(translation.check_rerun(&rerun, &engine).is_ok());
+        assert!(scaling.check_rerun(&rerun, &engine).is_ok());
+
+        // The transform lost on the way to the tape: g = f and h = f
+        let untransformed = |_: InputTransform, x: &[f64]| compute_engine_results(&calc, x);
+        let err = translation.check_rerun(&untransformed, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, .. } if oracle == "Translation Rev"));
+        let err = scaling.check_rerun(&untransformed, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, .. } if oracle == "Scaling Rev"));
+
+        // Only Forward AD off on the transformed tape
+        let forward_off = |t: InputTransform, x: &[f64]| {
+            let mut results = run_transformed(&calc, t, x);
+            if t != InputTransform::Identity {
+                results.forward[1] *= 1.001;
+            }
+            results
+        };
+        let err = scaling.check_rerun(&forward_off, &engine).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, index: 1, .. } if oracle == "Scaling Fwd"));
+    }
+}
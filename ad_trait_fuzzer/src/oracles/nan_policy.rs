@@ -0,0 +1,91 @@
+// src/oracles/nan_policy.rs
+
+// One rule for comparisons that involve NaN, shared by every oracle that compares two values
+// (rev_fwd, rev_gt, fwd_gt, primal, majority, non_finite, grad_check, secant, translation,
+// scaling, permutation) and by `run_ad_tests` for ground truths. Oracles that derive their
+// expectation from the values (hessian, identity, grad_bound, sensitivity, vjp_jvp) have nothing
+// to compare against a NaN and skip it under any policy; `domain` is about NaN itself.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// What a comparison does when at least one side is NaN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NanPolicy {
+    /// Nothing to compare: the pair passes.
+    Skip,
+    /// NaN agrees with NaN and with nothing else.
+    #[default]
+    CompareAsEqual,
+    /// Any NaN is a failure, a NaN ground truth included.
+    Fail,
+}
+
+impl NanPolicy {
+    pub const NAMES: [(&'static str, NanPolicy); 3] = [
+        ("skip", NanPolicy::Skip),
+        ("equal", NanPolicy::CompareAsEqual),
+        ("fail", NanPolicy::Fail),
+    ];
+
+    /// `Some(passes)` if `a` or `b` is NaN and the policy decides, `None` if neither is and the
+    /// numeric comparison does.
+    pub fn verdict(self, a: f64, b: f64) -> Option<bool> {
+        if !a.is_nan() && !b.is_nan() {
+            return None;
+        }
+        Some(match self {
+            NanPolicy::Skip => true,
+            NanPolicy::CompareAsEqual => a.is_nan() && b.is_nan(),
+            NanPolicy::Fail => false,
+        })
+    }
+}
+
+/// Failure details for a pair the policy rejected, in the style of `ulp_mismatch`.
+pub fn nan_mismatch(policy: NanPolicy, a_name: &str, a: f64, b_name: &str, b: f64) -> String {
+    format!(
+        "{} vs {} failed! (NaN policy: {})\n\
+        {}: {:.10e}, {}: {:.10e}",
+        a_name, b_name, policy,
+        a_name, a, b_name, b
+    )
+}
+
+impl fmt::Display for NanPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = NanPolicy::NAMES.iter().find(|(_, p)| p == self).map_or("?", |(n, _)| n);
+        f.write_str(name)
+    }
+}
+
+/// `skip`, `equal` or `fail` (case-insensitive).
+impl FromStr for NanPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NanPolicy::NAMES.iter()
+            .find(|(n, _)| s.trim().eq_ignore_ascii_case(n))
+            .map(|(_, p)| *p)
+            .ok_or_else(|| format!("unknown NaN policy '{}' (expected skip, equal or fail)", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nan_policy() {
+        let nan = f64::NAN;
+        assert_eq!(NanPolicy::Fail.verdict(1.0, 2.0), None);
+        assert_eq!(NanPolicy::Skip.verdict(nan, 2.0), Some(true));
+        assert_eq!(NanPolicy::CompareAsEqual.verdict(nan, nan), Some(true));
+        assert_eq!(NanPolicy::CompareAsEqual.verdict(1.0, nan), Some(false));
+        assert_eq!(NanPolicy::Fail.verdict(nan, nan), Some(false));
+
+        assert_eq!("Equal".parse::<NanPolicy>(), Ok(NanPolicy::CompareAsEqual));
+        assert_eq!(NanPolicy::Skip.to_string(), "skip");
+        assert!("ignore".parse::<NanPolicy>().is_err());
+    }
+}
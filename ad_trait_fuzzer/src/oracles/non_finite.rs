@@ -0,0 +1,122 @@
+// src/oracles/non_finite.rs
+
+use super::{EngineResults, Oracle, OracleContext, PointCheck, GroundTruth, NanPolicy};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// Where a derivative value sits with respect to blowing up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FiniteClass {
+    Finite,
+    NaN,
+    PosInf,
+    NegInf,
+}
+
+impl FiniteClass {
+    pub fn of(val: f64) -> Self {
+        if val.is_nan() {
+            FiniteClass::NaN
+        } else if val == f64::INFINITY {
+            FiniteClass::PosInf
+        } else if val == f64::NEG_INFINITY {
+            FiniteClass::NegInf
+        } else {
+            FiniteClass::Finite
+        }
+    }
+}
+
+/// NonFiniteConsistencyCheck: Fails when engines disagree on *whether* a derivative is
+/// finite, NaN or ±Inf (e.g. Reverse AD gives NaN where PyTorch gives 0.0).
+///
+/// Runs before the tolerance oracles, so a disagreement about where a derivative blows up is
+/// reported as such rather than as a tolerance failure. Pairs with a NaN follow `nan_policy`
+/// like everywhere else.
+#[derive(Clone, Default)]
+pub struct NonFiniteConsistencyCheck {
+    pub nan_policy: NanPolicy,
+}
+
+impl Oracle for NonFiniteConsistencyCheck {
+    fn name(&self) -> &str { "non_finite" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for i in 0..ctx.engine.reverse.len() {
+            self.check_at(ctx.engine, None, i)?;
+            for gt in ctx.ground_truths {
+                self.check_at(ctx.engine, Some(gt), i)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+impl PointCheck for NonFiniteConsistencyCheck {
+    /// Compares Reverse and Forward AD, plus the ground truth when one is given.
+    fn check_at(&self, engine: &EngineResults, gt: Option<&GroundTruth>, i: usize) -> Result<(), FuzzError> {
+        let (output, input) = engine.partial(i);
+        let mut values = vec![("Rev", engine.reverse[i]), ("Fwd", engine.forward[i])];
+        if let Some(gt) = gt {
+            values.push((gt.name, gt.jacobian[i]));
+        }
+
+        let (first_name, first_val) = values[0];
+        let first_class = FiniteClass::of(first_val);
+        for &(name, val) in &values[1..] {
+            let class = FiniteClass::of(val);
+            let agrees = self.nan_policy.verdict(first_val, val).unwrap_or(class == first_class);
+            if !agrees {
+                let summary: Vec<String> = values.iter()
+                    .map(|(n, v)| format!("{}: {:.10e} ({:?})", n, v, FiniteClass::of(*v)))
+                    .collect();
+                return Err(FuzzError::OracleMismatch {
+                    oracle: format!("NonFinite {} vs {}", first_name, name),
+                    inputs: engine.inputs.clone(),
+                    output,
+                    index: input,
+                    class: FailureClass::of(val, first_val),
+                    details: format!(
+                        "Non-finite consistency failed! Engines disagree on where the derivative blows up.\n{}",
+                        summary.join(", ")
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracles::EngineTiming;
+
+    #[test]
+    fn test_non_finite_check() {
+        let engine = EngineResults {
+            inputs: vec![0.0, 1.0],
+            reverse: vec![f64::INFINITY, 2.0],
+            forward: vec![f64::INFINITY, 2.0],
+            reverse_primal: vec![0.0],
+            forward_primal: vec![0.0],
+            timing: EngineTiming::default(),
+        };
+        let gt = GroundTruth { name: "PyTorch", jacobian: vec![f64::INFINITY, 2.5], value: None, hessian: None };
+        let check = NonFiniteConsistencyCheck::default();
+        // Finite values that differ are for the tolerance oracles
+        for i in 0..2 {
+            assert!(check.check_at(&engine, Some(&gt), i).is_ok());
+        }
+
+        let blown_up = GroundTruth { jacobian: vec![f64::NEG_INFINITY, 2.0], ..gt.clone() };
+        let err = check.check_at(&engine, Some(&blown_up), 0).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, index: 0, .. } if oracle == "NonFinite Rev vs PyTorch"));
+
+        let nan_forward = EngineResults { forward: vec![f64::NAN, 2.0], ..engine.clone() };
+        let err = check.check_at(&nan_forward, None, 0).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, .. } if oracle == "NonFinite Rev vs Fwd"));
+    }
+}
@@ -0,0 +1,133 @@
+// src/oracles/primal_value.rs
+
+use super::{EngineResults, Oracle, OracleContext, PointCheck, GroundTruth, NanPolicy, infinity_verdict};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// PrimalValueCheck: Compares the function *values* (not derivatives) computed by Reverse AD,
+/// Forward AD and the ground truth.
+///
+/// A derivative mismatch with matching primal values points at a derivative rule; one where the
+/// primal already disagrees points at the evaluation itself. Runs before the derivative oracles.
+#[derive(Clone)]
+pub struct PrimalValueCheck {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for PrimalValueCheck {
+    fn default() -> Self {
+        PrimalValueCheck {
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl PrimalValueCheck {
+    fn agrees(&self, a: f64, b: f64) -> bool {
+        if let Some(passes) = self.nan_policy.verdict(a, b).or_else(|| infinity_verdict(a, b)) {
+            return passes;
+        }
+        if a == b {
+            return true;
+        }
+        let threshold = self.abs_tolerance.max(b.abs() * self.rel_tolerance);
+        (a - b).abs() <= threshold
+    }
+
+    fn mismatch(&self, engine: &EngineResults, oracle: String, output: usize, a: (&str, f64), b: (&str, f64)) -> FuzzError {
+        FuzzError::OracleMismatch {
+            oracle,
+            inputs: engine.inputs.clone(),
+            output,
+            index: 0,
+            class: FailureClass::of(a.1, b.1),
+            details: format!(
+                "Primal value mismatch! {}: {:.10e}, {}: {:.10e}\n\
+                Absolute Diff: {:.10e} (Abs tol: {:.10e}, Rel tol: {:.10e})",
+                a.0, a.1, b.0, b.1,
+                (a.1 - b.1).abs(), self.abs_tolerance, self.rel_tolerance
+            ),
+        }
+    }
+}
+
+impl Oracle for PrimalValueCheck {
+    fn name(&self) -> &str { "primal" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for out in 0..ctx.engine.reverse_primal.len().min(ctx.engine.forward_primal.len()) {
+            self.check_at(ctx.engine, None, out)?;
+            for gt in ctx.ground_truths {
+                self.check_at(ctx.engine, Some(gt), out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+impl PointCheck for PrimalValueCheck {
+    /// `index` is the output index. Without a ground truth, compares Reverse vs Forward;
+    /// with one, compares Reverse against the ground truth value (if it reported one, output 0 only).
+    fn check_at(&self, engine: &EngineResults, gt: Option<&GroundTruth>, index: usize) -> Result<(), FuzzError> {
+        let rev = engine.reverse_primal[index];
+
+        match gt {
+            None => {
+                let fwd = engine.forward_primal[index];
+                if !self.agrees(rev, fwd) {
+                    return Err(self.mismatch(engine, "Primal Rev vs Fwd".to_string(), index, ("Rev", rev), ("Fwd", fwd)));
+                }
+            }
+            Some(gt) => {
+                if let (0, Some(gt_val)) = (index, gt.value) {
+                    if !self.agrees(rev, gt_val) {
+                        return Err(self.mismatch(engine, format!("Primal Rev vs {}", gt.name), index, ("Rev", rev), (gt.name, gt_val)));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracles::EngineTiming;
+
+    #[test]
+    fn test_primal_value_check() {
+        let engine = EngineResults {
+            inputs: vec![2.0],
+            reverse: vec![4.0, 1.0],
+            forward: vec![4.0, 1.0],
+            reverse_primal: vec![4.0, 2.0],
+            forward_primal: vec![4.0 * (1.0 + 1e-12), 2.0],
+            timing: EngineTiming::default(),
+        };
+        let gt = GroundTruth { name: "PyTorch", jacobian: vec![4.0, 1.0], value: Some(4.0), hessian: None };
+        let check = PrimalValueCheck::default();
+        assert!(check.check_at(&engine, None, 0).is_ok());
+        assert!(check.check_at(&engine, Some(&gt), 0).is_ok());
+        // The ground truth only reports output 0
+        assert!(check.check_at(&engine, Some(&gt), 1).is_ok());
+
+        let off = GroundTruth { value: Some(4.5), ..gt.clone() };
+        let err = check.check_at(&engine, Some(&off), 0).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, output: 0, .. } if oracle == "Primal Rev vs PyTorch"));
+
+        let diverged = EngineResults { forward_primal: vec![4.0, 2.1], ..engine.clone() };
+        let err = check.check_at(&diverged, None, 1).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, output: 1, .. } if oracle == "Primal Rev vs Fwd"));
+    }
+}
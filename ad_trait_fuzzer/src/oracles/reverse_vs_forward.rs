@@ -1,44 +1,100 @@
 // src/oracles/reverse_vs_forward.rs
 
-use super::{EngineResults, Oracle, GroundTruth};
-use std::error::Error;
+use super::{EngineResults, Oracle, OracleContext, PointCheck, GroundTruth, Comparison, NanPolicy, infinity_verdict, nan_mismatch, ulp_mismatch};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
 
 /// ReverseVsForwardCheck: Ensures that the Jacobians calculated by Reverse AD and 
 /// Forward AD are nearly identical, checking for internal consistency in the AD engine.
 #[derive(Clone)]
-pub struct ReverseVsForwardCheck;
+pub struct ReverseVsForwardCheck {
+    pub abs_tolerance: f64, // Absolute threshold (for results near zero)
+    pub rel_tolerance: f64, // Relative threshold (1e-9 = 1 part per billion)
+    pub comparison: Comparison,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for ReverseVsForwardCheck {
+    fn default() -> Self {
+        ReverseVsForwardCheck {
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+            comparison: Comparison::Hybrid,
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
 
 impl Oracle for ReverseVsForwardCheck {
-    /// Tolerance constant for trait satisfaction. The actual tolerances are defined below.
-    const TOLERANCE: f64 = 1e-9; 
+    fn name(&self) -> &str { "rev_fwd" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        for i in 0..ctx.engine.reverse.len() {
+            self.check_at(ctx.engine, None, i)?;
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn comparison_mut(&mut self) -> Option<&mut Comparison> { Some(&mut self.comparison) }
 
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+impl PointCheck for ReverseVsForwardCheck {
     /// Executes the check for a single partial derivative.
     /// Uses a hybrid tolerance model to handle results near zero and large results robustly.
-    fn check(&self, engine: &EngineResults, _gt: Option<&GroundTruth>, i: usize) -> Result<(), Box<dyn Error>> {
-        
-        // Define tolerances as local constants for the hybrid check.
-        const ABS_TOLERANCE: f64 = 1e-12; // Absolute threshold (for results near zero)
-        const REL_TOLERANCE: f64 = 1e-9;  // Relative threshold (1 part per billion)
+    fn check_at(&self, engine: &EngineResults, _gt: Option<&GroundTruth>, i: usize) -> Result<(), FuzzError> {
+        let abs_tolerance = self.abs_tolerance;
+        let rel_tolerance = self.rel_tolerance;
 
+        let (output, input) = engine.partial(i);
         let rev_result = engine.reverse[i];
         let fwd_result = engine.forward[i];
+
+        let nan_verdict = self.nan_policy.verdict(rev_result, fwd_result);
+        if nan_verdict == Some(true) {
+            return Ok(());
+        }
         
-        // // Skip check if either result is not finite (NaN, Inf)
-        // if !rev_result.is_finite() || !fwd_result.is_finite() {
-        //     return Ok(());
-        // }
+        if let Comparison::Ulps(max_ulps) = self.comparison {
+            let mismatch = match nan_verdict {
+                Some(_) => Some(nan_mismatch(self.nan_policy, "Rev", rev_result, "Fwd", fwd_result)),
+                None => ulp_mismatch("Rev", rev_result, "Fwd", fwd_result, max_ulps),
+            };
+            return match mismatch {
+                Some(details) => Err(FuzzError::OracleMismatch {
+                    oracle: "Rev vs Fwd".to_string(),
+                    inputs: engine.inputs.clone(),
+                    output,
+                    index: input,
+                    class: FailureClass::of(rev_result, fwd_result),
+                    details,
+                }),
+                None => Ok(()),
+            };
+        }
+        
+        // Same infinity on both sides agrees; the tolerance math below can't tell
+        let inf_verdict = infinity_verdict(rev_result, fwd_result);
+        if inf_verdict == Some(true) {
+            return Ok(());
+        }
 
         let diff = (rev_result - fwd_result).abs();
 
-        // 1. Calculate the scaled threshold: max(ABS_TOLERANCE, |Fwd Result| * REL_TOLERANCE)
-        let scaled_rel_threshold = fwd_result.abs() * REL_TOLERANCE;
-        let threshold = ABS_TOLERANCE.max(scaled_rel_threshold);
+        // 1. Calculate the scaled threshold: max(abs_tolerance, |Fwd Result| * rel_tolerance)
+        let scaled_rel_threshold = fwd_result.abs() * rel_tolerance;
+        let threshold = abs_tolerance.max(scaled_rel_threshold);
         
         // 2. Perform the Hybrid check: Fail only if difference is greater than the threshold
-        if diff > threshold || (rev_result.is_nan() != fwd_result.is_nan()) {
+        if diff > threshold || nan_verdict == Some(false) || inf_verdict == Some(false) {
             
             // Calculate relative difference, safely handling division by zero for presentation
-            let relative_diff = if fwd_result.abs() > ABS_TOLERANCE {
+            let relative_diff = if fwd_result.abs() > abs_tolerance {
                 diff / fwd_result.abs()
             } else {
                 // If result is near zero, the absolute difference is the most meaningful error metric.
@@ -47,7 +103,7 @@ impl Oracle for ReverseVsForwardCheck {
 
             let percent_diff = (relative_diff * 100.0).min(100.0);
             
-            Err(format!(
+            let details = format!(
                 "Reverse vs Forward failed! Gradients differ. (Hybrid Tolerance Check)\n\
                 Rev: {:.10e}, Fwd: {:.10e}\n\
                 Absolute Diff: {:.10e}\n\
@@ -56,8 +112,16 @@ impl Oracle for ReverseVsForwardCheck {
                 rev_result, fwd_result, 
                 diff, 
                 relative_diff, percent_diff,
-                threshold, ABS_TOLERANCE, scaled_rel_threshold
-            ).into())
+                threshold, abs_tolerance, scaled_rel_threshold
+            );
+            Err(FuzzError::OracleMismatch {
+                oracle: "Rev vs Fwd".to_string(),
+                inputs: engine.inputs.clone(),
+                output,
+                index: input,
+                class: FailureClass::of(rev_result, fwd_result),
+                details,
+            })
         } else {
             Ok(())
         }
@@ -0,0 +1,140 @@
+// src/oracles/secant.rs
+
+use super::{EngineResults, Oracle, OracleContext, NanPolicy};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+
+/// SecantCheck: For a perturbation pair (`x` and `x + h`, see `PerturbedInputDecoder`), checks
+/// that the AD directional derivative `J h` matches the secant `f(x + h) - f(x)` to first order.
+/// A directional sanity check along a direction the fuzzer picked, with no ground truth.
+///
+/// Without a perturbed point (`FuzzingOracles::with_perturbation`) it checks nothing. The
+/// tolerance grows with the curvature along `h` (secants over `h` and `h / 2` compared) and the
+/// rounding error of the difference (`eps * |f|`).
+#[derive(Clone)]
+pub struct SecantCheck {
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    /// `x + h`, one entry per input.
+    pub perturbed: Vec<f64>,
+    pub nan_policy: NanPolicy,
+}
+
+impl Default for SecantCheck {
+    fn default() -> Self {
+        SecantCheck {
+            abs_tolerance: 1e-5, // like grad_check
+            rel_tolerance: 1e-3,
+            perturbed: Vec::new(),
+            nan_policy: NanPolicy::default(),
+        }
+    }
+}
+
+impl SecantCheck {
+    /// Compares slopes along `h`, i.e. every difference divided by `|h|`.
+    pub fn check_function<F: Fn(&[f64]) -> Vec<f64>>(&self, f: F, engine: &EngineResults) -> Result<(), FuzzError> {
+        let x = &engine.inputs;
+        if self.perturbed.len() != x.len() {
+            return Ok(());
+        }
+        let h: Vec<f64> = self.perturbed.iter().zip(x).map(|(xh, x)| xh - x).collect();
+        let norm = h.iter().map(|d| d * d).sum::<f64>().sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            return Ok(());
+        }
+        let half: Vec<f64> = x.iter().zip(&h).map(|(x, d)| x + d / 2.0).collect();
+        let (f0, f1, f_half) = (f(x), f(&self.perturbed), f(&half));
+        // The input moved the most along h, for the report
+        let index = (0..h.len()).max_by(|&a, &b| h[a].abs().total_cmp(&h[b].abs())).unwrap_or(0);
+
+        for k in 0..engine.num_outputs() {
+            let at = |v: &[f64]| v.get(k).copied().unwrap_or(f64::NAN);
+            let (v0, v1, v_half) = (at(&f0), at(&f1), at(&f_half));
+            if ![v0, v1, v_half].iter().all(|v| v.is_finite()) {
+                continue; // non-finite neighbourhood, nothing to compare
+            }
+            let secant = (v1 - v0) / norm;
+            let secant_half = 2.0 * (v_half - v0) / norm;
+            let curvature = 2.0 * (secant - secant_half).abs();
+            let rounding = 4.0 * f64::EPSILON * v0.abs().max(v1.abs()) / norm;
+            let threshold = self.abs_tolerance + self.rel_tolerance * secant.abs() + curvature + rounding;
+
+            let row = k * x.len()..(k + 1) * x.len();
+            for (name, jacobian) in [("Rev", &engine.reverse), ("Fwd", &engine.forward)] {
+                let directional = jacobian[row.clone()].iter().zip(&h).map(|(g, d)| g * d).sum::<f64>() / norm;
+                let nan_verdict = self.nan_policy.verdict(directional, secant);
+                if nan_verdict == Some(true) {
+                    continue;
+                }
+                let diff = (directional - secant).abs();
+                if diff > threshold || nan_verdict == Some(false) {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Secant {} vs Perturbed Pair", name),
+                        inputs: x.clone(),
+                        output: k,
+                        index,
+                        class: FailureClass::of(directional, secant),
+                        details: format!(
+                            "Directional derivative disagrees with the secant to x + h = {:?}!\n\
+                            {} (J h / |h|): {:.10e}, Secant ((f(x + h) - f(x)) / |h|): {:.10e}\n\
+                            Absolute Diff: {:.10e}\n\
+                            Tolerance Threshold: {:.10e} (Abs:{:.10e} + Rel:{:.10e} * |secant| + curvature:{:.10e} + rounding:{:.10e})",
+                            self.perturbed, name, directional, secant,
+                            diff,
+                            threshold, self.abs_tolerance, self.rel_tolerance, curvature, rounding
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for SecantCheck {
+    fn name(&self) -> &str { "secant" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_function(ctx.function, ctx.engine)
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn perturbation_mut(&mut self) -> Option<&mut Vec<f64>> {
+        Some(&mut self.perturbed)
+    }
+
+    fn nan_policy_mut(&mut self) -> Option<&mut NanPolicy> { Some(&mut self.nan_policy) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oracles::EngineTiming;
+
+    #[test]
+    fn test_secant_check() {
+        // f(x, y) = x * y at (2, 3): gradient (3, 2)
+        let f = |x: &[f64]| vec![x[0] * x[1]];
+        let engine = |grad: Vec<f64>| EngineResults {
+            inputs: vec![2.0, 3.0],
+            reverse: grad.clone(),
+            forward: grad,
+            reverse_primal: vec![6.0],
+            forward_primal: vec![6.0],
+            timing: EngineTiming::default(),
+        };
+        let check = SecantCheck { perturbed: vec![2.0 + 1e-7, 3.0 - 2e-7], ..SecantCheck::default() };
+        assert!(check.check_function(f, &engine(vec![3.0, 2.0])).is_ok());
+
+        // d/dy wrong: off along h, which moves y
+        let err = check.check_function(f, &engine(vec![3.0, 5.0])).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { index: 1, .. }));
+
+        // No pair, nothing to check
+        assert!(SecantCheck::default().check_function(f, &engine(vec![3.0, 5.0])).is_ok());
+    }
+}
@@ -0,0 +1,180 @@
+// src/oracles/selection.rs
+
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+use std::str::FromStr;
+
+/// Set of oracle checks to run, parsed once from env/CLI (e.g. `FUZZ_ORACLE=rev_fwd,fwd_gt`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OracleSelection(u32);
+
+impl OracleSelection {
+    pub const NONE: OracleSelection = OracleSelection(0);
+    pub const REV_FWD: OracleSelection = OracleSelection(1 << 0);
+    pub const REV_GT: OracleSelection = OracleSelection(1 << 1);
+    pub const FWD_GT: OracleSelection = OracleSelection(1 << 2);
+    pub const NON_FINITE: OracleSelection = OracleSelection(1 << 3);
+    pub const PRIMAL: OracleSelection = OracleSelection(1 << 4);
+    pub const GRAD_CHECK: OracleSelection = OracleSelection(1 << 5);
+    pub const TRANSLATION: OracleSelection = OracleSelection(1 << 6);
+    pub const SCALING: OracleSelection = OracleSelection(1 << 7);
+    pub const MAJORITY: OracleSelection = OracleSelection(1 << 8);
+    pub const DOMAIN: OracleSelection = OracleSelection(1 << 9);
+    pub const HESSIAN: OracleSelection = OracleSelection(1 << 10);
+    pub const SECANT: OracleSelection = OracleSelection(1 << 11);
+    pub const IDENTITY: OracleSelection = OracleSelection(1 << 12);
+    pub const GRAD_BOUND: OracleSelection = OracleSelection(1 << 13);
+    pub const SENSITIVITY: OracleSelection = OracleSelection(1 << 14);
+    /// Opt-in: wall times depend on the machine, see `TimingCheck`.
+    pub const TIMING: OracleSelection = OracleSelection(1 << 15);
+    /// Opt-in: needs the baseline ground truths of the `ab` feature, see `AbVersionCheck`.
+    pub const AB_VERSION: OracleSelection = OracleSelection(1 << 16);
+    pub const VJP_JVP: OracleSelection = OracleSelection(1 << 17);
+    pub const PERMUTATION: OracleSelection = OracleSelection(1 << 18);
+    /// Every oracle but the opt-in ones, `TIMING` and `AB_VERSION`.
+    pub const ALL: OracleSelection = OracleSelection(((1 << 15) - 1) | (1 << 17) | (1 << 18));
+
+    /// Names accepted by `from_str`, in bit order.
+    const NAMES: &'static [(&'static str, OracleSelection)] = &[
+        ("rev_fwd", OracleSelection::REV_FWD),
+        ("rev_gt", OracleSelection::REV_GT),
+        ("fwd_gt", OracleSelection::FWD_GT),
+        ("non_finite", OracleSelection::NON_FINITE),
+        ("primal", OracleSelection::PRIMAL),
+        ("grad_check", OracleSelection::GRAD_CHECK),
+        ("translation", OracleSelection::TRANSLATION),
+        ("scaling", OracleSelection::SCALING),
+        ("majority", OracleSelection::MAJORITY),
+        ("domain", OracleSelection::DOMAIN),
+        ("hessian", OracleSelection::HESSIAN),
+        ("secant", OracleSelection::SECANT),
+        ("identity", OracleSelection::IDENTITY),
+        ("grad_bound", OracleSelection::GRAD_BOUND),
+        ("sensitivity", OracleSelection::SENSITIVITY),
+        ("timing", OracleSelection::TIMING),
+        ("ab", OracleSelection::AB_VERSION),
+        ("vjp_jvp", OracleSelection::VJP_JVP),
+        ("permutation", OracleSelection::PERMUTATION),
+    ];
+
+    pub fn contains(self, other: OracleSelection) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The flag for a single oracle name (no `all`, no lists).
+    pub fn from_name(name: &str) -> Option<OracleSelection> {
+        Self::NAMES.iter().find(|(n, _)| name.eq_ignore_ascii_case(n)).map(|(_, flag)| *flag)
+    }
+
+    pub fn without(self, other: OracleSelection) -> OracleSelection {
+        OracleSelection(self.0 & !other.0)
+    }
+
+    /// Every single-oracle name with its flag, in bit order.
+    pub fn known() -> &'static [(&'static str, OracleSelection)] {
+        Self::NAMES
+    }
+
+    /// What a single oracle needs beyond Reverse and Forward AD to check anything; without it,
+    /// it passes.
+    pub fn requirement(self) -> Option<&'static str> {
+        Some(match self {
+            OracleSelection::REV_GT | OracleSelection::FWD_GT => "a ground truth",
+            OracleSelection::MAJORITY => "a ground truth (three voters)",
+            OracleSelection::HESSIAN => "a ground truth with Hessians (Symbolic, evalexpr-jit, PyTorch or PyTorch (subprocess) with_hessians)",
+            OracleSelection::SECANT => "a perturbed point (FUZZ_PERTURBATION_STEP)",
+            OracleSelection::SENSITIVITY => "the expression (FuzzingOracles::specialize)",
+            OracleSelection::AB_VERSION => "the baseline ground truths of the `ab` feature",
+            OracleSelection::VJP_JVP => "a function with more than one output",
+            OracleSelection::PERMUTATION => "a function with more than one input",
+            _ => return None,
+        })
+    }
+}
+
+impl Default for OracleSelection {
+    fn default() -> Self {
+        OracleSelection::ALL
+    }
+}
+
+impl BitOr for OracleSelection {
+    type Output = OracleSelection;
+    fn bitor(self, rhs: OracleSelection) -> OracleSelection {
+        OracleSelection(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for OracleSelection {
+    fn bitor_assign(&mut self, rhs: OracleSelection) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Accepts `all` or a `,`/`|` separated list of oracle names (case-insensitive).
+/// Unknown names are an error instead of silently selecting nothing.
+impl FromStr for OracleSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selection = OracleSelection::NONE;
+        for part in s.split([',', '|']) {
+            let part = part.trim();
+            if part.eq_ignore_ascii_case("all") {
+                selection |= OracleSelection::ALL;
+                continue;
+            }
+            match Self::NAMES.iter().find(|(name, _)| part.eq_ignore_ascii_case(name)) {
+                Some((_, flag)) => selection |= *flag,
+                None => {
+                    let valid: Vec<&str> = Self::NAMES.iter().map(|(name, _)| *name).collect();
+                    return Err(format!("Unknown oracle '{}' (expected all, {})", part, valid.join(", ")));
+                }
+            }
+        }
+        Ok(selection)
+    }
+}
+
+impl fmt::Display for OracleSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == OracleSelection::ALL {
+            return write!(f, "all");
+        }
+        let names: Vec<&str> = Self::NAMES.iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| *name)
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!("all".parse(), Ok(OracleSelection::ALL));
+        assert_eq!(" Rev_Fwd , fwd_gt|PRIMAL ".parse(), Ok(OracleSelection::REV_FWD | OracleSelection::FWD_GT | OracleSelection::PRIMAL));
+        // The opt-in oracles are only on by name
+        let all_timing: OracleSelection = "all,timing".parse().unwrap();
+        assert!(all_timing.contains(OracleSelection::TIMING) && !OracleSelection::ALL.contains(OracleSelection::TIMING));
+        assert!(!all_timing.contains(OracleSelection::AB_VERSION));
+
+        let error = "rev_fwd,rev_fdw".parse::<OracleSelection>().unwrap_err();
+        assert!(error.starts_with("Unknown oracle 'rev_fdw'"), "{}", error);
+        assert!("".parse::<OracleSelection>().is_err());
+        assert!("rev_fwd,".parse::<OracleSelection>().is_err());
+
+        // Display parses back to the same selection
+        for selection in [OracleSelection::ALL, OracleSelection::REV_GT | OracleSelection::VJP_JVP, all_timing] {
+            assert_eq!(selection.to_string().parse(), Ok(selection));
+        }
+        assert_eq!((OracleSelection::REV_GT | OracleSelection::VJP_JVP).to_string(), "rev_gt,vjp_jvp");
+    }
+}
@@ -0,0 +1,246 @@
+// src/oracles/sensitivity.rs
+
+// Parameter sensitivity: one constant `c` of the expression is moved to `c ± h` and the
+// calculator under test differentiates each variant from scratch, on new tapes (see
+// `Calculator::with_constant`). The change of its gradient must match the mixed derivative
+// d/dc df/dx_i, which `symbolic_diff` derives from the expression. Afterwards the original is
+// differentiated again and has to reproduce the Jacobian the case started with to within
+// `rerun_ulps`, so state left behind by the earlier tapes shows up.
+
+use std::hash::Hasher;
+
+use super::{ulp_distance, InputTransform, Oracle, OracleContext};
+use crate::ast_evaluator::{evaluate, Env};
+use crate::ast_expr::{Expr, SimpleExpr};
+use crate::ast_visitor::{fold_children, ExprFolder};
+use crate::error::FuzzError;
+use crate::expr_codec::StableHasher;
+use crate::failure_classifier::FailureClass;
+use crate::symbolic_diff::derivative;
+
+/// Name the perturbed constant goes by while it is differentiated symbolically.
+const PARAMETER: &str = "__c";
+
+/// Replaces the `index`-th `Number` (pre-order) with `with` of its tag.
+struct ReplaceConstant<F> {
+    index: usize,
+    seen: usize,
+    with: F,
+}
+
+impl<Tag, F: FnMut(Tag) -> Expr<Tag>> ExprFolder<Tag> for ReplaceConstant<F> {
+    fn fold(&mut self, expr: Expr<Tag>) -> Expr<Tag> {
+        match expr {
+            Expr::Number(tag, value) => {
+                self.seen += 1;
+                if self.seen - 1 == self.index { (self.with)(tag) } else { Expr::Number(tag, value) }
+            }
+            other => fold_children(self, other),
+        }
+    }
+}
+
+/// `expr` with its `index`-th constant (pre-order) replaced by `with(tag)`.
+pub fn replace_constant<Tag>(expr: Expr<Tag>, index: usize, with: impl FnMut(Tag) -> Expr<Tag>) -> Expr<Tag> {
+    ReplaceConstant { index, seen: 0, with }.fold(expr)
+}
+
+fn constants(expr: &SimpleExpr) -> Vec<f64> {
+    let mut values = Vec::new();
+    crate::ast_visitor::for_each_node(expr, |node| {
+        if let Expr::Number(_, value) = node {
+            values.push(*value);
+        }
+    });
+    values
+}
+
+/// ConstantSensitivityCheck: see the top of the file. The constant is chosen by a hash of the
+/// inputs, so a reproduced crash input picks the same one; `h = epsilon * max(1, |c|)`. Like
+/// `grad_check`, the tolerance grows with the curvature (one-sided differences compared) and the
+/// rounding error of the difference. The expression (`FuzzingOracles::specialize`) is the first
+/// output, so only its partials are compared against the mixed derivative. Without an
+/// expression, a symbolic derivative or a calculator that can replace constants it checks nothing.
+#[derive(Clone)]
+pub struct ConstantSensitivityCheck {
+    pub epsilon: f64,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+    /// How far apart the Jacobian before and after the perturbed copies may be.
+    pub rerun_ulps: u64,
+    /// The expression under test.
+    pub expr: Option<SimpleExpr>,
+}
+
+impl Default for ConstantSensitivityCheck {
+    fn default() -> Self {
+        ConstantSensitivityCheck {
+            epsilon: 1e-6,
+            abs_tolerance: 1e-5, // like grad_check
+            rel_tolerance: 1e-3,
+            rerun_ulps: 4,
+            expr: None,
+        }
+    }
+}
+
+impl ConstantSensitivityCheck {
+    /// `StableHasher` of the input bits, so a failure picks the same constant when replayed.
+    fn choose(inputs: &[f64], count: usize) -> usize {
+        let mut hasher = StableHasher::default();
+        for x in inputs {
+            hasher.write_u64(x.to_bits());
+        }
+        (hasher.finish() % count as u64) as usize
+    }
+
+    pub fn check_expr(&self, expr: &SimpleExpr, ctx: &OracleContext) -> Result<(), FuzzError> {
+        let engine = ctx.engine;
+        let values = constants(expr);
+        if values.is_empty() {
+            return Ok(());
+        }
+        let n = engine.inputs.len();
+        let index = Self::choose(&engine.inputs, values.len());
+        let c = values[index];
+        let h = self.epsilon * c.abs().max(1.0);
+
+        // d/dc df/dx_i at (x, c)
+        let parametric = replace_constant(expr.clone(), index, |_| SimpleExpr::var(PARAMETER));
+        let mut env: Env<f64> = engine.inputs.iter().enumerate().map(|(i, x)| (format!("x_{}", i), *x)).collect();
+        env.insert(PARAMETER.to_string(), c);
+        let Ok(mixed) = (0..n)
+            .map(|i| derivative(&derivative(&parametric, &format!("x_{}", i))?, PARAMETER).and_then(|d| evaluate(&d, &env)))
+            .collect::<Result<Vec<f64>, FuzzError>>()
+        else {
+            return Ok(());
+        };
+
+        let run = |value: f64| (ctx.with_constant)(index, value, &engine.inputs);
+        let (Some(plus), Some(minus)) = (run(c + h), run(c - h)) else {
+            return Ok(());
+        };
+        let again = (ctx.rerun)(InputTransform::Identity, &engine.inputs);
+
+        for k in 0..engine.reverse.len() {
+            let rows = [
+                ("Rev", engine.reverse[k], plus.reverse.get(k), minus.reverse.get(k), again.reverse.get(k)),
+                ("Fwd", engine.forward[k], plus.forward.get(k), minus.forward.get(k), again.forward.get(k)),
+            ];
+            let (output, i) = engine.partial(k);
+            for (name, g, g_plus, g_minus, g_again) in rows {
+                let (Some(&g_plus), Some(&g_minus), Some(&g_again)) = (g_plus, g_minus, g_again) else {
+                    continue;
+                };
+                if ulp_distance(g, g_again) > self.rerun_ulps {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Sensitivity {}", name),
+                        inputs: engine.inputs.clone(),
+                        output,
+                        index: i,
+                        class: FailureClass::of(g_again, g),
+                        details: format!(
+                            "Gradient changed after differentiating perturbed copies of the expression!\n\
+                            first run: {:.17e}, after c = {} was moved by ±{:e}: {:.17e}",
+                            g, c, h, g_again
+                        ),
+                    });
+                }
+                if output != 0 || ![g, g_plus, g_minus, mixed[i]].iter().all(|v| v.is_finite()) {
+                    continue;
+                }
+                let measured = (g_plus - g_minus) / (2.0 * h);
+                let curvature = ((g_plus - g) / h - (g - g_minus) / h).abs();
+                let rounding = 4.0 * f64::EPSILON * g.abs().max(g_plus.abs()).max(g_minus.abs()) / h;
+                let threshold = self.abs_tolerance + self.rel_tolerance * mixed[i].abs() + curvature + rounding;
+                if (measured - mixed[i]).abs() > threshold {
+                    return Err(FuzzError::OracleMismatch {
+                        oracle: format!("Sensitivity {}", name),
+                        inputs: engine.inputs.clone(),
+                        output: 0,
+                        index: i,
+                        class: FailureClass::of(measured, mixed[i]),
+                        details: format!(
+                            "Gradient does not follow constant #{} (c = {}, h = {:e})!\n\
+                            (df/dx(c + h) - df/dx(c - h)) / 2h: {:.10e}, d/dc df/dx: {:.10e}\n\
+                            Absolute Diff: {:.10e} (Threshold: {:.10e})",
+                            index, c, h, measured, mixed[i], (measured - mixed[i]).abs(), threshold
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Oracle for ConstantSensitivityCheck {
+    fn name(&self) -> &str { "sensitivity" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        match &self.expr {
+            Some(expr) => self.check_expr(expr, ctx),
+            None => Ok(()),
+        }
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+
+    fn expression_mut(&mut self) -> Option<&mut Option<SimpleExpr>> {
+        Some(&mut self.expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::domain_analysis::Interval;
+    use crate::fuzz_harness::{compute_engine_results, Calculator};
+    use crate::oracles::{run_transformed, EngineResults};
+
+    fn check(oracle: &ConstantSensitivityCheck, calc: &AdPyUnified<()>, engine: &EngineResults) -> Result<(), FuzzError> {
+        oracle.check_expr(calc.get_expr(), &OracleContext {
+            engine,
+            ground_truths: &[],
+            failed_ground_truths: &[],
+            function: &|_: &[f64]| Vec::new(),
+            rerun: &|t, x| run_transformed(calc, t, x),
+            gradient_enclosure: &|region: &[Interval]| Ok(vec![Interval::ENTIRE; region.len()]),
+            vector_products: &|_, _, _| (Vec::new(), Vec::new()),
+            hessian: &|_| Err(FuzzError::UnsupportedExpr("no Hessian".to_string())),
+            with_constant: &|index, value, x| calc.with_constant(index, value).map(|copy| compute_engine_results(&copy, x)),
+        })
+    }
+
+    #[test]
+    fn test_constant_sensitivity() {
+        // f = sin(3 * x_0) * x_1^2: d/dc df/dx_0 at c = 3 is (cos(3 x_0) - 3 x_0 sin(3 x_0)) x_1^2 ...
+        let expr = SimpleExpr::mul(
+            SimpleExpr::sin(SimpleExpr::mul(SimpleExpr::num(3.0), SimpleExpr::var("x_0"))),
+            SimpleExpr::pow(SimpleExpr::var("x_1"), SimpleExpr::num(2.0)),
+        );
+        assert_eq!(constants(&expr), vec![3.0, 2.0]);
+        assert_eq!(replace_constant(expr.clone(), 1, |_| SimpleExpr::num(2.5)), SimpleExpr::mul(
+            SimpleExpr::sin(SimpleExpr::mul(SimpleExpr::num(3.0), SimpleExpr::var("x_0"))),
+            SimpleExpr::pow(SimpleExpr::var("x_1"), SimpleExpr::num(2.5)),
+        ));
+        // The hash picking the constant does not depend on the toolchain
+        assert_eq!(ConstantSensitivityCheck::choose(&[0.4, 1.5], 1000), 505);
+
+        // ... which the engines follow, whichever constant the inputs pick
+        let oracle = ConstantSensitivityCheck::default();
+        let calc = AdPyUnified::new(expr.clone(), 2, 1);
+        for x in [[0.4, 1.5], [-1.2, 0.7], [2.0, -3.0]] {
+            let engine = compute_engine_results(&calc, &x);
+            assert!(check(&oracle, &calc, &engine).is_ok());
+        }
+
+        // A gradient that did not come from this expression
+        let mut engine = compute_engine_results(&calc, &[0.4, 1.5]);
+        engine.forward[0] += 1e-3;
+        assert!(matches!(check(&oracle, &calc, &engine), Err(FuzzError::OracleMismatch { ref oracle, .. }) if oracle == "Sensitivity Fwd"));
+    }
+}
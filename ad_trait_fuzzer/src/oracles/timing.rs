@@ -0,0 +1,123 @@
+// src/oracles/timing.rs
+
+// Performance anomalies instead of wrong values. Reverse AD sweeps its tape once per output and
+// Forward AD (one tangent at a time) runs once per input, so for f: R^n -> R^m the engines'
+// times should stay near reverse / forward = m / n, up to a constant. An expression where one of
+// them is far slower than that points at a pathological path in ad_trait: tape growth, repeated
+// allocation, an accidental quadratic. Wall times depend on the machine and its load, so the
+// oracle is opt-in (not part of `all`) and re-measures before it reports.
+
+use std::time::Duration;
+
+use super::{EngineResults, EngineTiming, InputTransform, Oracle, OracleContext};
+use crate::error::FuzzError;
+
+/// TimingCheck: fails when, per sweep, one engine is more than `max_ratio` times slower than the
+/// other. A suspicious case is differentiated `repeats` more times and the fastest time of each
+/// engine decides, so a one-off stall (preemption, a page fault) is not reported.
+#[derive(Clone)]
+pub struct TimingCheck {
+    pub max_ratio: f64,
+    /// Cases where both engines finish faster than this are skipped: timer resolution and
+    /// scheduling noise dominate.
+    pub min_time: Duration,
+    pub repeats: usize,
+}
+
+impl Default for TimingCheck {
+    fn default() -> Self {
+        TimingCheck {
+            max_ratio: 20.0,
+            min_time: Duration::from_micros(200),
+            repeats: 3,
+        }
+    }
+}
+
+impl TimingCheck {
+    /// Reverse AD's time per sweep over Forward AD's; above 1 Reverse AD is the slower one.
+    fn slowdown(timing: &EngineTiming, inputs: usize, outputs: usize) -> f64 {
+        let per_sweep = |t: Duration, sweeps: usize| t.as_secs_f64() / sweeps.max(1) as f64;
+        per_sweep(timing.reverse, outputs) / per_sweep(timing.forward, inputs)
+    }
+
+    fn anomalous(&self, timing: &EngineTiming, inputs: usize, outputs: usize) -> bool {
+        let slowdown = Self::slowdown(timing, inputs, outputs);
+        timing.reverse.max(timing.forward) >= self.min_time
+            && (slowdown > self.max_ratio || slowdown * self.max_ratio < 1.0)
+    }
+
+    pub fn check_rerun(&self, rerun: &dyn Fn(InputTransform, &[f64]) -> EngineResults, engine: &EngineResults) -> Result<(), FuzzError> {
+        let (n, m) = (engine.inputs.len(), engine.num_outputs());
+        if !self.anomalous(&engine.timing, n, m) {
+            return Ok(());
+        }
+        let mut fastest = engine.timing;
+        for _ in 0..self.repeats {
+            let timing = rerun(InputTransform::Identity, &engine.inputs).timing;
+            fastest.reverse = fastest.reverse.min(timing.reverse);
+            fastest.forward = fastest.forward.min(timing.forward);
+        }
+        if !self.anomalous(&fastest, n, m) {
+            return Ok(());
+        }
+
+        let slowdown = Self::slowdown(&fastest, n, m);
+        let (name, slower, factor) = if slowdown > 1.0 {
+            ("Rev", "Reverse", slowdown)
+        } else {
+            ("Fwd", "Forward", 1.0 / slowdown)
+        };
+        Err(FuzzError::PerformanceAnomaly {
+            oracle: format!("Timing {}", name),
+            inputs: engine.inputs.clone(),
+            details: format!(
+                "{} AD is {:.1}x slower per sweep than the other engine (limit: {}x)!\n\
+                Rev: {:?} for {} output(s), Fwd: {:?} for {} input(s), fastest of {} runs",
+                slower, factor, self.max_ratio,
+                fastest.reverse, m, fastest.forward, n, self.repeats + 1
+            ),
+        })
+    }
+}
+
+impl Oracle for TimingCheck {
+    fn name(&self) -> &str { "timing" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        self.check_rerun(ctx.rerun, ctx.engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_check() {
+        let engine = |reverse_ms: u64, forward_ms: u64| EngineResults {
+            inputs: vec![1.0, 2.0],
+            reverse: vec![0.0; 2],
+            forward: vec![0.0; 2],
+            reverse_primal: vec![0.0],
+            forward_primal: vec![0.0],
+            timing: EngineTiming { reverse: Duration::from_millis(reverse_ms), forward: Duration::from_millis(forward_ms) },
+        };
+        let check = TimingCheck::default();
+
+        // One output, two inputs: 2 ms against 20 ms and 200 ms against 20 ms are 1/5 and 20x per sweep
+        let never = |_: InputTransform, _: &[f64]| -> EngineResults { panic!("not suspicious, no rerun") };
+        assert!(check.check_rerun(&never, &engine(2, 20)).is_ok());
+        assert!(check.check_rerun(&never, &engine(200, 20)).is_ok());
+
+        // 50 ms against 1 ms is 100x per sweep, and stays that way
+        let slow = engine(50, 1);
+        let same = |_: InputTransform, _: &[f64]| slow.clone();
+        let err = check.check_rerun(&same, &slow).unwrap_err();
+        assert!(matches!(err, FuzzError::PerformanceAnomaly { ref oracle, .. } if oracle == "Timing Rev"));
+
+        // A stall the reruns don't reproduce
+        let fast = |_: InputTransform, _: &[f64]| engine(1, 1);
+        assert!(check.check_rerun(&fast, &slow).is_ok());
+    }
+}
@@ -0,0 +1,204 @@
+// src/oracles/vector_product.rs
+
+// Dot-product test of the two engines for functions with several outputs. For a random
+// cotangent `u` and tangent `v`, Reverse AD of the contracted function `u^T f` (one output, one
+// backward pass) gives `u^T J`, Forward AD of `f` along the line through `x` in direction `v`
+// (one input, one tangent pass) gives `J v`, and `u^T (J v)` must equal `(u^T J) v`. Neither
+// side builds the Jacobian, so the check costs the same for any number of outputs, and the
+// engines are seeded with dense vectors instead of the unit vectors `derivative` uses.
+
+use ad_trait::AD;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{Oracle, OracleContext};
+use crate::error::FuzzError;
+use crate::failure_classifier::FailureClass;
+use crate::fuzz_harness::{forward_jacobian, reverse_jacobian, Calculator};
+
+/// `u^T f(x)`: the outputs of `f` weighted by a cotangent and summed on the tape.
+#[derive(Clone)]
+pub struct Contracted<G: Calculator> {
+    pub inner: G,
+    pub cotangent: Vec<f64>,
+}
+
+impl<G: Calculator> Calculator for Contracted<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        self.inner.eval_outputs(inputs).into_iter().zip(&self.cotangent)
+            .fold(T::zero(), |sum, (y, u)| sum + T::constant(*u) * y)
+    }
+
+    fn num_inputs(&self) -> usize { self.inner.num_inputs() }
+    fn num_outputs(&self) -> usize { 1 }
+}
+
+/// `t -> f(point + t * direction)`: `f` along a line, with `t` the only input.
+#[derive(Clone)]
+pub struct Along<G: Calculator> {
+    pub inner: G,
+    pub point: Vec<f64>,
+    pub direction: Vec<f64>,
+}
+
+impl<G: Calculator> Along<G> {
+    fn line<T: AD>(&self, t: T) -> Vec<T> {
+        self.point.iter().zip(&self.direction).map(|(x, v)| T::constant(*x) + T::constant(*v) * t).collect()
+    }
+}
+
+impl<G: Calculator> Calculator for Along<G> {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        self.inner.eval_expr(&self.line(inputs[0]))
+    }
+
+    fn eval_outputs<T: AD + PartialEq>(&self, inputs: &[T]) -> Vec<T> {
+        self.inner.eval_outputs(&self.line(inputs[0]))
+    }
+
+    fn num_inputs(&self) -> usize { 1 }
+    fn num_outputs(&self) -> usize { self.inner.num_outputs() }
+}
+
+/// `(u^T J, J v)` of `calc` at `inputs`: Reverse AD of `Contracted` and Forward AD of `Along`,
+/// each engine run only where it is the one under test.
+pub fn vector_products<G: Calculator>(calc: &G, inputs: &[f64], cotangent: &[f64], tangent: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let contracted = Contracted { inner: calc.clone(), cotangent: cotangent.to_vec() };
+    let along = Along { inner: calc.clone(), point: inputs.to_vec(), direction: tangent.to_vec() };
+    (reverse_jacobian(&contracted, inputs), forward_jacobian(&along, &[0.0]))
+}
+
+/// VectorProductCheck: see the top of the file. `samples` pairs of `u` and `v` with entries
+/// uniform in `[-1, 1]`, drawn from an RNG seeded with `seed` at every check, so a reproduced
+/// crash input draws the same ones. The tolerance is relative to the sum of the magnitudes of the products' terms.
+/// Non-finite products are left to `rev_fwd` and `non_finite`; single-output functions to
+/// `rev_fwd`, which compares the same numbers.
+#[derive(Clone)]
+pub struct VectorProductCheck {
+    pub samples: usize,
+    pub seed: u64,
+    pub abs_tolerance: f64,
+    pub rel_tolerance: f64,
+}
+
+impl Default for VectorProductCheck {
+    fn default() -> Self {
+        VectorProductCheck {
+            samples: 2,
+            seed: 0x5eed,
+            abs_tolerance: 1e-12,
+            rel_tolerance: 1e-9,
+        }
+    }
+}
+
+impl Oracle for VectorProductCheck {
+    fn name(&self) -> &str { "vjp_jvp" }
+
+    fn check(&self, ctx: &OracleContext) -> Result<(), FuzzError> {
+        let engine = ctx.engine;
+        let (m, n) = (engine.num_outputs(), engine.inputs.len());
+        if m < 2 || n == 0 {
+            return Ok(());
+        }
+        let mismatch = |class, details| FuzzError::OracleMismatch {
+            oracle: "VJP vs JVP".to_string(),
+            inputs: engine.inputs.clone(),
+            output: 0,
+            index: 0,
+            class,
+            details,
+        };
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        for _ in 0..self.samples {
+            let u: Vec<f64> = (0..m).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+            let v: Vec<f64> = (0..n).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+            let (vjp, jvp) = (ctx.vector_products)(&engine.inputs, &u, &v);
+            if vjp.len() != n || jvp.len() != m {
+                return Err(mismatch(FailureClass::ShapeMismatch, format!(
+                    "Vector products have the wrong size! u^T J: {} entries (expected {}), J v: {} (expected {})",
+                    vjp.len(), n, jvp.len(), m
+                )));
+            }
+            let through_fwd: Vec<f64> = u.iter().zip(&jvp).map(|(a, b)| a * b).collect();
+            let through_rev: Vec<f64> = vjp.iter().zip(&v).map(|(a, b)| a * b).collect();
+            if !through_fwd.iter().chain(&through_rev).all(|t| t.is_finite()) {
+                continue;
+            }
+            let (fwd, rev) = (through_fwd.iter().sum::<f64>(), through_rev.iter().sum::<f64>());
+            let scale = through_fwd.iter().chain(&through_rev).map(|t| t.abs()).sum::<f64>();
+            let threshold = self.abs_tolerance + self.rel_tolerance * scale;
+            if (fwd - rev).abs() > threshold {
+                return Err(mismatch(FailureClass::of(rev, fwd), format!(
+                    "Reverse and Forward vector products disagree!\n\
+                    u: {:?}\nv: {:?}\n\
+                    Rev (u^T J) v: {:.10e}, Fwd u^T (J v): {:.10e}\n\
+                    Absolute Diff: {:.10e} (Threshold: {:.10e})",
+                    u, v, rev, fwd, (fwd - rev).abs(), threshold
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn tolerances_mut(&mut self) -> Option<(&mut f64, &mut f64)> {
+        Some((&mut self.abs_tolerance, &mut self.rel_tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain_analysis::Interval;
+    use crate::fuzz_harness::compute_engine_results;
+    use crate::oracles::{EngineResults, InputTransform, VectorProductsFn};
+
+    /// f(x, y, z) = (x * y, sin(x) * z, exp(y))
+    #[derive(Clone)]
+    struct ThreeOutputs;
+
+    impl Calculator for ThreeOutputs {
+        fn eval_expr<T: AD + PartialEq>(&self, x: &[T]) -> T { x[0] * x[1] }
+        fn num_inputs(&self) -> usize { 3 }
+        fn num_outputs(&self) -> usize { 3 }
+        fn eval_outputs<T: AD + PartialEq>(&self, x: &[T]) -> Vec<T> { vec![x[0] * x[1], x[0].sin() * x[2], x[1].exp()] }
+    }
+
+    #[test]
+    fn test_vector_products() {
+        let x = [0.5, -1.5, 2.0];
+        let engine = compute_engine_results(&ThreeOutputs, &x);
+        // Against the Jacobian the engines build
+        let (u, v) = ([1.0, -2.0, 0.5], [0.25, 1.0, -1.0]);
+        let (vjp, jvp) = vector_products(&ThreeOutputs, &x, &u, &v);
+        for (j, vjp_j) in vjp.iter().enumerate() {
+            let expected: f64 = (0..3).map(|k| u[k] * engine.reverse[k * 3 + j]).sum();
+            assert!((vjp_j - expected).abs() < 1e-14);
+        }
+        for (k, jvp_k) in jvp.iter().enumerate() {
+            let expected: f64 = (0..3).map(|j| engine.forward[k * 3 + j] * v[j]).sum();
+            assert!((jvp_k - expected).abs() < 1e-14);
+        }
+
+        let rerun = |_: InputTransform, _: &[f64]| -> EngineResults { unreachable!() };
+        let run = |products: &VectorProductsFn<'_>| VectorProductCheck::default().check(&OracleContext {
+            engine: &engine,
+            ground_truths: &[],
+            failed_ground_truths: &[],
+            function: &|_: &[f64]| Vec::new(),
+            rerun: &rerun,
+            gradient_enclosure: &|region: &[Interval]| Ok(vec![Interval::ENTIRE; region.len()]),
+            vector_products: products,
+            hessian: &|_| Err(FuzzError::UnsupportedExpr("no Hessian".to_string())),
+            with_constant: &|_, _, _| None,
+        });
+        assert!(run(&|x, u, v| vector_products(&ThreeOutputs, x, u, v)).is_ok());
+        // A Forward pass that drops the last output's tangent
+        let err = run(&|x, u, v| {
+            let (vjp, mut jvp) = vector_products(&ThreeOutputs, x, u, v);
+            jvp[2] = 0.0;
+            (vjp, jvp)
+        }).unwrap_err();
+        assert!(matches!(err, FuzzError::OracleMismatch { ref oracle, .. } if oracle == "VJP vs JVP"));
+    }
+}
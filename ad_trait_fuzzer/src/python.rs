@@ -0,0 +1,265 @@
+// src/python.rs
+
+// Python bindings (feature "python"), built with maturin (see pyproject.toml):
+//
+//     import fuzz_core
+//     e = fuzz_core.Expr.parse("sin(x_0) * x_1")        # or fuzz_core.generate_from_bytes(data)
+//     e.evaluate([0.5, 2.0])
+//     fuzz_core.derivatives(e, [0.5, 2.0])             # {"reverse": (value, jacobian), ...}
+//     fuzz_core.run_ad_tests(e, [0.5, 2.0], oracles="rev_fwd,fwd_gt", ground_truth="pytorch")
+//
+// Jacobians are flat row-major lists like `EngineResults`, so `np.array(j).reshape(m, n)`.
+// Harness errors are raised as `fuzz_core.HarnessError` with the `FuzzError` kind in front.
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::ast_evaluator::unified::AdPyUnified;
+use crate::ast_evaluator::{evaluate, Env, InfixPrinter, SExprPrinter, SSAPrinter};
+use crate::ast_expr::SimpleExpr;
+use crate::ast_generator::{self, AstGenConfig};
+use crate::ast_parser::{parse_infix, parse_sexpr};
+use crate::error::FuzzError;
+use crate::fuzz_harness::{self, catch_backend_panic, compute_engine_results, GroundTruthCalculator, TestOutcome};
+use crate::gt_calculators::{DualNumberCalculator, EvalexprJitCalculator, PyTorchGroundTruthCalculator};
+use crate::oracles::{FuzzingOracles, OracleSelection};
+
+create_exception!(fuzz_core, HarnessError, PyException);
+
+impl From<FuzzError> for PyErr {
+    fn from(e: FuzzError) -> PyErr {
+        HarnessError::new_err(format!("{}: {}", e.kind(), e))
+    }
+}
+
+/// An expression over `x_0 .. x_{num_inputs - 1}`.
+#[pyclass(name = "Expr", module = "fuzz_core")]
+#[derive(Clone)]
+pub struct PyExpr {
+    expr: SimpleExpr,
+    #[pyo3(get)]
+    num_inputs: usize,
+}
+
+#[pymethods]
+impl PyExpr {
+    /// Parses the infix syntax the printers and `adfuzz` use.
+    #[staticmethod]
+    fn parse(src: &str) -> PyResult<Self> {
+        let (expr, num_inputs) = parse_infix(src)?;
+        Ok(PyExpr { expr, num_inputs })
+    }
+
+    /// Parses the S-expression syntax of `sexpr()`.
+    #[staticmethod]
+    fn parse_sexpr(src: &str) -> PyResult<Self> {
+        let (expr, num_inputs) = parse_sexpr(src)?;
+        Ok(PyExpr { expr, num_inputs })
+    }
+
+    fn infix(&self) -> String {
+        InfixPrinter::print(&self.expr, self.num_inputs)
+    }
+
+    fn sexpr(&self) -> String {
+        SExprPrinter::print(&self.expr, self.num_inputs)
+    }
+
+    fn ssa(&self) -> String {
+        SSAPrinter::print(&self.expr)
+    }
+
+    /// Value at `inputs`, in plain f64.
+    fn evaluate(&self, inputs: Vec<f64>) -> PyResult<f64> {
+        check_inputs(self, &inputs)?;
+        let env: Env<f64> = inputs.iter().enumerate().map(|(i, &x)| (format!("x_{}", i), x)).collect();
+        Ok(evaluate(&self.expr, &env)?)
+    }
+
+    fn __str__(&self) -> String {
+        self.infix()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Expr({:?})", self.infix())
+    }
+}
+
+fn check_inputs(expr: &PyExpr, inputs: &[f64]) -> PyResult<()> {
+    if inputs.len() != expr.num_inputs {
+        return Err(PyValueError::new_err(format!("expected {} inputs, got {}", expr.num_inputs, inputs.len())));
+    }
+    Ok(())
+}
+
+/// The expression the AST fuzz targets generate from `data`.
+#[pyfunction]
+#[pyo3(signature = (data, max_depth=4, max_variables=2, allow_division=true, allow_power=true, allow_log=false, allow_cast=false, grammar="general"))]
+#[allow(clippy::too_many_arguments)]
+fn generate_from_bytes(
+    data: &[u8],
+    max_depth: usize,
+    max_variables: usize,
+    allow_division: bool,
+    allow_power: bool,
+    allow_log: bool,
+    allow_cast: bool,
+    grammar: &str,
+) -> PyResult<PyExpr> {
+    let config = AstGenConfig {
+        max_depth,
+        max_variables,
+        allow_division,
+        allow_power,
+        allow_log,
+        allow_cast,
+        grammar: grammar.parse().map_err(PyValueError::new_err)?,
+        coverage_bias: false,
+    };
+    let generated = ast_generator::generate_from_bytes(data, config)
+        .map_err(|e| PyValueError::new_err(format!("cannot generate an expression: {}", e)))?;
+    Ok(PyExpr { expr: generated.expr, num_inputs: generated.num_inputs })
+}
+
+/// Value and Jacobian from Reverse AD, Forward AD and every referee (PyTorch, Dual,
+/// evalexpr-jit). A referee that fails maps to its error message instead. Runs without the GIL.
+#[pyfunction]
+fn derivatives<'py>(py: Python<'py>, expr: &PyExpr, inputs: Vec<f64>) -> PyResult<Bound<'py, PyDict>> {
+    check_inputs(expr, &inputs)?;
+    let calc = AdPyUnified::new(expr.expr.clone(), expr.num_inputs, 1);
+    let referees = [PyTorchGroundTruthCalculator::default().boxed(), DualNumberCalculator.boxed(), EvalexprJitCalculator.boxed()];
+    let (engine, results) = py.allow_threads(|| {
+        let engine = catch_backend_panic("AD (Rev/Fwd)", || compute_engine_results(&calc, &inputs));
+        let results: Vec<_> = referees.iter()
+            .map(|gt| (gt.name(), catch_backend_panic(gt.name(), || gt.calculate(&calc, &inputs)).and_then(|r| r)))
+            .collect();
+        (engine, results)
+    });
+    let engine = engine?;
+    let out = PyDict::new_bound(py);
+    out.set_item("reverse", (engine.reverse_primal[0], engine.reverse))?;
+    out.set_item("forward", (engine.forward_primal[0], engine.forward))?;
+    for (name, result) in results {
+        match result {
+            Ok(result) => out.set_item(name, (result.value.unwrap_or(f64::NAN), result.jacobian))?,
+            Err(e) => out.set_item(name, format!("{}: {}", e.kind(), e))?,
+        }
+    }
+    Ok(out)
+}
+
+/// `fuzz_harness::run_ad_tests` with the oracles in `oracles` (FUZZ_ORACLE syntax) and one
+/// ground truth: "pytorch", "dual" or "evalexpr-jit". Returns the outcome as a dict. Runs
+/// without the GIL.
+#[pyfunction]
+#[pyo3(signature = (expr, inputs, oracles="all", ground_truth="pytorch"))]
+fn run_ad_tests<'py>(
+    py: Python<'py>,
+    expr: &PyExpr,
+    inputs: Vec<f64>,
+    oracles: &str,
+    ground_truth: &str,
+) -> PyResult<Bound<'py, PyDict>> {
+    let selection: OracleSelection = oracles.parse().map_err(|e| PyValueError::new_err(format!("oracles: {}", e)))?;
+    let gt = match ground_truth {
+        "pytorch" => PyTorchGroundTruthCalculator::default().boxed(),
+        "dual" => DualNumberCalculator.boxed(),
+        "evalexpr-jit" => EvalexprJitCalculator.boxed(),
+        other => {
+            return Err(PyValueError::new_err(format!(
+                "unknown ground truth '{}' (expected \"pytorch\", \"dual\" or \"evalexpr-jit\")", other
+            )))
+        }
+    };
+    let expr = expr.clone();
+    let outcome = py.allow_threads(move || {
+        let oracles = FuzzingOracles::new(selection).specialize(&expr.expr);
+        let calc = AdPyUnified::new(expr.expr, expr.num_inputs, 1);
+        fuzz_harness::run_ad_tests(&inputs, calc, &oracles, &[gt])
+    })?;
+    outcome_to_dict(py, &outcome)
+}
+
+fn outcome_to_dict<'py>(py: Python<'py>, outcome: &TestOutcome) -> PyResult<Bound<'py, PyDict>> {
+    let engine = &outcome.engine_results;
+    let out = PyDict::new_bound(py);
+    out.set_item("passed", outcome.passed())?;
+    out.set_item("inputs", engine.inputs.clone())?;
+    out.set_item("reverse", (engine.reverse_primal.clone(), engine.reverse.clone()))?;
+    out.set_item("forward", (engine.forward_primal.clone(), engine.forward.clone()))?;
+
+    let ground_truths = PyDict::new_bound(py);
+    for gt in &outcome.ground_truths {
+        ground_truths.set_item(gt.name, (gt.value, gt.jacobian.clone(), gt.hessian.clone()))?;
+    }
+    for (name, e) in &outcome.failed_ground_truths {
+        ground_truths.set_item(*name, format!("{}: {}", e.kind(), e))?;
+    }
+    out.set_item("ground_truths", ground_truths)?;
+
+    let verdicts = PyList::empty_bound(py);
+    for verdict in &outcome.verdicts {
+        let v = PyDict::new_bound(py);
+        v.set_item("oracle", &verdict.oracle)?;
+        v.set_item("severity", format!("{:?}", verdict.severity))?;
+        v.set_item("passed", verdict.result.is_ok())?;
+        v.set_item("outcome", format!("{:?}", verdict.outcome()))?;
+        v.set_item("borderline", verdict.borderline)?;
+        if let Err(e) = &verdict.result {
+            v.set_item("kind", e.kind())?;
+            v.set_item("class", e.failure_class().map(|c| c.to_string()))?;
+            v.set_item("error", e.to_string())?;
+        }
+        v.set_item("elapsed_s", verdict.elapsed.as_secs_f64())?;
+        verdicts.append(v)?;
+    }
+    out.set_item("verdicts", verdicts)?;
+    Ok(out)
+}
+
+#[pymodule]
+fn fuzz_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyExpr>()?;
+    m.add_function(wrap_pyfunction!(generate_from_bytes, m)?)?;
+    m.add_function(wrap_pyfunction!(derivatives, m)?)?;
+    m.add_function(wrap_pyfunction!(run_ad_tests, m)?)?;
+    m.add("HarnessError", m.py().get_type_bound::<HarnessError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "fuzz_core").unwrap();
+            fuzz_core(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("fuzz_core", &module).unwrap();
+            py.run_bound(
+                r#"
+e = fuzz_core.Expr.parse("sin(x_0) * x_1")
+assert e.num_inputs == 2 and e.infix() == fuzz_core.Expr.parse(e.infix()).infix()
+assert abs(e.evaluate([0.5, 2.0]) - 0.958851077208406) < 1e-12
+d = fuzz_core.derivatives(e, [0.5, 2.0])
+assert d["reverse"][1] == d["forward"][1] and d["Dual"][1] == d["forward"][1]
+out = fuzz_core.run_ad_tests(e, [0.5, 2.0], oracles="rev_fwd,fwd_gt", ground_truth="dual")
+assert out["passed"] and len(out["verdicts"]) == 2
+try:
+    fuzz_core.Expr.parse("x_0 +")
+    raise AssertionError("no HarnessError")
+except fuzz_core.HarnessError as err:
+    assert str(err).startswith("UnsupportedExpr")
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
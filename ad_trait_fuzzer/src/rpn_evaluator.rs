@@ -0,0 +1,137 @@
+// src/rpn_evaluator.rs
+
+// Runs a `TestDefinition` straight from its RPN tokens: a stack machine over any `MainBackend`,
+// so the AD types, PyTorch tensors, `Dual` and `TapeCount` all evaluate the same token list.
+// The operators are applied by `apply_op1`/`apply_op2`, the ones `evaluate` uses for trees, and
+// `RpnEvaluator` implements the harness traits like `AdPyUnified`: an RPN test goes through the
+// same `run_ad_tests` as a generated AST.
+
+use ad_trait::AD;
+#[cfg(feature = "pytorch")]
+use tch::Tensor;
+
+#[cfg(feature = "pytorch")]
+use crate::ast_evaluator::pytorch_backend::PyTorchTensor;
+use crate::ast_evaluator::{apply_op1, apply_op2, Dual, InfixPrinter, MainBackend};
+use crate::error::FuzzError;
+use crate::fuzz_harness::{Calculator, PyTorchComputable};
+use crate::test_definition::{RpnToken, TestDefinition};
+
+#[derive(Clone)]
+pub struct RpnEvaluator {
+    pub definition: TestDefinition,
+    /// At least `definition.num_inputs()`; inputs the RPN never reads have a zero derivative.
+    pub num_inputs: usize,
+}
+
+impl RpnEvaluator {
+    pub fn new(definition: TestDefinition, num_inputs: usize) -> Self {
+        let num_inputs = num_inputs.max(definition.num_inputs());
+        RpnEvaluator { definition, num_inputs }
+    }
+
+    /// The value left on the stack after all tokens; an error for too few inputs or an RPN that
+    /// does not leave exactly one value.
+    pub fn eval<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        if inputs.len() < self.definition.num_inputs() {
+            return Err(FuzzError::InputMismatch { expected: self.definition.num_inputs(), got: inputs.len() });
+        }
+        let unbalanced = || FuzzError::UnsupportedExpr(format!("unbalanced RPN '{}'", self.definition.label()));
+        let mut stack: Vec<B> = Vec::with_capacity(self.definition.rpn.len());
+        for token in &self.definition.rpn {
+            let mut pop = || stack.pop().ok_or_else(unbalanced);
+            let value = match token {
+                RpnToken::Var(i) => inputs[*i].clone(),
+                RpnToken::Num(val) => B::from_f64(*val),
+                RpnToken::Unary(op) => apply_op1(op, pop()?),
+                RpnToken::Cast(ty) => pop()?.cast(ty),
+                RpnToken::Binary(op) => {
+                    let right = pop()?;
+                    apply_op2(op, pop()?, right)
+                }
+            };
+            stack.push(value);
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(value), true) => Ok(value),
+            _ => Err(unbalanced()),
+        }
+    }
+}
+
+impl Calculator for RpnEvaluator {
+    fn eval_expr<T: AD + PartialEq>(&self, inputs: &[T]) -> T {
+        match self.eval(inputs) {
+            Ok(result) => result,
+            Err(e) => panic!("Error during AD evaluation: {}", e)
+        }
+    }
+
+    fn eval_backend<B: MainBackend>(&self, inputs: &[B]) -> Result<B, FuzzError> {
+        self.eval(inputs)
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    fn num_outputs(&self) -> usize {
+        1
+    }
+
+    fn same_function(&self, other: &Self) -> bool {
+        self.num_inputs == other.num_inputs && self.definition.rpn == other.definition.rpn
+    }
+}
+
+impl PyTorchComputable for RpnEvaluator {
+    #[cfg(feature = "pytorch")]
+    fn compute_pytorch(&self, inputs: &[Tensor]) -> Result<Vec<Tensor>, FuzzError> {
+        let inputs: Vec<PyTorchTensor> = inputs.iter().map(|t| PyTorchTensor(t.shallow_clone())).collect();
+        let PyTorchTensor(result) = self.eval(&inputs)?;
+        Ok(vec![result])
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    fn num_outputs(&self) -> usize {
+        1
+    }
+
+    fn infix_expr(&self) -> Option<String> {
+        let expr = self.definition.to_expr().ok()?;
+        Some(InfixPrinter::print(&expr, self.num_inputs))
+    }
+
+    fn eval_dual(&self, inputs: &[Dual]) -> Result<Dual, FuzzError> {
+        self.eval(inputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::{evaluate, Env};
+
+    #[test]
+    fn test_rpn_evaluator() {
+        // The stack machine agrees with the tree `to_expr` builds, casts and every operator included
+        let inputs = [0.7, -1.3, 2.1];
+        let env: Env<f64> = inputs.iter().enumerate().map(|(i, x)| (format!("x_{}", i), *x)).collect();
+        for src in ["x y + sin", "x y - z * 2 ^", "z ln sqrt y abs / neg", "x cos tan exp", "y trunc x bool + float"] {
+            let definition = TestDefinition::from_rpn_str(src).unwrap();
+            let expected = evaluate(&definition.to_expr().unwrap(), &env).unwrap();
+            let calc = RpnEvaluator::new(definition, 3);
+            assert_eq!(calc.eval_backend(&inputs).unwrap().to_bits(), expected.to_bits(), "{}", src);
+        }
+
+        // Unused inputs are fine, missing ones are not
+        let calc = RpnEvaluator::new(TestDefinition::from_rpn_str("x_2 1 +").unwrap(), 0);
+        assert_eq!(Calculator::num_inputs(&calc), 3);
+        assert!(matches!(calc.eval(&[1.0, 2.0]), Err(FuzzError::InputMismatch { expected: 3, got: 2 })));
+        let unbalanced = RpnEvaluator { definition: TestDefinition { rpn: vec![RpnToken::Var(0), RpnToken::Var(0)], ..calc.definition.clone() }, num_inputs: 1 };
+        assert!(unbalanced.eval(&[1.0]).is_err());
+    }
+}
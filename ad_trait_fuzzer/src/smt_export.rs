@@ -0,0 +1,315 @@
+// src/smt_export.rs
+
+// SMT-LIB2 export for formal cross-checks with a delta-decision procedure (dReal) or Z3. The
+// query asks whether some point of a box D around the inputs has a symbolic gradient more than
+// ε away from the gradient an engine reported there:
+//
+//     ∃ x ∈ D:  ∨_i |∂f/∂x_i(x) - g_i| > ε
+//
+// dReal decides this only up to its precision δ: `unsat` means no point of D is off by more
+// than ε as far as dReal's interval arithmetic can tell, and only says anything when δ is well
+// below ε (`dreal --precision`; the default 1e-3 turns every query with ε = 1e-9 into
+// `delta-sat`). `delta-sat` comes with a model that may be spurious by up to δ, so check it in
+// f64 before believing it. Z3 takes the queries without sin/cos/tan/exp/log/sqrt.
+//
+// The query keeps to QF_NRA plus the transcendental functions dReal adds to it: `^` with a
+// small integer constant exponent becomes products, any other `a ^ b` is `exp(b * log(a))`
+// (like the `d/db` term, only defined for `a > 0`), and a cast to `Int` (truncation) has no
+// encoding and is rejected. The derivatives are built symbolically by `evaluate` over
+// `SmtDual`, one term per input; shared subterms are repeated, so keep the expressions shrunk.
+
+use crate::ast_evaluator::{evaluate, Env, InfixPrinter, MainBackend};
+use crate::ast_expr::{Expr, Type};
+use crate::error::FuzzError;
+
+/// SMT-LIB real literal: exact decimal, negatives as `(- x)`. `None` for NaN and ±Inf.
+fn smt_real(val: f64) -> Option<String> {
+    if !val.is_finite() {
+        return None;
+    }
+    // Display never uses an exponent, which SMT-LIB decimals don't have
+    let mut digits = format!("{}", val.abs());
+    if !digits.contains('.') {
+        digits.push_str(".0");
+    }
+    Some(if val.is_sign_negative() && val != 0.0 { format!("(- {})", digits) } else { digits })
+}
+
+const ZERO: &str = "0.0";
+const ONE: &str = "1.0";
+
+/// Largest `|k|` of an integer exponent written out as products.
+const MAX_EXPANDED_POWER: i64 = 16;
+
+/// `k` for the literal of an integer `k` (`3.0`, `(- 2.0)`).
+fn int_literal(term: &str) -> Option<i64> {
+    let (sign, digits) = match term.strip_prefix("(- ").and_then(|t| t.strip_suffix(')')) {
+        Some(digits) => (-1, digits),
+        None => (1, term),
+    };
+    let val: f64 = digits.parse().ok()?;
+    (val.fract() == 0.0 && val.abs() <= i64::MAX as f64).then(|| sign * val as i64)
+}
+
+/// `a ^ k` as products, `(/ 1.0 ...)` for negative `k`.
+fn powi(a: &str, k: i64) -> String {
+    let product = match k.unsigned_abs() {
+        0 => return ONE.to_string(),
+        1 => a.to_string(),
+        n => format!("(* {})", vec![a; n as usize].join(" ")),
+    };
+    if k < 0 { div(ONE, &product) } else { product }
+}
+
+fn add(a: &str, b: &str) -> String {
+    match (a, b) {
+        (ZERO, _) => b.to_string(),
+        (_, ZERO) => a.to_string(),
+        _ => format!("(+ {} {})", a, b),
+    }
+}
+
+fn sub(a: &str, b: &str) -> String {
+    match (a, b) {
+        (_, ZERO) => a.to_string(),
+        (ZERO, _) => format!("(- {})", b),
+        _ => format!("(- {} {})", a, b),
+    }
+}
+
+fn mul(a: &str, b: &str) -> String {
+    match (a, b) {
+        (ZERO, _) | (_, ZERO) => ZERO.to_string(),
+        (ONE, _) => b.to_string(),
+        (_, ONE) => a.to_string(),
+        _ => format!("(* {} {})", a, b),
+    }
+}
+
+fn div(a: &str, b: &str) -> String {
+    match (a, b) {
+        (ZERO, _) => ZERO.to_string(),
+        (_, ONE) => a.to_string(),
+        _ => format!("(/ {} {})", a, b),
+    }
+}
+
+/// A value and its partial derivatives as SMT-LIB terms, a symbolic `Dual`.
+#[derive(Clone)]
+pub struct SmtDual {
+    pub val: String,
+    pub der: Vec<String>,
+    /// Why the query cannot be written, set by the first constant SMT-LIB cannot express
+    /// (NaN, ±Inf) or operation outside the logic.
+    pub unsupported: Option<String>,
+}
+
+impl SmtDual {
+    fn map(self, val: String, der: impl Fn(&str) -> String) -> Self {
+        SmtDual { der: self.der.iter().map(|d| der(d)).collect(), val, unsupported: self.unsupported }
+    }
+
+    fn zip(self, other: Self, val: String, der: impl Fn(&str, &str) -> String) -> Self {
+        let der = if self.der.is_empty() {
+            other.der.iter().map(|d| der(ZERO, d)).collect()
+        } else if other.der.is_empty() {
+            self.der.iter().map(|d| der(d, ZERO)).collect()
+        } else {
+            self.der.iter().zip(&other.der).map(|(a, b)| der(a, b)).collect()
+        };
+        SmtDual { val, der, unsupported: self.unsupported.or(other.unsupported) }
+    }
+}
+
+// Constants carry no derivative terms (all zero, for any number of inputs)
+impl MainBackend for SmtDual {
+    fn from_f64(val: f64) -> Self {
+        match smt_real(val) {
+            Some(lit) => SmtDual { val: lit, der: Vec::new(), unsupported: None },
+            None => SmtDual { val: ZERO.to_string(), der: Vec::new(), unsupported: Some(format!("constant {} has no SMT-LIB real", val)) },
+        }
+    }
+    fn zero() -> Self { Self::from_f64(0.0) }
+    fn one() -> Self { Self::from_f64(1.0) }
+
+    fn neg(self) -> Self {
+        let val = sub(ZERO, &self.val);
+        self.map(val, |d| sub(ZERO, d))
+    }
+    fn sin(self) -> Self {
+        let cos = format!("(cos {})", self.val);
+        let val = format!("(sin {})", self.val);
+        self.map(val, |d| mul(&cos, d))
+    }
+    fn cos(self) -> Self {
+        let neg_sin = format!("(- (sin {}))", self.val);
+        let val = format!("(cos {})", self.val);
+        self.map(val, |d| mul(&neg_sin, d))
+    }
+    fn tan(self) -> Self {
+        let cos_sq = format!("(* (cos {0}) (cos {0}))", self.val);
+        let val = format!("(tan {})", self.val);
+        self.map(val, |d| div(d, &cos_sq))
+    }
+    fn exp(self) -> Self {
+        let val = format!("(exp {})", self.val);
+        let exp = val.clone();
+        self.map(val, |d| mul(&exp, d))
+    }
+    fn log(self) -> Self {
+        let arg = self.val.clone();
+        let val = format!("(log {})", self.val);
+        self.map(val, |d| div(d, &arg))
+    }
+    fn sqrt(self) -> Self {
+        let val = format!("(sqrt {})", self.val);
+        let twice = format!("(* 2.0 {})", val);
+        self.map(val, |d| div(d, &twice))
+    }
+    fn abs(self) -> Self {
+        // Subgradient +1 at 0, like ad_trait's signum
+        let sign = format!("(ite (>= {} 0.0) 1.0 (- 1.0))", self.val);
+        let val = format!("(abs {})", self.val);
+        self.map(val, |d| mul(&sign, d))
+    }
+
+    fn add(self, other: Self) -> Self {
+        let val = add(&self.val, &other.val);
+        self.zip(other, val, add)
+    }
+    fn sub(self, other: Self) -> Self {
+        let val = sub(&self.val, &other.val);
+        self.zip(other, val, sub)
+    }
+    fn mul(self, other: Self) -> Self {
+        let (a, b) = (self.val.clone(), other.val.clone());
+        let val = mul(&a, &b);
+        self.zip(other, val, |da, db| add(&mul(da, &b), &mul(&a, db)))
+    }
+    fn div(self, other: Self) -> Self {
+        let (a, b) = (self.val.clone(), other.val.clone());
+        let val = div(&a, &b);
+        let b_sq = format!("(* {0} {0})", b);
+        self.zip(other, val, |da, db| div(&sub(&mul(da, &b), &mul(&a, db)), &b_sq))
+    }
+    fn pow(self, other: Self) -> Self {
+        // d(a^b) = b a^(b-1) da + a^b ln(a) db
+        let (a, b) = (self.val.clone(), other.val.clone());
+        let exponent = int_literal(&b).filter(|k| k.abs() <= MAX_EXPANDED_POWER);
+        let (val, base_factor) = match exponent {
+            Some(k) => (powi(&a, k), mul(&b, &powi(&a, k - 1))),
+            None => (
+                format!("(exp (* {} (log {})))", b, a),
+                mul(&b, &format!("(exp (* {} (log {})))", sub(&b, ONE), a)),
+            ),
+        };
+        let exp_factor = mul(&val, &format!("(log {})", a));
+        let result = val.clone();
+        self.zip(other, result, |da, db| add(&mul(&base_factor, da), &mul(&exp_factor, db)))
+    }
+
+    fn cast(self, ty: &Type) -> Self {
+        let val = match ty {
+            Type::Float => return self,
+            // to_int is QF_NIRA, not QF_NRA
+            Type::Int => {
+                let unsupported = self.unsupported.clone().or_else(|| Some("a cast to Int (truncation) has no QF_NRA encoding".to_string()));
+                return SmtDual { unsupported, ..self.map(ZERO.to_string(), |_| ZERO.to_string()) };
+            }
+            Type::Bool => format!("(ite (= {} 0.0) 0.0 1.0)", self.val),
+        };
+        // Piecewise constant
+        self.map(val, |_| ZERO.to_string())
+    }
+}
+
+/// The query described at the top of the file, for `expr` at `inputs`.
+pub struct SmtQuery<'a, Tag> {
+    pub expr: &'a Expr<Tag>,
+    /// Where the gradient was computed, one value per variable.
+    pub inputs: &'a [f64],
+    /// The engine's gradient at `inputs`, e.g. `EngineResults::reverse`.
+    pub gradient: &'a [f64],
+    pub epsilon: f64,
+    /// D: x_i ranges over `inputs[i] ± radius * max(1, |inputs[i]|)`.
+    pub radius: f64,
+}
+
+impl<Tag> SmtQuery<'_, Tag> {
+    /// SMT-LIB2 source, `(check-sat)` and `(get-model)` included. Fails if the expression,
+    /// the inputs or the gradient contain NaN or ±Inf, which have no SMT-LIB real, or the
+    /// expression casts to `Int`.
+    pub fn to_smtlib(&self) -> Result<String, FuzzError> {
+        let n = self.inputs.len();
+        let real = |what: &str, val: f64| smt_real(val)
+            .ok_or_else(|| FuzzError::UnsupportedExpr(format!("{} {} has no SMT-LIB real", what, val)));
+        if self.gradient.len() != n {
+            return Err(FuzzError::InputMismatch { expected: n, got: self.gradient.len() });
+        }
+
+        let mut env = Env::new();
+        for i in 0..n {
+            let der = (0..n).map(|j| if i == j { ONE } else { ZERO }.to_string()).collect();
+            env.insert(format!("x_{}", i), SmtDual { val: format!("x_{}", i), der, unsupported: None });
+        }
+        let f = evaluate::<SmtDual, Tag>(self.expr, &env)?;
+        if let Some(reason) = f.unsupported {
+            return Err(FuzzError::UnsupportedExpr(reason));
+        }
+        let derivatives = if f.der.is_empty() { vec![ZERO.to_string(); n] } else { f.der };
+
+        let mut out = String::from("; Generated by ad_trait_fuzzer\n");
+        out.push_str(&format!("; f = {}\n", InfixPrinter::print(self.expr, n)));
+        out.push_str("; unsat: no point of the box is more than epsilon off, up to the solver's precision\n");
+        out.push_str("; (run dReal with --precision well below epsilon); delta-sat models may be spurious\n");
+        out.push_str("(set-logic QF_NRA)\n");
+        for i in 0..n {
+            out.push_str(&format!("(declare-fun x_{} () Real)\n", i));
+        }
+        for (i, x) in self.inputs.iter().enumerate() {
+            let r = self.radius * x.abs().max(1.0);
+            out.push_str(&format!("(assert (<= {} x_{} {}))\n", real("input", x - r)?, i, real("input", x + r)?));
+        }
+        let epsilon = real("epsilon", self.epsilon)?;
+        out.push_str("(assert (or\n");
+        for (i, (d, g)) in derivatives.iter().zip(self.gradient).enumerate() {
+            out.push_str(&format!("  ; d/dx_{}\n  (> (abs (- {} {})) {})\n", i, d, real("gradient", *g)?, epsilon));
+        }
+        out.push_str("))\n(check-sat)\n(get-model)\n");
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_smt_query() {
+        // x_0 * sin(x_1): gradient (sin x_1, x_0 cos x_1)
+        let expr = SimpleExpr::mul(SimpleExpr::var("x_0"), SimpleExpr::sin(SimpleExpr::var("x_1")));
+        let query = SmtQuery { expr: &expr, inputs: &[2.0, -0.5], gradient: &[-0.479, 1.755], epsilon: 1e-3, radius: 0.25 };
+        let smt = query.to_smtlib().unwrap();
+        assert!(smt.contains("(assert (<= 1.5 x_0 2.5))"));
+        assert!(smt.contains("(assert (<= (- 0.75) x_1 (- 0.25)))"));
+        assert!(smt.contains("(> (abs (- (sin x_1) (- 0.479))) 0.001)"));
+        assert!(smt.contains("(> (abs (- (* x_0 (cos x_1)) 1.755)) 0.001)"));
+
+        let nan = SimpleExpr::add(SimpleExpr::var("x_0"), SimpleExpr::num(f64::NAN));
+        let query = SmtQuery { expr: &nan, ..query };
+        assert!(query.to_smtlib().is_err());
+        assert_eq!(smt_real(1e21).unwrap(), "1000000000000000000000.0");
+
+        // No `^` or `to_int` outside the logic
+        let cube = SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(-3.0));
+        let smt = SmtQuery { expr: &cube, inputs: &[2.0], gradient: &[-0.1875], ..query }.to_smtlib().unwrap();
+        assert!(smt.contains("(* (- 3.0) (/ 1.0 (* x_0 x_0 x_0 x_0)))"));
+        let general = SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::var("x_1"));
+        let smt = SmtQuery { expr: &general, inputs: &[2.0, 0.5], gradient: &[0.35, 0.98], ..query }.to_smtlib().unwrap();
+        assert!(smt.contains("(exp (* x_1 (log x_0)))") && !smt.contains("(^"));
+        let trunc = SimpleExpr::cast(Type::Int, SimpleExpr::var("x_0"));
+        let err = SmtQuery { expr: &trunc, inputs: &[2.0], gradient: &[0.0], ..query }.to_smtlib().unwrap_err();
+        assert_eq!(err.kind(), "UnsupportedExpr");
+    }
+}
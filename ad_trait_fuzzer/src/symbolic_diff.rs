@@ -0,0 +1,140 @@
+// src/symbolic_diff.rs
+
+// Closed-form derivatives: `derivative(expr, "x_0")` is another expression, built by the textbook
+// rules and simplified (`partial_eval`, then `RuleSet::simplify`), which
+// `SymbolicGroundTruthCalculator` evaluates in f64 and crash reports print. Terms whose
+// derivative is structurally zero are left out rather than multiplied by 0, so `x_0 ^ 3`
+// differentiates to `3 * x_0 ^ 2` without the `ln(x_0) * 0` an AD engine carries along (NaN for
+// negative `x_0`): the result is the analytic answer.
+//
+// `abs` has derivative sign(a), 0 at 0 like `Dual`; without comparisons it is written with casts,
+// `bool(a) * (1 - 2 * bool(abs(a) - a))`. Casts to `int` and `bool` are piecewise constant.
+// `Let` is inlined first; control flow is not supported.
+
+use crate::ast_evaluator::{partial_eval, Env};
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr, Type};
+use crate::ast_rewrite::RuleSet;
+use crate::error::FuzzError;
+
+/// sign(a) with sign(0) = 0, see the top of the file.
+fn sign(a: SimpleExpr) -> SimpleExpr {
+    let bool = |e| SimpleExpr::cast(Type::Bool, e);
+    let negative = bool(SimpleExpr::sub(SimpleExpr::abs(a.clone()), a.clone()));
+    SimpleExpr::mul(bool(a), SimpleExpr::sub(SimpleExpr::num(1.0), SimpleExpr::mul(SimpleExpr::num(2.0), negative)))
+}
+
+/// `a + b` of two derivatives, `None` being structurally zero.
+fn sum(a: Option<SimpleExpr>, b: Option<SimpleExpr>) -> Option<SimpleExpr> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(SimpleExpr::add(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// d expr / d var, `None` where it is structurally zero.
+fn d(expr: &SimpleExpr, var: &str) -> Result<Option<SimpleExpr>, FuzzError> {
+    Ok(match expr {
+        Expr::Number(..) => None,
+        Expr::Id(_, name) => (name == var).then(|| SimpleExpr::num(1.0)),
+        Expr::UnOp(_, op, a) => {
+            let Some(da) = d(a, var)? else { return Ok(None) };
+            let a = (**a).clone();
+            Some(match op {
+                Op1::Neg => SimpleExpr::neg(da),
+                Op1::Sin => SimpleExpr::mul(SimpleExpr::cos(a), da),
+                Op1::Cos => SimpleExpr::mul(SimpleExpr::neg(SimpleExpr::sin(a)), da),
+                Op1::Tan => SimpleExpr::div(da, SimpleExpr::mul(SimpleExpr::cos(a.clone()), SimpleExpr::cos(a))),
+                Op1::Exp => SimpleExpr::mul(SimpleExpr::exp(a), da),
+                Op1::Log => SimpleExpr::div(da, a),
+                Op1::Sqrt => SimpleExpr::div(da, SimpleExpr::mul(SimpleExpr::num(2.0), SimpleExpr::sqrt(a))),
+                Op1::Abs => SimpleExpr::mul(sign(a), da),
+            })
+        }
+        Expr::BinOp(_, op, a, b) => {
+            let (da, db) = (d(a, var)?, d(b, var)?);
+            let (a, b) = ((**a).clone(), (**b).clone());
+            match op {
+                Op2::Add => sum(da, db),
+                Op2::Sub => sum(da, db.map(SimpleExpr::neg)),
+                Op2::Mul => sum(da.map(|da| SimpleExpr::mul(da, b.clone())), db.map(|db| SimpleExpr::mul(a.clone(), db))),
+                // a' / b - a b' / b^2
+                Op2::Div => sum(
+                    da.map(|da| SimpleExpr::div(da, b.clone())),
+                    db.map(|db| SimpleExpr::neg(SimpleExpr::div(SimpleExpr::mul(a.clone(), db), SimpleExpr::mul(b.clone(), b.clone())))),
+                ),
+                // b a^(b-1) a' + a^b ln(a) b'; a^0 is constant
+                Op2::Pow if matches!(b, Expr::Number(_, e) if e == 0.0) => None,
+                Op2::Pow => sum(
+                    da.map(|da| {
+                        let lowered = SimpleExpr::pow(a.clone(), SimpleExpr::sub(b.clone(), SimpleExpr::num(1.0)));
+                        SimpleExpr::mul(SimpleExpr::mul(b.clone(), lowered), da)
+                    }),
+                    db.map(|db| SimpleExpr::mul(SimpleExpr::mul(SimpleExpr::pow(a.clone(), b.clone()), SimpleExpr::log(a.clone())), db)),
+                ),
+            }
+        }
+        Expr::Cast(_, Type::Float, a) => d(a, var)?,
+        Expr::Cast(_, _, a) => {
+            d(a, var)?;
+            None
+        }
+        other => return Err(FuzzError::UnsupportedExpr(format!("no symbolic derivative of {:?}", other))),
+    })
+}
+
+fn simplify(expr: &SimpleExpr) -> Result<SimpleExpr, FuzzError> {
+    Ok(RuleSet::simplify().rewrite(&partial_eval(expr, &Env::new())?))
+}
+
+/// The simplified derivative of `expr` with respect to variable `var`, see the top of the file.
+pub fn derivative(expr: &SimpleExpr, var: &str) -> Result<SimpleExpr, FuzzError> {
+    let inlined = partial_eval(expr, &Env::new())?;
+    match d(&inlined, var)? {
+        Some(derivative) => simplify(&derivative),
+        None => Ok(SimpleExpr::num(0.0)),
+    }
+}
+
+/// `derivative` with respect to each of `x_0 .. x_{num_inputs - 1}`.
+pub fn symbolic_gradient(expr: &SimpleExpr, num_inputs: usize) -> Result<Vec<SimpleExpr>, FuzzError> {
+    (0..num_inputs).map(|i| derivative(expr, &format!("x_{}", i))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::{evaluate, Dual, InfixPrinter};
+    use crate::ast_parser::parse_sexpr;
+
+    fn gradient(src: &str, n: usize) -> Vec<String> {
+        let (expr, _) = parse_sexpr(src).unwrap();
+        symbolic_gradient(&expr, n).unwrap().iter().map(|d| InfixPrinter::print(d, n)).collect()
+    }
+
+    #[test]
+    fn test_symbolic_gradient() {
+        assert_eq!(gradient("(* x_0 (sin x_1))", 2), ["sin(x_1)", "(x_0 * cos(x_1))"]);
+        assert_eq!(gradient("(+ (* 3 x_0) 2)", 1), ["3"]);
+        assert_eq!(gradient("(log x_0)", 2), ["(1 / x_0)", "0"]);
+        // Constant exponent: no ln(x_0) term
+        assert_eq!(gradient("(pow x_0 3)", 1), ["(3 * (x_0 ^ 2))"]);
+
+        // Agrees with the dual numbers, sign of abs at 0 included
+        for src in ["(* (exp (neg x_0)) (tan x_1))", "(/ (sqrt x_0) (+ x_1 2))", "(abs (- x_0 x_1))", "(pow x_0 x_1)"] {
+            let (expr, _) = parse_sexpr(src).unwrap();
+            let gradient = symbolic_gradient(&expr, 2).unwrap();
+            for x in [[0.7, 1.3], [2.0, 2.0], [1.5, -0.25]] {
+                let env: Env<f64> = x.iter().enumerate().map(|(i, v)| (format!("x_{}", i), *v)).collect();
+                for (i, d) in gradient.iter().enumerate() {
+                    let duals: Env<Dual> = x.iter().enumerate()
+                        .map(|(j, v)| (format!("x_{}", j), if i == j { Dual::variable(*v) } else { Dual::constant(*v) }))
+                        .collect();
+                    let expected = evaluate(&expr, &duals).unwrap().der;
+                    let got: f64 = evaluate(d, &env).unwrap();
+                    assert!((got - expected).abs() <= 1e-12 * expected.abs().max(1.0), "{} d/dx_{} at {:?}: {} vs {}", src, i, x, got, expected);
+                }
+            }
+        }
+        assert!(derivative(&Expr::Loop((), Box::new(SimpleExpr::var("x_0"))), "x_0").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+// src/tape_stats.rs
+
+// Size of the Reverse AD tape behind a test case. ad_trait does not expose its tape, so the two
+// numbers are measured from the outside:
+// - nodes: the expression is replayed on `TapeCount`, which records like `adr` does, one node
+//   per input and one per operation with an operand on the tape (constants stay off it);
+// - peak bytes: the most memory the reverse pass held at once, counted by `CountingAllocator`.
+//   A binary opts in with `#[global_allocator] static ALLOC: CountingAllocator = CountingAllocator;`;
+//   without it the peak is unknown.
+// A `TapeBudget` turns either number into a failing "tape" verdict, see `run_ad_tests`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use serde::Serialize;
+
+use crate::ast_evaluator::MainBackend;
+use crate::ast_expr::Type;
+use crate::error::FuzzError;
+use crate::fuzz_harness::Calculator;
+
+// --- PEAK MEMORY ---
+
+/// Set by the first allocation that goes through `CountingAllocator`.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// Bytes allocated minus bytes freed on this thread, and the most since `measure_peak` began.
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting the bytes each thread holds. Memory freed on another thread
+/// than the one that allocated it is credited to the one that frees it.
+pub struct CountingAllocator;
+
+fn record(delta: isize) {
+    if !INSTALLED.load(Ordering::Relaxed) {
+        INSTALLED.store(true, Ordering::Relaxed);
+    }
+    // Thread-locals are gone while a thread is torn down; its frees are not counted
+    let _ = LIVE.try_with(|live| {
+        let now = live.get() + delta;
+        live.set(now);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new.is_null() {
+            record(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+/// Runs `f` and returns the most bytes it had allocated at once on this thread, beyond what was
+/// live before; `None` if `CountingAllocator` is not the global allocator.
+pub fn measure_peak<R>(f: impl FnOnce() -> R) -> (R, Option<usize>) {
+    let start = LIVE.with(Cell::get);
+    let outer = PEAK.with(|peak| peak.replace(start));
+    let result = f();
+    let peak = PEAK.with(|p| p.replace(p.get().max(outer)));
+    let bytes = INSTALLED.load(Ordering::Relaxed).then(|| (peak - start).max(0) as usize);
+    (result, bytes)
+}
+
+// --- TAPE NODES ---
+
+thread_local! {
+    static NODES: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A value that counts the nodes `adr` would push for it, see the top of the file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TapeCount {
+    pub val: f64,
+    pub on_tape: bool,
+}
+
+impl TapeCount {
+    fn input(val: f64) -> Self {
+        NODES.with(|n| n.set(n.get() + 1));
+        TapeCount { val, on_tape: true }
+    }
+
+    fn op(val: f64, operands: &[TapeCount]) -> Self {
+        let on_tape = operands.iter().any(|o| o.on_tape);
+        if on_tape {
+            NODES.with(|n| n.set(n.get() + 1));
+        }
+        TapeCount { val, on_tape }
+    }
+}
+
+impl MainBackend for TapeCount {
+    fn from_f64(val: f64) -> Self { TapeCount { val, on_tape: false } }
+    fn zero() -> Self { TapeCount::from_f64(0.0) }
+    fn one() -> Self { TapeCount::from_f64(1.0) }
+
+    fn neg(self) -> Self { TapeCount::op(-self.val, &[self]) }
+    fn sin(self) -> Self { TapeCount::op(self.val.sin(), &[self]) }
+    fn cos(self) -> Self { TapeCount::op(self.val.cos(), &[self]) }
+    fn tan(self) -> Self { TapeCount::op(self.val.tan(), &[self]) }
+    fn exp(self) -> Self { TapeCount::op(self.val.exp(), &[self]) }
+    fn log(self) -> Self { TapeCount::op(self.val.ln(), &[self]) }
+    fn sqrt(self) -> Self { TapeCount::op(self.val.sqrt(), &[self]) }
+    fn abs(self) -> Self { TapeCount::op(self.val.abs(), &[self]) }
+
+    fn add(self, other: Self) -> Self { TapeCount::op(self.val + other.val, &[self, other]) }
+    fn sub(self, other: Self) -> Self { TapeCount::op(self.val - other.val, &[self, other]) }
+    fn mul(self, other: Self) -> Self { TapeCount::op(self.val * other.val, &[self, other]) }
+    fn div(self, other: Self) -> Self { TapeCount::op(self.val / other.val, &[self, other]) }
+    fn pow(self, other: Self) -> Self { TapeCount::op(self.val.powf(other.val), &[self, other]) }
+
+    // Same as the AD backend: `trunc` is recorded, a Bool is a new constant
+    fn cast(self, ty: &Type) -> Self {
+        match ty {
+            Type::Float => self,
+            Type::Int => TapeCount::op(self.val.trunc(), &[self]),
+            Type::Bool => TapeCount::from_f64(if self.val != 0.0 { 1.0 } else { 0.0 }),
+        }
+    }
+}
+
+/// Nodes on the Reverse AD tape of the first output of `calc` at `inputs`, `None` if the
+/// expression can't be evaluated on a `MainBackend`.
+pub fn tape_nodes<G: Calculator>(calc: &G, inputs: &[f64]) -> Option<usize> {
+    NODES.with(|n| n.set(0));
+    let inputs: Vec<TapeCount> = inputs.iter().map(|&x| TapeCount::input(x)).collect();
+    calc.eval_backend(&inputs).ok()?;
+    Some(NODES.with(Cell::get))
+}
+
+// --- STATS AND BUDGET ---
+
+/// What `run_ad_tests` measured for the Reverse AD pass of a test case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TapeStats {
+    pub nodes: Option<usize>,
+    pub peak_bytes: Option<usize>,
+}
+
+impl fmt::Display for TapeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let known = |v: Option<usize>| v.map_or("?".to_string(), |v| v.to_string());
+        write!(f, "{} nodes, {} bytes peak", known(self.nodes), known(self.peak_bytes))
+    }
+}
+
+/// Limits on `TapeStats`; unset measures are unlimited, and so are unknown ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TapeBudget {
+    pub max_nodes: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+impl TapeBudget {
+    pub fn check(&self, stats: &TapeStats) -> Result<(), FuzzError> {
+        let measures = [("nodes", stats.nodes, self.max_nodes), ("bytes", stats.peak_bytes, self.max_bytes)];
+        for (measure, used, limit) in measures {
+            if let (Some(used), Some(limit)) = (used, limit) {
+                if used > limit {
+                    return Err(FuzzError::TapeBudgetExceeded { measure, used, limit });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Limits in effect, 0 for none, like `fuzz_harness::EVAL_TIMEOUT_MS`.
+static MAX_NODES: AtomicUsize = AtomicUsize::new(0);
+static MAX_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the budget `run_ad_tests` holds every test case to. The default has no limits.
+pub fn set_tape_budget(budget: TapeBudget) {
+    MAX_NODES.store(budget.max_nodes.unwrap_or(0), Ordering::Relaxed);
+    MAX_BYTES.store(budget.max_bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+pub fn tape_budget() -> TapeBudget {
+    let limit = |v: &AtomicUsize| Some(v.load(Ordering::Relaxed)).filter(|&v| v != 0);
+    TapeBudget { max_nodes: limit(&MAX_NODES), max_bytes: limit(&MAX_BYTES) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_evaluator::unified::AdPyUnified;
+    use crate::ast_expr::SimpleExpr;
+
+    #[test]
+    fn test_tape_stats() {
+        // 2 inputs, sin, mul; (2 + 3) is folded off the tape and only its sum with x_0 counts
+        let expr = SimpleExpr::add(
+            SimpleExpr::mul(SimpleExpr::sin(SimpleExpr::var("x_0")), SimpleExpr::var("x_1")),
+            SimpleExpr::add(SimpleExpr::num(2.0), SimpleExpr::num(3.0)),
+        );
+        assert_eq!(tape_nodes(&AdPyUnified::new(expr, 2, 1), &[0.5, 2.0]), Some(5));
+
+        // The test binary has the system allocator: nothing to measure
+        let (value, bytes) = measure_peak(|| vec![0u8; 1 << 16].len());
+        assert_eq!((value, bytes), (1 << 16, None));
+
+        let budget = TapeBudget { max_nodes: Some(4), max_bytes: None };
+        let stats = TapeStats { nodes: Some(5), peak_bytes: Some(1 << 30) };
+        assert!(matches!(budget.check(&stats), Err(FuzzError::TapeBudgetExceeded { measure: "nodes", used: 5, limit: 4 })));
+        assert!(TapeBudget { max_nodes: Some(5), ..budget }.check(&stats).is_ok());
+        assert!(TapeBudget::default().check(&stats).is_ok());
+    }
+}
@@ -0,0 +1,331 @@
+// src/test_definition.rs
+
+// Curated regression tests as data: a function in Reverse Polish Notation plus the points to
+// run it at, loaded from a JSON or YAML suite and run through `run_ad_tests`.
+//
+//     - name: sin of a sum
+//       rpn: "x y + sin"
+//       inputs: [[0.5, 2.0], [-1.0, 3.0]]
+//     - rpn: "x_0 0 /"
+//       inputs: [[1.0]]
+//       oracles: "non_finite,domain"   # FUZZ_ORACLE syntax, default: every configured oracle
+//       expect_failure: true           # a known bug: the suite passes while it still fails
+//
+// Tokens are separated by whitespace. Variables are x_0, x_1, ... (or x, y, z for the first
+// three), numbers anything `f64` parses (`-2.5`, `1e-3`, `inf`, `NaN`). Binary operators
+// + - * / ^ (or pow) take the two values below them, the first one pushed on the left; unary
+// ones are neg sin cos tan exp ln (or log) sqrt abs and the casts trunc, bool and float.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ast_expr::{Expr, Op1, Op2, SimpleExpr, Type};
+use crate::error::FuzzError;
+use crate::fuzz_harness::{run_ad_tests, GroundTruthCalculator, TestOutcome};
+use crate::oracles::{FuzzingOracles, OracleSelection};
+use crate::rpn_evaluator::RpnEvaluator;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpnToken {
+    Var(usize),
+    Num(f64),
+    Unary(Op1),
+    Binary(Op2),
+    Cast(Type),
+}
+
+impl RpnToken {
+    fn parse(word: &str) -> Option<RpnToken> {
+        Some(match word {
+            "x" => RpnToken::Var(0),
+            "y" => RpnToken::Var(1),
+            "z" => RpnToken::Var(2),
+            "+" => RpnToken::Binary(Op2::Add),
+            "-" => RpnToken::Binary(Op2::Sub),
+            "*" => RpnToken::Binary(Op2::Mul),
+            "/" => RpnToken::Binary(Op2::Div),
+            "^" | "pow" => RpnToken::Binary(Op2::Pow),
+            "neg" => RpnToken::Unary(Op1::Neg),
+            "sin" => RpnToken::Unary(Op1::Sin),
+            "cos" => RpnToken::Unary(Op1::Cos),
+            "tan" => RpnToken::Unary(Op1::Tan),
+            "exp" => RpnToken::Unary(Op1::Exp),
+            "ln" | "log" => RpnToken::Unary(Op1::Log),
+            "sqrt" => RpnToken::Unary(Op1::Sqrt),
+            "abs" => RpnToken::Unary(Op1::Abs),
+            "trunc" => RpnToken::Cast(Type::Int),
+            "bool" => RpnToken::Cast(Type::Bool),
+            "float" => RpnToken::Cast(Type::Float),
+            _ => match word.strip_prefix("x_") {
+                Some(index) => RpnToken::Var(index.parse().ok()?),
+                None => RpnToken::Num(word.parse().ok()?),
+            },
+        })
+    }
+
+    /// How many values the token pops off the stack.
+    fn arity(&self) -> usize {
+        match self {
+            RpnToken::Var(_) | RpnToken::Num(_) => 0,
+            RpnToken::Unary(_) | RpnToken::Cast(_) => 1,
+            RpnToken::Binary(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for RpnToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpnToken::Var(i) => write!(f, "x_{}", i),
+            RpnToken::Num(val) => write!(f, "{}", val),
+            RpnToken::Binary(op) => f.write_str(match op {
+                Op2::Add => "+",
+                Op2::Sub => "-",
+                Op2::Mul => "*",
+                Op2::Div => "/",
+                Op2::Pow => "^",
+            }),
+            RpnToken::Unary(op) => f.write_str(match op {
+                Op1::Neg => "neg",
+                Op1::Sin => "sin",
+                Op1::Cos => "cos",
+                Op1::Tan => "tan",
+                Op1::Exp => "exp",
+                Op1::Log => "ln",
+                Op1::Sqrt => "sqrt",
+                Op1::Abs => "abs",
+            }),
+            RpnToken::Cast(ty) => f.write_str(match ty {
+                Type::Int => "trunc",
+                Type::Bool => "bool",
+                Type::Float => "float",
+            }),
+        }
+    }
+}
+
+/// Parses whitespace-separated RPN, checking that it leaves exactly one value on the stack.
+pub fn parse_rpn(src: &str) -> Result<Vec<RpnToken>, FuzzError> {
+    let error = |msg: String| FuzzError::UnsupportedExpr(format!("{} in RPN '{}'", msg, src));
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    for (i, word) in src.split_whitespace().enumerate() {
+        let token = RpnToken::parse(word).ok_or_else(|| error(format!("unknown token '{}'", word)))?;
+        if depth < token.arity() {
+            return Err(error(format!("'{}' (token {}) needs {} operand(s), the stack has {}", word, i + 1, token.arity(), depth)));
+        }
+        depth = depth - token.arity() + 1;
+        tokens.push(token);
+    }
+    match depth {
+        1 => Ok(tokens),
+        0 => Err(error("no value".to_string())),
+        n => Err(error(format!("{} values left on the stack", n))),
+    }
+}
+
+fn rpn_string(tokens: &[RpnToken]) -> String {
+    tokens.iter().map(RpnToken::to_string).collect::<Vec<_>>().join(" ")
+}
+
+fn serialize_rpn<S: Serializer>(tokens: &[RpnToken], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&rpn_string(tokens))
+}
+
+fn deserialize_rpn<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<RpnToken>, D::Error> {
+    let src = String::deserialize(deserializer)?;
+    parse_rpn(&src).map_err(serde::de::Error::custom)
+}
+
+/// One curated test: a function in RPN and the points it is checked at, see the top of the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(serialize_with = "serialize_rpn", deserialize_with = "deserialize_rpn")]
+    pub rpn: Vec<RpnToken>,
+    /// One point per entry, each with `num_inputs()` values.
+    #[serde(default)]
+    pub inputs: Vec<Vec<f64>>,
+    /// Oracles to run, FUZZ_ORACLE syntax; all of the runner's if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oracles: Option<String>,
+    #[serde(default)]
+    pub expect_failure: bool,
+}
+
+impl TestDefinition {
+    /// A definition without inputs, e.g. `TestDefinition::from_rpn_str("x y + sin")`.
+    pub fn from_rpn_str(src: &str) -> Result<Self, FuzzError> {
+        Ok(TestDefinition { name: None, rpn: parse_rpn(src)?, inputs: Vec::new(), oracles: None, expect_failure: false })
+    }
+
+    pub fn with_inputs(mut self, inputs: Vec<Vec<f64>>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// One past the highest variable index used.
+    pub fn num_inputs(&self) -> usize {
+        self.rpn.iter().filter_map(|t| match t { RpnToken::Var(i) => Some(i + 1), _ => None }).max().unwrap_or(0)
+    }
+
+    /// The name, or the RPN if it has none.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| rpn_string(&self.rpn))
+    }
+
+    /// The same function as an expression tree; an error if `rpn` does not leave exactly one
+    /// value on the stack (only possible for tokens that did not come from `parse_rpn`).
+    pub fn to_expr(&self) -> Result<SimpleExpr, FuzzError> {
+        let unbalanced = || FuzzError::UnsupportedExpr(format!("unbalanced RPN '{}'", rpn_string(&self.rpn)));
+        let mut stack: Vec<SimpleExpr> = Vec::new();
+        for token in &self.rpn {
+            let mut pop = || stack.pop().map(Box::new).ok_or_else(unbalanced);
+            let expr = match token {
+                RpnToken::Var(i) => SimpleExpr::var(format!("x_{}", i)),
+                RpnToken::Num(val) => SimpleExpr::num(*val),
+                RpnToken::Unary(op) => Expr::UnOp((), op.clone(), pop()?),
+                RpnToken::Cast(ty) => Expr::Cast((), ty.clone(), pop()?),
+                RpnToken::Binary(op) => {
+                    let right = pop()?;
+                    Expr::BinOp((), op.clone(), pop()?, right)
+                }
+            };
+            stack.push(expr);
+        }
+        match (stack.pop(), stack.is_empty()) {
+            (Some(expr), true) => Ok(expr),
+            _ => Err(unbalanced()),
+        }
+    }
+
+    /// The RPN is balanced, every point has the right number of values and the oracle names exist.
+    fn validate(&self) -> Result<(), FuzzError> {
+        let invalid = |msg: String| FuzzError::InvalidConfig(format!("test '{}': {}", self.label(), msg));
+        self.to_expr().map_err(|e| invalid(e.to_string()))?;
+        if self.inputs.is_empty() {
+            return Err(invalid("no inputs".to_string()));
+        }
+        if let Some(point) = self.inputs.iter().find(|p| p.len() != self.num_inputs()) {
+            return Err(invalid(format!("{:?} is not a point of {} inputs", point, self.num_inputs())));
+        }
+        if let Some(names) = &self.oracles {
+            names.parse::<OracleSelection>().map_err(invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a suite, a list of `TestDefinition`s, from a `.json`, `.yaml` or `.yml` file.
+pub fn load_suite(path: impl AsRef<Path>) -> Result<Vec<TestDefinition>, FuzzError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)
+        .map_err(|e| FuzzError::InvalidConfig(format!("cannot read '{}': {}", path.display(), e)))?;
+    let invalid = |e: String| FuzzError::InvalidConfig(format!("{}: {}", path.display(), e));
+    let suite: Vec<TestDefinition> = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(|e| invalid(e.to_string()))?,
+        _ => return Err(invalid("expected a .json, .yaml or .yml file".to_string())),
+    };
+    for definition in &suite {
+        definition.validate()?;
+    }
+    Ok(suite)
+}
+
+/// `run_ad_tests` on one point of a definition.
+#[derive(Debug)]
+pub struct SuiteResult {
+    pub name: String,
+    pub inputs: Vec<f64>,
+    pub expect_failure: bool,
+    pub outcome: Result<TestOutcome, FuzzError>,
+}
+
+impl SuiteResult {
+    /// Failed, or could not run, exactly when the definition said it would.
+    pub fn as_expected(&self) -> bool {
+        let failed = !matches!(&self.outcome, Ok(outcome) if outcome.passed());
+        failed == self.expect_failure
+    }
+}
+
+/// Runs every point of every definition in `suite` with `oracles` (only those a definition
+/// names, if it names any) against `gt_calculators`.
+pub fn run_suite(
+    suite: &[TestDefinition],
+    oracles: &FuzzingOracles,
+    gt_calculators: &[Box<dyn GroundTruthCalculator>],
+) -> Vec<SuiteResult> {
+    let mut results = Vec::new();
+    for definition in suite {
+        let expr = match definition.to_expr() {
+            Ok(expr) => expr,
+            Err(e) => {
+                results.push(SuiteResult { name: definition.label(), inputs: Vec::new(), expect_failure: definition.expect_failure, outcome: Err(e) });
+                continue;
+            }
+        };
+        let mut oracles = oracles.specialize(&expr);
+        if let Some(selection) = definition.oracles.as_ref().and_then(|names| names.parse::<OracleSelection>().ok()) {
+            let names: Vec<String> = oracles.names().into_iter().map(String::from).collect();
+            for name in names {
+                // Custom oracles have no flag and always run
+                if OracleSelection::from_name(&name).is_some_and(|flag| !selection.contains(flag)) {
+                    oracles.remove(&name);
+                }
+            }
+        }
+        for point in &definition.inputs {
+            let calc = RpnEvaluator::new(definition.clone(), definition.num_inputs());
+            results.push(SuiteResult {
+                name: definition.label(),
+                inputs: point.clone(),
+                expect_failure: definition.expect_failure,
+                outcome: run_ad_tests(point, calc, &oracles, gt_calculators),
+            });
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpn_definitions() {
+        let definition = TestDefinition::from_rpn_str("x y + sin").unwrap();
+        assert_eq!(definition.to_expr().unwrap(), SimpleExpr::sin(SimpleExpr::add(SimpleExpr::var("x_0"), SimpleExpr::var("x_1"))));
+        assert_eq!(definition.num_inputs(), 2);
+        // Operand order and the printed form
+        let definition = TestDefinition::from_rpn_str("x_0 -2.5 ^ x_1 ln /").unwrap();
+        assert_eq!(definition.to_expr().unwrap(), SimpleExpr::div(
+            SimpleExpr::pow(SimpleExpr::var("x_0"), SimpleExpr::num(-2.5)),
+            SimpleExpr::log(SimpleExpr::var("x_1")),
+        ));
+        assert_eq!(definition.label(), "x_0 -2.5 ^ x_1 ln /");
+
+        assert!(TestDefinition::from_rpn_str("x +").is_err());
+        assert!(TestDefinition::from_rpn_str("x y").is_err());
+        assert!(TestDefinition::from_rpn_str("x sinh").is_err());
+        let unbalanced = TestDefinition { rpn: vec![RpnToken::Var(0), RpnToken::Var(1)], ..definition.clone() };
+        assert!(unbalanced.to_expr().is_err());
+
+        let suite: Vec<TestDefinition> = serde_json::from_str(r#"[
+            {"name": "sum", "rpn": "x y +", "inputs": [[1, 2]]},
+            {"rpn": "x 0 /", "inputs": [[1]], "oracles": "non_finite", "expect_failure": true}
+        ]"#).unwrap();
+        assert!(suite.iter().all(|d| d.validate().is_ok()));
+        assert_eq!(suite[1].label(), "x_0 0 /");
+        let json = serde_json::to_string(&suite[0]).unwrap();
+        assert_eq!(serde_json::from_str::<TestDefinition>(&json).unwrap(), suite[0]);
+
+        assert!(serde_json::from_str::<TestDefinition>(r#"{"rpn": "x +"}"#).is_err());
+        assert!(suite[0].clone().with_inputs(vec![vec![1.0]]).validate().is_err());
+    }
+}
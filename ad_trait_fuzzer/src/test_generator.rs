@@ -0,0 +1,200 @@
+// src/test_generator.rs
+
+// Random RPN functions for `fuzz_target_1`, the counterpart of `ast_generator` for the RPN
+// pipeline. `RpnGenConfig` takes the same limits and operator flags as `AstGenConfig` (see
+// `RpnGenConfig::from`), so both generators can run equivalent campaigns; on top of them it
+// picks constants from a pool, weighs the kinds of token, and can fix the RNG seed so a
+// campaign generates the same functions on every run.
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::ast_expr::{Op1, Op2, Type};
+use crate::ast_generator::AstGenConfig;
+use crate::test_definition::{RpnToken, TestDefinition};
+
+/// Constants for `RpnGenConfig::constants`: the identities, small integers, a fraction and a
+/// negative value, like the fixed picks of `ast_generator::generate_terminal`.
+pub const DEFAULT_CONSTANTS: [f64; 8] = [0.0, 1.0, 2.0, 3.0, 0.5, -1.0, -2.0, 10.0];
+
+/// Relative weights of the kinds of token. A token is a leaf (variable, constant) or an
+/// operator over the tokens before it; at `max_depth` only leaves are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenWeights {
+    pub variable: u32,
+    pub constant: u32,
+    pub unary: u32,
+    pub binary: u32,
+    /// Only drawn with `allow_cast`.
+    pub cast: u32,
+}
+
+/// Same odds as `ast_generator`: leaf, unary, binary and cast equally likely, a leaf is a
+/// variable 2 times in 5.
+impl Default for TokenWeights {
+    fn default() -> Self {
+        TokenWeights { variable: 2, constant: 3, unary: 5, binary: 5, cast: 5 }
+    }
+}
+
+/// Config for `generate_random_test`.
+#[derive(Debug, Clone)]
+pub struct RpnGenConfig {
+    pub max_depth: usize,
+    /// Variables are drawn from x_0 .. x_{max_variables - 1}.
+    pub max_variables: usize,
+    pub allow_division: bool,
+    pub allow_power: bool,
+    pub allow_log: bool,
+    pub allow_cast: bool,
+    /// Constants are drawn from this pool; uniform in [-10, 10] if it is empty.
+    pub constants: Vec<f64>,
+    pub weights: TokenWeights,
+    /// Fixes the RNG of `rng()`; from entropy if unset.
+    pub seed: Option<u64>,
+}
+
+impl Default for RpnGenConfig {
+    fn default() -> Self {
+        RpnGenConfig::from(&AstGenConfig::default())
+    }
+}
+
+/// The same limits and operators as an AST campaign, with the default pool and weights.
+impl From<&AstGenConfig> for RpnGenConfig {
+    fn from(ast: &AstGenConfig) -> Self {
+        RpnGenConfig {
+            max_depth: ast.max_depth,
+            max_variables: ast.max_variables,
+            allow_division: ast.allow_division,
+            allow_power: ast.allow_power,
+            allow_log: ast.allow_log,
+            allow_cast: ast.allow_cast,
+            constants: DEFAULT_CONSTANTS.to_vec(),
+            weights: TokenWeights::default(),
+            seed: None,
+        }
+    }
+}
+
+impl RpnGenConfig {
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+
+    fn unary_ops(&self) -> Vec<Op1> {
+        let mut ops = vec![Op1::Neg, Op1::Sin, Op1::Cos, Op1::Tan, Op1::Exp, Op1::Sqrt, Op1::Abs];
+        if self.allow_log {
+            ops.push(Op1::Log);
+        }
+        ops
+    }
+
+    fn binary_ops(&self) -> Vec<Op2> {
+        let mut ops = vec![Op2::Add, Op2::Sub, Op2::Mul];
+        if self.allow_division {
+            ops.push(Op2::Div);
+        }
+        if self.allow_power {
+            ops.push(Op2::Pow);
+        }
+        ops
+    }
+}
+
+fn push_expr<R: Rng + ?Sized>(rng: &mut R, config: &RpnGenConfig, depth: usize, out: &mut Vec<RpnToken>) {
+    let w = &config.weights;
+    let ops = depth < config.max_depth;
+    let weights = [
+        w.variable,
+        w.constant,
+        if ops { w.unary } else { 0 },
+        if ops { w.binary } else { 0 },
+        if ops && config.allow_cast { w.cast } else { 0 },
+    ];
+    // All-zero weights fall back to a variable
+    let kind = WeightedIndex::new(weights).map_or(0, |dist| dist.sample(rng));
+
+    match kind {
+        0 => out.push(RpnToken::Var(rng.gen_range(0..config.max_variables.max(1)))),
+        1 => {
+            let val = if config.constants.is_empty() {
+                rng.gen_range(-10.0..=10.0)
+            } else {
+                config.constants[rng.gen_range(0..config.constants.len())]
+            };
+            out.push(RpnToken::Num(val));
+        }
+        2 => {
+            push_expr(rng, config, depth + 1, out);
+            let ops = config.unary_ops();
+            out.push(RpnToken::Unary(ops[rng.gen_range(0..ops.len())].clone()));
+        }
+        3 => {
+            push_expr(rng, config, depth + 1, out);
+            push_expr(rng, config, depth + 1, out);
+            let ops = config.binary_ops();
+            out.push(RpnToken::Binary(ops[rng.gen_range(0..ops.len())].clone()));
+        }
+        _ => {
+            push_expr(rng, config, depth + 1, out);
+            let ty = [Type::Float, Type::Int, Type::Bool][rng.gen_range(0..3)].clone();
+            out.push(RpnToken::Cast(ty.clone()));
+            // Booleans are not numbers: back to 0/1 before any arithmetic sees them
+            if ty == Type::Bool {
+                out.push(RpnToken::Cast(Type::Float));
+            }
+        }
+    }
+}
+
+/// A random function under `config`, without inputs: the caller evaluates it at its own
+/// points, see `RpnEvaluator::new`.
+pub fn generate_random_test<R: Rng + ?Sized>(rng: &mut R, config: &RpnGenConfig) -> TestDefinition {
+    let mut rpn = Vec::new();
+    push_expr(rng, config, 0, &mut rpn);
+    TestDefinition { name: None, rpn, inputs: Vec::new(), oracles: None, expect_failure: false }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_expr::Expr;
+    use crate::ast_visitor::for_each_node;
+
+    #[test]
+    fn test_generate_random_test() {
+        let config = RpnGenConfig {
+            max_depth: 6,
+            allow_division: false,
+            allow_power: false,
+            constants: vec![0.25, 4.0],
+            seed: Some(7),
+            ..RpnGenConfig::default()
+        };
+        let (mut a, mut b) = (config.rng(), config.rng());
+        for _ in 0..200 {
+            let definition = generate_random_test(&mut a, &config);
+            // Same seed, same functions
+            assert_eq!(definition, generate_random_test(&mut b, &config));
+
+            let expr = definition.to_expr().unwrap();
+            assert!(definition.num_inputs() <= config.max_variables);
+            for_each_node(&expr, |node| match node {
+                Expr::Number(_, val) => assert!(config.constants.contains(val)),
+                Expr::BinOp(_, op, ..) => assert!(matches!(op, Op2::Add | Op2::Sub | Op2::Mul)),
+                Expr::UnOp(_, op, _) => assert_ne!(*op, Op1::Log),
+                Expr::Cast(..) => panic!("casts are off by default"),
+                _ => {}
+            });
+        }
+
+        // Only constants
+        let config = RpnGenConfig { weights: TokenWeights { variable: 0, unary: 0, binary: 0, ..TokenWeights::default() }, ..config };
+        assert_eq!(generate_random_test(&mut config.rng(), &config).num_inputs(), 0);
+    }
+}